@@ -0,0 +1,50 @@
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Exposes build metadata to src/utils/build_info.rs, every value falls back to "unknown"
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or("unknown".to_string());
+
+    // Reproducible builds pin the date through SOURCE_DATE_EPOCH
+    let seconds = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .or(SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs()));
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(|feature| feature.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=RASK_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=RASK_BUILD_DATE={}", seconds.map(format_date).unwrap_or("unknown".to_string()));
+    println!("cargo:rustc-env=RASK_BUILD_TARGET={}", env::var("TARGET").unwrap_or("unknown".to_string()));
+    println!("cargo:rustc-env=RASK_FEATURES={}", features.join(","));
+}
+
+// Days since the epoch to a civil date, see https://howardhinnant.github.io/date_algorithms.html
+fn format_date(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
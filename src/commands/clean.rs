@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, file, output, tmp};
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Remove the RASK_TMPDIR directories that runs kept of failed tasks")]
+    tmp: bool,
+}
+
+// Removes what rask keeps in .rask, every kind of state has its own flag
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, tmp } = arguments;
+
+    if !*tmp {
+        return Err("Nothing to clean, pass --tmp to remove the scratch directories of earlier runs".to_string().into());
+    }
+
+    let entry_config_path: PathBuf = config::resolve_config_path(config::get_entry(entry).as_str()).map_err(exit::config)?;
+    let run_count: usize = tmp::clean(file::get_parent_directory(&entry_config_path)?)?;
+    output::success(&format!("Removed the scratch directories of {} runs", run_count));
+
+    Ok(())
+}
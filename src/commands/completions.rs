@@ -0,0 +1,127 @@
+use std::env;
+use clap::{Args, ValueEnum};
+use clap::Command as ClapCommand;
+use crate::utils::index;
+use crate::utils::index::WorkspaceIndex;
+use crate::utils::exit::ExitError;
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum Shell {
+    BASH,
+    ZSH,
+    FISH,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum DynamicKind {
+    #[default]
+    TASKS,
+    CONFIGS,
+}
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(value_enum, required_unless_present = "dynamic", help = "The shell to print the completion script for")]
+    shell: Option<Shell>,
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "tasks", help = "Print the task or config names of the workspace index, used by the completion scripts")]
+    dynamic: Option<DynamicKind>,
+}
+
+pub fn execute(arguments: &Arguments, command: ClapCommand) -> Result<(), ExitError> {
+    let Arguments { shell, dynamic } = arguments;
+
+    if let Some(dynamic_kind) = dynamic {
+        print_dynamic_completions(dynamic_kind);
+        return Ok(());
+    }
+
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+
+    let run_flags: Vec<String> = command
+        .find_subcommand("run")
+        .map(|run| run.get_arguments().filter_map(|argument| argument.get_long()).map(|long| format!("--{}", long)).collect())
+        .unwrap_or_default();
+
+    let script = match shell {
+        Some(Shell::BASH) => bash_script(&subcommands, &run_flags),
+        Some(Shell::ZSH) => zsh_script(&subcommands, &run_flags),
+        Some(Shell::FISH) => fish_script(&subcommands, &run_flags),
+        None => return Err("No shell given".to_string().into()),
+    };
+
+    print!("{}", script);
+
+    Ok(())
+}
+
+// Only reads the index written by `list` and `run` or the engine file of a single package, anything else yields no completions.
+fn print_dynamic_completions(dynamic_kind: &DynamicKind) {
+    let Some(current_directory) = env::current_dir().ok() else {
+        return;
+    };
+
+    let Some(WorkspaceIndex { tasks, configs, .. }) = index::read_workspace_index(&current_directory) else {
+        return;
+    };
+
+    let names = match dynamic_kind {
+        DynamicKind::TASKS => tasks,
+        DynamicKind::CONFIGS => configs,
+    };
+
+    for name in names {
+        println!("{}", name);
+    }
+}
+
+fn bash_script(subcommands: &[String], run_flags: &[String]) -> String {
+    format!(r#"_rask() {{
+    local current="${{COMP_WORDS[COMP_CWORD]}}"
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=( $(compgen -W "{subcommands}" -- "$current") )
+        return
+    fi
+
+    if [ "${{COMP_WORDS[1]}}" = "run" ]; then
+        if [[ "$current" == -* ]]; then
+            COMPREPLY=( $(compgen -W "{run_flags}" -- "$current") )
+        else
+            COMPREPLY=( $(compgen -W "$(rask completions --dynamic tasks 2>/dev/null)" -- "$current") )
+        fi
+    fi
+}}
+complete -o default -F _rask rask
+"#, subcommands = subcommands.join(" "), run_flags = run_flags.join(" "))
+}
+
+fn zsh_script(subcommands: &[String], run_flags: &[String]) -> String {
+    format!(r#"#compdef rask
+_rask() {{
+    if (( CURRENT == 2 )); then
+        compadd -- {subcommands}
+    elif [[ ${{words[2]}} == run ]]; then
+        if [[ ${{words[CURRENT]}} == -* ]]; then
+            compadd -- {run_flags}
+        else
+            compadd -- ${{(f)"$(rask completions --dynamic tasks 2>/dev/null)"}}
+        fi
+    fi
+}}
+compdef _rask rask
+"#, subcommands = subcommands.join(" "), run_flags = run_flags.join(" "))
+}
+
+fn fish_script(subcommands: &[String], run_flags: &[String]) -> String {
+    let mut script = format!("complete -c rask -f -n '__fish_use_subcommand' -a '{}'\n", subcommands.join(" "));
+    script.push_str("complete -c rask -f -n '__fish_seen_subcommand_from run' -a '(rask completions --dynamic tasks 2>/dev/null)'\n");
+
+    for run_flag in run_flags {
+        script.push_str(&format!("complete -c rask -n '__fish_seen_subcommand_from run' -l '{}'\n", run_flag.trim_start_matches("--")));
+    }
+
+    script
+}
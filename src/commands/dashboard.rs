@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use crate::utils::config;
+use crate::utils::config::{Config, ConfigIssues, ConfigTask};
+use crate::utils::file::ConfigFile;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+const TOP_TASK_AMOUNT: usize = 10;
+
+// Shown when rask is invoked without a command
+pub fn execute() -> Result<(), ExitError> {
+    // Broken child configs are skipped with a warning in lenient mode
+    let mut config_issues = ConfigIssues::new(true);
+
+    // Resolve the entry path and read the config files it includes
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(&None), false, &mut config_issues)
+        .map_err(|err| format!("{}\nRun `rask init` to create a config, or `rask --help` for usage", err))?;
+
+    // Parse config files
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    config_issues.print_warnings();
+
+    let entry_config: &Config = config::get_entry_config(&entry_config_path, &configs).map_err(exit::config)?;
+    let top_tasks: Vec<(String, usize)> = get_top_tasks(&configs);
+
+    println!("Rask workspace \"{}\" @ {:?}", entry_config.name, entry_config_path);
+    println!("  {} configs, {} unique tasks", configs.len(), count_unique_tasks(&configs));
+    if let Some(default_task) = &entry_config.default_task {
+        println!("  Default task: {} (run `rask run` to execute it)", default_task);
+    }
+
+    println!();
+    println!("Most common tasks:");
+    for (task, amount) in top_tasks.iter().take(TOP_TASK_AMOUNT) {
+        println!("  -  {} ({} configs)", task, amount)
+    }
+
+    println!();
+    println!("Run `rask run <task>` to run a task, or `rask --help` for all commands");
+
+    Ok(())
+}
+
+fn count_unique_tasks(configs: &[Config]) -> usize {
+    get_top_tasks(configs).len()
+}
+
+fn get_top_tasks(configs: &[Config]) -> Vec<(String, usize)> {
+    let mut task_counts: HashMap<String, usize> = HashMap::new();
+
+    for config in configs {
+        // Engines can expose the same key more than once, count each config once
+        let mut config_keys: Vec<&String> = config.tasks.iter().map(|ConfigTask { key, .. }| key).collect();
+        config_keys.sort();
+        config_keys.dedup();
+
+        for key in config_keys {
+            *task_counts.entry(key.clone()).or_default() += 1;
+        }
+    }
+
+    let mut top_tasks: Vec<(String, usize)> = task_counts.into_iter().collect();
+    top_tasks.sort_by(|(a_key, a_count), (b_key, b_count)| b_count.cmp(a_count).then(a_key.cmp(b_key)));
+
+    top_tasks
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+use clap::Args;
+use crate::utils::{config, output, process, time};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, SortableTask, Task};
+use crate::utils::file::ConfigFile;
+use crate::utils::output::Tone;
+use crate::utils::supervisor::Supervisor;
+use crate::utils::writer::OutputStyle;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(default_value = "dev", help = "The long running task to start in every config, matched exactly")]
+    task_name: String,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, value_parser = time::parse_duration, default_value = "1s", help = "How often the configs are checked for added, removed and changed tasks")]
+    interval: Duration,
+    #[arg(long, help = "Start every line of output with the time it was written")]
+    timestamps: bool,
+}
+
+// Keeps the dev task of every config running, and follows the configs as packages come and go
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { task_name, entry, require_config, interval, timestamps } = arguments;
+    let entry: String = config::get_entry(entry);
+
+    let tasks: Vec<Task> = load_dev_tasks(&entry, *require_config, task_name)?;
+    let task_refs: Vec<&Task> = tasks.iter().flat_map(|task| task.dependencies.iter().chain([task])).collect();
+    process::ensure_shells(&task_refs)?;
+    process::ensure_privileges(&task_refs)?;
+
+    if tasks.is_empty() {
+        output::warning(&format!("No config declares a \"{}\" task yet, waiting for one to be added", task_name));
+    }
+
+    let mut supervisor = Supervisor::new(OutputStyle { prefix: true, timestamps: *timestamps, fold: None });
+    supervisor.sync(tasks);
+    output::line("DEV", Tone::INFO, &format!("Running {} tasks, watching the configs for changes, press Ctrl+C to stop", supervisor.running()));
+
+    // Configs are mid-edit at times, a broken config keeps the current processes until it is fixed
+    let mut last_error: Option<ExitError> = None;
+    loop {
+        sleep(*interval);
+        if let Some(signal) = process::get_interruption() {
+            supervisor.wait();
+            return Err(ExitError::interrupted(signal));
+        }
+        supervisor.reap();
+
+        match load_dev_tasks(&entry, *require_config, task_name) {
+            Ok(tasks) => {
+                last_error = None;
+                supervisor.sync(tasks);
+            },
+            Err(err) => {
+                if last_error.as_ref() != Some(&err) {
+                    output::warning(&format!("Failed to reload the configs, keeping the running tasks: {}", err));
+                }
+                last_error = Some(err);
+            },
+        }
+    }
+}
+
+fn load_dev_tasks(entry: &str, require_config: bool, task_name: &str) -> Result<Vec<Task>, ExitError> {
+    // Broken child configs are skipped, their tasks stop until they parse again
+    let mut config_issues = ConfigIssues::new(true);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(entry, require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+
+    Ok(config::resolve_sortable_task(&config_structure, task_name, &true).map_err(exit::config)?
+        .into_iter()
+        .map(|SortableTask { task, .. }| task)
+        .collect())
+}
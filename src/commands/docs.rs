@@ -0,0 +1,54 @@
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, docs, export, file, output};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure};
+use crate::utils::docs::DocsFormat;
+use crate::utils::export::WorkspaceExport;
+use crate::utils::file::ConfigFile;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, value_enum, default_value_t = DocsFormat::MARKDOWN, help = "The format of the docs")]
+    format: DocsFormat,
+    #[arg(long, short, help = "Write the docs to the given file, instead of printing them")]
+    output: Option<PathBuf>,
+    #[arg(long, requires = "output", help = "Fail when the output file differs from the docs, instead of writing it")]
+    check: bool,
+}
+
+// Developer docs of the configs and tasks, generated so they can be committed and checked in CI
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, require_config, format, output, check } = arguments;
+
+    // Docs of a partial workspace would look complete, so nothing is skipped
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+    let workspace_export: WorkspaceExport = export::export_workspace(&entry_config_path, &config_structure).map_err(exit::config)?;
+    let content: String = docs::render_docs(&workspace_export, *format);
+
+    match (output, check) {
+        (Some(output), true) => match read_to_string(output) {
+            Ok(committed) if committed == content => {
+                output::success(&format!("{:?} is up to date", output));
+                Ok(())
+            },
+            Ok(_) => Err(format!("{:?} is out of date, run rask docs --output {:?} to update it", output, output).into()),
+            Err(err) => Err(format!("Failed to read {:?}: {}", output, err).into()),
+        },
+        (Some(output), false) => Ok(file::write_file_content(output, &content)?),
+        (None, _) => {
+            print!("{}", content);
+            Ok(())
+        },
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, environment, output, prerequisites};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, SortableTask, SortableTasks, Task};
+use crate::utils::file::ConfigFile;
+use crate::utils::interpolation::Variables;
+use crate::utils::output::{Stream, Tone};
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "Which task to check, defaults to the default_task of the entry config")]
+    task_name: Option<String>,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, help = "enable strict command matching, defaults to checking if a command starts with a key")]
+    strict: bool,
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = ".env", value_name = "FILE", help = "Write the env of the tasks to this file in every config directory instead of checking prerequisites, .env by default")]
+    write: Option<String>,
+    #[arg(long, requires = "write", help = "Write the values of secrets instead of masking them")]
+    include_secrets: bool,
+}
+
+// Lists what the tasks need from the machine, and fails when something is missing
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { task_name, entry, require_config, strict, write, include_secrets } = arguments;
+
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+
+    let entry_config: &Config = config::get_entry_config(&entry_config_path, &configs).map_err(exit::config)?;
+    let task_name: String = config::resolve_task_name(task_name, entry_config).map_err(exit::config)?;
+
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+    let sortable_tasks: SortableTasks = config::resolve_sortable_task(&config_structure, &task_name, strict).map_err(exit::config)?;
+
+    if let Some(file_name) = write {
+        return write_env_files(&sortable_tasks, file_name, *include_secrets).map_err(ExitError::from);
+    }
+
+    let mut missing: Vec<String> = vec![];
+
+    println!("The following prerequisites are required:");
+    for SortableTask { task, .. } in &sortable_tasks {
+        let Task { key, directory, .. } = task;
+        println!("  -  {} @ {:?}", key, directory);
+
+        // Dependencies run as part of the task, so they count as its prerequisites
+        for task in task.dependencies.iter().chain([task]) {
+            let Task { command, env, .. } = task;
+
+            for binary in prerequisites::find_command_binaries(command) {
+                let available = prerequisites::is_binary_available(&binary);
+                print_prerequisite("binary", &binary, available);
+                if !available {
+                    missing.push(format!("binary {} of {}", binary, key));
+                }
+            }
+
+            for name in prerequisites::find_env_references(command) {
+                let available = prerequisites::is_env_available(&name, env);
+                print_prerequisite("env", &name, available);
+                if !available {
+                    missing.push(format!("env {} of {}", name, key));
+                }
+            }
+        }
+    }
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(format!("Missing {} prerequisites: {}", missing.len(), missing.join(", ")).into()),
+    }
+}
+
+fn print_prerequisite(kind: &str, name: &str, available: bool) {
+    let status = match available {
+        true => output::paint("found", Tone::SUCCESS, Stream::STDOUT),
+        false => output::paint("missing", Tone::ERROR, Stream::STDOUT),
+    };
+
+    println!("       {} {}: {}", kind, name, status);
+}
+
+// The env is set per config, so every config directory gets one file for the tasks that run in it
+fn write_env_files(sortable_tasks: &SortableTasks, file_name: &str, include_secrets: bool) -> Result<(), String> {
+    let mut environments: Vec<(&PathBuf, &Variables)> = vec![];
+
+    for SortableTask { task, .. } in sortable_tasks {
+        match environments.iter().find(|(directory, _)| *directory == &task.directory) {
+            Some((_, env)) if *env != &task.env => return Err(format!("The tasks of {:?} have a different env, write the env of one task at a time", task.directory)),
+            Some(_) => continue,
+            None => environments.push((&task.directory, &task.env)),
+        }
+    }
+
+    let env_files: Vec<(PathBuf, String)> = environments
+        .into_iter()
+        .map(|(directory, env)| (directory.join(file_name), environment::format_env_file(env, include_secrets)))
+        .collect();
+    environment::write_env_files(&env_files)?;
+
+    for (path, _) in &env_files {
+        output::line("WRITTEN", Tone::SUCCESS, &format!("{:?}", path));
+    }
+
+    Ok(())
+}
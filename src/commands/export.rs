@@ -0,0 +1,41 @@
+use std::fs::write;
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, export};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure};
+use crate::utils::export::WorkspaceExport;
+use crate::utils::file::ConfigFile;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, short, help = "Write the JSON to the given file, instead of printing it")]
+    output: Option<PathBuf>,
+}
+
+// The resolved workspace as JSON, for tools that should not re-implement discovery
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, require_config, output } = arguments;
+
+    // Any broken config makes the export incomplete, so nothing is skipped
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+    let workspace_export: WorkspaceExport = export::export_workspace(&entry_config_path, &config_structure)?;
+    let json: String = serde_json::to_string_pretty(&workspace_export).map_err(|err| format!("Failed to serialize the workspace: {}", err))?;
+
+    match output {
+        Some(output) => write(output, json + "\n").map_err(|err| format!("Failed to write the export to {:?}: {}", output, err).into()),
+        None => {
+            println!("{}", json);
+            Ok(())
+        },
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{stdout, IsTerminal, Write};
+use std::process::Stdio;
+use clap::Args;
+use clap::Command as ClapCommand;
+use crate::utils::{man, process};
+use crate::utils::exit::ExitError;
+
+// Topics are plain text, their first line is the title
+pub const TOPICS: [(&str, &str); 5] = [
+    ("config", include_str!("topics/config.txt")),
+    ("engines", include_str!("topics/engines.txt")),
+    ("caching", include_str!("topics/caching.txt")),
+    ("scheduling", include_str!("topics/scheduling.txt")),
+    ("exit-codes", include_str!("topics/exit-codes.txt")),
+];
+
+const DEFAULT_PAGER: &str = "less -R";
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "A topic like config, engines, caching, scheduling or exit-codes, or a command")]
+    topic: Option<String>,
+    #[arg(long, conflicts_with = "topic", help = "Print the man page in roff format, e.g. rask help --man > rask.1")]
+    man: bool,
+}
+
+pub fn execute(arguments: &Arguments, mut command: ClapCommand) -> Result<(), ExitError> {
+    let Arguments { topic, man } = arguments;
+
+    if *man {
+        print!("{}", man::render_man_page(&command, &TOPICS));
+        return Ok(());
+    }
+
+    let text: String = match topic {
+        None => {
+            let topic_names: Vec<&str> = TOPICS.iter().map(|(name, _)| *name).collect();
+            format!("{}\nTopics, read them with rask help <topic>:\n  {}\n", command.render_long_help(), topic_names.join(", "))
+        },
+        Some(topic) => match TOPICS.iter().find(|(name, _)| name == topic) {
+            Some((_, text)) => text.to_string(),
+            None => match command.find_subcommand_mut(topic) {
+                Some(subcommand) => subcommand.render_long_help().to_string(),
+                None => return Err(format!("Unknown help topic \"{}\", expected a command or one of {}", topic, TOPICS.map(|(name, _)| name).join(", ")).into()),
+            },
+        },
+    };
+
+    page(&text);
+
+    Ok(())
+}
+
+// Pages through $PAGER when printing to a terminal, falling back on printing it
+fn page(text: &str) {
+    if !stdout().is_terminal() {
+        print!("{}", text);
+        return;
+    }
+
+    let pager: String = std::env::var("PAGER").ok().filter(|pager| !pager.is_empty()).unwrap_or(DEFAULT_PAGER.to_string());
+    let paged = process::build_shell_command(&pager)
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()
+        .and_then(|mut child| {
+            let _ = child.stdin.take()?.write_all(text.as_bytes());
+            child.wait().ok()
+        })
+        .is_some_and(|status| status.success());
+
+    if !paged {
+        print!("{}", text);
+    }
+}
@@ -1,5 +1,9 @@
+use std::path::{Path, PathBuf};
 use clap::Args;
-use crate::utils::file::{ConfigFile, parse_path_string, write_config_file};
+use crate::utils::{build_info, config, file, prompt};
+use crate::utils::config::{ConfigTask, ConfigTasks};
+use crate::utils::file::{ConfigFile, ConfigFileDirectory, ConfigFileTaskValue, get_parent_directory, parse_path_string, TaskEngine, write_config_file};
+use crate::utils::exit::{ExitError, ExitKind};
 
 #[derive(Args, Debug)]
 pub struct Arguments {
@@ -7,10 +11,18 @@ pub struct Arguments {
     entry: Option<String>,
     #[arg(help = "The rask config name, defaults to the directory name")]
     name: Option<String>,
+    #[arg(long, short, help = "Write the config without asking for confirmation")]
+    yes: bool,
+    #[arg(long, conflicts_with = "no_register", help = "Add the directory to the directories of the parent config without asking")]
+    register: bool,
+    #[arg(long, help = "Leave the parent config untouched")]
+    no_register: bool,
+    #[arg(long, short, help = "Ask for the name, engine, directories and a first task, and preview the config before writing it")]
+    interactive: bool,
 }
 
-pub fn execute(arguments: &Arguments) -> Result<(), String> {
-    let Arguments { entry, name } = arguments;
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, name, yes, register, no_register, interactive } = arguments;
 
     let mut path = parse_path_string(&entry.clone().unwrap_or(".".to_string()))?;
     if path.is_dir() {
@@ -18,36 +30,162 @@ pub fn execute(arguments: &Arguments) -> Result<(), String> {
     }
 
     if path.exists() {
-        return Err(format!("Rask already initialised at {:?}", path));
+        return Err(format!("Rask already initialised at {:?}", path).into());
     }
 
-    let config_name: String;
-    if name.is_none() {
-        config_name = path.parent()
-            .unwrap()
+    // Inspect the directory, so the config matches the project it lives in
+    let directory = get_parent_directory(&path)?;
+
+    let config_name: String = match name {
+        None => directory
             .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-    } else {
-        config_name = name.clone().unwrap();
-    }
+            .and_then(|file_name| file_name.to_str())
+            .ok_or(format!("Unable to derive a name from {:?}, pass one with --name", directory))?
+            .to_string(),
+        Some(name) => name.clone(),
+    };
 
-    let config_file: ConfigFile = ConfigFile {
+    let mut config_file: ConfigFile = ConfigFile {
         name: config_name,
-        task_engine: Default::default(),
+        dir: None,
+        task_engine: config::detect_task_engine(directory),
+        engine_tasks: Default::default(),
+        engine_overrides: Default::default(),
         directories: vec![],
         tasks: Default::default(),
+        task_templates: Default::default(),
+        constants: Default::default(),
+        env: Default::default(),
+        default_task: None,
+        shell: None,
+        before_level: None,
+        after_level: None,
+        serial: false,
+        ensure_installed: false,
+        default_parallelism: None,
         __file_path: Default::default(),
         __dir_path: Default::default(),
     };
 
-    write_config_file(path.clone(), config_file)?;
+    println!("Detected task engine: {:?}", config_file.task_engine);
+    if *interactive {
+        ask_config(directory, &mut config_file)?;
+    }
+
+    let tasks: ConfigTasks = config::parse_engine_tasks(directory, &config_file.task_engine, &Default::default(), config_file.tasks.clone())?;
+    match tasks.is_empty() {
+        true => println!("No tasks will be exposed yet"),
+        false => {
+            println!("The following tasks will be exposed:");
+            for ConfigTask { key, .. } in &tasks {
+                println!("  -  {}", key)
+            }
+        }
+    }
+
+    if *interactive {
+        let preview: String = serde_yaml::to_string(&config_file).map_err(|err| format!("Failed to serialize the config: {}", err))?;
+        println!("\n{}", preview);
+    }
+
+    if !yes && !prompt::confirm(&format!("Write {:?}?", path))? {
+        return Err(ExitError::new(ExitKind::CANCELLED, "Rask initialisation cancelled".to_string()));
+    }
 
-    // NOTE: We could improve the init command by adding a reverse search for a parent rake file
+    write_config_file(path.clone(), config_file)?;
 
     println!("Rask initialised: {:?}", path);
 
+    if !no_register {
+        register_with_parent_config(&path, *register || *yes)?;
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+// Walks through the config, every question has a default so enter keeps what was detected
+fn ask_config(directory: &Path, config_file: &mut ConfigFile) -> Result<(), String> {
+    config_file.name = prompt::ask("Name:", &config_file.name)?;
+
+    let engines: Vec<&str> = ["auto", "none"].into_iter().chain(build_info::SUPPORTED_ENGINES).collect();
+    let detected_engine: String = serde_yaml::to_string(&config_file.task_engine).map_err(|err| err.to_string())?.trim().to_string();
+    config_file.task_engine = loop {
+        let answer: String = prompt::ask(&format!("Task engine, one of {}:", engines.join(", ")), &detected_engine)?;
+        match serde_yaml::from_str::<TaskEngine>(&answer) {
+            Ok(task_engine) if engines.contains(&answer.as_str()) => break task_engine,
+            _ => println!("Unknown task engine \"{}\"", answer),
+        }
+    };
+
+    // Patterns are checked against what is on disk, a pattern that matches nothing is most likely a typo
+    loop {
+        let pattern: String = prompt::ask("Directories with configs to include, like packages/*, empty to continue:", "")?;
+        if pattern.is_empty() {
+            break;
+        }
+        if pattern.starts_with('!') {
+            config_file.directories.push(ConfigFileDirectory::Pattern(pattern));
+            continue;
+        }
+
+        let matched_directories: Vec<PathBuf> = match glob::glob(&directory.join(&pattern).to_string_lossy()) {
+            Ok(paths) => paths.flatten().filter(|path| path.is_dir()).collect(),
+            Err(err) => {
+                println!("Invalid pattern \"{}\": {}", pattern, err);
+                continue;
+            },
+        };
+        let config_count: usize = config::find_pattern_config_paths(directory, &pattern).len();
+        println!("  matches {} directories, {} with a rask.yaml", matched_directories.len(), config_count);
+
+        if !matched_directories.is_empty() || prompt::confirm("It matches no directory, keep it anyway?")? {
+            config_file.directories.push(ConfigFileDirectory::Pattern(pattern));
+        }
+    }
+
+    let task_name: String = prompt::ask("First task, like build, empty to skip:", "")?;
+    if !task_name.is_empty() {
+        let command: String = loop {
+            match prompt::ask(&format!("Command of {}:", task_name), "")? {
+                command if command.is_empty() => println!("The task needs a command"),
+                command => break command,
+            }
+        };
+        config_file.tasks.insert(task_name, ConfigFileTaskValue::String(command));
+    }
+
+    Ok(())
+}
+
+// Keeps the workspace connected, a config no parent includes is never discovered from the root
+fn register_with_parent_config(config_path: &Path, skip_confirmation: bool) -> Result<(), String> {
+    let directory = get_parent_directory(config_path)?;
+    let Some(parent_config_path) = config::find_parent_config_path(directory) else {
+        return Ok(());
+    };
+
+    let parent_config_file: ConfigFile = file::read_config_file(parent_config_path.clone())?;
+    let parent_directory: &Path = get_parent_directory(&parent_config_path)?;
+    let directory_variables = config::get_directory_variables(&parent_config_file.constants).map_err(|err| format!("Unable to resolve the constants of {:?}: {}", parent_config_path, err))?;
+    let parent_directories: Vec<ConfigFileDirectory> = config::interpolate_directories(&parent_config_file.directories, &directory_variables, &parent_config_path)?;
+    if config::includes_config_path(parent_directory, &parent_directories, config_path)? {
+        println!("Already included by the parent config: {:?}", parent_config_path);
+        return Ok(());
+    }
+
+    let relative_directory: PathBuf = directory.strip_prefix(parent_directory).map_err(|err| err.to_string())?.to_path_buf();
+    let relative_directory: &str = file::path_to_str(&relative_directory)?;
+
+    if !skip_confirmation && !prompt::confirm(&format!("Add {:?} to the directories of {:?}?", relative_directory, parent_config_path))? {
+        return Ok(());
+    }
+
+    let content: String = file::read_file_content(parent_config_path.clone())?;
+    let unable_to_register = format!("Unable to add {:?} to the directories of {:?}, add it manually", relative_directory, parent_config_path);
+    let content: String = file::add_config_directory(&content, relative_directory).ok_or(unable_to_register)?;
+
+    file::write_file_content(&parent_config_path, &content)?;
+    println!("Registered with the parent config: {:?}", parent_config_path);
+
+    Ok(())
+}
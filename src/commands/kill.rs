@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+use clap::Args;
+use crate::utils::{config, file, output, process, running};
+use crate::utils::config::{Config, ConfigIssues, TaskOptions};
+use crate::utils::file::ConfigFile;
+use crate::utils::output::Tone;
+use crate::utils::running::RunningTask;
+use crate::utils::exit;
+use crate::utils::exit::{ExitError, ExitKind};
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The running task to cancel, like app:test, packages/app#test or just test when only one config runs it")]
+    task: String,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+}
+
+// Cancels one task of a run in another shell, the run marks it cancelled and goes on with the other tasks
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { task, entry } = arguments;
+
+    let mut config_issues = ConfigIssues::new(true);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), false, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    let entry_directory: &Path = file::get_parent_directory(&entry_config_path)?;
+
+    let running_tasks: Vec<RunningTask> = running::list_running_tasks(entry_directory);
+    let matching_tasks: Vec<&RunningTask> = running_tasks
+        .iter()
+        .filter(|running_task| get_task_addresses(running_task, entry_directory, &configs).contains(task))
+        .collect();
+
+    if matching_tasks.is_empty() {
+        let running_keys: Vec<String> = running_tasks.iter().map(|running_task| format!("{} @ {:?}", running_task.key, running_task.directory)).collect();
+        return Err(ExitError::new(ExitKind::UNMATCHED, match running_keys.is_empty() {
+            true => format!("No task is running in {:?}", entry_directory),
+            false => format!("No running task matches \"{}\", running are:\n  -  {}", task, running_keys.join("\n  -  ")),
+        }));
+    }
+
+    for RunningTask { pid, process_group, key, directory, stop_signal, stop_grace_period, .. } in matching_tasks {
+        running::mark_cancelled(entry_directory, *pid)?;
+        process::stop_task(*pid, *process_group, &TaskOptions { stop_signal: stop_signal.clone(), stop_grace_period: *stop_grace_period, ..Default::default() })?;
+        output::line("CANCELLED", Tone::WARNING, &format!("{} @ {:?}", key, directory));
+    }
+
+    Ok(())
+}
+
+// The key alone, or prefixed by the name or relative directory of its config, like rask run addresses tasks
+fn get_task_addresses(running_task: &RunningTask, entry_directory: &Path, configs: &[Config]) -> Vec<String> {
+    let RunningTask { key, directory, .. } = running_task;
+    let mut config_references: Vec<String> = configs.iter().filter(|config| &config.dir_path == directory).map(|config| config.name.clone()).collect();
+    if let Ok(relative_directory) = directory.strip_prefix(entry_directory) {
+        config_references.push(match relative_directory.as_os_str().is_empty() {
+            true => ".".to_string(),
+            false => relative_directory.to_string_lossy().to_string(),
+        });
+    }
+
+    let mut addresses: Vec<String> = vec![key.clone()];
+    for config_reference in config_references {
+        addresses.push(format!("{}:{}", config_reference, key));
+        addresses.push(format!("{}#{}", config_reference, key));
+    }
+
+    addresses
+}
@@ -1,32 +1,74 @@
 use std::path::PathBuf;
 use clap::Args;
-use crate::utils::config;
-use crate::utils::config::{Config, ConfigTask};
-use crate::utils::file::ConfigFile;
+use crate::utils::{config, environment, index};
+use crate::utils::config::{CandidateOutcome, Config, ConfigTrace, DiscoveryTrace, PatternTrace, ConfigIssues, ConfigStructure, ConfigTask, SortableTask, SortableTasks, Task};
+use crate::utils::file::{ConfigFile, TaskEngine};
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
 
 #[derive(Args, Debug)]
 pub struct Arguments {
     #[arg(help = "The entry directory or rask.yaml file")]
-    entry: Option<String>
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, help = "Fail on unknown keys in rask.yaml files, instead of ignoring them")]
+    strict_config: bool,
+    #[arg(long, help = "Print the resolved environment each task receives, secret values are masked")]
+    env: bool,
+    #[arg(long, requires = "env", help = "Include the environment inherited from the current shell")]
+    inherited: bool,
+    #[arg(long, conflicts_with = "env", help = "Print per config which engine is used, which marker files were found and where each task comes from")]
+    engines: bool,
+    #[arg(long, value_delimiter = ',', value_parser = config::TASK_SOURCES, help = "Only list tasks from these comma separated sources, yaml or an engine like npm")]
+    engine: Option<Vec<String>>,
+    #[arg(long, conflicts_with_all = ["env", "engines"], help = "Print per config which directories patterns were expanded, which configs they found and why candidates were skipped")]
+    debug_discovery: bool,
 }
 
-pub fn execute (arguments: &Arguments) -> Result<(), String> {
-    let Arguments { entry } = arguments;
+pub fn execute (arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, require_config, strict_config, env, inherited, engine, engines, debug_discovery } = arguments;
 
-    // Resolve the entry path
-    let entry_config_path: PathBuf = config::resolve_config_path(&entry.clone().unwrap_or(".".to_string()))?;
+    if *debug_discovery {
+        return print_discovery_trace(&config::get_entry(entry));
+    }
+
+    // Broken child configs are skipped with a warning in lenient mode
+    let mut config_issues = ConfigIssues::new(true);
+
+    // Resolve the entry path and read the config files it includes
+    let (entry_config_path, mut config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
 
-    // Discover all config paths
-    let config_file_paths: Vec<PathBuf> = config::discover_config_paths(&entry_config_path)?;
+    if *strict_config {
+        config::validate_config_files(&config_files).map_err(exit::config)?;
+    }
 
-    // Parse config file content
-    let config_files: Vec<ConfigFile> = config::read_config_files(config_file_paths)?;
+    // Engines outside the listed sources are not read at all
+    if let Some(engine) = engine {
+        config::restrict_task_sources(&mut config_files, engine);
+    }
 
     // Parse config files
-    let configs: Vec<Config> = config::parse_config_files(config_files)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    config_issues.print_warnings();
+
+    // Keep the completion index fresh, it is a cache so failing to write it is fine.
+    // A listing of some sources only knows part of the tasks.
+    if engine.is_none() {
+        let _ = index::write_workspace_index(&entry_config_path, &configs);
+    }
+
+    if *env {
+        let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+        return print_task_environments(config_structure, inherited);
+    }
+
+    if *engines {
+        return print_config_engines(&configs).map_err(ExitError::from);
+    }
 
     // get all available tasks
-    let tasks: Vec<String> = get_config_tasks(&configs)?;
+    let tasks: Vec<String> = get_config_tasks(&configs, engine)?;
 
     println!("The following tasks are available:");
     for task in tasks {
@@ -36,17 +78,114 @@ pub fn execute (arguments: &Arguments) -> Result<(), String> {
     Ok(())
 }
 
-fn get_config_tasks(configs: &Vec<Config>) -> Result<Vec<String>, String> {
+fn get_config_tasks(configs: &[Config], engine: &Option<Vec<String>>) -> Result<Vec<String>, String> {
     let mut tasks: Vec<String> = vec![];
 
     for config in configs {
         for config_task in &config.tasks {
-            let ConfigTask { key, .. } = config_task;
-            if !tasks.contains(&key) {
+            let ConfigTask { key, task_type, .. } = config_task;
+            let is_included = engine.as_ref().is_none_or(|sources| sources.iter().any(|source| source == config::get_task_source(task_type)));
+            if is_included && !tasks.contains(key) {
                 tasks.push(key.clone());
             }
         }
     }
 
     Ok(tasks)
-}
\ No newline at end of file
+}
+fn print_config_engines(configs: &[Config]) -> Result<(), String> {
+    println!("The following engines are used:");
+    for config in configs {
+        let Config { name, tasks, task_engine, dir_path, .. } = config;
+
+        // Tasks grouped by their source, in order of appearance
+        let mut sources: Vec<(&str, Vec<&String>)> = vec![];
+        for ConfigTask { task_type, key, .. } in tasks {
+            let source = config::get_task_source(task_type);
+            match sources.iter_mut().find(|(existing_source, _)| *existing_source == source) {
+                Some((_, keys)) => keys.push(key),
+                None => sources.push((source, vec![key])),
+            }
+        }
+
+        let engine = format!("{:?}", task_engine).to_lowercase();
+        let detected_engines: Vec<&str> = sources.iter().map(|(source, _)| *source).filter(|source| *source != "yaml").collect();
+        let marker_files: Vec<&str> = config::find_marker_files(dir_path);
+
+        println!("  -  {} @ {:?}", name, dir_path);
+        match task_engine {
+            TaskEngine::AUTO => println!("       engine: {} (detected: {})", engine, join_or_none(&detected_engines)),
+            _ => println!("       engine: {}", engine),
+        }
+        println!("       markers: {}", join_or_none(&marker_files));
+        for (source, mut keys) in sources {
+            keys.sort();
+            println!("       {}: {}", source, keys.iter().map(|key| key.as_str()).collect::<Vec<&str>>().join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+fn join_or_none(values: &[&str]) -> String {
+    match values.is_empty() {
+        true => "none".to_string(),
+        false => values.join(", "),
+    }
+}
+
+fn print_task_environments(config_structure: ConfigStructure, inherited: &bool) -> Result<(), ExitError> {
+    // An empty, non-strict task name matches every task
+    let sortable_tasks: SortableTasks = config::resolve_sortable_task(&config_structure, "", &false).map_err(exit::config)?;
+
+    println!("The following environments are resolved:");
+    for sortable_task in sortable_tasks {
+        let SortableTask { task, .. } = sortable_task;
+        let Task { key, directory, env, options, .. } = task;
+
+        println!("  -  {} @ {:?}", key, directory);
+        for (name, value) in environment::resolve_task_environment(&env, &options, *inherited) {
+            println!("       {}={}", name, environment::mask_value(&name, &value));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_discovery_trace(entry: &str) -> Result<(), ExitError> {
+    let entry_config_path: PathBuf = config::resolve_config_path(entry).map_err(exit::config)?;
+    let mut discovery_trace = DiscoveryTrace::default();
+    config::trace_config_files(&entry_config_path, &mut ConfigIssues::new(true), &mut discovery_trace).map_err(exit::config)?;
+
+    println!("The following configs were discovered:");
+    for ConfigTrace { config_path, patterns, excludes, error } in discovery_trace.configs {
+        println!("  -  {:?}", config_path);
+        if let Some(error) = error {
+            println!("       skipped, failed to parse: {}", error);
+            continue;
+        }
+
+        if patterns.is_empty() {
+            println!("       no directories");
+        }
+        for PatternTrace { pattern, glob, candidates } in patterns {
+            println!("       {} -> {:?}", pattern, glob);
+            if candidates.is_empty() {
+                println!("         no matches");
+            }
+            for (candidate_path, outcome) in candidates {
+                match outcome {
+                    CandidateOutcome::FOUND => println!("         found {:?}", candidate_path),
+                    CandidateOutcome::EXCLUDED(exclude) => println!("         excluded {:?} by {}", candidate_path, exclude),
+                    CandidateOutcome::VISITED => println!("         already visited {:?}", candidate_path),
+                    CandidateOutcome::UNRESOLVED(error) => println!("         unresolved {:?}: {}", candidate_path, error),
+                }
+            }
+        }
+        if !excludes.is_empty() {
+            println!("       excludes: {}", excludes.join(", "));
+        }
+    }
+
+    Ok(())
+}
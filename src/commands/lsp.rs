@@ -0,0 +1,14 @@
+use clap::Args;
+use crate::utils::lsp;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(long, help = "Talk over stdin and stdout, the only transport and accepted for editors that always pass it")]
+    stdio: bool,
+}
+
+// Editors start this as the language server of rask.yaml files
+pub fn execute(_arguments: &Arguments) -> Result<(), ExitError> {
+    lsp::serve().map_err(ExitError::from)
+}
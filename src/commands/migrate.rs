@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, deprecation, file, output};
+use crate::utils::config::ConfigIssues;
+use crate::utils::deprecation::{DeprecatedKey, DEPRECATIONS};
+use crate::utils::file::ConfigFile;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when a config uses a deprecated field, instead of updating it")]
+    check: bool,
+}
+
+// Rewrites the deprecated fields of every config of the workspace, editing the yaml text so comments survive
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, check } = arguments;
+
+    // A config that does not parse has nothing to migrate, it is reported and skipped
+    let mut config_issues = ConfigIssues::new(true);
+    let entry_config_path: PathBuf = config::resolve_config_path(config::get_entry(entry).as_str()).map_err(exit::config)?;
+    let config_files: Vec<ConfigFile> = config::discover_config_files(&entry_config_path, &mut config_issues).map_err(exit::config)?;
+    config_issues.print_warnings();
+
+    // Documents of one file share it, configs of engines without a rask.yaml have no file to edit
+    let mut config_file_paths: Vec<PathBuf> = vec![];
+    for ConfigFile { __file_path: file_path, .. } in &config_files {
+        let config_file_path: PathBuf = file::get_document_file(file_path).unwrap_or(file_path.clone());
+        if config_file_path.is_file() && !config_file_paths.contains(&config_file_path) {
+            config_file_paths.push(config_file_path);
+        }
+    }
+
+    let mut migrated_count: usize = 0;
+    for config_file_path in &config_file_paths {
+        let content: String = file::read_file_content(config_file_path.clone())?;
+        let deprecated_keys: Vec<DeprecatedKey> = deprecation::find_deprecated_keys(&content, &DEPRECATIONS).map_err(|err| format!("Failed to parse {:?}: {}", config_file_path, err))?;
+        let Some(migrated_content) = deprecation::migrate_config_content(&content, &DEPRECATIONS).map_err(|err| format!("Failed to migrate {:?}: {}", config_file_path, err))? else {
+            continue;
+        };
+
+        migrated_count += 1;
+        match check {
+            true => println!("{:?} uses deprecated fields:", config_file_path),
+            false => {
+                file::write_file_content(config_file_path, &migrated_content)?;
+                println!("Migrated {:?}:", config_file_path);
+            },
+        }
+        for deprecated_key in &deprecated_keys {
+            println!("  -  {}", deprecated_key.describe_migration());
+        }
+    }
+
+    match (migrated_count, check) {
+        (0, _) => {
+            output::success(&format!("No deprecated fields in {} config files", config_file_paths.len()));
+            Ok(())
+        },
+        (_, true) => Err(exit::config(format!("{} config files use deprecated fields, run rask migrate to update them", migrated_count))),
+        (_, false) => {
+            output::success(&format!("Migrated {} config files", migrated_count));
+            Ok(())
+        },
+    }
+}
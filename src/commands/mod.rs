@@ -1,3 +1,21 @@
 pub mod run;
 pub mod list;
-pub mod init;
\ No newline at end of file
+pub mod init;
+pub mod dashboard;
+pub mod completions;
+pub mod resume;
+pub mod env;
+pub mod watch;
+pub mod dev;
+pub mod stats;
+pub mod export;
+pub mod version;
+pub mod help;
+pub mod validate;
+pub mod kill;
+pub mod lsp;
+pub mod plan;
+pub mod docs;
+pub mod clean;
+pub mod query;
+pub mod migrate;
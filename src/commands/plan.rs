@@ -0,0 +1,231 @@
+use std::io::{stdin, BufRead, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use clap::Args;
+use crate::utils::{affected, config, file, index, plan, schedule, shadowing, vcs};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, LevelHooks, SortableTasks};
+use crate::utils::shadowing::ShadowedTask;
+use crate::utils::file::ConfigFile;
+use crate::utils::plan::Plan;
+use crate::utils::schedule::Shard;
+use crate::utils::vcs::VcsKind;
+use crate::utils::exit;
+use crate::utils::exit::{ExitError, ExitKind};
+
+// The flags that decide what runs, rask run takes them as well
+#[derive(Args, Debug)]
+#[group(id = "planning")]
+pub struct Arguments {
+    #[arg(help = "Which task to run, address a single config like app#build, packages/app#build or app:build to only run that task there, defaults to the default_task of the entry config")]
+    pub task_name: Option<String>,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    pub entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    pub require_config: bool,
+    #[arg(long, help = "Fail on unknown keys in rask.yaml files, instead of ignoring them")]
+    pub strict_config: bool,
+    #[arg(long, help = "Skip child configs that fail to parse, instead of failing the run")]
+    pub lenient: bool,
+    #[arg(long, help = "enable strict command matching, defaults to checking if a command starts with a key")]
+    pub strict: bool,
+    #[arg(long, value_delimiter = ',', value_parser = config::TASK_SOURCES, help = "Only run tasks from these comma separated sources, yaml or an engine like npm")]
+    pub engine: Option<Vec<String>>,
+    #[arg(long, help = "Load variables from this env file on top of the config env, repeat it to layer several files")]
+    pub env_file: Vec<PathBuf>,
+    #[arg(long, help = "Run tasks that only the entry config defines once in every config, RASK_CONFIG_NAME, RASK_CONFIG_DIR and RASK_CONFIG_FILE tell where")]
+    pub propagate: bool,
+    #[arg(long, value_delimiter = ',', help = "Only run the tasks of configs containing one of these comma separated files, use - to read them from stdin")]
+    pub changed_files: Option<Vec<String>>,
+    #[arg(long, conflicts_with = "changed_files", help = "Only run the tasks of configs with files changed since this revision, uncommitted changes included")]
+    pub changed_since: Option<String>,
+    #[arg(long, value_enum, requires = "changed_since", default_value_t = VcsKind::AUTO, help = "The version control system to ask for the changed files")]
+    pub vcs: VcsKind,
+    #[arg(long, conflicts_with_all = ["task_name", "command"], help = "Run the task names listed in this file in one run, one per line, - reads them from stdin. Lines starting with # are comments")]
+    pub task_file: Option<String>,
+    #[arg(long, conflicts_with = "task_name", help = "Run a one-off shell command in every config as a task named command, - reads it from stdin")]
+    pub command: Option<String>,
+    #[arg(long, requires = "command", value_delimiter = ',', help = "Only run --command in the configs whose directory relative to the entry, or name, matches these comma separated globs")]
+    pub scope: Vec<String>,
+    #[arg(long, value_parser = schedule::parse_shard, help = "Only run one part of the tasks, like 1/3, for CI jobs that split a run. Tasks stay with the tasks they reference")]
+    pub shard: Option<Shard>,
+}
+
+// The tasks a run would execute, before anything runs
+pub struct ResolvedPlan {
+    pub entry_config_path: PathBuf,
+    pub task_name: String,
+    // Every task name of a task file, the task name lists them for display
+    pub task_names: Vec<String>,
+    pub strict: bool,
+    pub command: Option<String>,
+    pub sortable_tasks: SortableTasks,
+    pub level_hooks: LevelHooks,
+    // The job slots of parallel runs without --jobs
+    pub default_parallelism: Option<u64>,
+    // Read by the run, the plan only refers to them
+    pub env_files: Vec<PathBuf>,
+    // The run warns about them, a plan file was checked when it was planned
+    pub shadowed_tasks: Vec<ShadowedTask>,
+}
+
+// Prints the plan as JSON, `rask run --plan` executes it later without looking at the configs again
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let ResolvedPlan { entry_config_path, task_name, sortable_tasks, level_hooks, default_parallelism, env_files, .. } = resolve_plan(arguments)?;
+
+    let plan: Plan = plan::create_plan(file::get_parent_directory(&entry_config_path)?, &task_name, &env_files, &level_hooks, default_parallelism, &sortable_tasks);
+    println!("{}", serde_json::to_string_pretty(&plan).map_err(|err| format!("Failed to serialize the plan: {}", err))?);
+
+    Ok(())
+}
+
+pub fn resolve_plan(arguments: &Arguments) -> Result<ResolvedPlan, ExitError> {
+    let Arguments { task_name, entry, require_config, strict_config, lenient, strict, engine, env_file, propagate, changed_files, changed_since, vcs, task_file, command, scope, shard } = arguments;
+
+    // Broken child configs are skipped with a warning in lenient mode
+    let mut config_issues = ConfigIssues::new(*lenient);
+
+    // Resolve the entry path and read the config files it includes
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+
+    if *strict_config {
+        config::validate_config_files(&config_files).map_err(exit::config)?;
+    }
+
+    // Parse config files
+    let mut configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    config_issues.print_warnings();
+
+    // Keep the completion index fresh, it is a cache so failing to write it is fine.
+    // Single packages skip it, completions read their engine file instead and the run starts a little sooner
+    let is_single_package: bool = config::is_single_package(&entry_config_path, &configs);
+    if !is_single_package {
+        let _ = index::write_workspace_index(&entry_config_path, &configs);
+    }
+
+    // A one-off command becomes a task of the configs in scope, it is addressed exactly
+    let command: Option<String> = command.as_deref().map(read_command).transpose()?;
+    if let Some(command) = &command {
+        config::inject_command_task(&mut configs, &entry_config_path, command, scope).map_err(exit::config)?;
+    }
+    let strict: bool = *strict || command.is_some();
+
+    let entry_config: &Config = config::get_entry_config(&entry_config_path, &configs).map_err(exit::config)?;
+
+    // Fall back on the default task when no task is given
+    let task_names: Vec<String> = match (&command, task_file) {
+        (Some(_), _) => vec![config::COMMAND_TASK_KEY.to_string()],
+        (None, Some(task_file)) => read_task_file(task_file)?,
+        (None, None) => vec![config::resolve_task_name(task_name, entry_config).map_err(exit::config)?],
+    };
+    let task_name: String = task_names.join(", ");
+
+    let level_hooks: LevelHooks = config::resolve_level_hooks(entry_config).map_err(exit::config)?;
+    let default_parallelism: Option<u64> = entry_config.default_parallelism;
+
+    let affected_directories: Option<Vec<PathBuf>> = match (changed_files, changed_since) {
+        (Some(changed_files), _) => Some(affected::find_affected_directories(&configs, &affected::read_changed_files(changed_files)?)),
+        (_, Some(changed_since)) => Some(affected::find_affected_directories(&configs, &vcs::find_changed_files(*vcs, &entry_config.dir_path, changed_since)?)),
+        (None, None) => None,
+    };
+
+    // Resolve dependencies based on the directory structure
+    // (In the future this will be configurable based on a dependency config field)
+    let config_structure: ConfigStructure = match is_single_package {
+        true => config::create_single_package_structure(configs),
+        false => config::resolve_config_structure(&entry_config_path, configs),
+    }.map_err(exit::config)?;
+    let shadowed_tasks: Vec<ShadowedTask> = shadowing::find_shadowed_tasks(&config_structure);
+
+    // Gather the tasks from the config, the tasks of several task names share one schedule
+    let mut sortable_tasks: SortableTasks = vec![];
+    for task_name in &task_names {
+        let named_tasks: SortableTasks = config::resolve_sortable_task(&config_structure, task_name, &strict).map_err(exit::config)?;
+        if named_tasks.is_empty() && task_names.len() > 1 {
+            return Err(ExitError::new(ExitKind::UNMATCHED, format!("No task matches \"{}\" of the task file", task_name)));
+        }
+
+        config::merge_sortable_tasks(&mut sortable_tasks, named_tasks);
+    }
+    let sortable_tasks: SortableTasks = match propagate {
+        true => config::propagate_entry_tasks(&config_structure, sortable_tasks),
+        false => sortable_tasks,
+    };
+
+    // Dependencies of the remaining tasks still run, whatever their source
+    let sortable_tasks: SortableTasks = match engine {
+        Some(engine) => config::filter_task_sources(sortable_tasks, engine),
+        None => sortable_tasks,
+    };
+
+    // A task name that matches nothing is most likely a typo, unlike changes that affect no config
+    if sortable_tasks.is_empty() {
+        return Err(ExitError::new(ExitKind::UNMATCHED, format!("No task matches \"{}\"", task_name)));
+    }
+
+    // Leave out the configs without changes, for CI systems that know what changed
+    let sortable_tasks: SortableTasks = match affected_directories {
+        Some(affected_directories) => affected::filter_affected_tasks(sortable_tasks, &affected_directories),
+        None => sortable_tasks,
+    };
+
+    // Every CI job resolves the same tasks and keeps its own part of them
+    let sortable_tasks: SortableTasks = match shard {
+        Some(shard) => {
+            let groups: Vec<Vec<usize>> = config::group_ordered_tasks(&sortable_tasks, &config_structure);
+            schedule::select_shard(sortable_tasks, groups, file::get_parent_directory(&entry_config_path)?, *shard)
+        },
+        None => sortable_tasks,
+    };
+
+    let shadowed_tasks: Vec<ShadowedTask> = shadowing::filter_scheduled_tasks(shadowed_tasks, &sortable_tasks);
+
+    Ok(ResolvedPlan { entry_config_path, task_name, task_names, strict, command, sortable_tasks, level_hooks, default_parallelism, env_files: env_file.clone(), shadowed_tasks })
+}
+
+// The tasks of a plan file, placed in the checkout of the entry instead of the one it was planned in
+pub fn read_plan_file(plan_path: &Path, entry: &Option<String>) -> Result<ResolvedPlan, ExitError> {
+    let entry_config_path: PathBuf = config::resolve_config_path(config::get_entry(entry).as_str()).map_err(exit::config)?;
+    let plan: Plan = plan::read_plan(plan_path)?;
+    let (sortable_tasks, level_hooks, env_files) = plan.resolve(file::get_parent_directory(&entry_config_path)?);
+
+    Ok(ResolvedPlan { entry_config_path, task_names: vec![plan.task_name.clone()], task_name: plan.task_name, strict: true, command: None, sortable_tasks, level_hooks, default_parallelism: plan.default_parallelism, env_files, shadowed_tasks: vec![] })
+}
+
+fn read_command(command: &str) -> Result<String, String> {
+    if command != "-" {
+        return Ok(command.to_string());
+    }
+
+    let mut stdin_command = String::new();
+    stdin().read_to_string(&mut stdin_command).map_err(|err| format!("Failed to read the command from stdin: {}", err))?;
+
+    match stdin_command.trim() {
+        "" => Err("The command read from stdin is empty".to_string()),
+        stdin_command => Ok(stdin_command.to_string()),
+    }
+}
+
+// One task name or address per line, for CI matrices and scripted selections that outgrow a command line
+fn read_task_file(task_file: &str) -> Result<Vec<String>, String> {
+    let lines: Vec<String> = match task_file {
+        "-" => {
+            if stdin().is_terminal() {
+                return Err("Expected the task names on stdin, pipe them in or pass a task file".to_string());
+            }
+
+            stdin().lock().lines().collect::<Result<Vec<String>, _>>().map_err(|err| format!("Failed to read the task names from stdin: {}", err))?
+        },
+        _ => file::read_file_content(PathBuf::from(task_file))?.lines().map(str::to_string).collect(),
+    };
+
+    let task_names: Vec<String> = lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+
+    match task_names.is_empty() {
+        true => Err(format!("The task file {} lists no task names", task_file)),
+        false => Ok(task_names),
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, query};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure};
+use crate::utils::file::ConfigFile;
+use crate::utils::query::{Query, QueryFormat, QueryResult, TaskGraph};
+use crate::utils::exit;
+use crate::utils::exit::{ExitError, ExitKind};
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The query, like deps(api#build), rdeps(libs/core#build, 1) or *#test")]
+    expression: String,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, value_enum, default_value_t = QueryFormat::NAMES, help = "Print the names of the tasks, or the tasks with their dependencies as JSON")]
+    format: QueryFormat,
+}
+
+// Answers questions about the task graph, without running anything
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { expression, entry, require_config, format } = arguments;
+    let query: Query = query::parse_query(expression)?;
+
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+
+    let task_graph: TaskGraph = query::build_task_graph(&config_structure);
+    let positions: Vec<usize> = query::run_query(&task_graph, &query)?;
+    if positions.is_empty() {
+        return Err(ExitError::new(ExitKind::UNMATCHED, format!("No task matches \"{}\"", expression)));
+    }
+
+    let query_results: Vec<QueryResult> = query::create_query_results(&task_graph, &positions);
+    match format {
+        QueryFormat::NAMES => query_results.iter().for_each(|query_result| println!("{}", query_result.name)),
+        QueryFormat::JSON => println!("{}", serde_json::to_string_pretty(&query_results).map_err(|err| format!("Failed to serialize the query results: {}", err))?),
+    }
+
+    Ok(())
+}
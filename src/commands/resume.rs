@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::env::current_dir;
+use std::path::PathBuf;
+use std::time::Instant;
+use clap::Args;
+use crate::commands::run;
+use crate::commands::run::RunSettings;
+use crate::utils::{ci, config, cooldown, environment, file, history, install, plan, process, prompt, run_state, running, tmp};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, LevelHooks, SortableTask, SortableTasks, Task};
+use crate::utils::environment::EnvFile;
+use crate::utils::file::ConfigFile;
+use crate::utils::output::Verbosity;
+use crate::utils::report::ReportFormat;
+use crate::utils::run_state::{PlannedTask, RunState};
+use crate::utils::writer::OutputStyle;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The id of the run to resume, as printed when the run started")]
+    run_id: String,
+    #[arg(long, short, help = "Run the tasks marked with confirm without asking")]
+    yes: bool,
+}
+
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { run_id, yes } = arguments;
+
+    let start_time = Instant::now();
+    let working_directory: PathBuf = current_dir().map_err(|err| format!("Failed to get the current directory: {}", err))?;
+    let run_state: RunState = run_state::read_run_state(&working_directory, run_id)?;
+    let RunState { entry, parallel, jobs, env_files, plan, .. } = &run_state;
+    let entry_directory: PathBuf = file::get_parent_directory(entry)?.to_path_buf();
+
+    // The plan is resolved again from the configs, so tasks pick up changed commands. Plan files run as they were written
+    let (sortable_tasks, mut level_hooks): (SortableTasks, LevelHooks) = match plan {
+        Some(plan_path) => {
+            let (sortable_tasks, level_hooks, _) = plan::read_plan(plan_path)?.resolve(&entry_directory);
+            (sortable_tasks, level_hooks)
+        },
+        None => resolve_tasks(&run_state)?,
+    };
+
+    for PlannedTask { key, directory, succeeded, .. } in &run_state.tasks {
+        let is_resolved = sortable_tasks.iter().any(|SortableTask { task, .. }| &task.key == key && &task.directory == directory);
+        if !succeeded && !is_resolved {
+            return Err(format!("Task {} @ {:?} is no longer part of the configs, unable to resume run {}", key, directory, run_id).into());
+        }
+    }
+
+    // Tasks that already succeeded are skipped, the rest runs in its original order
+    let pending_tasks: SortableTasks = sortable_tasks
+        .into_iter()
+        .filter(|sortable_task| run_state.is_pending(sortable_task))
+        .collect();
+
+    let env_files: Vec<EnvFile> = env_files.iter().map(|path| environment::read_env_file(path)).collect::<Result<Vec<EnvFile>, String>>()?;
+    let pending_tasks: SortableTasks = run::apply_env_files(pending_tasks, &mut level_hooks, &env_files);
+
+    let tasks: Vec<&Task> = pending_tasks
+        .iter()
+        .flat_map(|sortable_task| sortable_task.task.dependencies.iter().chain([&sortable_task.task]))
+        .collect();
+    process::ensure_shells(&tasks)?;
+    process::ensure_privileges(&tasks)?;
+    prompt::confirm_tasks(&tasks, *yes)?;
+
+    let parallel: bool = *parallel;
+    let output_style = OutputStyle { prefix: parallel, timestamps: false, fold: ci::detect() };
+    let run_settings = RunSettings {
+        parallel,
+        jobs: *jobs,
+        sequential_within_config: false,
+        level_hooks,
+        deadline: None,
+        run_state: Some(RefCell::new(run_state)),
+        history: Some(RefCell::new(history::read_history(&entry_directory))),
+        report: None,
+        report_format: ReportFormat::JSON,
+        report_url: None,
+        verbosity: Verbosity::NORMAL,
+        output_style,
+        schedule_seed: None,
+        deduplicate: true,
+    };
+
+    install::ensure_installed(&tasks, run_settings.verbosity)?;
+
+    // Registers the task processes so `rask kill` finds them, a run goes on without it
+    let _ = running::init(&entry_directory);
+    let _ = tmp::init(&entry_directory, run_id);
+    cooldown::init(&entry_directory, false);
+
+    let result = run::run_planned_tasks(&pending_tasks, &run_settings, start_time);
+    tmp::finish();
+
+    result
+}
+
+fn resolve_tasks(run_state: &RunState) -> Result<(SortableTasks, LevelHooks), ExitError> {
+    let RunState { entry, task_name, task_names, strict, propagate, command, scope, .. } = run_state;
+
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&entry.to_string_lossy(), false, &mut config_issues).map_err(exit::config)?;
+    let mut configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    if let Some(command) = command {
+        config::inject_command_task(&mut configs, &entry_config_path, command, scope).map_err(exit::config)?;
+    }
+    let entry_config: &Config = config::get_entry_config(&entry_config_path, &configs).map_err(exit::config)?;
+    let level_hooks: LevelHooks = config::resolve_level_hooks(entry_config).map_err(exit::config)?;
+
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+    // Runs from before task files only know their task name
+    let task_names: Vec<String> = match task_names.is_empty() {
+        true => vec![task_name.clone()],
+        false => task_names.clone(),
+    };
+    let mut sortable_tasks: SortableTasks = vec![];
+    for task_name in &task_names {
+        config::merge_sortable_tasks(&mut sortable_tasks, config::resolve_sortable_task(&config_structure, task_name, strict).map_err(exit::config)?);
+    }
+    let sortable_tasks: SortableTasks = match propagate {
+        true => config::propagate_entry_tasks(&config_structure, sortable_tasks),
+        false => sortable_tasks,
+    };
+
+    Ok((sortable_tasks, level_hooks))
+}
@@ -1,146 +1,686 @@
+use std::cell::RefCell;
 use std::fmt::Debug;
+use std::fs::canonicalize;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::thread;
-use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use clap::Args;
-use crate::utils::config;
-use crate::utils::config::{Config, ConfigStructure, get_ordered_tasks, SortableTask, SortableTasks, Task, TaskExit};
-use crate::utils::file::ConfigFile;
+use crate::commands::plan;
+use crate::commands::plan::ResolvedPlan;
+use crate::utils::{ci, config, cooldown, environment, executor, file, history, install, output, package_manager, process, prompt, report, run_state, running, schedule, time, tmp};
+use crate::utils::run_state::RunState;
+use crate::utils::executor::{DependencyMemo, Executor, ResultsMemo};
+use crate::utils::output::{Stream, Tone, Verbosity};
+use crate::utils::report::{ReportFormat, ResultMatrix, TaskResult, TaskResults, TaskStatus};
+use crate::utils::config::{get_ordered_tasks, LevelHooks, SortableTask, SortableTasks, Task, TaskExit};
+use crate::utils::environment::{EnvFile, SourcedVariable};
+use crate::utils::history::DurationHistory;
+use crate::utils::writer::OutputStyle;
+use crate::utils::exit::{ExitError, ExitKind};
 
 #[derive(Args, Debug)]
 pub struct Arguments {
-    #[arg(help = "Which task to run")]
-    task_name: String,
-    #[arg(long, help = "The entry directory or rask.yaml file")]
-    entry: Option<String>,
+    // What runs, shared with rask plan
+    #[command(flatten)]
+    planning: plan::Arguments,
+    #[arg(long, conflicts_with_all = ["task_name", "require_config", "strict_config", "lenient", "strict", "engine", "env_file", "propagate", "changed_files", "changed_since", "task_file", "command", "scope", "shard"], help = "Run the tasks of a plan written by rask plan exactly as planned, instead of resolving them from the configs")]
+    plan: Option<PathBuf>,
     #[arg(long, help = "enable strict command matching, defaults to checking if a command starts with a key")]
     parallel: bool,
-    #[arg(long, help = "enable strict command matching, defaults to checking if a command starts with a key")]
-    strict: bool,
+    #[arg(long, short, requires = "parallel", value_parser = clap::value_parser!(u64).range(1..), help = "The maximum amount of tasks running at the same time in parallel mode")]
+    jobs: Option<u64>,
+    #[arg(long, requires = "parallel", help = "Never run tasks of the same config at the same time, like every config sets serial: true")]
+    sequential_within_config: bool,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help = "Repeat the task the given amount of times and report the failure rate")]
+    repeat: Option<u64>,
+    #[arg(long, help = "Keep repeating the task until it fails, bounded by --repeat when given")]
+    repeat_until_failure: bool,
+    #[arg(long, help = "The seed of the first repetition, exposed as RASK_SEED, defaults to the current time")]
+    seed: Option<u64>,
+    #[arg(long, conflicts_with = "summary_only", help = "Hide the output of tasks, only printing which commands run and the output of tasks that fail")]
+    quiet: bool,
+    #[arg(long, help = "Print nothing until the run completes, then a table of the task results with their CPU time and peak memory, and the output of tasks that fail")]
+    summary_only: bool,
+    #[arg(long, help = "Start every output line with the task it comes from, always on in parallel mode")]
+    prefix_output: bool,
+    #[arg(long, help = "Start every output line with the time it was printed at")]
+    timestamps: bool,
+    #[arg(long, help = "Stream the output of tasks in CI as well, instead of folding the output of every command into a collapsible section")]
+    no_ci_groups: bool,
+    #[arg(long, help = "Ring the terminal bell once the run completes")]
+    bell: bool,
+    #[arg(long, help = "A command to invoke once the run completes, the outcome is exposed as RASK_* env")]
+    on_complete: Option<String>,
+    #[arg(long, value_parser = time::parse_duration, help = "Stop the run once it takes longer than the given duration, e.g. 30m")]
+    max_duration: Option<Duration>,
+    #[arg(long, help = "Write a JSON report of the task results to the given file, including their CPU time and peak memory and the last output lines of tasks that did not succeed")]
+    report: Option<PathBuf>,
+    #[arg(long, value_enum, requires = "report", default_value_t = ReportFormat::JSON, help = "The format of the report, markdown renders a matrix of configs and tasks for pull request comments")]
+    report_format: ReportFormat,
+    #[arg(long, help = "POST the JSON report to this URL once the run completes, retried with backoff, RASK_REPORT_TOKEN is sent as a bearer token")]
+    report_url: Option<String>,
+    #[arg(long, help = "Print the final environment of every task and where each variable comes from, instead of running")]
+    print_env: bool,
+    #[arg(long, short, help = "Run the tasks marked with confirm without asking")]
+    yes: bool,
+    #[arg(long, help = "Run tasks whose commands use ${name} placeholders nothing sets, instead of failing before the run starts")]
+    allow_unresolved: bool,
+    #[arg(long, help = "Start the tasks of a level one config at a time in turn, instead of in the order the configs were found")]
+    round_robin: bool,
+    #[arg(long, requires = "round_robin", help = "The seed of the config order of --round-robin, printed when the run starts, defaults to the current time")]
+    schedule_seed: Option<u64>,
+    #[arg(long, help = "Run every task, instead of reusing the result of an earlier task in the run with the same command, directory and env")]
+    no_deduplicate: bool,
+    #[arg(long, help = "Run tasks within their cooldown, instead of skipping them")]
+    force: bool,
+}
+
+pub struct RunSettings {
+    pub parallel: bool,
+    // Limits the tasks running at the same time in parallel mode, unlimited when none
+    pub jobs: Option<usize>,
+    pub sequential_within_config: bool,
+    pub level_hooks: LevelHooks,
+    pub deadline: Option<Instant>,
+    // Tracks the progress of single runs so they can be resumed
+    pub run_state: Option<RefCell<RunState>>,
+    // Durations of previous runs, to start the longest tasks first and estimate how long a run takes
+    pub history: Option<RefCell<DurationHistory>>,
+    // Output is only captured when there is a report to embed it in, or when it is not streamed
+    pub report: Option<PathBuf>,
+    pub report_format: ReportFormat,
+    pub report_url: Option<String>,
+    pub verbosity: Verbosity,
+    pub output_style: OutputStyle,
+    // Interleaves the configs of every level with this seed, see --round-robin
+    pub schedule_seed: Option<u64>,
+    // Tasks with the same command, directory and env as an earlier task of the run reuse its result
+    pub deduplicate: bool,
 }
 
-pub fn execute (arguments: &Arguments) -> Result<(), String> {
-    let Arguments { entry, task_name, parallel, strict } = arguments;
+pub fn execute (arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { planning, plan, parallel, jobs, sequential_within_config, repeat, repeat_until_failure, seed, quiet, summary_only, prefix_output, timestamps, no_ci_groups, bell, on_complete, max_duration, report, report_format, report_url, print_env, yes, allow_unresolved, round_robin, schedule_seed, no_deduplicate, force } = arguments;
 
     // Start the timer
     let start_time = Instant::now();
 
-    // Resolve the entry path
-    let entry_config_path: PathBuf = config::resolve_config_path(&entry.clone().unwrap_or(".".to_string()))?;
+    // A plan runs exactly as written, placed in the checkout of the entry
+    let ResolvedPlan { entry_config_path, task_name, task_names, strict, command, sortable_tasks, mut level_hooks, default_parallelism, env_files, shadowed_tasks } = match plan {
+        Some(plan_path) => plan::read_plan_file(plan_path, &planning.entry)?,
+        None => plan::resolve_plan(planning)?,
+    };
+
+    let env_files: Vec<EnvFile> = env_files.iter().map(|path| environment::read_env_file(path)).collect::<Result<Vec<EnvFile>, String>>()?;
+    if *print_env {
+        print_task_environments(&sortable_tasks, &env_files);
+        return Ok(());
+    }
+
+    let sortable_tasks: SortableTasks = apply_env_files(sortable_tasks, &mut level_hooks, &env_files);
+    let verbosity: Verbosity = match (quiet, summary_only) {
+        (_, true) => Verbosity::SUMMARY,
+        (true, _) => Verbosity::QUIET,
+        _ => Verbosity::NORMAL,
+    };
+
+    // Nothing but the summary is printed with --summary-only, warnings included
+    if verbosity != Verbosity::SUMMARY {
+        print_shared_tasks(&sortable_tasks);
+        for shadowed_task in &shadowed_tasks {
+            output::warning(&shadowed_task.describe());
+        }
+    }
+
+    let tasks: Vec<&Task> = sortable_tasks
+        .iter()
+        .flat_map(|sortable_task| sortable_task.task.dependencies.iter().chain([&sortable_task.task]))
+        .collect();
+    process::ensure_shells(&tasks)?;
+    if !*allow_unresolved {
+        environment::ensure_resolved_variables(&tasks)?;
+    }
+    process::ensure_privileges(&tasks)?;
+    prompt::confirm_tasks(&tasks, *yes)?;
+
+    // The entry config can limit parallel runs that do not pass --jobs
+    let jobs: Option<usize> = jobs.or(default_parallelism).map(|jobs| jobs as usize);
+
+    // Repeated runs start over on every iteration, there is nothing to resume
+    let is_repeated = repeat.is_some() || *repeat_until_failure;
+    let run_state: Option<RunState> = match is_repeated {
+        true => None,
+        false => Some(RunState {
+            task_names,
+            jobs,
+            propagate: planning.propagate,
+            env_files: env_files.iter().map(|env_file| env_file.path.clone()).collect(),
+            command: command.clone(),
+            scope: planning.scope.clone(),
+            plan: plan.as_ref().map(|plan_path| canonicalize(plan_path).unwrap_or(plan_path.clone())),
+            ..RunState::new(&entry_config_path, &task_name, strict, *parallel, &sortable_tasks)
+        }),
+    };
+
+    let run_settings = RunSettings {
+        parallel: *parallel,
+        jobs,
+        sequential_within_config: *sequential_within_config,
+        level_hooks,
+        deadline: max_duration.map(|max_duration| start_time + max_duration),
+        run_state: run_state.map(RefCell::new),
+        history: Some(RefCell::new(history::read_history(file::get_parent_directory(&entry_config_path)?))),
+        report: report.clone(),
+        report_format: *report_format,
+        report_url: report_url.clone(),
+        verbosity,
+        output_style: OutputStyle {
+            prefix: *prefix_output || *parallel,
+            timestamps: *timestamps,
+            fold: ci::detect().filter(|_| !*no_ci_groups),
+        },
+        schedule_seed: round_robin.then(|| schedule_seed.unwrap_or_else(generate_seed)),
+        deduplicate: !*no_deduplicate,
+    };
+
+    if verbosity != Verbosity::SUMMARY {
+        if let Some(schedule_seed) = run_settings.schedule_seed {
+            output::line("SCHEDULE", Tone::INFO, &format!("Round robin across configs, reproduce the order with --schedule-seed {}", schedule_seed));
+        }
+
+        for mismatch in package_manager::find_version_mismatches(&tasks) {
+            output::warning(&mismatch);
+        }
+    }
+    install::ensure_installed(&tasks, run_settings.verbosity)?;
+
+    // Registers the task processes so `rask kill` finds them, a run goes on without it
+    let _ = running::init(file::get_parent_directory(&entry_config_path)?);
+    // Tasks run without a scratch directory when it can not be created
+    let run_id: String = run_settings.run_state.as_ref().map(|run_state| run_state.borrow().id.clone()).unwrap_or_else(run_state::generate_run_id);
+    let _ = tmp::init(file::get_parent_directory(&entry_config_path)?, &run_id);
+    cooldown::init(file::get_parent_directory(&entry_config_path)?, *force);
+
+    let result: Result<(), ExitError> = match is_repeated {
+        true => run_repeated_sortable_tasks(&sortable_tasks, &run_settings, repeat, repeat_until_failure, seed),
+        false => run_planned_tasks(&sortable_tasks, &run_settings, start_time),
+    };
+
+    tmp::finish();
+    notify_completion(&task_name, &result, start_time, bell, on_complete);
+
+    // Tasks that failed once the budget was spent were stopped by it, unless rask was interrupted as well
+    result.map_err(|err| match (max_duration, err.kind) {
+        (Some(max_duration), kind) if kind != ExitKind::TIMEOUT && !matches!(kind, ExitKind::INTERRUPTED(_)) && is_budget_exceeded(&run_settings.deadline) => {
+            ExitError::new(ExitKind::TIMEOUT, format!("{}, the run exceeded its budget of {} seconds", err.message, time::format_seconds(*max_duration)))
+        },
+        _ => err,
+    })
+}
+
+// Configs included by several parents run their tasks once, this shows through which parents
+fn print_shared_tasks(sortable_tasks: &SortableTasks) {
+    for SortableTask { task, provenance, .. } in sortable_tasks {
+        if provenance.len() < 2 {
+            continue;
+        }
 
-    // Discover all config paths
-    let config_file_paths: Vec<PathBuf> = config::discover_config_paths(&entry_config_path)?;
+        let chains: Vec<String> = provenance
+            .iter()
+            .map(|config_chain| config_chain.iter().map(|directory| format!("{:?}", directory)).collect::<Vec<String>>().join(" > "))
+            .collect();
+        output::line("SHARED", Tone::MUTED, &format!("{} @ {:?} runs once, it is included through {}", task.key, task.directory, chains.join(" and ")));
+    }
+}
 
-    // Parse config file content
-    let config_files: Vec<ConfigFile> = config::read_config_files(config_file_paths)?;
+// Env files apply to the hooks as well, they run in the same environment as the tasks
+pub fn apply_env_files(sortable_tasks: SortableTasks, level_hooks: &mut LevelHooks, env_files: &[EnvFile]) -> SortableTasks {
+    for hook in [&mut level_hooks.before, &mut level_hooks.after].into_iter().flatten() {
+        environment::apply_env_files(hook, env_files);
+    }
 
-    // Parse config files
-    let configs: Vec<Config> = config::parse_config_files(config_files)?;
+    sortable_tasks
+        .into_iter()
+        .map(|mut sortable_task| {
+            environment::apply_env_files(&mut sortable_task.task, env_files);
+            sortable_task
+        })
+        .collect()
+}
 
-    // Resolve dependencies based on the directory structure
-    // (In the future this will be configurable based on a dependency config field)
-    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs)?;
+fn print_task_environments(sortable_tasks: &SortableTasks, env_files: &[EnvFile]) {
+    println!("The following environments are resolved:");
+    for SortableTask { task, .. } in sortable_tasks {
+        println!("  -  {} @ {:?}", task.key, task.directory);
+        for (name, SourcedVariable { value, source, overrides }) in environment::resolve_sourced_environment(task, env_files) {
+            let origin: String = match overrides.is_empty() {
+                true => source,
+                false => format!("{}, overrides {}", source, overrides.join(" and ")),
+            };
+            println!("       {}={}  {}", name, environment::mask_value(&name, &value), output::paint(&format!("({})", origin), Tone::MUTED, Stream::STDOUT));
+        }
+    }
+}
 
-    // Gather the tasks from the config
-    let sortable_tasks: SortableTasks = config::resolve_sortable_task(config_structure, task_name, strict)?;
+fn is_budget_exceeded(deadline: &Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
 
+// Runs the tasks once, keeping the run state up to date so a failed or killed run can be resumed
+pub fn run_planned_tasks(sortable_tasks: &SortableTasks, run_settings: &RunSettings, start_time: Instant) -> Result<(), ExitError> {
+    if let Some(run_state) = &run_settings.run_state {
+        let run_state = run_state.borrow();
+        if run_settings.verbosity != Verbosity::SUMMARY {
+            output::line("RUN", Tone::INFO, &run_state.id);
+        }
+        // The run state is a convenience, the run itself should not fail on it
+        if let Err(err) = run_state::write_run_state(&run_state) {
+            output::warning(&format!("Failed to save the run state: {}", err));
+        }
+    }
+
+    let result = run_sortable_tasks_once(sortable_tasks, run_settings, start_time);
+
+    if let Some(run_state) = &run_settings.run_state {
+        let run_state = run_state.borrow();
+        match &result {
+            Ok(_) => { let _ = run_state::remove_run_state(&run_state); },
+            Err(_) => output::line("RUN", Tone::INFO, &format!("Continue where this run stopped with `rask resume {}`", run_state.id)),
+        }
+    }
+
+    result
+}
+
+fn record_task_result(run_settings: &RunSettings, task_result: &TaskResult) {
+    if let Some(run_state) = &run_settings.run_state {
+        let mut run_state = run_state.borrow_mut();
+        run_state.record(task_result);
+        if let Err(err) = run_state::write_run_state(&run_state) {
+            output::warning(&format!("Failed to save the run state: {}", err));
+        }
+    }
+}
+
+fn run_sortable_tasks_once(sortable_tasks: &SortableTasks, run_settings: &RunSettings, start_time: Instant) -> Result<(), ExitError> {
     // Run the commands, one by one
     // > In the future this is configurable on the rask level and maybe on the config file level
     // > Initially it fails the whole command if one task fails, but will also be configurable in the future
-    let task_exit: TaskExit = run_sortable_tasks(&sortable_tasks, parallel)?;
+    let task_results: TaskResults = run_sortable_tasks(sortable_tasks, run_settings)?;
+    if let Some(history) = &run_settings.history {
+        let mut history = history.borrow_mut();
+        history.record(&task_results);
+        // Like the run state, the history is a convenience the run should not fail on
+        if let Err(err) = history::write_history(&history) {
+            output::warning(&format!("Failed to save the task durations: {}", err));
+        }
+    }
+    let task_exit: TaskExit = match report::has_failures(&task_results) {
+        true => TaskExit::FAILURE,
+        false => TaskExit::SUCCESS,
+    };
 
-    let task_amount = sortable_tasks.len();
+    let task_amount = task_results.len();
     let execution_time = start_time.elapsed().as_secs_f32();
     let formatted_execution_time = (execution_time * 100.0).round() / 100.0;
 
+    if let Some(report_path) = &run_settings.report {
+        if let Err(err) = report::write_report(report_path, run_settings.report_format, &task_results, execution_time) {
+            output::warning(&format!("Failed to write the report to {:?}: {}", report_path, err));
+        }
+    }
+
+    if let Some(report_url) = &run_settings.report_url {
+        if let Err(err) = report::upload_json_report(report_url, &task_results, execution_time) {
+            output::warning(&format!("Failed to upload the report to {}: {}", report_url, err));
+        }
+    }
+
+    if run_settings.verbosity == Verbosity::SUMMARY {
+        print_summary_table(&task_results);
+    }
+
+    let result_matrix: ResultMatrix = report::build_result_matrix(&task_results);
+    if result_matrix.is_multi_task() {
+        print_result_matrix(&result_matrix);
+    }
+
+    // Streamed output was already seen, captured output is shown for the tasks that need looking into
+    if run_settings.verbosity != Verbosity::NORMAL {
+        for TaskResult { key, directory, output, .. } in task_results.iter().filter(|task_result| !task_result.output.is_empty()) {
+            output::captured_output(&format!("{} @ {:?}", key, directory), output);
+        }
+    }
+
+    for TaskResult { key, directory, error, .. } in report::get_results_with_status(&task_results, TaskStatus::ALLOWED) {
+        output::warning(&format!("Allowed failure of {} @ {:?}: {}", key, directory, error.unwrap_or_default()));
+    }
+
+    for TaskResult { key, directory, .. } in report::get_results_with_status(&task_results, TaskStatus::TIMEOUT) {
+        output::warning(&format!("Terminated {} @ {:?}, the time budget was exceeded", key, directory));
+    }
+
+    for TaskResult { key, directory, .. } in report::get_results_with_status(&task_results, TaskStatus::CANCELLED) {
+        output::warning(&format!("Cancelled {} @ {:?} with rask kill", key, directory));
+    }
+
+    // Failures are annotated on the summary of a CI job, pointing at the file the task comes from
+    if let Some(provider) = ci::detect() {
+        for TaskResult { key, directory, source, error, .. } in task_results.iter().filter(|task_result| matches!(task_result.status, TaskStatus::FAILURE | TaskStatus::TIMEOUT)) {
+            let task_file: Option<PathBuf> = config::find_task_file(directory, source);
+            let title = format!("{} @ {:?}", key, directory);
+            if let Some(annotation) = ci::format_error_annotation(provider, task_file.as_deref(), &title, &error.clone().unwrap_or_default()) {
+                println!("{}", annotation);
+            }
+        }
+    }
+
+    // Hints come last, so they are what is seen right above the final error
+    for TaskResult { key, directory, hint, .. } in task_results.iter().filter(|task_result| task_result.status != TaskStatus::TIMEOUT) {
+        if let Some(hint) = hint {
+            output::error_line("HINT", Tone::INFO, &format!("{} @ {:?}: {}", key, directory, hint));
+        }
+    }
+
+    // The tasks were stopped, so their failures are not what went wrong
+    if let Some(signal) = process::get_interruption() {
+        output::failure(&format!("Interrupted after executing {} of {} tasks within {} seconds", task_amount, sortable_tasks.len(), formatted_execution_time));
+        return Err(ExitError::interrupted(signal));
+    }
+
+    let all_executed = task_amount == sortable_tasks.len() && report::get_results_with_status(&task_results, TaskStatus::TIMEOUT).is_empty();
+    if !all_executed && is_budget_exceeded(&run_settings.deadline) {
+        output::failure(&format!("Time budget exceeded after executing {} of {} tasks within {} seconds", task_amount, sortable_tasks.len(), formatted_execution_time));
+        return Err(ExitError::new(ExitKind::TIMEOUT, "Not all tasks were executed".to_string()));
+    }
+
     match task_exit {
-        TaskExit::SUCCESS => println!("{}", format!("Successfully executed {} tasks within {} seconds", task_amount, formatted_execution_time)),
-        // TaskExit::FAILURE => println!("{}", format!("Failed after executing {} tasks within {} seconds", task_amount, formatted_execution_time)),
+        TaskExit::SUCCESS => output::success(&format!("Successfully executed {} tasks within {} seconds", task_amount, formatted_execution_time)),
+        TaskExit::FAILURE => output::failure(&format!("Failed after executing {} tasks within {} seconds", task_amount, formatted_execution_time)),
+    }
+
+    // Failures count before cancellations, a cancelled run only had tasks stopped on purpose
+    let failed_results = report::get_results_with_status(&task_results, TaskStatus::FAILURE).into_iter().map(|task_result| (ExitKind::TASK, task_result));
+    let cancelled_results = report::get_results_with_status(&task_results, TaskStatus::CANCELLED).into_iter().map(|task_result| (ExitKind::CANCELLED, task_result));
+    match failed_results.chain(cancelled_results).next() {
+        Some((kind, TaskResult { key, directory, error, .. })) => Err(ExitError::new(kind, format!("Task {} @ {:?} did not execute: {}", key, directory, error.unwrap_or_default()))),
+        None => Ok(()),
+    }
+}
+
+fn print_summary_table(task_results: &TaskResults) {
+    let rows: Vec<(String, String, &str, String, String, String)> = task_results
+        .iter()
+        .map(|TaskResult { key, directory, source, status, deduplicated, resource_usage, .. }| {
+            let status = match deduplicated {
+                true => format!("{:?} (deduplicated)", status),
+                false => format!("{:?}", status),
+            };
+            // Skipped and deduplicated tasks ran nothing, other platforms do not report it
+            let (cpu, max_rss) = match resource_usage {
+                Some(resource_usage) => (resource_usage.describe_cpu(), resource_usage.describe_max_rss()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            (status, key.clone(), *source, cpu, max_rss, format!("{:?}", directory))
+        })
+        .collect();
+    let status_width = rows.iter().map(|(status, ..)| status.len()).max().unwrap_or_default().max("STATUS".len());
+    let key_width = rows.iter().map(|(_, key, ..)| key.len()).max().unwrap_or_default().max("TASK".len());
+    let source_width = rows.iter().map(|(_, _, source, ..)| source.len()).max().unwrap_or_default().max("SOURCE".len());
+    let cpu_width = rows.iter().map(|(_, _, _, cpu, ..)| cpu.len()).max().unwrap_or_default().max("CPU".len());
+    let max_rss_width = rows.iter().map(|(_, _, _, _, max_rss, _)| max_rss.len()).max().unwrap_or_default().max("MAX RSS".len());
+
+    println!("{:status_width$}  {:key_width$}  {:source_width$}  {:>cpu_width$}  {:>max_rss_width$}  DIRECTORY", "STATUS", "TASK", "SOURCE", "CPU", "MAX RSS");
+    for (status, key, source, cpu, max_rss, directory) in rows {
+        let tone = match status.split(' ').next().unwrap_or_default() {
+            "SUCCESS" => Tone::SUCCESS,
+            "ALLOWED" => Tone::WARNING,
+            "SKIPPED" => Tone::MUTED,
+            _ => Tone::ERROR,
+        };
+        println!("{}  {:key_width$}  {:source_width$}  {:>cpu_width$}  {:>max_rss_width$}  {}", output::paint(&format!("{:status_width$}", status), tone, Stream::STDOUT), key, source, cpu, max_rss, directory);
     }
+}
 
-    Ok(())
+fn print_result_matrix(result_matrix: &ResultMatrix) {
+    let ResultMatrix { tasks, rows } = result_matrix;
+    let name_width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or_default().max("CONFIG".len());
+
+    println!("{:name_width$}  {}", "CONFIG", tasks.join("  "));
+    for (name, statuses) in rows {
+        let cells: Vec<String> = statuses
+            .iter()
+            .zip(tasks)
+            .map(|(status, task)| {
+                let tone = match status {
+                    Some(TaskStatus::SUCCESS) => Tone::SUCCESS,
+                    Some(TaskStatus::ALLOWED) => Tone::WARNING,
+                    Some(TaskStatus::SKIPPED) | None => Tone::MUTED,
+                    _ => Tone::ERROR,
+                };
+                // Symbols are one column wide, the padding keeps them under their task
+                let padding = " ".repeat(task.chars().count().saturating_sub(1));
+                format!("{}{}", output::paint(report::get_status_symbol(*status), tone, Stream::STDOUT), padding)
+            })
+            .collect();
+        println!("{}", format!("{:name_width$}  {}", name, cells.join("  ")).trim_end());
+    }
 }
 
-fn run_sortable_tasks(sortable_tasks: &SortableTasks, parallel: &bool) -> Result<TaskExit, String> {
-    let highest_order = find_highest_order(&sortable_tasks)?;
+fn notify_completion(task_name: &str, result: &Result<(), ExitError>, start_time: Instant, bell: &bool, on_complete: &Option<String>) {
+    if *bell {
+        eprint!("\x07");
+    }
+
+    if let Some(on_complete) = on_complete {
+        let status = match result {
+            Ok(_) => "success",
+            Err(_) => "failure",
+        };
+
+        let mut command = process::build_shell_command(on_complete);
+        command
+            .env("RASK_STATUS", status)
+            .env("RASK_TASK", task_name)
+            .env("RASK_DURATION", format!("{:.2}", start_time.elapsed().as_secs_f32()))
+            .env("RASK_ERROR", result.as_ref().err().map(|err| err.message.as_str()).unwrap_or_default());
+
+        // The outcome of the run is leading, a broken notification only warrants a warning
+        match command.status() {
+            Ok(exit_status) if exit_status.success() => {}
+            Ok(exit_status) => output::warning(&format!("The on-complete command exited with {}", exit_status)),
+            Err(err) => output::warning(&format!("Failed to execute the on-complete command: {}", err)),
+        }
+    }
+}
+
+fn run_sortable_tasks(sortable_tasks: &SortableTasks, run_settings: &RunSettings) -> Result<TaskResults, ExitError> {
+    let RunSettings { level_hooks, deadline, .. } = run_settings;
+    let highest_order = find_highest_order(sortable_tasks)?;
+    let mut task_results: TaskResults = vec![];
+    // Every repetition runs its commands again
+    let results_memo: Option<ResultsMemo> = run_settings.deduplicate.then(ResultsMemo::default);
+    let dependency_memo: DependencyMemo = DependencyMemo::default();
+
+    if run_settings.verbosity != Verbosity::SUMMARY {
+        if let Some(estimate) = estimate_duration(sortable_tasks, run_settings) {
+            output::line("ESTIMATE", Tone::MUTED, &format!("About {} seconds based on previous runs", time::format_seconds(Duration::from_secs_f64(estimate))));
+        }
+    }
 
     for order in (0..=highest_order).rev() {
         let ordered_tasks = get_ordered_tasks(sortable_tasks, order)?;
+        if ordered_tasks.is_empty() {
+            continue;
+        }
 
-        let result = match parallel {
-            true => run_parallel_ordered_tasks(&ordered_tasks),
-            false => run_ordered_tasks(&ordered_tasks),
-        };
+        // Stop scheduling once the time budget is spent, or rask was interrupted
+        if is_budget_exceeded(deadline) || process::is_interrupted() {
+            break;
+        }
+
+        if let Some(before_level) = &level_hooks.before {
+            run_level_hook(before_level, order, None, run_settings.verbosity)?;
+        }
 
-        match result {
-            Ok(_) => {}, // Silence is victory!~
-            Err(err) => return Err(err)
+        let level_results: TaskResults = run_level_tasks(&ordered_tasks, run_settings, &results_memo, &dependency_memo);
+        // A cancelled task fails the run in the end, the levels after it still run
+        let level_failed = !report::get_results_with_status(&level_results, TaskStatus::FAILURE).is_empty();
+        task_results.extend(level_results);
+
+        // The after hook also runs for failed levels, so it can tear down what the before hook started.
+        // The failed level is what went wrong then, a failing teardown is only reported next to it.
+        if let Some(after_level) = &level_hooks.after {
+            match (run_level_hook(after_level, order, Some(!level_failed), run_settings.verbosity), level_failed) {
+                (Err(err), true) => output::error(&err.message),
+                (hook_result, _) => hook_result?,
+            }
+        }
+
+        if level_failed {
+            break;
         }
     }
 
-    Ok(TaskExit::SUCCESS)
+    Ok(task_results)
 }
 
-fn run_ordered_tasks (ordered_tasks: &SortableTasks) -> Result<(), String> {
-    for sortable_task in ordered_tasks {
-        let SortableTask{ task, .. } = sortable_task;
-        match execute_task(task.clone()) {
-            Ok(_) => {}
-            Err(err) => return Err(format!("Command did not execute {:?}", err))
-        }
+// A failing hook is a failing task, it exits like one
+fn run_level_hook(hook: &Task, order: u64, succeeded: Option<bool>, verbosity: Verbosity) -> Result<(), ExitError> {
+    let mut hook = hook.clone();
+    hook.env.insert("RASK_LEVEL".to_string(), order.to_string());
+    if let Some(succeeded) = succeeded {
+        hook.env.insert("RASK_LEVEL_STATUS".to_string(), match succeeded { true => "success", false => "failure" }.to_string());
     }
 
-    Ok(())
+    executor::execute_task(hook.clone(), verbosity).map_err(|err| ExitError::new(ExitKind::TASK, format!("The {} hook failed for level {}: {}", hook.key, order, err)))
 }
 
-fn run_parallel_ordered_tasks (ordered_tasks: &SortableTasks) -> Result<(), String> {
-    let mut task_threads: Vec<JoinHandle<Result<(), String>>> = vec![];
+fn run_repeated_sortable_tasks(sortable_tasks: &SortableTasks, run_settings: &RunSettings, repeat: &Option<u64>, until_failure: &bool, seed: &Option<u64>) -> Result<(), ExitError> {
+    let start_time = Instant::now();
+    let base_seed: u64 = seed.unwrap_or_else(generate_seed);
+    let mut iterations: u64 = 0;
+    let mut failures: Vec<(u64, u64)> = vec![];
+
+    while repeat.is_none_or(|repeat| iterations < repeat) && !is_budget_exceeded(&run_settings.deadline) && !process::is_interrupted() {
+        iterations += 1;
+        let iteration_seed = base_seed.wrapping_add(iterations - 1);
+
+        output::line("ITERATION", Tone::INFO, &format!("{} (seed {})", iterations, iteration_seed));
+        let iteration_tasks: SortableTasks = sortable_tasks
+            .iter()
+            .cloned()
+            .map(|mut sortable_task| {
+                sortable_task.task.env.insert("RASK_ITERATION".to_string(), iterations.to_string());
+                sortable_task.task.env.insert("RASK_SEED".to_string(), iteration_seed.to_string());
+                sortable_task
+            })
+            .collect();
+
+        let iteration_result: Result<(), String> = run_sortable_tasks(&iteration_tasks, run_settings)
+            .map_err(|err| err.message)
+            .and_then(|task_results| match report::get_results_with_status(&task_results, TaskStatus::FAILURE).first() {
+                Some(TaskResult { key, error, .. }) => Err(format!("Task {} did not execute: {}", key, error.clone().unwrap_or_default())),
+                None => Ok(()),
+            });
+
+        if let Err(err) = iteration_result {
+            output::error_line("ITERATION", Tone::ERROR, &format!("{} failed: {}", iterations, err));
+            failures.push((iterations, iteration_seed));
+
+            if *until_failure {
+                break;
+            }
+        }
+    }
 
-    for sortable_task in ordered_tasks {
-        let SortableTask{ task, .. } = sortable_task;
-        let handle = execute_task_parallel(task.clone());
-        task_threads.push(handle);
+    let execution_time = start_time.elapsed().as_secs_f32();
+    let formatted_execution_time = (execution_time * 100.0).round() / 100.0;
+    let failure_rate = (failures.len() as f64 / iterations as f64) * 100.0;
+
+    let summary = format!("Executed {} iterations within {} seconds, base seed {}\n{} of {} iterations failed ({:.2}% failure rate)", iterations, formatted_execution_time, base_seed, failures.len(), iterations, failure_rate);
+    match failures.is_empty() {
+        true => output::success(&summary),
+        false => output::failure(&summary),
     }
 
-    for task_thread in task_threads {
-        match task_thread.join() {
-            Ok(_) => {}
-            Err(err) => return Err(format!("Command did not execute {:?}", err))
-        }
+    if let Some(signal) = process::get_interruption() {
+        return Err(ExitError::interrupted(signal));
     }
 
-    Ok(())
-}
+    // The last iteration was cut short, its terminated tasks are no failures of their own
+    if is_budget_exceeded(&run_settings.deadline) {
+        return Err(ExitError::new(ExitKind::TIMEOUT, format!("The time budget ran out during iteration {}", iterations)));
+    }
+
+    if failures.is_empty() {
+        return Ok(())
+    }
 
-// Function to execute a command string and wait for it to finish
-fn execute_task(task: Task) -> Result<(), String> {
-    let Task { command, directory } = task.clone();
+    let failed_seeds: Vec<String> = failures
+        .iter()
+        .map(|(iteration, seed)| format!("#{} (seed {})", iteration, seed))
+        .collect();
 
-    println!("[COMMAND] {} @ {:?}", command, directory);
-    let mut binding = Command::new("sh");
-    let command = binding
-        .arg("-c")
-        .arg(command)
-        .current_dir(directory)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
+    Err(ExitError::new(ExitKind::TASK, format!("Failed iterations: {}", failed_seeds.join(", "))))
+}
+
+fn generate_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default()
+}
 
-    let status = command.status().expect("Failed to execute command");
-    if !status.success() {
-        return Err("Command execution failed".to_string());
+fn run_level_tasks(ordered_tasks: &[&SortableTask], run_settings: &RunSettings, results_memo: &Option<ResultsMemo>, dependency_memo: &DependencyMemo) -> TaskResults {
+    let RunSettings { parallel, jobs, sequential_within_config, deadline, report, report_url, .. } = run_settings;
+
+    // Sequential runs are a parallel run of one, that stops at the first failure.
+    // Weights only share out a limited number of slots, without one every task of the level starts at once.
+    let executor = Executor {
+        concurrency: match (parallel, jobs) {
+            (true, Some(jobs)) => *jobs,
+            (true, None) => ordered_tasks.iter().map(|sortable_task| sortable_task.task.options.get_weight()).sum(),
+            (false, _) => 1,
+        },
+        fail_fast: !parallel,
+        deadline: *deadline,
+        capture_output: report.is_some() || report_url.is_some(),
+        serial_configs: *sequential_within_config,
+        verbosity: run_settings.verbosity,
+        output_style: run_settings.output_style,
+        results_memo: results_memo.clone(),
+        dependency_memo: dependency_memo.clone(),
+    };
+
+    // Tasks move to the worker threads, which outlive the borrow of the plan
+    let mut tasks: Vec<Task> = ordered_tasks.iter().map(|sortable_task| sortable_task.task.clone()).collect();
+    match (run_settings.schedule_seed, parallel, &run_settings.history) {
+        // Every level is shuffled differently, and the same for the same seed
+        (Some(schedule_seed), _, _) => {
+            let order: u64 = ordered_tasks.first().map(|sortable_task| sortable_task.order).unwrap_or_default();
+            tasks = schedule::interleave_configs(tasks, schedule_seed.wrapping_add(order));
+        },
+        (None, true, Some(history)) => history.borrow().sort_longest_first(&mut tasks),
+        _ => {},
     }
 
-    Ok(())
+    executor.run(tasks, &mut |task_result| record_task_result(run_settings, task_result))
 }
 
-// Function to execute a command string without blocking
-fn execute_task_parallel(task: Task) -> JoinHandle<Result<(), String> > {
-    let handle = thread::spawn(move || {
-        execute_task(task)
-    });
-    handle
+fn estimate_duration(sortable_tasks: &SortableTasks, run_settings: &RunSettings) -> Option<f64> {
+    let history = run_settings.history.as_ref()?.borrow();
+    let highest_order = find_highest_order(sortable_tasks).ok()?;
+
+    let levels: Vec<Vec<&Task>> = (0..=highest_order)
+        .rev()
+        .map(|order| sortable_tasks.iter().filter(|sortable_task| sortable_task.order == order).map(|sortable_task| &sortable_task.task).collect::<Vec<&Task>>())
+        .filter(|level| !level.is_empty())
+        .collect();
+    let concurrency: Option<usize> = match run_settings.parallel {
+        true => Some(run_settings.jobs.unwrap_or(usize::MAX)),
+        false => None,
+    };
+
+    history.estimate(&levels, concurrency)
 }
 
 fn find_highest_order(ordered_tasks: &SortableTasks) -> Result<u64, String> {
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, stats};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure};
+use crate::utils::file::ConfigFile;
+use crate::utils::stats::{LevelStats, WorkspaceStats};
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+}
+
+// The shape of the workspace, the width of the widest level is a sensible amount of --jobs
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, require_config } = arguments;
+
+    let mut config_issues = ConfigIssues::new(true);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    config_issues.print_warnings();
+
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs.clone()).map_err(exit::config)?;
+    let workspace_stats: WorkspaceStats = stats::collect_workspace_stats(&configs, &config_structure);
+
+    println!("The workspace has the following shape:");
+    println!("  -  configs: {}", workspace_stats.config_count);
+    println!("  -  tasks: {}", workspace_stats.task_count);
+    println!("  -  average tasks per config: {:.1}", workspace_stats.average_tasks_per_config());
+    println!("  -  deepest nesting: {}", workspace_stats.deepest_nesting());
+
+    println!("Tasks per engine:");
+    for (source, count) in &workspace_stats.tasks_per_source {
+        println!("  -  {}: {}", source, count);
+    }
+
+    println!("Largest configs:");
+    for (name, count) in &workspace_stats.largest_configs {
+        println!("  -  {}: {} tasks", name, count);
+    }
+
+    println!("Width per level:");
+    for (level, LevelStats { configs, tasks }) in workspace_stats.levels.iter().enumerate() {
+        println!("  -  level {}: {} configs, {} tasks", level, configs, tasks);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, output, shadowing};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, UnreachableConfig};
+use crate::utils::file::ConfigFile;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Report rask.yaml files under the entry directory that no directories pattern reaches")]
+    unreachable: bool,
+}
+
+// Fails on the first broken config, unknown keys included, unlike the lenient commands
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { entry, unreachable } = arguments;
+
+    let mut config_issues = ConfigIssues::new(false);
+    let entry_config_path: PathBuf = config::resolve_config_path(config::get_entry(entry).as_str()).map_err(exit::config)?;
+    let config_files: Vec<ConfigFile> = config::discover_config_files(&entry_config_path, &mut config_issues).map_err(exit::config)?;
+    config::validate_config_files(&config_files).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    let config_count = configs.len();
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+
+    println!("All {} configs are valid", config_count);
+
+    // Shadowing is allowed, the warnings of run and list only show once so they are repeated here
+    for shadowed_task in shadowing::find_shadowed_tasks(&config_structure) {
+        output::warning(&shadowed_task.describe());
+    }
+
+    if !*unreachable {
+        return Ok(());
+    }
+
+    let unreachable_configs: Vec<UnreachableConfig> = config::find_unreachable_configs(&entry_config_path)?;
+    if unreachable_configs.is_empty() {
+        println!("Every rask.yaml under {:?} is reachable", entry_config_path.parent().unwrap_or(&entry_config_path));
+        return Ok(());
+    }
+
+    println!("The following configs are not reachable from {:?}:", entry_config_path);
+    for UnreachableConfig { config_path, excluded_by } in &unreachable_configs {
+        match excluded_by {
+            Some(excluded_by) => println!("  -  {:?} (excluded by {})", config_path, excluded_by),
+            None => println!("  -  {:?}", config_path),
+        }
+    }
+
+    // Excluding a config is a decision, a config no pattern matches is likely forgotten
+    let forgotten_count = unreachable_configs.iter().filter(|unreachable_config| unreachable_config.excluded_by.is_none()).count();
+    match forgotten_count {
+        0 => Ok(()),
+        _ => Err(exit::config(format!("{} configs are not matched by any directories pattern", forgotten_count))),
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use clap::Args;
+use crate::utils::{config, output, process, prompt, report, version};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, Task};
+use crate::utils::executor::{DependencyMemo, Executor};
+use crate::utils::file::ConfigFile;
+use crate::utils::output::{Tone, Verbosity};
+use crate::utils::report::{TaskResult, TaskResults, TaskStatus};
+use crate::utils::version::{BumpLevel, VersionStep};
+use crate::utils::writer::OutputStyle;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(long, value_enum, help = "Which part of the version to bump, exposed to version tasks as RASK_BUMP")]
+    bump: BumpLevel,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Print which configs would bump their version and how, without running anything")]
+    dry_run: bool,
+    #[arg(long, short, help = "Run the tasks marked with confirm without asking")]
+    yes: bool,
+}
+
+// Bumps every config in lockstep, through its version task or `npm version` for versioned packages
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { bump, entry, dry_run, yes } = arguments;
+
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), false, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+
+    let version_steps: Vec<VersionStep> = version::plan_version_bump(&config_structure, *bump)?;
+    if version_steps.is_empty() {
+        return Err(format!("No config has a {} task or a package.json with a version to bump", version::VERSION_TASK).into());
+    }
+
+    if *dry_run {
+        println!("The following configs would bump their {} version:", bump.as_str());
+        for VersionStep { task, native, .. } in &version_steps {
+            let source = match native { true => "npm", false => "version task" };
+            println!("  -  {} @ {:?} ({})", task.command, task.directory, source);
+        }
+        return Ok(());
+    }
+
+    let tasks: Vec<Task> = version_steps.into_iter().map(|version_step| version_step.task).collect();
+    let task_refs: Vec<&Task> = tasks.iter().flat_map(|task| task.dependencies.iter().chain([task])).collect();
+    process::ensure_shells(&task_refs)?;
+    process::ensure_privileges(&task_refs)?;
+    prompt::confirm_tasks(&task_refs, *yes)?;
+
+    // One by one, a failing bump stops the rest so the workspace is never bumped halfway unnoticed
+    let executor = Executor { concurrency: 1, fail_fast: true, deadline: None, capture_output: false, serial_configs: false, verbosity: Verbosity::NORMAL, output_style: OutputStyle::default(), results_memo: None, dependency_memo: DependencyMemo::default() };
+    let task_count = tasks.len();
+    let task_results: TaskResults = executor.run(tasks, &mut |_| {});
+
+    match report::get_results_with_status(&task_results, TaskStatus::FAILURE).first() {
+        Some(TaskResult { key, directory, error, .. }) => Err(format!("Task {} @ {:?} did not execute: {}, {} of {} configs were bumped", key, directory, error.clone().unwrap_or_default(), task_results.len() - 1, task_count).into()),
+        None => {
+            output::line("VERSION", Tone::SUCCESS, &format!("Bumped the {} version of {} configs", bump.as_str(), task_count));
+            Ok(())
+        },
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+use clap::Args;
+use crate::utils::{config, output, process, prompt, report, time, watch};
+use crate::utils::config::{Config, ConfigIssues, ConfigStructure, SortableTask, SortableTasks, Task};
+use crate::utils::executor::{DependencyMemo, Executor};
+use crate::utils::file::ConfigFile;
+use crate::utils::output::{Tone, Verbosity};
+use crate::utils::report::{TaskResult, TaskResults, TaskStatus};
+use crate::utils::watch::FileSnapshot;
+use crate::utils::writer::OutputStyle;
+use crate::utils::exit;
+use crate::utils::exit::ExitError;
+
+#[derive(Args, Debug)]
+pub struct Arguments {
+    #[arg(help = "Which tasks to watch, defaults to the default_task of the entry config")]
+    task_name: Option<String>,
+    #[arg(long, help = "The entry directory or rask.yaml file")]
+    entry: Option<String>,
+    #[arg(long, help = "Fail when there is no rask.yaml, instead of using the detected package.json, composer.json, Cargo.toml, Taskfile, justfile or compose file")]
+    require_config: bool,
+    #[arg(long, help = "enable strict command matching, defaults to checking if a command starts with a key")]
+    strict: bool,
+    #[arg(long, value_parser = time::parse_duration, default_value = "500ms", help = "How often the inputs are checked for changes")]
+    interval: Duration,
+    #[arg(long, short, help = "Run the tasks marked with confirm without asking")]
+    yes: bool,
+}
+
+// Re-runs only the tasks whose inputs changed, until interrupted
+pub fn execute(arguments: &Arguments) -> Result<(), ExitError> {
+    let Arguments { task_name, entry, require_config, strict, interval, yes } = arguments;
+
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files): (PathBuf, Vec<ConfigFile>) = config::read_entry_config_files(&config::get_entry(entry), *require_config, &mut config_issues).map_err(exit::config)?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).map_err(exit::config)?;
+
+    let entry_config: &Config = config::get_entry_config(&entry_config_path, &configs).map_err(exit::config)?;
+    let task_name: String = config::resolve_task_name(task_name, entry_config).map_err(exit::config)?;
+
+    let config_structure: ConfigStructure = config::resolve_config_structure(&entry_config_path, configs).map_err(exit::config)?;
+    let (sortable_tasks, unwatched_tasks): (SortableTasks, SortableTasks) = config::resolve_sortable_task(&config_structure, &task_name, strict).map_err(exit::config)?
+        .into_iter()
+        .partition(|SortableTask { task, .. }| !task.inputs.is_empty());
+
+    for SortableTask { task, .. } in &unwatched_tasks {
+        output::warning(&format!("Not watching {} @ {:?}, it declares no inputs", task.key, task.directory));
+    }
+
+    if sortable_tasks.is_empty() {
+        return Err(format!("None of the tasks matching \"{}\" declare inputs, add inputs to watch them", task_name).into());
+    }
+
+    let tasks: Vec<&Task> = sortable_tasks
+        .iter()
+        .flat_map(|sortable_task| sortable_task.task.dependencies.iter().chain([&sortable_task.task]))
+        .collect();
+    process::ensure_shells(&tasks)?;
+    process::ensure_privileges(&tasks)?;
+    prompt::confirm_tasks(&tasks, *yes)?;
+
+    let mut file_snapshot: FileSnapshot = watch::snapshot_inputs(&sortable_tasks)?;
+    output::line("WATCH", Tone::INFO, &format!("Watching {} files of {} tasks, press Ctrl+C to stop", file_snapshot.len(), sortable_tasks.len()));
+
+    loop {
+        sleep(*interval);
+        if let Some(signal) = process::get_interruption() {
+            return Err(ExitError::interrupted(signal));
+        }
+
+        let current_snapshot: FileSnapshot = watch::snapshot_inputs(&sortable_tasks)?;
+        let changed_files: Vec<PathBuf> = watch::find_changed_files(&file_snapshot, &current_snapshot);
+        file_snapshot = current_snapshot;
+        if changed_files.is_empty() {
+            continue;
+        }
+
+        let triggered_tasks: Vec<&SortableTask> = watch::find_triggered_tasks(&sortable_tasks, &changed_files)?;
+        output::line("WATCH", Tone::INFO, &format!("{} files changed, running {} tasks", changed_files.len(), triggered_tasks.len()));
+        run_triggered_tasks(&triggered_tasks);
+    }
+}
+
+// A failing task is reported and the watch goes on, the next change gets another chance
+fn run_triggered_tasks(triggered_tasks: &[&SortableTask]) {
+    let executor = Executor { concurrency: 1, fail_fast: true, deadline: None, capture_output: false, serial_configs: false, verbosity: Verbosity::NORMAL, output_style: OutputStyle::default(), results_memo: None, dependency_memo: DependencyMemo::default() };
+    let tasks: Vec<Task> = triggered_tasks.iter().map(|sortable_task| sortable_task.task.clone()).collect();
+    let task_results: TaskResults = executor.run(tasks, &mut |_| {});
+
+    match report::get_results_with_status(&task_results, TaskStatus::FAILURE).first() {
+        Some(TaskResult { key, directory, error, .. }) => output::failure(&format!("Task {} @ {:?} did not execute: {}", key, directory, error.clone().unwrap_or_default())),
+        None => output::success(&format!("Successfully executed {} tasks, waiting for changes", task_results.len())),
+    }
+}
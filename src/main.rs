@@ -1,11 +1,36 @@
+#![allow(clippy::upper_case_acronyms)]
+
 use std::process::exit;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use commands::run;
 use commands::list;
 use commands::init;
+use commands::dashboard;
+use commands::completions;
+use commands::resume;
+use commands::env;
+use commands::watch;
+use commands::dev;
+use commands::stats;
+use commands::export;
+use commands::docs;
+use commands::query;
+use commands::validate;
+use commands::migrate;
+use commands::kill;
+use commands::clean;
+use commands::lsp;
+use commands::plan;
+use commands::version;
+use commands::help;
+use utils::{build_info, output};
+use utils::exit::{ExitError, ExitKind};
+use utils::output::ColorChoice;
 
 mod commands;
 mod utils;
+#[cfg(test)]
+mod testing;
 
 #[derive(Subcommand, Debug)]
 enum Command {
@@ -14,27 +39,98 @@ enum Command {
     /// List available tasks
     List(list::Arguments),
     /// Run specific tasks
-    Run(run::Arguments),
+    Run(Box<run::Arguments>),
+    /// Print the tasks a run would execute as a plan for `rask run --plan`
+    Plan(plan::Arguments),
+    /// Resume a failed or interrupted run
+    Resume(resume::Arguments),
+    /// Cancel one running task while the rest of its run goes on
+    Kill(kill::Arguments),
+    /// Remove what earlier runs left behind, like the scratch directories of failed tasks
+    Clean(clean::Arguments),
+    /// Re-run tasks when the files matching their inputs change
+    Watch(watch::Arguments),
+    /// Keep the dev task of every config running while packages come and go
+    Dev(dev::Arguments),
+    /// Check the binaries and env variables tasks need
+    Env(env::Arguments),
+    /// Print statistics about the shape of the workspace
+    Stats(stats::Arguments),
+    /// Print the resolved workspace as JSON
+    Export(export::Arguments),
+    /// Render the configs and tasks as Markdown or HTML docs, --check fails when they are stale
+    Docs(docs::Arguments),
+    /// Query the task graph, like deps(api#build) or rdeps(libs/core#build)
+    Query(query::Arguments),
+    /// Check every config of the workspace, and which configs it leaves out
+    Validate(validate::Arguments),
+    /// Update the config fields that were renamed or removed, --check fails when a config uses one
+    Migrate(migrate::Arguments),
+    /// Serve completion, diagnostics and run lenses for rask.yaml files to editors
+    Lsp(lsp::Arguments),
+    /// Bump the version of every config in lockstep
+    Version(version::Arguments),
+    /// Print help on a topic or command, or the man page
+    Help(help::Arguments),
+    /// Print shell completion scripts
+    Completions(completions::Arguments),
 }
 
 #[derive(Parser, Debug)]
-#[command(author, version, about = "Rask - The universal way of running tasks", long_about = None, propagate_version = true)]
+#[command(author, version, about = "Rask - The universal way of running tasks", long_about = None, disable_version_flag = true, disable_help_subcommand = true)]
 struct Arguments {
     #[command(subcommand)]
-    command: Command
+    command: Option<Command>,
+    #[arg(short = 'V', long, global = true, help = "Print version")]
+    version: bool,
+    #[arg(long, global = true, requires = "version", help = "Print the version with its build metadata as JSON")]
+    json: bool,
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::AUTO, help = "When to use colors, honors NO_COLOR and CLICOLOR(_FORCE) in auto mode")]
+    color: ColorChoice,
 }
 
 fn main() {
-    let Arguments { command } = Arguments::parse();
+    // Help and version are printed with 0, usage errors get a code of their own instead of the 2 of clap
+    let Arguments { command, version, json, color } = Arguments::try_parse().unwrap_or_else(|err| {
+        if !err.use_stderr() {
+            err.exit();
+        }
+        let _ = err.print();
+        exit(ExitKind::USAGE.code())
+    });
+
+    output::init(color);
 
     let result = match command {
-        Command::Run(arguments) => { run::execute(&arguments) },
-        Command::List(arguments) => { list::execute(&arguments) },
-        Command::Init(arguments) => { init::execute(&arguments) },
+        _ if version => { build_info::print_version(json).map_err(ExitError::from) },
+        Some(Command::Run(arguments)) => { run::execute(&arguments) },
+        Some(Command::Plan(arguments)) => { plan::execute(&arguments) },
+        Some(Command::Resume(arguments)) => { resume::execute(&arguments) },
+        Some(Command::Kill(arguments)) => { kill::execute(&arguments) },
+        Some(Command::Clean(arguments)) => { clean::execute(&arguments) },
+        Some(Command::Lsp(arguments)) => { lsp::execute(&arguments) },
+        Some(Command::List(arguments)) => { list::execute(&arguments) },
+        Some(Command::Init(arguments)) => { init::execute(&arguments) },
+        Some(Command::Watch(arguments)) => { watch::execute(&arguments) },
+        Some(Command::Dev(arguments)) => { dev::execute(&arguments) },
+        Some(Command::Env(arguments)) => { env::execute(&arguments) },
+        Some(Command::Stats(arguments)) => { stats::execute(&arguments) },
+        Some(Command::Export(arguments)) => { export::execute(&arguments) },
+        Some(Command::Docs(arguments)) => { docs::execute(&arguments) },
+        Some(Command::Query(arguments)) => { query::execute(&arguments) },
+        Some(Command::Validate(arguments)) => { validate::execute(&arguments) },
+        Some(Command::Migrate(arguments)) => { migrate::execute(&arguments) },
+        Some(Command::Version(arguments)) => { version::execute(&arguments) },
+        Some(Command::Help(arguments)) => { help::execute(&arguments, Arguments::command()) },
+        Some(Command::Completions(arguments)) => { completions::execute(&arguments, Arguments::command()) },
+        None => { dashboard::execute() },
     };
 
     match result {
         Ok(_) => exit(0),
-        Err(err) => eprintln!("{}", err)
+        Err(ExitError { kind, message }) => {
+            output::error(&message);
+            exit(kind.code())
+        }
     }
 }
@@ -0,0 +1,131 @@
+use std::env;
+use std::fs::{canonicalize, create_dir_all, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::utils::config::{self, Config, ConfigIssues, ConfigStructure, SortableTask, SortableTasks, Task, TaskOptions, TaskType};
+
+static WORKSPACE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A temporary directory tree of configs and engine files, removed again when dropped
+pub struct TestWorkspace {
+    root: PathBuf,
+}
+
+impl TestWorkspace {
+    pub fn new() -> Self {
+        let name = format!("rask-test-{}-{}", process::id(), WORKSPACE_COUNTER.fetch_add(1, Ordering::SeqCst));
+        let root = env::temp_dir().join(name);
+        create_dir_all(&root).expect("Failed to create test workspace");
+
+        // Canonical, so paths match the ones rask resolves itself
+        TestWorkspace { root: canonicalize(root).expect("Failed to resolve test workspace") }
+    }
+
+    pub fn with_config(self, directory: &str, content: &str) -> Self {
+        self.with_file(&Path::new(directory).join("rask.yaml").to_string_lossy(), content)
+    }
+
+    pub fn with_package_json(self, directory: &str, scripts: &[&str]) -> Self {
+        let scripts: Vec<String> = scripts.iter().map(|script| format!("\"{}\": \"echo {}\"", script, script)).collect();
+        self.with_file(&Path::new(directory).join("package.json").to_string_lossy(), &format!("{{\"scripts\": {{{}}}}}", scripts.join(", ")))
+    }
+
+    pub fn with_file(self, relative_path: &str, content: &str) -> Self {
+        let path = self.path(relative_path);
+        create_dir_all(path.parent().unwrap()).expect("Failed to create test directory");
+        write(&path, content).expect("Failed to write test file");
+
+        self
+    }
+
+    pub fn path(&self, relative_path: &str) -> PathBuf {
+        match relative_path {
+            "" | "." => self.root.clone(),
+            _ => self.root.join(relative_path),
+        }
+    }
+
+    pub fn config_path(&self, directory: &str) -> PathBuf {
+        self.path(directory).join("rask.yaml")
+    }
+
+    // Discovers and parses the configs below the rask.yaml of a directory, like a run does
+    pub fn load_configs(&self, directory: &str) -> Vec<Config> {
+        let entry_config_path = self.config_path(directory);
+        let mut config_issues = ConfigIssues::new(false);
+        let config_files = config::discover_config_files(&entry_config_path, &mut config_issues).expect("Failed to discover test configs");
+
+        config::parse_config_files(config_files, &entry_config_path, &mut config_issues).expect("Failed to parse test configs")
+    }
+
+    pub fn load_structure(&self, directory: &str) -> ConfigStructure {
+        config::resolve_config_structure(&self.config_path(directory), self.load_configs(directory)).expect("Failed to resolve test configs")
+    }
+
+    pub fn relative(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path).to_string_lossy().to_string();
+        match relative.is_empty() {
+            true => ".".to_string(),
+            false => relative,
+        }
+    }
+
+    pub fn relative_paths(&self, paths: &[PathBuf]) -> Vec<String> {
+        let mut relative_paths: Vec<String> = paths.iter().map(|path| self.relative(path)).collect();
+        relative_paths.sort();
+        relative_paths
+    }
+
+    // Renders the structure as an indented tree of config names, children sorted for stable output
+    pub fn render_structure(&self, config_structure: &ConfigStructure) -> String {
+        let mut lines: Vec<String> = vec![];
+        render_structure_lines(config_structure, 0, &mut lines);
+        lines.join("\n")
+    }
+
+    // Renders tasks as `order key @ directory` lines in execution order
+    pub fn render_tasks(&self, sortable_tasks: &SortableTasks) -> String {
+        let mut lines: Vec<(u64, String)> = sortable_tasks
+            .iter()
+            .map(|SortableTask { task, order, .. }| (*order, format!("{} {} @ {}", order, task.key, self.relative(&task.directory))))
+            .collect();
+        lines.sort_by(|(a_order, a_line), (b_order, b_line)| b_order.cmp(a_order).then(a_line.cmp(b_line)));
+
+        lines.into_iter().map(|(_, line)| line).collect::<Vec<String>>().join("\n")
+    }
+}
+
+impl Drop for TestWorkspace {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.root);
+    }
+}
+
+// A shell task without options in the temp directory, tests change the fields they are about
+pub fn create_task(key: &str, command: &str) -> Task {
+    Task {
+        key: key.to_string(),
+        command: command.to_string(),
+        steps: vec![],
+        directory: env::temp_dir(),
+        env: Default::default(),
+        options: TaskOptions::default(),
+        shell: None,
+        dependencies: vec![],
+        inputs: vec![],
+        outputs: vec![],
+        install: None,
+        task_type: TaskType::SHELL,
+    }
+}
+
+fn render_structure_lines(config_structure: &ConfigStructure, depth: usize, lines: &mut Vec<String>) {
+    lines.push(format!("{}{}", "  ".repeat(depth), config_structure.config.name));
+
+    let mut children: Vec<&ConfigStructure> = config_structure.children.iter().collect();
+    children.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+    for child in children {
+        render_structure_lines(child, depth + 1, lines);
+    }
+}
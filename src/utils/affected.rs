@@ -0,0 +1,101 @@
+use std::env::current_dir;
+use std::io::{stdin, BufRead, IsTerminal};
+use std::path::{Component, Path, PathBuf};
+use crate::utils::config::{Config, SortableTask, SortableTasks};
+
+// Reads the changed files as given, a single `-` reads one path per line from stdin instead
+pub fn read_changed_files(values: &[String]) -> Result<Vec<PathBuf>, String> {
+    let values: Vec<String> = match values {
+        [value] if value == "-" => {
+            if stdin().is_terminal() {
+                return Err("Expected the changed files on stdin, pipe them in or pass them as a comma separated list".to_string());
+            }
+
+            stdin().lock().lines().collect::<Result<Vec<String>, _>>().map_err(|err| format!("Failed to read the changed files from stdin: {}", err))?
+        },
+        _ => values.to_vec(),
+    };
+
+    let working_directory: PathBuf = current_dir().map_err(|err| format!("Failed to get the current directory: {}", err))?;
+
+    Ok(values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| normalize_path(&working_directory.join(value)))
+        .collect())
+}
+
+// Changed files may be deleted already, so the path can't be canonicalized
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized_path = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => { normalized_path.pop(); },
+            _ => normalized_path.push(component),
+        }
+    }
+
+    normalized_path
+}
+
+// A file belongs to the deepest config that contains it
+pub fn find_affected_directories(configs: &[Config], changed_files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut affected_directories: Vec<PathBuf> = vec![];
+
+    for changed_file in changed_files {
+        let owning_directory = configs
+            .iter()
+            .map(|config| &config.dir_path)
+            .filter(|dir_path| changed_file.starts_with(dir_path))
+            .max_by_key(|dir_path| dir_path.components().count());
+
+        if let Some(owning_directory) = owning_directory {
+            if !affected_directories.contains(owning_directory) {
+                affected_directories.push(owning_directory.clone());
+            }
+        }
+    }
+
+    affected_directories
+}
+
+pub fn filter_affected_tasks(sortable_tasks: SortableTasks, affected_directories: &[PathBuf]) -> SortableTasks {
+    sortable_tasks
+        .into_iter()
+        .filter(|SortableTask { task, .. }| affected_directories.contains(&task.directory))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn changed_files_belong_to_the_deepest_config() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\n")
+            .with_config("packages/app", "name: app\n")
+            .with_config("packages/lib", "name: lib\n");
+
+        let changed_files: Vec<PathBuf> = ["packages/app/src/main.js", "packages/app/../app/README.md", "docs/index.md"]
+            .iter()
+            .map(|path| normalize_path(&workspace.path(path)))
+            .collect();
+        let affected_directories = find_affected_directories(&workspace.load_configs("."), &changed_files);
+
+        assert_eq!(workspace.relative_paths(&affected_directories), vec![".", "packages/app"]);
+    }
+
+    #[test]
+    fn files_outside_the_workspace_affect_nothing() {
+        let workspace = TestWorkspace::new().with_config(".", "name: root\n");
+
+        let affected_directories = find_affected_directories(&workspace.load_configs("."), &[PathBuf::from("/elsewhere/file.txt")]);
+
+        assert!(affected_directories.is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+// The engines a task_engine can be set to, next to none and auto
+pub const SUPPORTED_ENGINES: [&str; 8] = ["composer", "npm", "yarn", "pnpm", "cargo", "taskfile", "just", "compose"];
+
+// Populated at compile time by build.rs, read by scripts and the self-updater through `--version --json`
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub commit: &'static str,
+    pub build_date: &'static str,
+    pub target: &'static str,
+    pub features: Vec<&'static str>,
+    pub engines: Vec<&'static str>,
+}
+
+pub fn get_build_info() -> BuildInfo {
+    BuildInfo {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("RASK_GIT_COMMIT"),
+        build_date: env!("RASK_BUILD_DATE"),
+        target: env!("RASK_BUILD_TARGET"),
+        features: env!("RASK_FEATURES").split(',').filter(|feature| !feature.is_empty()).collect(),
+        engines: SUPPORTED_ENGINES.to_vec(),
+    }
+}
+
+pub fn print_version(json: bool) -> Result<(), String> {
+    let build_info = get_build_info();
+
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&build_info).map_err(|err| err.to_string())?),
+        false => println!("{} {}", build_info.name, build_info.version),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::file::TaskEngine;
+
+    #[test]
+    fn supported_engines_are_task_engines() {
+        for engine in SUPPORTED_ENGINES {
+            assert!(serde_yaml::from_str::<TaskEngine>(engine).is_ok(), "{} is not a task engine", engine);
+        }
+    }
+}
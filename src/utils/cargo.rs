@@ -0,0 +1,161 @@
+use std::fs::canonicalize;
+use std::path::{Path, PathBuf};
+use crate::utils::file;
+
+const CARGO_TOML_FILE: &str = "Cargo.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceMember {
+    // The package name, what `cargo -p` expects
+    pub name: String,
+    pub directory: PathBuf,
+}
+
+// The members of the workspace a Cargo.toml defines, none when it is not a workspace root
+pub fn find_workspace_members(dir_path: &Path) -> Result<Vec<WorkspaceMember>, String> {
+    let content = file::read_file_content(dir_path.join(CARGO_TOML_FILE))?;
+    let excludes: Vec<PathBuf> = read_array(&content, "workspace", "exclude")
+        .iter()
+        .filter_map(|exclude| canonicalize(dir_path.join(exclude)).ok())
+        .collect();
+
+    let mut members: Vec<WorkspaceMember> = vec![];
+    for pattern in read_array(&content, "workspace", "members") {
+        let pattern = dir_path.join(&pattern);
+        let pattern: &str = file::path_to_str(&pattern)?;
+
+        for member_path in glob::glob(pattern).map_err(|err| format!("Failed to read workspace member pattern {:?}: {}", pattern, err))?.flatten() {
+            let Ok(directory) = canonicalize(&member_path) else {
+                continue;
+            };
+
+            let is_member = directory.join(CARGO_TOML_FILE).is_file() && directory != dir_path && !excludes.contains(&directory);
+            if is_member && !members.iter().any(|member| member.directory == directory) {
+                members.push(WorkspaceMember { name: read_package_name(&directory)?, directory });
+            }
+        }
+    }
+
+    Ok(members)
+}
+
+pub fn read_package_name(dir_path: &Path) -> Result<String, String> {
+    let cargo_toml_path = dir_path.join(CARGO_TOML_FILE);
+    let content = file::read_file_content(cargo_toml_path.clone())?;
+
+    read_string(&content, "package", "name").ok_or(format!("Unable to find the package name in {:?}", cargo_toml_path))
+}
+
+// Cargo.toml is read without a toml parser, only plain `key = value` lines of a table are understood
+fn read_value(content: &str, table: &str, key: &str) -> Option<String> {
+    let mut current_table = "";
+    let mut value: Option<String> = None;
+
+    for line in content.lines().map(strip_comment) {
+        let line = line.trim();
+
+        if let Some(value) = value.as_mut() {
+            value.push_str(line);
+            if is_complete(value) {
+                break;
+            }
+            continue;
+        }
+
+        if line.starts_with('[') {
+            current_table = line.trim_matches(['[', ']']).trim();
+            continue;
+        }
+
+        if current_table != table {
+            continue;
+        }
+
+        if let Some((line_key, line_value)) = line.split_once('=') {
+            if line_key.trim() == key {
+                let line_value = line_value.trim().to_string();
+                if is_complete(&line_value) {
+                    return Some(line_value);
+                }
+                value = Some(line_value);
+            }
+        }
+    }
+
+    value
+}
+
+// Arrays may span several lines, they end once every opened bracket is closed
+fn is_complete(value: &str) -> bool {
+    value.matches('[').count() <= value.matches(']').count()
+}
+
+fn read_string(content: &str, table: &str, key: &str) -> Option<String> {
+    read_quoted_strings(&read_value(content, table, key)?).into_iter().next()
+}
+
+fn read_array(content: &str, table: &str, key: &str) -> Vec<String> {
+    read_value(content, table, key).map(|value| read_quoted_strings(&value)).unwrap_or_default()
+}
+
+fn read_quoted_strings(value: &str) -> Vec<String> {
+    let mut strings: Vec<String> = vec![];
+    let mut rest = value;
+
+    while let Some(start) = rest.find(['"', '\'']) {
+        let quote = &rest[start..start + 1];
+        let after_start = &rest[start + 1..];
+        let Some(end) = after_start.find(quote) else {
+            break;
+        };
+
+        strings.push(after_start[..end].to_string());
+        rest = &after_start[end + 1..];
+    }
+
+    strings
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+
+    for (index, character) in line.char_indices() {
+        match (quote, character) {
+            (None, '#') => return &line[..index],
+            (None, '"' | '\'') => quote = Some(character),
+            (Some(open_quote), _) if character == open_quote => quote = None,
+            _ => {},
+        }
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn reads_values_from_tables() {
+        let content = "[package]\nname = \"rask\" # the binary\n\n[workspace]\nmembers = [\n  \"crates/*\", # libraries\n  'tools/cli',\n]\n\n[dependencies]\nname = \"other\"\n";
+
+        assert_eq!(read_string(content, "package", "name"), Some("rask".to_string()));
+        assert_eq!(read_array(content, "workspace", "members"), vec!["crates/*", "tools/cli"]);
+        assert_eq!(read_array(content, "workspace", "exclude"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn finds_the_members_of_a_workspace() {
+        let workspace = TestWorkspace::new()
+            .with_file("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/legacy\"]\n")
+            .with_file("crates/core/Cargo.toml", "[package]\nname = \"app-core\"\n")
+            .with_file("crates/legacy/Cargo.toml", "[package]\nname = \"legacy\"\n")
+            .with_file("crates/notes/README.md", "");
+
+        let members = find_workspace_members(&workspace.path(".")).unwrap();
+
+        assert_eq!(members, vec![WorkspaceMember { name: "app-core".to_string(), directory: workspace.path("crates/core") }]);
+        assert!(read_package_name(&workspace.path(".")).is_err());
+    }
+}
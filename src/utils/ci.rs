@@ -0,0 +1,116 @@
+use std::env;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The CI providers that fold sections of a log, see
+// https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions and
+// https://docs.gitlab.com/ee/ci/jobs/job_logs.html#custom-collapsible-sections
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CiProvider {
+    GITHUB,
+    GITLAB,
+}
+
+pub fn detect() -> Option<CiProvider> {
+    let is_set = |name: &str| env::var(name).is_ok_and(|value| value == "true");
+
+    match (is_set("GITHUB_ACTIONS"), is_set("GITLAB_CI")) {
+        (true, _) => Some(CiProvider::GITHUB),
+        (_, true) => Some(CiProvider::GITLAB),
+        _ => None,
+    }
+}
+
+// GitLab pairs the start and end of a section by name, so every section gets its own
+static SECTION_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A collapsible section of the log. GitLab shows how long a section took from the timestamps of its start and end.
+#[derive(Debug, Clone)]
+pub struct CiGroup {
+    provider: CiProvider,
+    name: String,
+    start_time: SystemTime,
+}
+
+impl CiGroup {
+    // The section starts now, even when its start line is written later
+    pub fn new(provider: CiProvider) -> Self {
+        CiGroup { provider, name: format!("rask_{}", SECTION_COUNTER.fetch_add(1, Ordering::Relaxed)), start_time: SystemTime::now() }
+    }
+
+    // Gitlab keeps sections that are not collapsed open
+    pub fn format_start(&self, title: &str, collapsed: bool) -> String {
+        match self.provider {
+            CiProvider::GITHUB => format!("::group::{}\n", escape_data(title)),
+            CiProvider::GITLAB => {
+                let options = match collapsed {
+                    true => "[collapsed=true]",
+                    false => "",
+                };
+
+                format!("\x1b[0Ksection_start:{}:{}{}\r\x1b[0K{}\n", get_timestamp(self.start_time), self.name, options, title)
+            },
+        }
+    }
+
+    pub fn format_end(&self) -> String {
+        match self.provider {
+            CiProvider::GITHUB => "::endgroup::\n".to_string(),
+            CiProvider::GITLAB => format!("\x1b[0Ksection_end:{}:{}\r\x1b[0K\n", get_timestamp(SystemTime::now()), self.name),
+        }
+    }
+}
+
+fn get_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default()
+}
+
+// Only github turns lines into annotations, gitlab shows failures in the log as they are
+pub fn format_error_annotation(provider: CiProvider, file: Option<&Path>, title: &str, message: &str) -> Option<String> {
+    match provider {
+        CiProvider::GITHUB => {
+            let mut properties: Vec<String> = vec![];
+            if let Some(file) = file {
+                // Annotations attach to paths relative to the checkout, which is where CI runs rask from
+                let file = env::current_dir().ok().and_then(|current_dir| file.strip_prefix(current_dir).ok().map(Path::to_path_buf)).unwrap_or(file.to_path_buf());
+                properties.push(format!("file={}", escape_property(&file.to_string_lossy())));
+            }
+            properties.push(format!("title={}", escape_property(title)));
+
+            Some(format!("::error {}::{}", properties.join(","), escape_data(message)))
+        },
+        CiProvider::GITLAB => None,
+    }
+}
+
+fn escape_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_the_markers_of_each_provider() {
+        let group = CiGroup::new(CiProvider::GITHUB);
+        assert_eq!((group.format_start("build @ api", true).as_str(), group.format_end().as_str()), ("::group::build @ api\n", "::endgroup::\n"));
+
+        let group = CiGroup { start_time: UNIX_EPOCH + Duration::from_secs(1_700_000_000), ..CiGroup::new(CiProvider::GITLAB) };
+        let (start, end) = (group.format_start("build @ api", true), group.format_end());
+        assert_eq!(start, format!("\x1b[0Ksection_start:1700000000:{}[collapsed=true]\r\x1b[0Kbuild @ api\n", group.name));
+        assert!(end.starts_with("\x1b[0Ksection_end:") && end.ends_with(&format!(":{}\r\x1b[0K\n", group.name)));
+        assert!(end.split(':').nth(1).unwrap().parse::<u64>().unwrap() > 1_700_000_000);
+        assert!(!group.format_start("build @ api", false).contains("collapsed"));
+
+        let annotation = format_error_annotation(CiProvider::GITHUB, Some(Path::new("/app/rask.yaml")), "build, failed", "Exited with code 1\n50%");
+        assert_eq!(annotation.as_deref(), Some("::error file=/app/rask.yaml,title=build%2C failed::Exited with code 1%0A50%25"));
+        assert_eq!(format_error_annotation(CiProvider::GITLAB, None, "build", "failed"), None);
+    }
+}
@@ -1,393 +1,3192 @@
+use std::env;
+use std::fs::{canonicalize, read_dir};
 use std::path::{Path, PathBuf};
 use std::fmt::Debug;
-use std::collections::HashMap;
-use globset::{Glob, GlobSetBuilder};
-use serde::Deserialize;
-use crate::utils::file;
-use crate::utils::file::{ConfigFile, ConfigFileTasks, ConfigFileTaskValue, TaskEngine};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use crate::utils::{cargo, deprecation, file, install, output, package_manager, process, time};
+use crate::utils::package_manager::{PACKAGE_JSON_FILE, PNPM_LOCK_FILE, YARN_LOCK_FILE};
+use crate::utils::install::InstallStep;
+use crate::utils::cargo::WorkspaceMember;
+use crate::utils::deprecation::DEPRECATIONS;
+use crate::utils::failure::FailurePattern;
+use crate::utils::interpolation::{interpolate, interpolate_command, interpolate_strict, interpolate_variables, merge_variables, resolve_variables, Variables};
+use crate::utils::file::{ConfigFile, ConfigFileCommand, ConfigFileDirectory, ConfigFileTask, ConfigFileTaskTemplate, ConfigFileTasks, ConfigFileTaskValue, ConfigFileTemplateTask, EngineTaskFilter, TaskCache, TaskEngine};
 
 #[derive(Debug, Clone)]
 pub enum TaskExit {
     SUCCESS,
-    // FAILURE
+    FAILURE
 }
 
 #[derive(Debug, Clone)]
 pub struct Task {
+    pub key: String,
+    // The steps joined with `&&` for tasks with steps, used for display and inspection
     pub command: String,
+    // Commands that run one after the other, the task fails on the first failing step
+    pub steps: Vec<String>,
     pub directory: PathBuf,
+    pub env: HashMap<String, String>,
+    pub options: TaskOptions,
+    // The interpreter of the command, the platform shell when none is configured
+    pub shell: Option<String>,
+    // Run in order before the task itself
+    pub dependencies: Vec<Task>,
+    // Absolute glob patterns of the files the task depends on, watched by rask watch
+    pub inputs: Vec<PathBuf>,
+    // Absolute glob patterns of the files the task produces
+    pub outputs: Vec<PathBuf>,
+    // Set for engine tasks of configs with ensure_installed
+    pub install: Option<InstallStep>,
+    // Where the task comes from, a yaml task or the script of an engine
+    pub task_type: TaskType,
 }
 
+// A config task is the same instance wherever it is scheduled from, the command tells
+// engine tasks sharing a key apart and differs when parents pass on other constants.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaskIdentity {
+    pub directory: PathBuf,
+    pub key: String,
+    pub command: String,
+}
+
+impl Task {
+    pub fn identity(&self) -> TaskIdentity {
+        TaskIdentity { directory: self.directory.clone(), key: self.key.clone(), command: self.command.clone() }
+    }
+}
+
+// The config directories from the entry config down to the config of a task
+pub type ConfigChain = Vec<PathBuf>;
+
 #[derive(Debug, Clone)]
 pub struct SortableTask {
     pub task: Task,
-    pub order: u64
+    pub order: u64,
+    // Every chain the task was scheduled through, more than one when configs are included twice
+    pub provenance: Vec<ConfigChain>,
 }
 
 pub type SortableTasks = Vec<SortableTask>;
 
-pub fn get_ordered_tasks(sortable_tasks: &SortableTasks, wanted_order: u64) -> Result<SortableTasks, String> {
-    let mut ordered_tasks: SortableTasks = vec![];
+pub fn get_ordered_tasks(sortable_tasks: &SortableTasks, wanted_order: u64) -> Result<Vec<&SortableTask>, String> {
+    let mut ordered_tasks: Vec<&SortableTask> = vec![];
 
     for task in sortable_tasks {
         let SortableTask { order, .. } = task;
         if *order == wanted_order {
-            ordered_tasks.push(task.clone())
+            ordered_tasks.push(task)
         }
     }
 
     Ok(ordered_tasks)
 }
 
-pub fn resolve_sortable_task(config_structure: ConfigStructure, task_name: &String, strict_match: &bool) -> Result<SortableTasks, String> {
+// `app#build`, `packages/app#build` and `app:build` run the exact task of one config, with its dependencies.
+// The config is given by its name or by its directory relative to the entry config.
+#[derive(Debug, Clone)]
+struct TaskAddress {
+    config_path: Option<PathBuf>,
+    task_name: String,
+}
+
+fn parse_task_address(config_structure: &ConfigStructure, task_name: &str) -> Result<TaskAddress, String> {
+    if let Some((config_selector, config_task_name)) = task_name.split_once('#') {
+        let config_path: PathBuf = find_addressed_config(config_structure, config_selector)
+            .ok_or(format!("Unknown config \"{}\" in task name \"{}\"", config_selector, task_name))?;
+
+        return Ok(TaskAddress { config_path: Some(config_path), task_name: config_task_name.to_string() });
+    }
+
+    // Keys like `build:docs` contain a colon too, an existing task key always wins
+    if let Some((config_selector, config_task_name)) = task_name.split_once(':') {
+        if !contains_task_key(config_structure, task_name) {
+            if let Some(config_path) = find_addressed_config(config_structure, config_selector) {
+                return Ok(TaskAddress { config_path: Some(config_path), task_name: config_task_name.to_string() });
+            }
+        }
+    }
+
+    Ok(TaskAddress { config_path: None, task_name: task_name.to_string() })
+}
+
+pub fn resolve_sortable_task(config_structure: &ConfigStructure, task_name: &str, strict_match: &bool) -> Result<SortableTasks, String> {
     let mut sortable_tasks: SortableTasks = vec![];
+    let task_address: TaskAddress = parse_task_address(config_structure, task_name)?;
 
-    sort_tasks(&mut sortable_tasks, config_structure, task_name, 0, strict_match);
+    sort_tasks(&mut sortable_tasks, config_structure, &task_address, 0, strict_match, &Inherited::default())?;
+
+    // Referenced tasks are added to the end, so they are visited as well
+    let mut position: usize = 0;
+    while position < sortable_tasks.len() {
+        schedule_task_references(&mut sortable_tasks, config_structure, position, &mut vec![])?;
+        position += 1;
+    }
+
+    order_after_tasks(&mut sortable_tasks, config_structure)?;
 
     Ok(sortable_tasks)
 }
 
-fn sort_tasks(ordered_tasks: &mut SortableTasks, config_structure: ConfigStructure, task_name: &String, index: u64, strict_match: &bool) {
-    let ConfigStructure { config, children } = config_structure;
-    let Config { tasks, dir_path, .. } = config;
+// Dependencies like ../shared-lib#build point into another config, by its directory relative to the config or by its name
+fn is_task_reference(dependency: &str) -> bool {
+    dependency.contains('#')
+}
+
+// The references of a task and of the dependencies that run through it, with the directory of the config they are in
+fn find_task_references(config_structure: &ConfigStructure, task: &Task) -> Vec<(PathBuf, String)> {
+    let mut references: Vec<(PathBuf, String)> = vec![];
 
-    for config_task in tasks {
-        let ConfigTask { ref key, .. } = config_task;
-        let key_matches = match strict_match {
-            true => key == task_name,
-            false => key.starts_with(task_name),
+    for task in [task].into_iter().chain(&task.dependencies) {
+        let config = find_config(config_structure, &|config| config.dir_path == task.directory && find_config_task(&config.tasks, &task.key).is_some());
+        let Some(config_task) = config.and_then(|config| find_config_task(&config.tasks, &task.key)) else {
+            continue;
         };
 
-        if key_matches {
-            ordered_tasks.push(SortableTask {
-                task: Task {
-                    command: resolve_config_task_command(&config_task.clone()),
-                    directory: dir_path.clone(), // compiler says it's being moved, No idea where...
-                },
-                order: index
-            })
+        for dependency in config_task.depends_on.iter().filter(|dependency| is_task_reference(dependency)) {
+            references.push((task.directory.clone(), dependency.clone()));
         }
     }
 
-    for child in children {
-        sort_tasks(ordered_tasks, child, task_name, index+1, strict_match);
-    }
+    references
 }
 
-#[derive(Debug, Clone)]
-pub struct ConfigStructure {
-    pub config: Config,
-    pub children: Vec<ConfigStructure>
+// The config file paths and keys of the tasks a task depends on, references to unknown configs are left out
+pub fn find_task_dependencies(config_structure: &ConfigStructure, config: &Config, config_task: &ConfigTask) -> Vec<(PathBuf, String)> {
+    config_task.depends_on
+        .iter()
+        .filter_map(|dependency| match is_task_reference(dependency) {
+            true => resolve_task_reference(config_structure, &config.dir_path, dependency)
+                .ok()
+                .and_then(|TaskAddress { config_path, task_name }| config_path.map(|config_path| (config_path, task_name))),
+            false => Some((config.file_path.clone(), dependency.clone())),
+        })
+        .collect()
 }
 
-pub fn resolve_config_structure(entry_config_path: &PathBuf, configs: Vec<Config>) -> Result<ConfigStructure, String> {
-    let mut path_map: HashMap<PathBuf, Config> = HashMap::new();
+fn resolve_task_reference(config_structure: &ConfigStructure, directory: &Path, reference: &str) -> Result<TaskAddress, String> {
+    let (config_selector, task_name) = reference.split_once('#').ok_or(format!("Invalid task reference \"{}\"", reference))?;
+    let selected_directory: Option<PathBuf> = canonicalize(directory.join(config_selector)).ok();
 
-    for config in configs {
-        path_map.insert(config.clone().file_path, config);
+    let config = find_config(config_structure, &|config| Some(&config.dir_path) == selected_directory.as_ref() || config.name == config_selector)
+        .ok_or(format!("Unknown config \"{}\" in dependency \"{}\" of {:?}", config_selector, reference, directory))?;
+
+    Ok(TaskAddress { config_path: Some(config.file_path.clone()), task_name: task_name.to_string() })
+}
+
+// A referenced task is scheduled a level deeper than the task that depends on it, so it has finished before that task starts.
+// The configs below it move along when the run is ordered afterwards, by order_after_tasks.
+fn schedule_task_references(ordered_tasks: &mut SortableTasks, config_structure: &ConfigStructure, position: usize, stack: &mut Vec<TaskIdentity>) -> Result<(), String> {
+    let SortableTask { task, order, .. } = ordered_tasks[position].clone();
+    stack.push(task.identity());
+
+    for (directory, reference) in find_task_references(config_structure, &task) {
+        let task_address: TaskAddress = resolve_task_reference(config_structure, &directory, &reference)?;
+        let mut referenced_tasks: SortableTasks = vec![];
+        sort_tasks(&mut referenced_tasks, config_structure, &task_address, 0, &true, &Inherited::default())?;
+
+        if referenced_tasks.is_empty() {
+            return Err(format!("Task \"{}\" depends on unknown task \"{}\"", task.key, reference));
+        }
+
+        for SortableTask { task: referenced_task, provenance, .. } in referenced_tasks {
+            let identity: TaskIdentity = referenced_task.identity();
+            if stack.contains(&identity) {
+                return Err(format!("Task \"{}\" has a circular dependency through \"{}\"", task.key, reference));
+            }
+
+            let referenced_position: usize = match ordered_tasks.iter().position(|sortable_task| sortable_task.task.identity() == identity) {
+                Some(existing_position) if ordered_tasks[existing_position].order > order => continue,
+                Some(existing_position) => {
+                    ordered_tasks[existing_position].order = order + 1;
+                    existing_position
+                },
+                None => {
+                    ordered_tasks.push(SortableTask { task: referenced_task, order: order + 1, provenance });
+                    ordered_tasks.len() - 1
+                },
+            };
+
+            schedule_task_references(ordered_tasks, config_structure, referenced_position, stack)?;
+        }
     }
 
-    let config_structure: ConfigStructure = construct_config_structure(entry_config_path, &path_map)?;
+    stack.pop();
 
-    Ok(config_structure)
+    Ok(())
 }
 
-fn construct_config_structure(config_path: &PathBuf, config_path_map: &HashMap<PathBuf, Config>) -> Result<ConfigStructure, String> {
-    let config = config_path_map.get(config_path).ok_or("Unknown config path")?;
+// The positions of the scheduled tasks grouped with the tasks they are ordered against, through any number of
+// edges. The edges are the ones the levels are ordered by: after, references and the config tree.
+pub fn group_ordered_tasks(sortable_tasks: &SortableTasks, config_structure: &ConfigStructure) -> Vec<Vec<usize>> {
+    let mut group_ids: Vec<usize> = (0..sortable_tasks.len()).collect();
 
-    let paths: Vec<PathBuf> = config_path_map.keys().cloned().collect();
-    let Config { directories, .. } = config;
-    let config_directory: &Path = config_path.parent().unwrap();
-    let mut child_paths: Vec<PathBuf> = vec![];
+    for (position, preceding_position) in find_ordering_edges(sortable_tasks, config_structure) {
+        // Merges the two groups, every member of the one takes the id of the other
+        let (from_id, to_id) = (group_ids[preceding_position], group_ids[position]);
+        for group_id in group_ids.iter_mut().filter(|group_id| **group_id == from_id) {
+            *group_id = to_id;
+        }
+    }
 
-    for directory in directories {
-        let path_pattern: PathBuf = get_config_glob_pattern(config_directory, &directory);
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut seen_ids: Vec<usize> = vec![];
+    for group_id in &group_ids {
+        if !seen_ids.contains(group_id) {
+            seen_ids.push(*group_id);
+            groups.push((0..group_ids.len()).filter(|position| group_ids[*position] == *group_id).collect());
+        }
+    }
+
+    groups
+}
+
+// The positions of the scheduled tasks that have to finish first, from after and from references
+fn find_preceding_positions(sortable_tasks: &SortableTasks, config_structure: &ConfigStructure, position: usize) -> Vec<usize> {
+    let task: &Task = &sortable_tasks[position].task;
+    let mut preceding_tasks: Vec<(PathBuf, String)> = vec![];
 
-        let pattern = match Glob::new(path_pattern.to_str().unwrap()) {
-            Ok(pattern) => pattern,
-            Err(err) => return Err(format!("Failed to create glob pattern: {:?}", err)),
+    for task in [task].into_iter().chain(&task.dependencies) {
+        let config = find_config(config_structure, &|config| config.dir_path == task.directory && find_config_task(&config.tasks, &task.key).is_some());
+        let Some(config_task) = config.and_then(|config| find_config_task(&config.tasks, &task.key)) else {
+            continue;
         };
-        let mut builder = GlobSetBuilder::new();
-        builder.add(pattern);
-        let glob_set = builder.build().unwrap();
 
-        for path in &paths {
-            if glob_set.is_match(path) {
-                child_paths.push(path.to_path_buf());
+        for preceding in config_task.after.iter().chain(config_task.depends_on.iter().filter(|dependency| is_task_reference(dependency))) {
+            match is_task_reference(preceding) {
+                // An unknown config is as absent as an unknown task, it only orders what exists
+                true => if let Ok(TaskAddress { config_path: Some(config_path), task_name }) = resolve_task_reference(config_structure, &task.directory, preceding) {
+                    preceding_tasks.extend(config_path.parent().map(|directory| (directory.to_path_buf(), task_name)));
+                },
+                false => preceding_tasks.push((task.directory.clone(), preceding.clone())),
             }
         }
     }
 
-    let config_structure = ConfigStructure {
-        config: config.clone(),
-        children: child_paths
-            .iter()
-            .map(|path| construct_config_structure(path, config_path_map).unwrap())
-            .collect()
-    };
+    sortable_tasks
+        .iter()
+        .enumerate()
+        .filter(|(other_position, SortableTask { task, .. })| *other_position != position && preceding_tasks.contains(&(task.directory.clone(), task.key.clone())))
+        .map(|(other_position, _)| other_position)
+        .collect()
+}
 
-    Ok(config_structure)
+// The offsets of the configs below every config, by the directories of the parent and the child
+fn collect_child_offsets<'a>(config_structure: &'a ConfigStructure, child_offsets: &mut HashMap<(&'a Path, &'a Path), u64>) {
+    for child in &config_structure.children {
+        child_offsets.insert((config_structure.config.dir_path.as_path(), child.config.dir_path.as_path()), child.offset);
+        collect_child_offsets(child, child_offsets);
+    }
 }
 
-#[derive(Debug, Clone, Default, Copy)]
-pub enum TaskType {
-    #[default]
-    SHELL,
-    COMPOSER,
-    NPM,
-    YARN,
+// Whether the config tree runs the config of a chain first: configs below another config run before it,
+// and the configs of an earlier directories entry before those of the later entries
+fn is_chain_before(config_chain: &ConfigChain, other_chain: &ConfigChain, child_offsets: &HashMap<(&Path, &Path), u64>) -> bool {
+    let shared: usize = config_chain.iter().zip(other_chain).take_while(|(directory, other_directory)| directory == other_directory).count();
+
+    match (shared.checked_sub(1).map(|parent| &config_chain[parent]), config_chain.get(shared), other_chain.get(shared)) {
+        (Some(_), Some(_), None) => true,
+        (Some(parent), Some(directory), Some(other_directory)) => {
+            let get_offset = |directory: &PathBuf| child_offsets.get(&(parent.as_path(), directory.as_path())).copied().unwrap_or_default();
+            get_offset(directory) > get_offset(other_directory)
+        },
+        _ => false,
+    }
 }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct ConfigTask {
-    pub(crate) task_type: TaskType,
-    pub(crate) key: String,
-    pub(crate) value: String,
+// The positions of the scheduled tasks that the config tree runs before a task
+fn find_structural_positions(sortable_tasks: &SortableTasks, child_offsets: &HashMap<(&Path, &Path), u64>, position: usize) -> Vec<usize> {
+    let provenance: &Vec<ConfigChain> = &sortable_tasks[position].provenance;
+
+    sortable_tasks
+        .iter()
+        .enumerate()
+        .filter(|(other_position, SortableTask { provenance: other_provenance, .. })| {
+            *other_position != position && other_provenance.iter().any(|other_chain| provenance.iter().any(|config_chain| is_chain_before(other_chain, config_chain, child_offsets)))
+        })
+        .map(|(other_position, _)| other_position)
+        .collect()
 }
 
-pub fn resolve_config_task_command(config_task: &ConfigTask) -> String {
-    let ConfigTask { task_type, key, value } = config_task;
+// Every task with the tasks that have to finish first, by after, references and the config tree
+fn find_ordering_edges(sortable_tasks: &SortableTasks, config_structure: &ConfigStructure) -> Vec<(usize, usize)> {
+    let mut child_offsets: HashMap<(&Path, &Path), u64> = HashMap::new();
+    collect_child_offsets(config_structure, &mut child_offsets);
+
+    (0..sortable_tasks.len())
+        .flat_map(|position| {
+            let mut preceding_positions: Vec<usize> = find_preceding_positions(sortable_tasks, config_structure, position);
+            preceding_positions.extend(find_structural_positions(sortable_tasks, &child_offsets, position));
+            preceding_positions.into_iter().map(move |preceding_position| (position, preceding_position))
+        })
+        .collect()
+}
 
-    match task_type {
-        TaskType::SHELL => value.clone(),
-        TaskType::COMPOSER => format!("composer run {}", key),
-        TaskType::NPM => format!("npm run {}", key),
-        TaskType::YARN => format!("yarn run {}", key),
+// Tasks after other scheduled tasks move to a level above them, the tasks they are after are not added to the run.
+// The references are ordered again, as a moved task takes the tasks it references along,
+// and so is the config tree, as a referenced task moving a level deeper takes the configs below it along.
+fn order_after_tasks(sortable_tasks: &mut SortableTasks, config_structure: &ConfigStructure) -> Result<(), String> {
+    // Without after and references, the levels of the config tree are in order already
+    if (0..sortable_tasks.len()).all(|position| find_preceding_positions(sortable_tasks, config_structure, position).is_empty()) {
+        return Ok(());
+    }
+    let edges: Vec<(usize, usize)> = find_ordering_edges(sortable_tasks, config_structure);
+
+    // Every round settles at least one more task, so more rounds than tasks means the tasks wait on each other
+    for _ in 0..=sortable_tasks.len() {
+        let mut is_changed: bool = false;
+
+        for (position, preceding_position) in &edges {
+            if sortable_tasks[*preceding_position].order <= sortable_tasks[*position].order {
+                sortable_tasks[*preceding_position].order = sortable_tasks[*position].order + 1;
+                is_changed = true;
+            }
+        }
+
+        if !is_changed {
+            return Ok(());
+        }
     }
+
+    let mut keys: Vec<String> = edges.iter().map(|(position, _)| sortable_tasks[*position].task.key.clone()).collect();
+    keys.dedup();
+    Err(format!("The after dependencies of tasks {} are circular", keys.join(", ")))
 }
 
-type ConfigTasks = Vec<ConfigTask>;
-type ConfigDirectories = Vec<String>;
+fn find_addressed_config(config_structure: &ConfigStructure, config_selector: &str) -> Option<PathBuf> {
+    let entry_directory: &PathBuf = &config_structure.config.dir_path;
+    let selected_directory: Option<PathBuf> = canonicalize(entry_directory.join(config_selector)).ok();
 
-#[derive(Debug, Clone)]
-pub struct Config {
-    #[allow(dead_code)]
-    pub(crate) name: String,
-    pub(crate) tasks: ConfigTasks,
-    pub(crate) file_path: PathBuf,
-    pub(crate) dir_path: PathBuf,
-    pub(crate) directories: ConfigDirectories,
+    find_config(config_structure, &|config| config.name == config_selector || Some(&config.dir_path) == selected_directory.as_ref())
+        .map(|config| config.file_path.clone())
 }
 
-pub fn parse_config_files(config_files: Vec<ConfigFile>) -> Result<Vec<Config>, String> {
-    let mut configs: Vec<Config> = vec![];
+fn find_config<'a>(config_structure: &'a ConfigStructure, predicate: &dyn Fn(&Config) -> bool) -> Option<&'a Config> {
+    let ConfigStructure { config, children, .. } = config_structure;
 
-    for config_file in config_files {
-        let config = parse_config_file(config_file)?;
-        configs.push(config);
+    match predicate(config) {
+        true => Some(config.as_ref()),
+        false => children.iter().find_map(|child| find_config(child, predicate)),
     }
+}
 
-    Ok(configs)
+fn contains_task_key(config_structure: &ConfigStructure, key: &str) -> bool {
+    find_config(config_structure, &|config| config.tasks.iter().any(|config_task| config_task.key == key)).is_some()
 }
 
-fn parse_config_file(config_file: ConfigFile) -> Result<Config, String> {
-    let ConfigFile { name, directories, task_engine, tasks: config_file_tasks, .. } = config_file;
-    let ConfigFile { __file_path: file_path, __dir_path: dir_path, .. } = config_file;
+// What a config passes on to the configs it includes
+#[derive(Debug, Clone, Default)]
+struct Inherited {
+    constants: Variables,
+    shell: Option<String>,
+    config_chain: ConfigChain,
+}
 
-    let tasks: ConfigTasks = match task_engine {
-        TaskEngine::COMPOSER => parse_composer_json_tasks(&dir_path)?,
-        TaskEngine::NPM => parse_package_json_tasks(&dir_path, TaskType::NPM)?,
-        TaskEngine::YARN => parse_package_json_tasks(&dir_path, TaskType::YARN)?,
-        TaskEngine::NONE => parse_config_tasks(config_file_tasks)?,
-        TaskEngine::AUTO => parse_discovered_tasks(&dir_path, config_file_tasks)?,
+fn sort_tasks(ordered_tasks: &mut SortableTasks, config_structure: &ConfigStructure, task_address: &TaskAddress, index: u64, strict_match: &bool, inherited: &Inherited) -> Result<(), String> {
+    let ConfigStructure { config, children, .. } = config_structure;
+    let Config { tasks, dir_path, constants, env, shell, serial, ensure_installed, .. } = config.as_ref();
+    let TaskAddress { config_path, task_name } = task_address;
+    let shell: Option<String> = shell.clone().or(inherited.shell.clone());
+    let mut config_chain: ConfigChain = inherited.config_chain.clone();
+    config_chain.push(dir_path.clone());
+
+    // Child constants override the ones inherited from the parent configs,
+    // the unresolved values are passed on so computed constants pick up overrides.
+    let constants: Variables = merge_variables(&inherited.constants, constants);
+    let resolved_constants: Variables = resolve_variables(&constants).map_err(|err| format!("Unable to resolve the constants of {:?}: {}", config.file_path, err))?;
+    let env: Variables = interpolate_variables(env, &resolved_constants);
+
+    let create_task = |config_task: &ConfigTask, dependencies: Vec<Task>| Task {
+        key: config_task.key.clone(),
+        command: interpolate_command(&resolve_config_task_command(config_task), &resolved_constants),
+        steps: config_task.steps.iter().map(|step| interpolate_command(step, &resolved_constants)).collect(),
+        directory: dir_path.clone(),
+        env: env.clone(),
+        options: TaskOptions { serial: *serial, ..config_task.options.clone() },
+        shell: shell.clone(),
+        dependencies,
+        inputs: config_task.inputs.iter().map(|input| dir_path.join(interpolate(input, &resolved_constants))).collect(),
+        outputs: config_task.outputs.iter().map(|output| dir_path.join(interpolate(output, &resolved_constants))).collect(),
+        install: ensure_installed.then(|| install::get_install_step(&config_task.task_type)).flatten(),
+        task_type: config_task.task_type,
     };
 
-    let config: Config = Config { name, tasks, file_path, dir_path, directories };
+    // Addressed configs are still walked through, the configs below them inherit from them
+    let is_addressed: bool = config_path.as_ref().is_none_or(|config_path| *config_path == config.file_path);
+    let matching_tasks: Vec<&ConfigTask> = tasks
+        .iter()
+        .filter(|_| is_addressed)
+        .filter(|ConfigTask { key, .. }| match *strict_match || config_path.is_some() {
+            true => key == task_name,
+            false => key.starts_with(task_name),
+        })
+        .collect();
+
+    let mut dependency_keys: Vec<Vec<String>> = vec![];
+    for config_task in &matching_tasks {
+        let mut resolved_keys: Vec<String> = vec![];
+        resolve_task_dependencies(&config_task.key, tasks, &mut vec![], &mut resolved_keys)?;
+        dependency_keys.push(resolved_keys);
+    }
 
-    Ok(config)
-}
+    for (config_task, resolved_keys) in matching_tasks.iter().zip(&dependency_keys) {
+        // Matching tasks that are a dependency of another matching task already run through that task
+        if dependency_keys.iter().any(|keys| keys.contains(&config_task.key)) {
+            continue;
+        }
 
-const PACKAGE_JSON_FILE: &str = "package.json";
-const YARN_LOCK_FILE: &str = "yarn.lock";
-const COMPOSER_JSON_FILE: &str = "composer.json";
+        let dependencies: Vec<Task> = resolved_keys
+            .iter()
+            .filter_map(|key| find_config_task(tasks, key))
+            .map(|dependency| create_task(dependency, vec![]))
+            .collect();
 
-fn parse_discovered_tasks(dir_path: &PathBuf, config_file_tasks: ConfigFileTasks) -> Result<ConfigTasks, String> {
-    let mut config_tasks: ConfigTasks = parse_config_tasks(config_file_tasks)?;
+        schedule_task(ordered_tasks, create_task(config_task, dependencies), index, &config_chain);
+    }
 
-    // Gathering facts
-    let has_composer_json = dir_path.join(COMPOSER_JSON_FILE).exists();
-    let has_package_json = dir_path.join(PACKAGE_JSON_FILE).exists();
-    let has_yarn_lock = dir_path.join(YARN_LOCK_FILE).exists();
+    let inherited = Inherited { constants, shell, config_chain };
+    for child in children {
+        sort_tasks(ordered_tasks, child, task_address, index + 1 + child.offset, strict_match, &inherited)?;
+    }
 
+    Ok(())
+}
 
-    if has_composer_json {
-        let composer_config_tasks = parse_composer_json_tasks(dir_path)?;
-        config_tasks.extend(composer_config_tasks.into_iter())
-    }
+// The first task with a key is the addressable one, yaml tasks come before engine tasks
+fn find_config_task<'a>(config_tasks: &'a ConfigTasks, key: &String) -> Option<&'a ConfigTask> {
+    config_tasks.iter().find(|config_task| &config_task.key == key)
+}
 
-    if has_package_json {
-        let package_config_tasks: ConfigTasks;
+// Collects the dependencies of a task depth first, so every dependency comes after its own dependencies
+fn resolve_task_dependencies(key: &String, config_tasks: &ConfigTasks, stack: &mut Vec<String>, resolved_keys: &mut Vec<String>) -> Result<(), String> {
+    let config_task = find_config_task(config_tasks, key).ok_or(format!("Unknown task \"{}\"", key))?;
+    stack.push(key.clone());
 
-        if has_yarn_lock {
-            package_config_tasks = parse_package_json_tasks(dir_path, TaskType::YARN)?;
-        } else {
-            // No lock file, for now we assume the uses intends to use NPM.
-            package_config_tasks = parse_package_json_tasks(dir_path, TaskType::NPM)?;
+    for dependency in config_task.depends_on.iter().filter(|dependency| !is_task_reference(dependency)) {
+        if stack.contains(dependency) {
+            return Err(format!("Task \"{}\" has a circular dependency: {} -> {}", key, stack.join(" -> "), dependency));
         }
 
-        config_tasks.extend(package_config_tasks);
+        if !resolved_keys.contains(dependency) {
+            resolve_task_dependencies(dependency, config_tasks, stack, resolved_keys)?;
+            resolved_keys.push(dependency.clone());
+        }
     }
 
-    Ok(config_tasks)
-}
+    stack.pop();
 
-#[derive(Debug, Clone, Deserialize, Default)]
-struct PackageJsonFile {
-    #[serde(default)]
-    scripts: HashMap<String, String>,
+    Ok(())
 }
 
-fn parse_package_json_tasks(dir_path: &PathBuf, task_type: TaskType) -> Result<ConfigTasks, String> {
-    let package_json = file::read_json_file::<PackageJsonFile>(&dir_path.join(PACKAGE_JSON_FILE))?;
+// Commands of the entry config that run around every scheduling level
+#[derive(Debug, Clone, Default)]
+pub struct LevelHooks {
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
 
-    let mut config_tasks: ConfigTasks = vec![];
-    for key in package_json.scripts.keys() {
-        config_tasks.push(ConfigTask {
-            task_type,
-            key: key.clone(),
-            value: key.clone()
-        });
+// A task reached a second time only records where it came from, it keeps the deepest level
+// so it still runs before the tasks of every config that includes it.
+fn schedule_task(ordered_tasks: &mut SortableTasks, task: Task, order: u64, config_chain: &ConfigChain) {
+    let identity = task.identity();
+
+    match ordered_tasks.iter_mut().find(|sortable_task| sortable_task.task.identity() == identity) {
+        Some(sortable_task) => {
+            sortable_task.order = sortable_task.order.max(order);
+            sortable_task.provenance.push(config_chain.clone());
+        },
+        None => ordered_tasks.push(SortableTask { task, order, provenance: vec![config_chain.clone()] }),
     }
+}
 
-    Ok(config_tasks)
+// Tasks of several task names run in one schedule, a task both reach runs once at its deepest level
+pub fn merge_sortable_tasks(sortable_tasks: &mut SortableTasks, other_tasks: SortableTasks) {
+    for SortableTask { task, order, provenance } in other_tasks {
+        let identity = task.identity();
+
+        match sortable_tasks.iter_mut().find(|sortable_task| sortable_task.task.identity() == identity) {
+            Some(sortable_task) => {
+                sortable_task.order = sortable_task.order.max(order);
+                for config_chain in provenance {
+                    if !sortable_task.provenance.contains(&config_chain) {
+                        sortable_task.provenance.push(config_chain);
+                    }
+                }
+            },
+            None => sortable_tasks.push(SortableTask { task, order, provenance }),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)]
-enum ComposerJsonScriptValue {
-    Single(String),
-    Multiple(Vec<String>),
+// Tasks only the entry config defines run once in every config, RASK_CONFIG_* tells each run where it is
+pub fn propagate_entry_tasks(config_structure: &ConfigStructure, sortable_tasks: SortableTasks) -> SortableTasks {
+    let entry_directory: &PathBuf = &config_structure.config.dir_path;
+    let configs: Vec<(&Config, u64, ConfigChain)> = list_configs(config_structure);
+
+    let entry_tasks: Vec<SortableTask> = sortable_tasks
+        .iter()
+        .filter(|SortableTask { task, .. }| &task.directory == entry_directory)
+        .filter(|SortableTask { task, .. }| !configs.iter().skip(1).any(|(config, _, _)| config.tasks.iter().any(|config_task| config_task.key == task.key)))
+        .cloned()
+        .collect();
+
+    // The entry config runs its own copy too, with RASK_CONFIG_* set like everywhere else
+    let mut propagated_tasks: SortableTasks = sortable_tasks
+        .into_iter()
+        .filter(|sortable_task| !entry_tasks.iter().any(|entry_task| entry_task.task.identity() == sortable_task.task.identity()))
+        .collect();
+    for SortableTask { task, .. } in entry_tasks {
+        for (config, order, config_chain) in &configs {
+            schedule_task(&mut propagated_tasks, relocate_task(&task, entry_directory, config), *order, config_chain);
+        }
+    }
+
+    propagated_tasks
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
-struct ComposerJsonFile {
-    #[serde(default)]
-    scripts: HashMap<String, ComposerJsonScriptValue>,
+// Every config once with its level and config chain, the entry config first
+pub fn list_configs(config_structure: &ConfigStructure) -> Vec<(&Config, u64, ConfigChain)> {
+    let mut configs: Vec<(&Config, u64, ConfigChain)> = vec![];
+    collect_configs(config_structure, 0, &vec![], &mut configs);
+
+    configs
 }
 
-fn parse_composer_json_tasks(dir_path: &PathBuf) -> Result<ConfigTasks, String> {
-    let package_json = file::read_json_file::<ComposerJsonFile>(&dir_path.join(COMPOSER_JSON_FILE))?;
+// Every config once, at the deepest level it is included at
+fn collect_configs<'a>(config_structure: &'a ConfigStructure, order: u64, parent_chain: &ConfigChain, configs: &mut Vec<(&'a Config, u64, ConfigChain)>) {
+    let ConfigStructure { config, children, .. } = config_structure;
+    let mut config_chain: ConfigChain = parent_chain.clone();
+    config_chain.push(config.dir_path.clone());
 
-    let mut config_tasks: ConfigTasks = vec![];
-    for key in package_json.scripts.keys() {
-        config_tasks.push(ConfigTask {
-            task_type: TaskType::COMPOSER,
-            key: key.clone(),
-            value: key.clone(),
-        });
+    match configs.iter_mut().find(|(existing, _, _)| existing.file_path == config.file_path) {
+        Some((_, existing_order, _)) => *existing_order = (*existing_order).max(order),
+        None => configs.push((config.as_ref(), order, config_chain.clone())),
     }
 
-    Ok(config_tasks)
+    for child in children {
+        collect_configs(child, order + 1 + child.offset, &config_chain, configs);
+    }
 }
 
-fn flatten_config_tasks(tasks: &ConfigFileTasks, prefix: &str, results: &mut ConfigTasks) {
-    for (task_name, task_value) in tasks {
-        let key = match prefix.len() == 0 {
-            true => task_name.clone(),
-            false => format!("{}:{}", prefix, task_name)
-        };
+// Moves a task of the entry config, and the paths it declares, into another config
+fn relocate_task(task: &Task, entry_directory: &Path, config: &Config) -> Task {
+    let relocate = |path: &PathBuf| match path.strip_prefix(entry_directory) {
+        Ok(relative_path) => config.dir_path.join(relative_path),
+        Err(_) => path.clone(),
+    };
 
-        match task_value {
-            ConfigFileTaskValue::String(value) => {
-                results.push(ConfigTask{
-                    task_type: TaskType::SHELL,
-                    key,
-                    value: value.clone()
-                });
-            }
-            ConfigFileTaskValue::ConfigFileTasks(subtasks) => {
-                flatten_config_tasks(subtasks, &key, results);
-            }
-        }
+    let mut env: Variables = task.env.clone();
+    env.insert("RASK_CONFIG_NAME".to_string(), config.name.clone());
+    env.insert("RASK_CONFIG_DIR".to_string(), config.dir_path.to_string_lossy().to_string());
+    env.insert("RASK_CONFIG_FILE".to_string(), config.file_path.to_string_lossy().to_string());
+
+    Task {
+        directory: config.dir_path.clone(),
+        env,
+        dependencies: task.dependencies.iter().map(|dependency| relocate_task(dependency, entry_directory, config)).collect(),
+        inputs: task.inputs.iter().map(relocate).collect(),
+        outputs: task.outputs.iter().map(relocate).collect(),
+        ..task.clone()
     }
 }
 
-fn parse_config_tasks(tasks: ConfigFileTasks) -> Result<ConfigTasks, String> {
-    let mut config_tasks: ConfigTasks = vec![];
-
-    flatten_config_tasks(&tasks, "", &mut config_tasks);
+pub fn resolve_level_hooks(entry_config: &Config) -> Result<LevelHooks, String> {
+    let Config { before_level, after_level, dir_path, file_path, constants, env, shell, .. } = entry_config;
+    let constants: Variables = resolve_variables(constants).map_err(|err| format!("Unable to resolve the constants of {:?}: {}", file_path, err))?;
+    let env: Variables = interpolate_variables(env, &constants);
+
+    let create_hook = |key: &str, command: &String| Task {
+        key: key.to_string(),
+        command: interpolate_command(command, &constants),
+        steps: vec![],
+        directory: dir_path.clone(),
+        env: env.clone(),
+        options: TaskOptions::default(),
+        shell: shell.clone(),
+        dependencies: vec![],
+        inputs: vec![],
+        outputs: vec![],
+        install: None,
+        task_type: TaskType::SHELL,
+    };
 
-    Ok(config_tasks)
+    Ok(LevelHooks {
+        before: before_level.as_ref().map(|command| create_hook("before_level", command)),
+        after: after_level.as_ref().map(|command| create_hook("after_level", command)),
+    })
 }
 
-pub fn read_config_files(config_file_paths: Vec<PathBuf>) -> Result<Vec<ConfigFile>, String> {
-    let mut configs_files: Vec<ConfigFile> = vec![];
+// Configs are shared, a config included by several parents is not copied for each of them
+#[derive(Debug, Clone)]
+pub struct ConfigStructure {
+    pub config: Arc<Config>,
+    pub children: Vec<ConfigStructure>,
+    // Levels the tasks of the config run below the level of its depth, so they finish before siblings that come later
+    pub offset: u64,
+}
 
-    for config_file_path in config_file_paths {
-        let config_file = file::read_config_file(config_file_path)?;
-        configs_files.push(config_file);
+// Without a rask.yaml and without a workspace to expand, the detected engine file is all there is
+pub fn is_single_package(entry_config_path: &Path, configs: &[Config]) -> bool {
+    match configs {
+        [config] => !entry_config_path.exists() && config.directories.is_empty(),
+        _ => false,
     }
+}
 
-    Ok(configs_files)
+// The structure of a single package, without globbing for children it can not have
+pub fn create_single_package_structure(mut configs: Vec<Config>) -> Result<ConfigStructure, String> {
+    let config: Config = configs.pop().ok_or("Failed to find the config of the package")?;
+
+    Ok(ConfigStructure { config: Arc::new(config), children: vec![], offset: 0 })
 }
 
-pub fn discover_config_paths(path: &PathBuf) -> Result<Vec<PathBuf>, String> {
-    let mut found_config_paths: Vec<PathBuf> = vec![path.clone()];
+pub fn resolve_config_structure(entry_config_path: &PathBuf, configs: Vec<Config>) -> Result<ConfigStructure, String> {
+    let mut path_map: HashMap<PathBuf, Arc<Config>> = HashMap::new();
+
+    for config in configs {
+        path_map.insert(config.file_path.clone(), Arc::new(config));
+    }
 
-    // Read config
-    let mut path_stack: Vec<PathBuf> = vec![path.clone()];
-    while !path_stack.is_empty() {
-        let ConfigFile { directories, __file_path: _file_path, .. } = file::read_config_file(path_stack.pop().unwrap())?;
-
-        // Extract directories
-        let config_directory = _file_path.parent().ok_or("Failed to get parent directory")?;
-        for directory in directories {
-            let pattern = get_config_glob_pattern(config_directory, &directory);
-
-            // Find config files based on the pattern in the directories value
-            let pattern_string: &str = pattern.to_str().unwrap();
-            for pattern_results in glob::glob(pattern_string).map_err(|e| format!("Failed to read glob pattern: {}", e))? {
-                if let Ok(found_config_path) = pattern_results {
-                    // Only add if the path was not already processed, preventing loops.
-                    if !found_config_paths.contains(&found_config_path) {
-                        found_config_paths.push(found_config_path.clone());
-                        path_stack.push(found_config_path.clone());
-                    }
-                }
-            }
+    // The other documents of a multi-document file belong to its first document
+    let mut document_map: HashMap<PathBuf, Vec<&PathBuf>> = HashMap::new();
+    for path in path_map.keys() {
+        if let Some(file_path) = file::get_document_file(path) {
+            document_map.entry(file_path).or_default().push(path);
         }
     }
 
-    Ok(found_config_paths)
+    let config_structure: ConfigStructure = construct_config_structure(entry_config_path, &path_map, &document_map, &[])?;
+
+    Ok(config_structure)
 }
 
-fn get_config_glob_pattern(root_path: &Path, glob_pattern: &String) -> PathBuf {
-    let mut pattern: PathBuf = root_path.to_path_buf();
+// The order of the directories entry that matched a child config and its position when the entry is not parallel
+type DirectorySlot = (u64, usize);
 
-    pattern.push(glob_pattern);
-    if !pattern.ends_with(".yaml") {
-        pattern.push("rask.yaml");
-    }
+// The levels below a config, its tasks run at the level of the config itself
+fn get_structure_height(config_structure: &ConfigStructure) -> u64 {
+    config_structure.children.iter().map(|child| 1 + child.offset + get_structure_height(child)).max().unwrap_or(0)
+}
 
-    pattern
+// Every distinct slot runs after the slots before it, with everything below them, so a slot goes below the levels of the slots after it
+fn set_child_offsets(children: &mut [ConfigStructure], child_slots: &[DirectorySlot]) {
+    let mut slots: Vec<DirectorySlot> = child_slots.to_vec();
+    slots.sort();
+    slots.dedup();
+
+    let slot_heights: Vec<u64> = slots
+        .iter()
+        .map(|slot| children.iter().zip(child_slots).filter(|(_, child_slot)| *child_slot == slot).map(|(child, _)| 1 + get_structure_height(child)).max().unwrap_or(1))
+        .collect();
+
+    for (child, child_slot) in children.iter_mut().zip(child_slots) {
+        let position: usize = slots.iter().position(|slot| slot == child_slot).unwrap_or_default();
+        child.offset = slot_heights[position + 1..].iter().sum();
+    }
 }
 
-pub fn resolve_config_path<P: AsRef<Path> + Debug + Clone + Copy>(path: P) -> Result<PathBuf, String> {
-    let full_path = file::parse_path_string(path)?;
+fn construct_config_structure(config_path: &PathBuf, config_path_map: &HashMap<PathBuf, Arc<Config>>, document_map: &HashMap<PathBuf, Vec<&PathBuf>>, ancestor_paths: &[&PathBuf]) -> Result<ConfigStructure, String> {
+    let config = config_path_map.get(config_path).ok_or(format!("Unknown config path {:?}", config_path))?;
 
-    if full_path.is_dir() {
-        let config_file = find_config_file(full_path)?;
-        return Ok(config_file)
+    let Config { directories, dir_path: config_directory, .. } = config.as_ref();
+    // Other documents of the file run side by side with the configs of plain patterns
+    let mut child_paths: Vec<(&PathBuf, DirectorySlot)> = document_map.get(config_path).cloned().unwrap_or_default().into_iter().map(|path| (path, (u64::MAX, 0))).collect();
+
+    let DirectoryPatterns { includes, excludes, .. } = parse_directory_patterns(config_directory, directories)?;
+    for (directory, path_pattern) in includes {
+        let pattern = create_glob(&path_pattern)?;
+        let mut builder = GlobSetBuilder::new();
+        builder.add(pattern);
+        let glob_set = builder.build().map_err(|err| format!("Failed to create glob pattern {:?} of {:?}: {}", path_pattern, config_path, err))?;
+
+        let mut matched_paths: Vec<&PathBuf> = config_path_map
+            .keys()
+            // Configs including their own ancestors would never end
+            .filter(|path| *path != config_path && !ancestor_paths.contains(path))
+            .filter(|path| glob_set.is_match(path) && !excludes.is_match(path) && !child_paths.iter().any(|(child_path, _)| child_path == path))
+            .collect();
+        matched_paths.sort();
+
+        let (order, parallel) = directory.get_ordering();
+        for (position, path) in matched_paths.into_iter().enumerate() {
+            child_paths.push((path, (order.unwrap_or(u64::MAX), if parallel { 0 } else { position })));
+        }
     }
 
-    Ok(full_path)
+    let mut child_ancestor_paths: Vec<&PathBuf> = ancestor_paths.to_vec();
+    child_ancestor_paths.push(config_path);
+
+    let mut children: Vec<ConfigStructure> = child_paths
+        .iter()
+        .map(|(path, _)| construct_config_structure(path, config_path_map, document_map, &child_ancestor_paths))
+        .collect::<Result<Vec<ConfigStructure>, String>>()?;
+    let child_slots: Vec<DirectorySlot> = child_paths.iter().map(|(_, slot)| *slot).collect();
+    set_child_offsets(&mut children, &child_slots);
+
+    let config_structure = ConfigStructure {
+        config: Arc::clone(config),
+        children,
+        offset: 0,
+    };
+
+    Ok(config_structure)
 }
 
-const CONFIG_FILENAMES: [&str; 1] = ["rask.yaml"];
+#[derive(Debug, Clone, Default, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskType {
+    #[default]
+    SHELL,
+    COMPOSER,
+    NPM,
+    YARN,
+    PNPM,
+    CARGO,
+    TASKFILE,
+    JUST,
+    COMPOSE,
+}
 
-fn find_config_file(directory_path: PathBuf) -> Result<PathBuf, String> {
-    if !directory_path.is_dir() {
-        return Err(format!("\"{:?}\" is not a directory", directory_path))
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct ConfigTask {
+    pub(crate) task_type: TaskType,
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) steps: Vec<String>,
+    pub(crate) options: TaskOptions,
+    pub(crate) depends_on: Vec<String>,
+    pub(crate) after: Vec<String>,
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<String>,
+    pub(crate) description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TaskOptions {
+    pub nice: Option<i32>,
+    // In bytes
+    pub max_memory: Option<u64>,
+    pub allow_failure: bool,
+    // Run as another user, implies sudo
+    pub user: Option<String>,
+    pub sudo: bool,
+    // Taken from the config, tasks of a serial config never run at the same time
+    pub serial: bool,
+    pub cache: Option<TaskCache>,
+    // Inherit only PATH and the pass_env variables from the environment rask runs in
+    pub clean_env: bool,
+    pub pass_env: Vec<String>,
+    // Limits the output shown in the terminal, in lines and bytes
+    pub max_output_lines: Option<u64>,
+    pub max_output_bytes: Option<u64>,
+    // Sent when the task is cancelled, without the SIG prefix, TERM when not set
+    pub stop_signal: Option<String>,
+    pub stop_grace_period: Option<Duration>,
+    // The platforms the task runs on, named like Rust names them, empty runs everywhere
+    pub os: Vec<String>,
+    pub arch: Vec<String>,
+    // Asked for before the run starts, --yes skips the question
+    pub confirm: bool,
+    // Skipped when the task succeeded less than this long ago, --force runs it anyway
+    pub cooldown: Option<Duration>,
+    // Fails the task when a line of its output matches, even when it exits with 0
+    pub failure_pattern: Option<FailurePattern>,
+    // The job slots the task takes in parallel runs, 1 when not set
+    pub weight: Option<u64>,
+}
+
+impl TaskOptions {
+    pub fn get_weight(&self) -> usize {
+        self.weight.unwrap_or(1) as usize
     }
 
-    for filename in CONFIG_FILENAMES {
-        let possible_config_file = directory_path.join(filename);
-        match possible_config_file.exists() {
-            true => return Ok(possible_config_file),
-            false => {}
-        }
+    pub fn requires_privileges(&self) -> bool {
+        self.sudo || self.user.is_some()
     }
 
-    Err(format!("Unable to find a config file (\"{:?}\") in {:?}", CONFIG_FILENAMES, directory_path))
+    pub fn supports_platform(&self) -> bool {
+        let supports = |platforms: &Vec<String>, current: &str| platforms.is_empty() || platforms.iter().any(|platform| platform == current);
+
+        supports(&self.os, env::consts::OS) && supports(&self.arch, env::consts::ARCH)
+    }
+
+    // Like `linux, macos on x86_64`
+    pub fn describe_platforms(&self) -> String {
+        match (self.os.is_empty(), self.arch.is_empty()) {
+            (false, false) => format!("{} on {}", self.os.join(", "), self.arch.join(", ")),
+            (false, true) => self.os.join(", "),
+            _ => self.arch.join(", "),
+        }
+    }
+}
+
+const OPERATING_SYSTEMS: [&str; 10] = ["linux", "macos", "windows", "freebsd", "openbsd", "netbsd", "dragonfly", "solaris", "android", "ios"];
+const ARCHITECTURES: [&str; 10] = ["x86", "x86_64", "arm", "aarch64", "riscv64", "powerpc", "powerpc64", "s390x", "mips", "mips64"];
+
+// Common aliases of other tools are accepted, darwin is macos and amd64 is x86_64
+fn parse_platforms(values: &[String], known_platforms: &[&str], key: &str, name: &str) -> Result<Vec<String>, String> {
+    values
+        .iter()
+        .map(|value| {
+            let platform: String = match value.to_lowercase().as_str() {
+                "darwin" | "mac" => "macos".to_string(),
+                "win" | "win32" => "windows".to_string(),
+                "amd64" | "x64" => "x86_64".to_string(),
+                "arm64" => "aarch64".to_string(),
+                "i386" | "i686" => "x86".to_string(),
+                platform => platform.to_string(),
+            };
+
+            match known_platforms.contains(&platform.as_str()) {
+                true => Ok(platform),
+                false => Err(format!("Invalid {} \"{}\" for task \"{}\", expected one of {}", name, value, key, known_platforms.join(", "))),
+            }
+        })
+        .collect()
+}
+
+fn parse_task_options(config_file_task: &ConfigFileTask, key: &String) -> Result<TaskOptions, String> {
+    let ConfigFileTask { nice, max_memory, allow_failure, user, sudo, inputs, outputs, cache, clean_env, pass_env, max_output_lines, max_output_bytes, stop_signal, stop_grace_period, os, arch, confirm, cooldown, failure_pattern, weight, .. } = config_file_task;
+
+    if !clean_env && !pass_env.is_empty() {
+        return Err(format!("Task \"{}\" sets pass_env, which only applies with clean_env: true", key));
+    }
+
+    if cache.is_some() && (inputs.is_empty() || outputs.is_empty()) {
+        return Err(format!("Task \"{}\" sets a cache, which needs both inputs and outputs", key));
+    }
+
+    if let Some(nice) = nice {
+        if !(-20..=19).contains(nice) {
+            return Err(format!("Invalid nice value {} for task \"{}\", expected a value between -20 and 19", nice, key));
+        }
+    }
+
+    let max_memory: Option<u64> = match max_memory {
+        Some(max_memory) => Some(parse_memory_size(max_memory).ok_or(format!("Invalid max_memory \"{}\" for task \"{}\", expected a size like 512M or 2G", max_memory, key))?),
+        None => None,
+    };
+
+    let max_output_bytes: Option<u64> = match max_output_bytes {
+        Some(max_output_bytes) => Some(parse_memory_size(max_output_bytes).ok_or(format!("Invalid max_output_bytes \"{}\" for task \"{}\", expected a size like 512K or 1M", max_output_bytes, key))?),
+        None => None,
+    };
+
+    let stop_signal: Option<String> = match stop_signal {
+        Some(stop_signal) => Some(process::parse_stop_signal(stop_signal).ok_or(format!("Invalid stop_signal \"{}\" for task \"{}\", expected one of SIGHUP, SIGINT, SIGQUIT, SIGKILL or SIGTERM", stop_signal, key))?),
+        None => None,
+    };
+
+    let stop_grace_period: Option<Duration> = match stop_grace_period {
+        Some(stop_grace_period) => Some(time::parse_duration(stop_grace_period).map_err(|err| format!("Invalid stop_grace_period for task \"{}\": {}", key, err))?),
+        None => None,
+    };
+
+    let cooldown: Option<Duration> = match cooldown {
+        Some(cooldown) => Some(time::parse_duration(cooldown).map_err(|err| format!("Invalid cooldown for task \"{}\": {}", key, err))?),
+        None => None,
+    };
+
+    if *weight == Some(0) {
+        return Err(format!("Invalid weight 0 for task \"{}\", expected at least 1", key));
+    }
+
+    let failure_pattern: Option<FailurePattern> = match failure_pattern {
+        Some(failure_pattern) => Some(FailurePattern::parse(failure_pattern).map_err(|err| format!("Invalid failure_pattern \"{}\" for task \"{}\", {}", failure_pattern, key, err))?),
+        None => None,
+    };
+
+    Ok(TaskOptions {
+        nice: *nice,
+        max_memory,
+        allow_failure: *allow_failure,
+        user: user.clone(),
+        sudo: *sudo,
+        serial: false,
+        cache: *cache,
+        clean_env: *clean_env,
+        pass_env: pass_env.clone(),
+        max_output_lines: *max_output_lines,
+        max_output_bytes,
+        stop_signal,
+        stop_grace_period,
+        os: parse_platforms(os, &OPERATING_SYSTEMS, key, "os")?,
+        arch: parse_platforms(arch, &ARCHITECTURES, key, "arch")?,
+        confirm: *confirm,
+        cooldown,
+        failure_pattern,
+        weight: *weight,
+    })
+}
+
+fn parse_memory_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_uppercase();
+    let value = value.strip_suffix('B').unwrap_or(&value);
+
+    let (amount, multiplier) = match value.chars().last()? {
+        'K' => (&value[..value.len() - 1], 1024u64),
+        'M' => (&value[..value.len() - 1], 1024u64.pow(2)),
+        'G' => (&value[..value.len() - 1], 1024u64.pow(3)),
+        'T' => (&value[..value.len() - 1], 1024u64.pow(4)),
+        _ => (value, 1u64),
+    };
+
+    amount.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+// Where a task comes from, yaml tasks are shell commands
+pub const TASK_SOURCES: [&str; 9] = ["yaml", "composer", "npm", "yarn", "pnpm", "cargo", "taskfile", "just", "compose"];
+
+pub fn get_task_source(task_type: &TaskType) -> &'static str {
+    match task_type {
+        TaskType::SHELL => "yaml",
+        TaskType::COMPOSER => "composer",
+        TaskType::NPM => "npm",
+        TaskType::YARN => "yarn",
+        TaskType::PNPM => "pnpm",
+        TaskType::CARGO => "cargo",
+        TaskType::TASKFILE => "taskfile",
+        TaskType::JUST => "just",
+        TaskType::COMPOSE => "compose",
+    }
+}
+
+pub fn filter_task_sources(sortable_tasks: SortableTasks, sources: &[String]) -> SortableTasks {
+    sortable_tasks
+        .into_iter()
+        .filter(|SortableTask { task, .. }| sources.iter().any(|source| source == get_task_source(&task.task_type)))
+        .collect()
+}
+
+pub fn resolve_config_task_command(config_task: &ConfigTask) -> String {
+    let ConfigTask { task_type, key, value, .. } = config_task;
+
+    match task_type {
+        TaskType::SHELL => value.clone(),
+        TaskType::COMPOSER => format!("composer run {}", key),
+        TaskType::NPM => format!("npm run {}", key),
+        TaskType::YARN => format!("yarn run {}", key),
+        TaskType::PNPM => format!("pnpm run {}", key),
+        TaskType::CARGO => format!("cargo {}", value),
+        TaskType::TASKFILE => format!("task {}", key),
+        TaskType::JUST => format!("just {}", key),
+        TaskType::COMPOSE => format!("docker compose {}", value),
+    }
+}
+
+pub type ConfigTasks = Vec<ConfigTask>;
+type ConfigDirectories = Vec<ConfigFileDirectory>;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    #[allow(dead_code)]
+    pub(crate) name: String,
+    pub(crate) tasks: ConfigTasks,
+    pub(crate) task_engine: TaskEngine,
+    pub(crate) file_path: PathBuf,
+    pub(crate) dir_path: PathBuf,
+    pub(crate) directories: ConfigDirectories,
+    pub(crate) constants: Variables,
+    pub(crate) env: Variables,
+    pub(crate) default_task: Option<String>,
+    pub(crate) shell: Option<String>,
+    pub(crate) before_level: Option<String>,
+    pub(crate) after_level: Option<String>,
+    pub(crate) serial: bool,
+    pub(crate) ensure_installed: bool,
+    pub(crate) default_parallelism: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+// Collects errors of child configs in lenient mode, in strict mode every error is handed back
+#[derive(Debug, Clone, Default)]
+pub struct ConfigIssues {
+    lenient: bool,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigIssues {
+    pub fn new(lenient: bool) -> Self {
+        ConfigIssues { lenient, issues: vec![] }
+    }
+
+    pub fn record(&mut self, path: &Path, error: String) -> Result<(), String> {
+        if !self.lenient {
+            return Err(format!("Failed to parse {:?}: {}", path, error));
+        }
+
+        self.issues.push(ConfigIssue { path: path.to_path_buf(), error });
+
+        Ok(())
+    }
+
+    pub fn print_warnings(&self) {
+        if self.issues.is_empty() {
+            return;
+        }
+
+        output::warning(&format!("Skipped {} configs that could not be parsed:", self.issues.len()));
+        for ConfigIssue { path, error } in &self.issues {
+            eprintln!("  -  {:?}: {}", path, error);
+        }
+    }
+}
+
+pub fn get_entry_config<'a>(entry_config_path: &Path, configs: &'a [Config]) -> Result<&'a Config, String> {
+    configs
+        .iter()
+        .find(|config| config.file_path == entry_config_path)
+        .ok_or(format!("Unable to find the entry config {:?}", entry_config_path))
+}
+
+pub fn resolve_task_name(task_name: &Option<String>, entry_config: &Config) -> Result<String, String> {
+    match (task_name, &entry_config.default_task) {
+        (Some(task_name), _) => Ok(task_name.clone()),
+        (None, Some(default_task)) => Ok(default_task.clone()),
+        (None, None) => Err(format!("No task given and no default_task configured in {:?}", entry_config.file_path)),
+    }
+}
+
+// The key of the task `rask run --command` adds to the configs in scope
+pub const COMMAND_TASK_KEY: &str = "command";
+
+// Adds a one-off shell task to every config in scope, it is scheduled, interpolated and reported like a yaml task.
+// A scope matches the directory of a config relative to the entry, . for the entry itself, or the name of a config.
+pub fn inject_command_task(configs: &mut [Config], entry_config_path: &Path, command: &str, scope: &[String]) -> Result<(), String> {
+    let entry_directory: &Path = file::get_parent_directory(entry_config_path)?;
+    let scope_matchers: Vec<GlobMatcher> = scope
+        .iter()
+        .map(|pattern| create_glob(Path::new(pattern.trim_end_matches('/'))).map(|glob| glob.compile_matcher()))
+        .collect::<Result<Vec<GlobMatcher>, String>>()?;
+
+    let mut injected_count: usize = 0;
+    for config in configs.iter_mut() {
+        let relative_directory: PathBuf = match config.dir_path.strip_prefix(entry_directory) {
+            Ok(relative_directory) if relative_directory.as_os_str().is_empty() => PathBuf::from("."),
+            Ok(relative_directory) => relative_directory.to_path_buf(),
+            Err(_) => config.dir_path.clone(),
+        };
+        let in_scope = scope_matchers.is_empty() || scope_matchers.iter().any(|matcher| matcher.is_match(&relative_directory) || matcher.is_match(&config.name));
+        if !in_scope {
+            continue;
+        }
+
+        if config.tasks.iter().any(|config_task| config_task.key == COMMAND_TASK_KEY) {
+            return Err(format!("Config {:?} already has a task named \"{}\", unable to add the --command task", config.file_path, COMMAND_TASK_KEY));
+        }
+
+        config.tasks.push(ConfigTask {
+            task_type: TaskType::SHELL,
+            key: COMMAND_TASK_KEY.to_string(),
+            value: command.to_string(),
+            steps: vec![],
+            options: TaskOptions::default(),
+            depends_on: vec![],
+            after: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            description: None,
+        });
+        injected_count += 1;
+    }
+
+    match injected_count {
+        0 => Err(format!("No config matches the scope {}", scope.join(", "))),
+        _ => Ok(()),
+    }
+}
+
+pub fn parse_config_files(config_files: Vec<ConfigFile>, entry_config_path: &Path, config_issues: &mut ConfigIssues) -> Result<Vec<Config>, String> {
+    let mut configs: Vec<Config> = vec![];
+    let engine_overrides: Vec<EngineOverride> = match config_files.iter().find(|config_file| config_file.__file_path == entry_config_path) {
+        Some(entry_config_file) => parse_engine_overrides(&entry_config_file.__dir_path, &entry_config_file.engine_overrides)?,
+        None => vec![],
+    };
+    let task_templates: HashMap<String, ConfigFileTaskTemplate> = config_files
+        .iter()
+        .find(|config_file| config_file.__file_path == entry_config_path)
+        .map(|entry_config_file| entry_config_file.task_templates.clone())
+        .unwrap_or_default();
+
+    for mut config_file in config_files {
+        let file_path = config_file.__file_path.clone();
+        let parsed_configs = expand_task_templates(std::mem::take(&mut config_file.tasks), &task_templates)
+            .and_then(|tasks| parse_config_file(ConfigFile { tasks, ..config_file }, &engine_overrides));
+        match parsed_configs {
+            Ok(parsed_configs) => configs.extend(parsed_configs),
+            // A broken entry config leaves nothing to work with
+            Err(err) if file_path == entry_config_path => return Err(format!("Failed to parse {:?}: {}", file_path, err)),
+            Err(err) => config_issues.record(&file_path, err)?,
+        }
+    }
+
+    validate_config_names(&configs)?;
+
+    Ok(configs)
+}
+
+// Configs are addressed by name, so every name may only be used once
+fn validate_config_names(configs: &[Config]) -> Result<(), String> {
+    let mut duplicates: Vec<String> = vec![];
+
+    for (index, config) in configs.iter().enumerate() {
+        let is_first = configs[..index].iter().all(|other_config| other_config.name != config.name);
+        let file_paths: Vec<String> = configs
+            .iter()
+            .filter(|other_config| other_config.name == config.name)
+            .map(|other_config| format!("{:?}", other_config.file_path))
+            .collect();
+
+        if is_first && file_paths.len() > 1 {
+            duplicates.push(format!("\"{}\" is used by {}", config.name, file_paths.join(", ")));
+        }
+    }
+
+    match duplicates.is_empty() {
+        true => Ok(()),
+        false => Err(format!("Config names must be unique within a workspace:\n  - {}", duplicates.join("\n  - "))),
+    }
+}
+
+// Replaces the tasks that use a template by the task of the template, with its parameters filled in
+fn expand_task_templates(tasks: ConfigFileTasks, task_templates: &HashMap<String, ConfigFileTaskTemplate>) -> Result<ConfigFileTasks, String> {
+    tasks
+        .into_iter()
+        .map(|(task_name, task_value)| match task_value {
+            ConfigFileTaskValue::TemplateTask(template_task) => Ok((task_name.clone(), instantiate_task_template(&task_name, &template_task, task_templates)?)),
+            // A mapping with `uses` and keys other than `with` is a template task with a typo, not a group with a task named uses
+            ConfigFileTaskValue::ConfigFileTasks(subtasks) if subtasks.contains_key("uses") => {
+                Err(format!("Task \"{}\" uses a task template, only \"with\" can be given next to \"uses\", nested tasks cannot be named uses", task_name))
+            },
+            ConfigFileTaskValue::ConfigFileTasks(subtasks) => Ok((task_name, ConfigFileTaskValue::ConfigFileTasks(expand_task_templates(subtasks, task_templates)?))),
+            task_value => Ok((task_name, task_value)),
+        })
+        .collect()
+}
+
+fn instantiate_task_template(task_name: &str, template_task: &ConfigFileTemplateTask, task_templates: &HashMap<String, ConfigFileTaskTemplate>) -> Result<ConfigFileTaskValue, String> {
+    let ConfigFileTemplateTask { uses, with } = template_task;
+    let ConfigFileTaskTemplate { parameters, task } = task_templates
+        .get(uses)
+        .ok_or(format!("Task \"{}\" uses unknown task template \"{}\", task templates are read from the entry config", task_name, uses))?;
+
+    if let Some(name) = with.keys().find(|name| !parameters.contains_key(*name)) {
+        return Err(format!("Task \"{}\" passes unknown parameter \"{}\" to task template \"{}\"", task_name, name, uses));
+    }
+
+    let mut variables = Variables::new();
+    for (name, default) in parameters {
+        let value: &String = with.get(name).or(default.as_ref()).ok_or(format!("Task \"{}\" does not pass parameter \"{}\" to task template \"{}\"", task_name, name, uses))?;
+        variables.insert(name.clone(), value.clone());
+    }
+
+    // Placeholders that are not parameters stay, they are constants or env of the config using the template
+    let mut value: serde_yaml::Value = serde_yaml::to_value(task).map_err(|err| format!("Failed to read task template \"{}\": {}", uses, err))?;
+    interpolate_yaml_strings(&mut value, &variables);
+    let task_value: ConfigFileTaskValue = serde_yaml::from_value(value).map_err(|err| format!("Failed to read task template \"{}\": {}", uses, err))?;
+
+    match contains_template_task(&task_value) {
+        true => Err(format!("Task template \"{}\" uses another task template, templates do not nest", uses)),
+        false => Ok(task_value),
+    }
+}
+
+fn interpolate_yaml_strings(value: &mut serde_yaml::Value, variables: &Variables) {
+    match value {
+        serde_yaml::Value::String(string) => *string = interpolate(string, variables),
+        serde_yaml::Value::Sequence(sequence) => sequence.iter_mut().for_each(|item| interpolate_yaml_strings(item, variables)),
+        serde_yaml::Value::Mapping(mapping) => mapping.values_mut().for_each(|item| interpolate_yaml_strings(item, variables)),
+        _ => {},
+    }
+}
+
+fn contains_template_task(task_value: &ConfigFileTaskValue) -> bool {
+    match task_value {
+        ConfigFileTaskValue::TemplateTask(_) => true,
+        ConfigFileTaskValue::ConfigFileTasks(subtasks) => subtasks.values().any(contains_template_task),
+        _ => false,
+    }
+}
+
+// Forces the engine of the configs in the directories matching the pattern
+struct EngineOverride {
+    pattern: String,
+    matcher: GlobMatcher,
+    task_engine: TaskEngine,
+}
+
+fn parse_engine_overrides(config_directory: &Path, engine_overrides: &HashMap<String, TaskEngine>) -> Result<Vec<EngineOverride>, String> {
+    engine_overrides
+        .iter()
+        .map(|(pattern, task_engine)| Ok(EngineOverride {
+            pattern: pattern.clone(),
+            matcher: create_glob(&config_directory.join(pattern))?.compile_matcher(),
+            task_engine: task_engine.clone(),
+        }))
+        .collect()
+}
+
+// The longest matching pattern is the most specific one
+fn find_engine_override<'a>(engine_overrides: &'a [EngineOverride], dir_path: &Path) -> Option<&'a TaskEngine> {
+    engine_overrides
+        .iter()
+        .filter(|engine_override| engine_override.matcher.is_match(dir_path))
+        .max_by(|a, b| a.pattern.len().cmp(&b.pattern.len()).then(b.pattern.cmp(&a.pattern)))
+        .map(|engine_override| &engine_override.task_engine)
+}
+
+// A config file yields a single config, or one more per crate at the root of a Cargo workspace
+fn parse_config_file(config_file: ConfigFile, engine_overrides: &[EngineOverride]) -> Result<Vec<Config>, String> {
+    let ConfigFile { name, mut directories, task_engine, engine_tasks, tasks: config_file_tasks, constants, env, default_task, shell, before_level, after_level, serial, ensure_installed, default_parallelism, .. } = config_file;
+    let ConfigFile { __file_path: file_path, __dir_path: dir_path, .. } = config_file;
+    let task_engine: TaskEngine = find_engine_override(engine_overrides, &dir_path).cloned().unwrap_or(task_engine);
+
+    let mut tasks: ConfigTasks = parse_engine_tasks(&dir_path, &task_engine, &engine_tasks, config_file_tasks)?;
+    let member_configs: Vec<Config> = expand_cargo_workspace(&dir_path, &engine_tasks, &mut tasks, &mut directories)?;
+    if engine_tasks.sources.is_none() {
+        validate_task_dependencies(&tasks)?;
+    }
+    if default_parallelism == Some(0) {
+        return Err("Invalid default_parallelism 0, expected at least 1".to_string());
+    }
+
+    let config: Config = Config { name, tasks, task_engine, file_path, dir_path, directories, constants, env, default_task, shell, before_level, after_level, serial, ensure_installed, default_parallelism };
+
+    Ok([config].into_iter().chain(member_configs).collect())
+}
+
+// At a workspace root every crate becomes a config of its own, so tasks and changes are tracked per crate.
+// The root keeps the cargo tasks of its own package, members with a rask.yaml are regular configs.
+fn expand_cargo_workspace(dir_path: &Path, engine_task_filter: &EngineTaskFilter, tasks: &mut ConfigTasks, directories: &mut ConfigDirectories) -> Result<Vec<Config>, String> {
+    if !tasks.iter().any(|config_task| matches!(config_task.task_type, TaskType::CARGO)) {
+        return Ok(vec![]);
+    }
+
+    let members: Vec<WorkspaceMember> = cargo::find_workspace_members(dir_path)?;
+    if members.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let root_package: Option<String> = cargo::read_package_name(dir_path).ok();
+    let root_tasks: ConfigTasks = std::mem::take(tasks);
+    for config_task in root_tasks {
+        match (config_task.task_type, &root_package) {
+            (TaskType::CARGO, Some(root_package)) => tasks.push(scope_cargo_task(config_task, root_package)),
+            (TaskType::CARGO, None) => {},
+            _ => tasks.push(config_task),
+        }
+    }
+
+    let task_filter: TaskFilter = parse_task_filter(engine_task_filter)?;
+    let mut member_configs: Vec<Config> = vec![];
+    for WorkspaceMember { name, directory } in members {
+        let file_path: PathBuf = directory.join(CONFIG_FILENAMES[0]);
+        if file_path.exists() {
+            continue;
+        }
+
+        let relative_directory: &Path = directory.strip_prefix(dir_path).map_err(|err| err.to_string())?;
+        directories.push(ConfigFileDirectory::Pattern(file::path_to_str(relative_directory)?.to_string()));
+
+        member_configs.push(Config {
+            tasks: parse_cargo_toml_tasks(&directory, &task_filter)?.into_iter().map(|config_task| scope_cargo_task(config_task, &name)).collect(),
+            name,
+            task_engine: TaskEngine::CARGO,
+            file_path,
+            dir_path: directory,
+            directories: vec![],
+            constants: Variables::new(),
+            env: Variables::new(),
+            default_task: None,
+            shell: None,
+            before_level: None,
+            after_level: None,
+            serial: false,
+            // Members share the target directory of the workspace, the root config installs for them
+            ensure_installed: false,
+            default_parallelism: None,
+        });
+    }
+
+    Ok(member_configs)
+}
+
+fn scope_cargo_task(config_task: ConfigTask, package: &str) -> ConfigTask {
+    ConfigTask { value: format!("{} -p {}", config_task.key, package), ..config_task }
+}
+
+fn validate_task_dependencies(config_tasks: &ConfigTasks) -> Result<(), String> {
+    for ConfigTask { key, depends_on, .. } in config_tasks {
+        for dependency in depends_on.iter().filter(|dependency| !is_task_reference(dependency)) {
+            if find_config_task(config_tasks, dependency).is_none() {
+                return Err(format!("Task \"{}\" depends on unknown task \"{}\"", key, dependency));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+const COMPOSER_JSON_FILE: &str = "composer.json";
+const CARGO_TOML_FILE: &str = "Cargo.toml";
+// The names the tools look for, in their order of preference
+const TASKFILE_FILES: [&str; 4] = ["Taskfile.yml", "Taskfile.yaml", "taskfile.yml", "taskfile.yaml"];
+const JUSTFILE_FILES: [&str; 3] = ["justfile", "Justfile", ".justfile"];
+const COMPOSE_FILES: [&str; 4] = ["compose.yaml", "compose.yml", "docker-compose.yaml", "docker-compose.yml"];
+
+fn find_engine_file(dir_path: &Path, file_names: &[&'static str]) -> Option<&'static str> {
+    file_names.iter().copied().find(|file_name| dir_path.join(file_name).exists())
+}
+
+// The file a task is defined in, by the source of the task
+pub fn find_task_file(dir_path: &Path, source: &str) -> Option<PathBuf> {
+    let file_names: &[&'static str] = match source {
+        "yaml" => &CONFIG_FILENAMES,
+        "npm" | "yarn" | "pnpm" => &[PACKAGE_JSON_FILE],
+        "composer" => &[COMPOSER_JSON_FILE],
+        "cargo" => &[CARGO_TOML_FILE],
+        "taskfile" => &TASKFILE_FILES,
+        "just" => &JUSTFILE_FILES,
+        "compose" => &COMPOSE_FILES,
+        _ => &[],
+    };
+
+    find_engine_file(dir_path, file_names).map(|file_name| dir_path.join(file_name))
+}
+
+pub fn parse_engine_tasks(dir_path: &Path, task_engine: &TaskEngine, engine_task_filter: &EngineTaskFilter, config_file_tasks: ConfigFileTasks) -> Result<ConfigTasks, String> {
+    let mut config_tasks: ConfigTasks = parse_config_tasks(config_file_tasks)?;
+    let task_filter: TaskFilter = parse_task_filter(engine_task_filter)?;
+
+    let engine_tasks: ConfigTasks = match task_engine {
+        TaskEngine::COMPOSER => parse_source(TaskType::COMPOSER, &task_filter, || parse_composer_json_tasks(dir_path, &task_filter))?,
+        TaskEngine::NPM => parse_source(TaskType::NPM, &task_filter, || parse_package_json_tasks(dir_path, TaskType::NPM, &task_filter))?,
+        TaskEngine::YARN => parse_source(TaskType::YARN, &task_filter, || parse_package_json_tasks(dir_path, TaskType::YARN, &task_filter))?,
+        TaskEngine::PNPM => parse_source(TaskType::PNPM, &task_filter, || parse_package_json_tasks(dir_path, TaskType::PNPM, &task_filter))?,
+        TaskEngine::CARGO => parse_source(TaskType::CARGO, &task_filter, || parse_cargo_toml_tasks(dir_path, &task_filter))?,
+        TaskEngine::TASKFILE => parse_source(TaskType::TASKFILE, &task_filter, || parse_taskfile_tasks(dir_path, &task_filter))?,
+        TaskEngine::JUST => parse_source(TaskType::JUST, &task_filter, || parse_justfile_tasks(dir_path, &task_filter))?,
+        TaskEngine::COMPOSE => parse_source(TaskType::COMPOSE, &task_filter, || parse_compose_tasks(dir_path, &task_filter))?,
+        TaskEngine::NONE => vec![],
+        TaskEngine::AUTO => parse_discovered_tasks(dir_path, &task_filter)?,
+    };
+
+    // Yaml tasks shadow engine tasks with the same key, engines detected side by side all keep theirs
+    let yaml_keys: Vec<String> = config_tasks.iter().map(|config_task| config_task.key.clone()).collect();
+    config_tasks.extend(engine_tasks.into_iter().filter(|engine_task| !yaml_keys.contains(&engine_task.key)));
+
+    Ok(config_tasks)
+}
+
+struct TaskFilter {
+    // None includes every task
+    includes: Option<GlobSet>,
+    excludes: GlobSet,
+    sources: Option<Vec<String>>,
+}
+
+impl TaskFilter {
+    fn matches(&self, key: &str) -> bool {
+        let included = self.includes.as_ref().is_none_or(|includes| includes.is_match(key));
+
+        included && !self.excludes.is_match(key)
+    }
+
+    fn includes_source(&self, task_type: &TaskType) -> bool {
+        self.sources.as_ref().is_none_or(|sources| sources.iter().any(|source| source == get_task_source(task_type)))
+    }
+}
+
+// Engines left out by the task sources are never read, which keeps filtered listings of large workspaces fast
+fn parse_source(task_type: TaskType, task_filter: &TaskFilter, parse: impl FnOnce() -> Result<ConfigTasks, String>) -> Result<ConfigTasks, String> {
+    match task_filter.includes_source(&task_type) {
+        true => parse(),
+        false => Ok(vec![]),
+    }
+}
+
+// Only the tasks of these sources are parsed, dependencies on the tasks that are left out are no longer checked
+pub fn restrict_task_sources(config_files: &mut [ConfigFile], sources: &[String]) {
+    for config_file in config_files {
+        config_file.engine_tasks.sources = Some(sources.to_vec());
+    }
+}
+
+fn parse_task_filter(engine_task_filter: &EngineTaskFilter) -> Result<TaskFilter, String> {
+    let EngineTaskFilter { include, exclude, sources } = engine_task_filter;
+
+    let build_glob_set = |patterns: &Vec<String>| -> Result<GlobSet, String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).map_err(|err| format!("Invalid engine_tasks pattern \"{}\": {}", pattern, err))?);
+        }
+
+        builder.build().map_err(|err| format!("Failed to create engine_tasks filter: {}", err))
+    };
+
+    Ok(TaskFilter {
+        includes: match include.is_empty() {
+            true => None,
+            false => Some(build_glob_set(include)?),
+        },
+        excludes: build_glob_set(exclude)?,
+        sources: sources.clone(),
+    })
+}
+
+pub fn find_marker_files(dir_path: &Path) -> Vec<&'static str> {
+    let mut marker_files: Vec<&'static str> = [COMPOSER_JSON_FILE, PACKAGE_JSON_FILE, YARN_LOCK_FILE, PNPM_LOCK_FILE, CARGO_TOML_FILE]
+        .into_iter()
+        .filter(|marker_file| dir_path.join(marker_file).exists())
+        .collect();
+
+    marker_files.extend(find_engine_file(dir_path, &TASKFILE_FILES));
+    marker_files.extend(find_engine_file(dir_path, &JUSTFILE_FILES));
+    marker_files.extend(find_engine_file(dir_path, &COMPOSE_FILES));
+
+    marker_files
+}
+
+// Picks the engine matching the marker files in a directory, AUTO when there are none or several.
+pub fn detect_task_engine(dir_path: &Path) -> TaskEngine {
+    let mut detected_engines: Vec<TaskEngine> = vec![];
+
+    if dir_path.join(COMPOSER_JSON_FILE).exists() {
+        detected_engines.push(TaskEngine::COMPOSER);
+    }
+
+    if dir_path.join(PACKAGE_JSON_FILE).exists() {
+        detected_engines.push(match package_manager::detect_package_manager(dir_path) {
+            TaskType::YARN => TaskEngine::YARN,
+            TaskType::PNPM => TaskEngine::PNPM,
+            _ => TaskEngine::NPM,
+        });
+    }
+
+    if dir_path.join(CARGO_TOML_FILE).exists() {
+        detected_engines.push(TaskEngine::CARGO);
+    }
+
+    if find_engine_file(dir_path, &TASKFILE_FILES).is_some() {
+        detected_engines.push(TaskEngine::TASKFILE);
+    }
+
+    if find_engine_file(dir_path, &JUSTFILE_FILES).is_some() {
+        detected_engines.push(TaskEngine::JUST);
+    }
+
+    if find_engine_file(dir_path, &COMPOSE_FILES).is_some() {
+        detected_engines.push(TaskEngine::COMPOSE);
+    }
+
+    match detected_engines.as_slice() {
+        [task_engine] => task_engine.clone(),
+        _ => TaskEngine::AUTO,
+    }
+}
+
+fn parse_discovered_tasks(dir_path: &Path, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let mut config_tasks: ConfigTasks = vec![];
+
+    // Gathering facts
+    let has_composer_json = dir_path.join(COMPOSER_JSON_FILE).exists();
+    let has_package_json = dir_path.join(PACKAGE_JSON_FILE).exists();
+    let has_cargo_toml = dir_path.join(CARGO_TOML_FILE).exists();
+
+    if has_composer_json {
+        let composer_config_tasks = parse_source(TaskType::COMPOSER, task_filter, || parse_composer_json_tasks(dir_path, task_filter))?;
+        config_tasks.extend(composer_config_tasks)
+    }
+
+    if has_package_json {
+        let task_type: TaskType = package_manager::detect_package_manager(dir_path);
+
+        config_tasks.extend(parse_source(task_type, task_filter, || parse_package_json_tasks(dir_path, task_type, task_filter))?);
+    }
+
+    if has_cargo_toml {
+        let cargo_config_tasks = parse_source(TaskType::CARGO, task_filter, || parse_cargo_toml_tasks(dir_path, task_filter))?;
+        config_tasks.extend(cargo_config_tasks)
+    }
+
+    if find_engine_file(dir_path, &TASKFILE_FILES).is_some() {
+        config_tasks.extend(parse_source(TaskType::TASKFILE, task_filter, || parse_taskfile_tasks(dir_path, task_filter))?);
+    }
+
+    if find_engine_file(dir_path, &JUSTFILE_FILES).is_some() {
+        config_tasks.extend(parse_source(TaskType::JUST, task_filter, || parse_justfile_tasks(dir_path, task_filter))?);
+    }
+
+    if find_engine_file(dir_path, &COMPOSE_FILES).is_some() {
+        config_tasks.extend(parse_source(TaskType::COMPOSE, task_filter, || parse_compose_tasks(dir_path, task_filter))?);
+    }
+
+    Ok(config_tasks)
+}
+
+fn parse_package_json_tasks(dir_path: &Path, task_type: TaskType, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let package_json = package_manager::read_package_json(dir_path)?;
+
+    let mut config_tasks: ConfigTasks = vec![];
+    for key in package_json.scripts.keys().filter(|key| task_filter.matches(key)) {
+        config_tasks.push(ConfigTask {
+            task_type,
+            key: key.clone(),
+            value: key.clone(),
+            steps: vec![],
+            options: TaskOptions::default(),
+            depends_on: vec![],
+            after: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            description: None,
+        });
+    }
+
+    Ok(config_tasks)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum ComposerJsonScriptValue {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct ComposerJsonFile {
+    #[serde(default)]
+    scripts: HashMap<String, ComposerJsonScriptValue>,
+}
+
+fn parse_composer_json_tasks(dir_path: &Path, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let package_json = file::read_json_file::<ComposerJsonFile>(&dir_path.join(COMPOSER_JSON_FILE))?;
+
+    let mut config_tasks: ConfigTasks = vec![];
+    for key in package_json.scripts.keys().filter(|key| task_filter.matches(key)) {
+        config_tasks.push(ConfigTask {
+            task_type: TaskType::COMPOSER,
+            key: key.clone(),
+            value: key.clone(),
+            steps: vec![],
+            options: TaskOptions::default(),
+            depends_on: vec![],
+            after: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            description: None,
+        });
+    }
+
+    Ok(config_tasks)
+}
+
+// Cargo has no script section, the common subcommands are exposed instead.
+const CARGO_TASKS: [&str; 7] = ["build", "check", "test", "run", "clippy", "fmt", "doc"];
+
+fn parse_cargo_toml_tasks(dir_path: &Path, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let cargo_toml_path = dir_path.join(CARGO_TOML_FILE);
+    if !cargo_toml_path.exists() {
+        return Err(format!("Unable to find {:?}", cargo_toml_path));
+    }
+
+    let mut config_tasks: ConfigTasks = vec![];
+    for key in CARGO_TASKS.into_iter().filter(|key| task_filter.matches(key)) {
+        config_tasks.push(ConfigTask {
+            task_type: TaskType::CARGO,
+            key: key.to_string(),
+            value: key.to_string(),
+            steps: vec![],
+            options: TaskOptions::default(),
+            depends_on: vec![],
+            after: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            description: None,
+        });
+    }
+
+    Ok(config_tasks)
+}
+
+fn create_engine_task(task_type: TaskType, key: &str) -> ConfigTask {
+    ConfigTask {
+        task_type,
+        key: key.to_string(),
+        value: key.to_string(),
+        steps: vec![],
+        options: TaskOptions::default(),
+        depends_on: vec![],
+        after: vec![],
+        inputs: vec![],
+        outputs: vec![],
+        description: None,
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct TaskfileFile {
+    // Sorted, so the tasks are listed in the same order on every run
+    #[serde(default)]
+    tasks: BTreeMap<String, serde_yaml::Value>,
+}
+
+fn parse_taskfile_tasks(dir_path: &Path, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let file_name = find_engine_file(dir_path, &TASKFILE_FILES).ok_or(format!("Unable to find a Taskfile in {:?}", dir_path))?;
+    let content = file::read_file_content(dir_path.join(file_name))?;
+    let taskfile = serde_yaml::from_str::<TaskfileFile>(&content).map_err(|err| format!("Failed to parse {:?}: {}", dir_path.join(file_name), err))?;
+
+    // Internal tasks can't be called from the command line
+    let is_internal = |value: &serde_yaml::Value| value.get("internal").and_then(|internal| internal.as_bool()).unwrap_or(false);
+
+    Ok(taskfile.tasks
+        .iter()
+        .filter(|(key, value)| !is_internal(value) && task_filter.matches(key))
+        .map(|(key, _)| create_engine_task(TaskType::TASKFILE, key))
+        .collect())
+}
+
+fn parse_justfile_tasks(dir_path: &Path, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let file_name = find_engine_file(dir_path, &JUSTFILE_FILES).ok_or(format!("Unable to find a justfile in {:?}", dir_path))?;
+    let content = file::read_file_content(dir_path.join(file_name))?;
+
+    Ok(parse_justfile_recipes(&content)
+        .into_iter()
+        .filter(|recipe| task_filter.matches(recipe))
+        .map(|recipe| create_engine_task(TaskType::JUST, &recipe))
+        .collect())
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct ComposeFile {
+    #[serde(default)]
+    services: BTreeMap<String, serde_yaml::Value>,
+}
+
+// Compose has no tasks of its own, the project commands are exposed with an `up:<service>` per service
+fn parse_compose_tasks(dir_path: &Path, task_filter: &TaskFilter) -> Result<ConfigTasks, String> {
+    let file_name = find_engine_file(dir_path, &COMPOSE_FILES).ok_or(format!("Unable to find a compose file in {:?}", dir_path))?;
+    let content = file::read_file_content(dir_path.join(file_name))?;
+    let compose_file = serde_yaml::from_str::<ComposeFile>(&content).map_err(|err| format!("Failed to parse {:?}: {}", dir_path.join(file_name), err))?;
+
+    let mut tasks: Vec<(String, String)> = vec![
+        ("up".to_string(), "up --detach".to_string()),
+        ("down".to_string(), "down".to_string()),
+        ("logs".to_string(), "logs".to_string()),
+    ];
+    tasks.extend(compose_file.services.keys().map(|service| (format!("up:{}", service), format!("up --detach {}", service))));
+
+    Ok(tasks
+        .into_iter()
+        .filter(|(key, _)| task_filter.matches(key))
+        .map(|(key, value)| ConfigTask { value, ..create_engine_task(TaskType::COMPOSE, &key) })
+        .collect())
+}
+
+// Recipes start at the beginning of a line with their name, optional parameters and a colon.
+// Comments and recipe bodies are skipped, so are the private recipes just hides from its listing.
+fn parse_justfile_recipes(content: &str) -> Vec<String> {
+    let mut recipes: Vec<String> = vec![];
+    let mut is_private = false;
+
+    for line in content.lines() {
+        if line.starts_with([' ', '\t', '#']) || line.trim().is_empty() {
+            continue;
+        }
+
+        // Attributes like `[private]` apply to the recipe that follows
+        if line.starts_with('[') {
+            is_private = is_private || line.contains("private");
+            continue;
+        }
+
+        let header = line.trim_start_matches('@');
+        let name: &str = header.split(|character: char| !(character.is_alphanumeric() || character == '_' || character == '-')).next().unwrap_or_default();
+        // Assignments, aliases and settings use `:=`, imports and modules have no colon at all
+        let rest = &header[name.len()..];
+        let is_recipe = !name.is_empty() && rest.find(':').is_some_and(|colon| !rest[colon..].starts_with(":="));
+
+        if is_recipe && !is_private && !name.starts_with('_') && !recipes.contains(&name.to_string()) {
+            recipes.push(name.to_string());
+        }
+        is_private = false;
+    }
+
+    recipes
+}
+
+fn flatten_config_tasks(tasks: &ConfigFileTasks, prefix: &str, results: &mut ConfigTasks) -> Result<(), String> {
+    for (task_name, task_value) in tasks {
+        let key = match prefix.is_empty() {
+            true => task_name.clone(),
+            false => format!("{}:{}", prefix, task_name)
+        };
+
+        match task_value {
+            ConfigFileTaskValue::String(value) => {
+                results.push(ConfigTask{
+                    task_type: TaskType::SHELL,
+                    key,
+                    value: value.clone(),
+                    steps: vec![],
+                    options: TaskOptions::default(),
+                    depends_on: vec![],
+                    after: vec![],
+                    inputs: vec![],
+                    outputs: vec![],
+                    description: None,
+                });
+            }
+            ConfigFileTaskValue::Steps(steps) => {
+                results.push(ConfigTask{
+                    task_type: TaskType::SHELL,
+                    value: join_steps(&key, steps)?,
+                    key,
+                    steps: steps.clone(),
+                    options: TaskOptions::default(),
+                    depends_on: vec![],
+                    after: vec![],
+                    inputs: vec![],
+                    outputs: vec![],
+                    description: None,
+                });
+            }
+            ConfigFileTaskValue::ConfigFileTask(config_file_task) => {
+                let options = parse_task_options(config_file_task, &key)?;
+                let (value, steps) = match &config_file_task.command {
+                    ConfigFileCommand::String(command) => (command.clone(), vec![]),
+                    ConfigFileCommand::Steps(steps) => (join_steps(&key, steps)?, steps.clone()),
+                };
+                results.push(ConfigTask{
+                    task_type: TaskType::SHELL,
+                    key,
+                    value,
+                    steps,
+                    options,
+                    depends_on: config_file_task.depends_on.clone(),
+                    after: config_file_task.after.clone(),
+                    inputs: config_file_task.inputs.clone(),
+                    outputs: config_file_task.outputs.clone(),
+                    description: config_file_task.description.clone(),
+                });
+            }
+            // Expanded when the config files are parsed, only the entry config has the templates
+            ConfigFileTaskValue::TemplateTask(ConfigFileTemplateTask { uses, .. }) => {
+                return Err(format!("Task \"{}\" uses task template \"{}\", which was not expanded", key, uses));
+            }
+            ConfigFileTaskValue::ConfigFileTasks(subtasks) => {
+                flatten_config_tasks(subtasks, &key, results)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The joined steps are what gets displayed, the steps themselves are executed one by one
+fn join_steps(key: &str, steps: &[String]) -> Result<String, String> {
+    if steps.is_empty() {
+        return Err(format!("Task \"{}\" has no steps, expected at least one command", key));
+    }
+
+    Ok(steps.join(" && "))
+}
+
+fn parse_config_tasks(tasks: ConfigFileTasks) -> Result<ConfigTasks, String> {
+    let mut config_tasks: ConfigTasks = vec![];
+
+    flatten_config_tasks(&tasks, "", &mut config_tasks)?;
+
+    Ok(config_tasks)
+}
+
+pub fn read_entry_config_files(entry: &str, require_config: bool, config_issues: &mut ConfigIssues) -> Result<(PathBuf, Vec<ConfigFile>), String> {
+    // Resolve the entry path
+    let entry_config_path: PathBuf = match resolve_config_path(entry) {
+        Ok(entry_config_path) => entry_config_path,
+        Err(err) if require_config => return Err(err),
+        // Without a config, rask still works as a script runner for the detected engines
+        Err(err) => {
+            let config_file: ConfigFile = synthesize_config_file(entry).ok_or(err)?;
+            return Ok((config_file.__file_path.clone(), vec![config_file]));
+        }
+    };
+
+    // Discover and read all config files
+    let config_files: Vec<ConfigFile> = discover_config_files(&entry_config_path, config_issues)?;
+
+    Ok((entry_config_path, config_files))
+}
+
+// Synthesized configs have no file to validate
+pub fn validate_config_files(config_files: &[ConfigFile]) -> Result<(), String> {
+    let errors: Vec<String> = config_files
+        .iter()
+        .filter(|config_file| config_file.__file_path.is_file())
+        .filter_map(|config_file| file::validate_config_file_keys(&config_file.__file_path).err())
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
+}
+
+fn synthesize_config_file(entry: &str) -> Option<ConfigFile> {
+    let directory: PathBuf = file::parse_path_string(entry).ok()?;
+    if !directory.is_dir() {
+        return None;
+    }
+
+    let has_engine_file = [PACKAGE_JSON_FILE, COMPOSER_JSON_FILE, CARGO_TOML_FILE]
+        .iter()
+        .any(|engine_file| directory.join(engine_file).exists())
+        || find_engine_file(&directory, &TASKFILE_FILES).is_some()
+        || find_engine_file(&directory, &JUSTFILE_FILES).is_some()
+        || find_engine_file(&directory, &COMPOSE_FILES).is_some();
+    if !has_engine_file {
+        return None;
+    }
+
+    Some(ConfigFile {
+        name: directory.file_name()?.to_str()?.to_string(),
+        task_engine: TaskEngine::AUTO,
+        __file_path: directory.join(CONFIG_FILENAMES[0]),
+        __dir_path: directory,
+        ..Default::default()
+    })
+}
+
+// Every config is read once, the files are returned in the order they were found
+pub fn discover_config_files(path: &Path, config_issues: &mut ConfigIssues) -> Result<Vec<ConfigFile>, String> {
+    trace_config_files(path, config_issues, &mut DiscoveryTrace::default())
+}
+
+// Why a path matched by a `directories` pattern did or did not become a config
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandidateOutcome {
+    FOUND,
+    EXCLUDED(String),
+    VISITED,
+    UNRESOLVED(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternTrace {
+    pub pattern: String,
+    pub glob: PathBuf,
+    pub candidates: Vec<(PathBuf, CandidateOutcome)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigTrace {
+    pub config_path: PathBuf,
+    pub patterns: Vec<PatternTrace>,
+    pub excludes: Vec<String>,
+    pub error: Option<String>,
+}
+
+// Records every step of the discovery, printed by `rask list --debug-discovery`
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryTrace {
+    pub configs: Vec<ConfigTrace>,
+}
+
+pub fn trace_config_files(path: &Path, config_issues: &mut ConfigIssues, discovery_trace: &mut DiscoveryTrace) -> Result<Vec<ConfigFile>, String> {
+    let mut found_config_paths: Vec<PathBuf> = vec![path.to_path_buf()];
+    let mut config_files: HashMap<PathBuf, Vec<ConfigFile>> = HashMap::new();
+
+    // Read config
+    let mut path_stack: Vec<PathBuf> = vec![path.to_path_buf()];
+    let mut directory_variables: Variables = Variables::new();
+    while let Some(config_path) = path_stack.pop() {
+        let mut config_trace = ConfigTrace { config_path: config_path.clone(), patterns: vec![], excludes: vec![], error: None };
+        let mut file_configs: Vec<ConfigFile> = match file::read_config_files(config_path.clone()) {
+            Ok(file_configs) => {
+                deprecation::warn_deprecated_keys(&config_path, &DEPRECATIONS);
+                file_configs
+            },
+            // A broken entry config leaves nothing to work with
+            Err(err) if config_path == path => return Err(format!("Failed to parse {:?}: {}", config_path, err)),
+            Err(err) => {
+                config_trace.error = Some(err.clone());
+                discovery_trace.configs.push(config_trace);
+                config_issues.record(&config_path, err)?;
+                found_config_paths.retain(|found_config_path| *found_config_path != config_path);
+                continue;
+            }
+        };
+
+        // The entry config is read first, its constants fill in the patterns of every config
+        if config_path == path {
+            directory_variables = get_directory_variables(file_configs.first().map(|file_config| &file_config.constants).unwrap_or(&HashMap::new()))
+                .map_err(|err| format!("Unable to resolve the constants of {:?}: {}", config_path, err))?;
+        }
+        for file_config in &mut file_configs {
+            file_config.directories = interpolate_directories(&file_config.directories, &directory_variables, &config_path)?;
+        }
+
+        // Extract directories, every document of the file has its own
+        for ConfigFile { directories, __dir_path: config_directory, .. } in &file_configs {
+            let DirectoryPatterns { includes, excludes, exclude_patterns } = parse_directory_patterns(config_directory, directories)?;
+            for (directory, pattern) in includes {
+                let mut pattern_trace = PatternTrace { pattern: directory.get_pattern().to_string(), glob: pattern.clone(), candidates: vec![] };
+
+                // Find config files based on the pattern in the directories value
+                let pattern_string: &str = file::path_to_str(&pattern)?;
+                for found_config_path in glob::glob(pattern_string).map_err(|e| format!("Failed to read glob pattern {:?} of {:?}: {}", pattern_string, config_path, e))?.flatten() {
+                    // Patterns like `..` yield paths that only match once they are normalised
+                    let found_config_path = match canonicalize(&found_config_path) {
+                        Ok(canonical_path) => canonical_path,
+                        Err(err) => {
+                            pattern_trace.candidates.push((found_config_path, CandidateOutcome::UNRESOLVED(err.to_string())));
+                            continue;
+                        }
+                    };
+
+                    if let Some(index) = excludes.matches(&found_config_path).first() {
+                        pattern_trace.candidates.push((found_config_path, CandidateOutcome::EXCLUDED(exclude_patterns[*index].clone())));
+                        continue;
+                    }
+
+                    // Only add if the path was not already processed, preventing loops.
+                    match found_config_paths.contains(&found_config_path) {
+                        true => pattern_trace.candidates.push((found_config_path, CandidateOutcome::VISITED)),
+                        false => {
+                            found_config_paths.push(found_config_path.clone());
+                            path_stack.push(found_config_path.clone());
+                            pattern_trace.candidates.push((found_config_path, CandidateOutcome::FOUND));
+                        }
+                    }
+                }
+
+                config_trace.patterns.push(pattern_trace);
+            }
+
+            config_trace.excludes.extend(exclude_patterns);
+        }
+
+        discovery_trace.configs.push(config_trace);
+        config_files.insert(config_path, file_configs);
+    }
+
+    Ok(found_config_paths
+        .iter()
+        .filter_map(|found_config_path| config_files.remove(found_config_path))
+        .flatten()
+        .collect())
+}
+
+// A rask.yaml on disk that discovery never reaches, configs skipped by a `!` pattern name it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreachableConfig {
+    pub config_path: PathBuf,
+    pub excluded_by: Option<String>,
+}
+
+// Dependencies and build output, their configs belong to other workspaces
+pub const SKIPPED_DIRECTORIES: [&str; 3] = ["node_modules", "vendor", "target"];
+
+pub fn find_unreachable_configs(entry_config_path: &Path) -> Result<Vec<UnreachableConfig>, String> {
+    let mut discovery_trace = DiscoveryTrace::default();
+    trace_config_files(entry_config_path, &mut ConfigIssues::new(true), &mut discovery_trace)?;
+
+    let mut config_paths: Vec<PathBuf> = vec![];
+    collect_config_paths(file::get_parent_directory(entry_config_path)?, &mut config_paths);
+    config_paths.sort();
+
+    let reached_config_paths: Vec<&PathBuf> = discovery_trace.configs.iter().map(|config_trace| &config_trace.config_path).collect();
+    let excluded_config_paths: Vec<(&PathBuf, &String)> = discovery_trace
+        .configs
+        .iter()
+        .flat_map(|config_trace| config_trace.patterns.iter())
+        .flat_map(|pattern_trace| pattern_trace.candidates.iter())
+        .filter_map(|(candidate_path, outcome)| match outcome {
+            CandidateOutcome::EXCLUDED(exclude_pattern) => Some((candidate_path, exclude_pattern)),
+            _ => None,
+        })
+        .collect();
+
+    Ok(config_paths
+        .into_iter()
+        .filter(|config_path| !reached_config_paths.contains(&config_path))
+        .map(|config_path| UnreachableConfig {
+            excluded_by: excluded_config_paths.iter().find(|(excluded_path, _)| **excluded_path == config_path).map(|(_, exclude_pattern)| exclude_pattern.to_string()),
+            config_path,
+        })
+        .collect())
+}
+
+// Hidden directories and symlinks are not followed, which keeps .git out and the walk finite
+fn collect_config_paths(directory: &Path, config_paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(directory) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name: String = entry.file_name().to_string_lossy().to_string();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() && !name.starts_with('.') && !SKIPPED_DIRECTORIES.contains(&name.as_str()) => collect_config_paths(&entry.path(), config_paths),
+            Ok(file_type) if file_type.is_file() && CONFIG_FILENAMES.contains(&name.as_str()) => config_paths.push(entry.path()),
+            _ => {},
+        }
+    }
+}
+
+struct DirectoryPatterns {
+    // The entry as written next to the glob it expands to
+    includes: Vec<(ConfigFileDirectory, PathBuf)>,
+    excludes: GlobSet,
+    exclude_patterns: Vec<String>,
+}
+
+// Entries starting with `!` exclude the configs matched by the remainder of the pattern
+fn parse_directory_patterns(config_directory: &Path, directories: &[ConfigFileDirectory]) -> Result<DirectoryPatterns, String> {
+    let mut includes: Vec<(ConfigFileDirectory, PathBuf)> = vec![];
+    let mut exclude_patterns: Vec<String> = vec![];
+    let mut exclude_builder = GlobSetBuilder::new();
+
+    for directory in directories {
+        let pattern: &str = directory.get_pattern();
+        match pattern.strip_prefix('!') {
+            Some(excluded_directory) => {
+                let path_pattern: PathBuf = get_config_glob_pattern(config_directory, excluded_directory);
+                exclude_builder.add(create_glob(&path_pattern)?);
+                exclude_patterns.push(pattern.to_string());
+            }
+            None => includes.push((directory.clone(), get_config_glob_pattern(config_directory, pattern))),
+        }
+    }
+
+    let excludes: GlobSet = exclude_builder.build().map_err(|err| format!("Failed to create the exclude patterns of {:?}: {}", config_directory, err))?;
+
+    Ok(DirectoryPatterns { includes, excludes, exclude_patterns })
+}
+
+// Matches like the glob crate used during discovery does, `*` never crosses a path separator
+pub fn create_glob(path_pattern: &Path) -> Result<Glob, String> {
+    GlobBuilder::new(file::path_to_str(path_pattern)?)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| format!("Failed to create glob pattern {:?}: {}", path_pattern, err))
+}
+
+fn get_config_glob_pattern(root_path: &Path, glob_pattern: &str) -> PathBuf {
+    let mut pattern: PathBuf = root_path.to_path_buf();
+
+    pattern.push(glob_pattern);
+    if !pattern.ends_with(".yaml") {
+        pattern.push("rask.yaml");
+    }
+
+    pattern
+}
+
+pub fn resolve_config_path<P: AsRef<Path> + Debug + Clone + Copy>(path: P) -> Result<PathBuf, String> {
+    let full_path = file::parse_path_string(path)?;
+
+    if full_path.is_dir() {
+        let config_file = find_config_file(full_path)?;
+        return Ok(config_file)
+    }
+
+    Ok(full_path)
+}
+
+const CONFIG_FILENAMES: [&str; 1] = ["rask.yaml"];
+
+// The closest config above the given directory, the one a new config would belong to
+pub fn find_parent_config_path(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .skip(1)
+        .find_map(|ancestor| find_config_file(ancestor.to_path_buf()).ok())
+}
+
+pub const ENTRY_ENV: &str = "RASK_ENTRY";
+
+// Without --entry, RASK_ENTRY or the nearest config from the current directory up is the entry, like git finds .git
+pub fn get_entry(entry: &Option<String>) -> String {
+    let env_entry: Option<String> = env::var(ENTRY_ENV).ok();
+
+    match env::current_dir() {
+        Ok(current_dir) => resolve_entry(entry, env_entry, &current_dir),
+        Err(_) => entry.clone().or(env_entry.filter(|env_entry| !env_entry.is_empty())).unwrap_or(".".to_string()),
+    }
+}
+
+// Directories without a config above them stay the entry, rask then runs the tasks of their engines
+fn resolve_entry(entry: &Option<String>, env_entry: Option<String>, current_dir: &Path) -> String {
+    if let Some(entry) = entry.clone().or(env_entry.filter(|env_entry| !env_entry.is_empty())) {
+        return entry;
+    }
+
+    current_dir
+        .ancestors()
+        .find_map(|ancestor| find_config_file(ancestor.to_path_buf()).ok())
+        .map(|config_path| config_path.to_string_lossy().to_string())
+        .unwrap_or(".".to_string())
+}
+
+// Constants give the defaults of `directories` placeholders, the environment overrides them per checkout
+pub fn get_directory_variables(constants: &Variables) -> Result<Variables, String> {
+    get_directory_variables_with(constants, env::vars())
+}
+
+fn get_directory_variables_with(constants: &Variables, environment: impl IntoIterator<Item = (String, String)>) -> Result<Variables, String> {
+    let mut variables: Variables = resolve_variables(constants)?;
+    variables.extend(environment);
+
+    Ok(variables)
+}
+
+pub fn interpolate_directories(directories: &[ConfigFileDirectory], variables: &Variables, config_path: &Path) -> Result<Vec<ConfigFileDirectory>, String> {
+    directories
+        .iter()
+        .map(|directory| {
+            let pattern: &str = directory.get_pattern();
+            interpolate_strict(pattern, variables)
+                .map(|pattern| directory.with_pattern(pattern))
+                .map_err(|err| format!("Unable to resolve the directories pattern {:?} of {:?}: {}", pattern, config_path, err))
+        })
+        .collect()
+}
+
+// The configs a single `directories` pattern picks up on disk
+pub fn find_pattern_config_paths(config_directory: &Path, directory: &str) -> Vec<PathBuf> {
+    let pattern: PathBuf = get_config_glob_pattern(config_directory, directory);
+
+    file::path_to_str(&pattern)
+        .ok()
+        .and_then(|pattern| glob::glob(pattern).ok())
+        .map(|config_paths| config_paths.flatten().collect())
+        .unwrap_or_default()
+}
+
+// Whether the directories patterns of a config already pick up the given config path
+pub fn includes_config_path(config_directory: &Path, directories: &[ConfigFileDirectory], config_path: &Path) -> Result<bool, String> {
+    let DirectoryPatterns { includes, excludes, .. } = parse_directory_patterns(config_directory, directories)?;
+
+    for (_, path_pattern) in includes {
+        if create_glob(&path_pattern)?.compile_matcher().is_match(config_path) && !excludes.is_match(config_path) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn find_config_file(directory_path: PathBuf) -> Result<PathBuf, String> {
+    if !directory_path.is_dir() {
+        return Err(format!("\"{:?}\" is not a directory", directory_path))
+    }
+
+    for filename in CONFIG_FILENAMES {
+        let possible_config_file = directory_path.join(filename);
+        if possible_config_file.exists() {
+            return Ok(possible_config_file)
+        }
+    }
+
+    Err(format!("Unable to find a config file (\"{:?}\") in {:?}", CONFIG_FILENAMES, directory_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+    use crate::utils::schedule;
+
+    fn discover_paths(workspace: &TestWorkspace, config_issues: &mut ConfigIssues) -> Vec<PathBuf> {
+        let config_files = discover_config_files(&workspace.config_path("."), config_issues).unwrap();
+
+        config_files.into_iter().map(|config_file| config_file.__file_path).collect()
+    }
+
+    fn monorepo() -> TestWorkspace {
+        TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\n  - tools\ntasks:\n  build: echo root\n  test: echo root\n")
+            .with_config("packages/api", "name: api\ndirectories:\n  - plugins/*\ntasks:\n  build: echo api\n  build:docs: echo docs\n")
+            .with_config("packages/api/plugins/auth", "name: auth\ntasks:\n  build: echo auth\n")
+            .with_config("packages/web", "name: web\ntask_engine: npm\n")
+            .with_package_json("packages/web", &["build", "lint"])
+            .with_config("tools", "name: tools\ntasks:\n  test: echo tools\n")
+            .with_config("unlisted", "name: unlisted\ntasks:\n  build: echo unlisted\n")
+    }
+
+    #[test]
+    fn entry_is_found_upwards_unless_given() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\n")
+            .with_file("packages/app/src/main.js", "");
+        let current_dir = workspace.path("packages/app/src");
+        let root_config = workspace.config_path(".").to_string_lossy().to_string();
+
+        assert_eq!(resolve_entry(&None, None, &current_dir), root_config);
+        assert_eq!(resolve_entry(&None, Some(String::new()), &current_dir), root_config);
+        assert_eq!(resolve_entry(&None, Some("apps".to_string()), &current_dir), "apps");
+        assert_eq!(resolve_entry(&Some("tools".to_string()), Some("apps".to_string()), &current_dir), "tools");
+        assert_eq!(resolve_entry(&None, None, &TestWorkspace::new().path(".")), ".");
+    }
+
+    #[test]
+    fn discovers_configs_through_directory_patterns() {
+        let workspace = monorepo();
+
+        let config_paths = discover_paths(&workspace, &mut ConfigIssues::new(false));
+
+        assert_eq!(workspace.relative_paths(&config_paths), vec![
+            "packages/api/plugins/auth/rask.yaml",
+            "packages/api/rask.yaml",
+            "packages/web/rask.yaml",
+            "rask.yaml",
+            "tools/rask.yaml",
+        ]);
+    }
+
+    #[test]
+    fn discovery_excludes_negated_patterns() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\n  - '!packages/legacy-*'\n")
+            .with_config("packages/app", "name: app\n")
+            .with_config("packages/legacy-app", "name: legacy-app\n");
+
+        let config_paths = discover_paths(&workspace, &mut ConfigIssues::new(false));
+
+        assert_eq!(workspace.relative_paths(&config_paths), vec!["packages/app/rask.yaml", "rask.yaml"]);
+        assert_eq!(workspace.render_structure(&workspace.load_structure(".")), "root\n  app");
+    }
+
+    #[test]
+    fn directory_patterns_interpolate_constants_and_environment() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [\"${APPS_DIR}/*\", \"${RASK_TEST_LIBS_DIR:-libs}/*\", \"!${APPS_DIR}/legacy\"]\nconstants:\n  APPS_DIR: apps\n")
+            .with_config("apps/web", "name: web\ndirectories: [\"../../${APPS_DIR}/api\"]\n")
+            .with_config("apps/api", "name: api\n")
+            .with_config("apps/legacy", "name: legacy\n")
+            .with_config("libs/ui", "name: ui\n")
+            .with_config("shared/ui", "name: shared-ui\n");
+
+        let config_paths = discover_paths(&workspace, &mut ConfigIssues::new(false));
+        assert_eq!(workspace.relative_paths(&config_paths), vec!["apps/api/rask.yaml", "apps/web/rask.yaml", "libs/ui/rask.yaml", "rask.yaml"]);
+
+        let environment = [("RASK_TEST_LIBS_DIR".to_string(), "shared".to_string())];
+        let variables = get_directory_variables_with(&Variables::from([("APPS_DIR".to_string(), "apps".to_string())]), environment).unwrap();
+        let directories = vec![ConfigFileDirectory::Pattern("${RASK_TEST_LIBS_DIR:-libs}/*".to_string())];
+        assert_eq!(interpolate_directories(&directories, &variables, Path::new("rask.yaml")).unwrap(), vec![ConfigFileDirectory::Pattern("shared/*".to_string())]);
+
+        let directories = vec![ConfigFileDirectory::Pattern("${RASK_TEST_MISSING_DIR}/*".to_string())];
+        assert!(interpolate_directories(&directories, &variables, Path::new("rask.yaml")).unwrap_err().contains("${RASK_TEST_MISSING_DIR} is not set"));
+    }
+
+    #[test]
+    fn discovery_traces_why_candidates_were_skipped() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\n  - '!packages/legacy-*'\n  - tools/*\n")
+            .with_config("packages/app", "name: app\ndirectories:\n  - ../..\n")
+            .with_config("packages/legacy-app", "name: legacy-app\n");
+
+        let mut discovery_trace = DiscoveryTrace::default();
+        trace_config_files(&workspace.config_path("."), &mut ConfigIssues::new(false), &mut discovery_trace).unwrap();
+
+        let root_trace = &discovery_trace.configs[0];
+        assert_eq!(root_trace.excludes, vec!["!packages/legacy-*"]);
+        assert_eq!(root_trace.patterns[0].pattern, "packages/*");
+        assert_eq!(root_trace.patterns[0].candidates, vec![
+            (workspace.config_path("packages/app"), CandidateOutcome::FOUND),
+            (workspace.config_path("packages/legacy-app"), CandidateOutcome::EXCLUDED("!packages/legacy-*".to_string())),
+        ]);
+        assert!(root_trace.patterns[1].candidates.is_empty());
+
+        let app_trace = &discovery_trace.configs[1];
+        assert_eq!(app_trace.patterns[0].candidates, vec![(workspace.config_path("."), CandidateOutcome::VISITED)]);
+    }
+
+    #[test]
+    fn discovery_stops_at_cycles() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - child\n")
+            .with_config("child", "name: child\ndirectories:\n  - ..\n");
+
+        let config_paths = discover_paths(&workspace, &mut ConfigIssues::new(false));
+
+        assert_eq!(config_paths.len(), 2);
+    }
+
+    #[test]
+    fn lenient_discovery_skips_broken_child_configs() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\n")
+            .with_config("packages/app", "name: app\n")
+            .with_config("packages/broken", "name: [broken\n");
+
+        let mut config_issues = ConfigIssues::new(true);
+        let config_paths = discover_paths(&workspace, &mut config_issues);
+
+        assert_eq!(workspace.relative_paths(&config_paths), vec!["packages/app/rask.yaml", "rask.yaml"]);
+        assert_eq!(config_issues.issues.len(), 1);
+        assert_eq!(config_issues.issues[0].path, workspace.config_path("packages/broken"));
+        assert!(discover_config_files(&workspace.config_path("."), &mut ConfigIssues::new(false)).is_err());
+    }
+
+    #[test]
+    fn lenient_discovery_still_fails_on_the_entry_config() {
+        let workspace = TestWorkspace::new().with_config(".", "name: [broken\n");
+
+        assert!(discover_config_files(&workspace.config_path("."), &mut ConfigIssues::new(true)).is_err());
+    }
+
+    #[test]
+    fn strict_config_suggests_known_keys() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - app\ntasks:\n  build: make\n")
+            .with_config("app", "name: app\ntask:\n  build: make\nfoo: bar\n");
+
+        let (_, config_files) = read_entry_config_files(&workspace.path(".").to_string_lossy(), true, &mut ConfigIssues::new(false)).unwrap();
+        let error = validate_config_files(&config_files).unwrap_err();
+
+        assert!(error.contains("Unknown key \"task\""));
+        assert!(error.contains("did you mean \"tasks\"?"));
+        assert!(error.contains("Unknown key \"foo\""));
+        assert!(!error.contains("\"name\" in"));
+    }
+
+    #[test]
+    fn resolves_the_config_structure() {
+        let workspace = monorepo();
+
+        let config_structure = workspace.load_structure(".");
+
+        assert_eq!(workspace.render_structure(&config_structure), [
+            "root",
+            "  api",
+            "    auth",
+            "  tools",
+            "  web",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn orders_tasks_from_the_deepest_config_up() {
+        let workspace = monorepo();
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "build", &false).unwrap();
+
+        assert_eq!(workspace.render_tasks(&sortable_tasks), [
+            "2 build @ packages/api/plugins/auth",
+            "1 build @ packages/api",
+            "1 build @ packages/web",
+            "1 build:docs @ packages/api",
+            "0 build @ .",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn strict_matching_ignores_prefixed_tasks() {
+        let workspace = monorepo();
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "build", &true).unwrap();
+
+        assert_eq!(workspace.render_tasks(&sortable_tasks), [
+            "2 build @ packages/api/plugins/auth",
+            "1 build @ packages/api",
+            "1 build @ packages/web",
+            "0 build @ .",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn engine_tasks_use_the_engine_command() {
+        let workspace = monorepo();
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("packages/web"), "lint", &true).unwrap();
+
+        assert_eq!(sortable_tasks.len(), 1);
+        assert_eq!(sortable_tasks[0].task.command, "npm run lint");
+    }
+
+    #[test]
+    fn taskfile_and_justfile_tasks_run_through_their_tools() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\n")
+            .with_file("Taskfile.yml", "version: '3'\ntasks:\n  build:\n    cmds: [go build]\n  setup:\n    internal: true\n    cmds: [go mod download]\n")
+            .with_file("justfile", "set shell := [\"bash\", \"-c\"]\nversion := \"1.0\"\nalias t := test\n\n# Run the tests\ntest filter=\"\": build\n    cargo test {{filter}}\n\n@lint:\n    cargo clippy\n\n_helper:\n    echo hidden\n\n[private]\nsecret:\n    echo hidden\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "", &false).unwrap();
+        let mut commands: Vec<&String> = sortable_tasks.iter().map(|sortable_task| &sortable_task.task.command).collect();
+        commands.sort();
+
+        assert_eq!(commands, vec!["just lint", "just test", "task build"]);
+        assert!(matches!(detect_task_engine(&workspace.path(".")), TaskEngine::AUTO));
+        assert_eq!(find_marker_files(&workspace.path(".")), vec!["Taskfile.yml", "justfile"]);
+    }
+
+    #[test]
+    fn configs_keep_what_their_engines_were_detected_from() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntask_engine: auto\ntasks:\n  deploy: ./deploy.sh\n")
+            .with_package_json(".", &["build"])
+            .with_file("yarn.lock", "")
+            .with_file("composer.json", "{\"scripts\": {\"serve\": \"php -S\"}}");
+
+        let configs = workspace.load_configs(".");
+        let mut sources: Vec<(&str, &str)> = configs[0].tasks.iter().map(|ConfigTask { task_type, key, .. }| (get_task_source(task_type), key.as_str())).collect();
+        sources.sort();
+
+        assert!(matches!(configs[0].task_engine, TaskEngine::AUTO));
+        assert_eq!(find_marker_files(&workspace.path(".")), vec!["composer.json", "package.json", "yarn.lock"]);
+        assert_eq!(sources, vec![("composer", "serve"), ("yaml", "deploy"), ("yarn", "build")]);
+    }
+
+    #[test]
+    fn taskfile_tasks_are_sorted_by_key() {
+        let workspace = TestWorkspace::new()
+            .with_file("Taskfile.yml", "version: '3'\ntasks:\n  test: go test\n  build: go build\n  lint: golangci-lint run\n  deploy: ./deploy.sh\n");
+        let task_filter = parse_task_filter(&EngineTaskFilter::default()).unwrap();
+
+        let keys: Vec<String> = parse_taskfile_tasks(&workspace.path("."), &task_filter).unwrap().into_iter().map(|config_task| config_task.key).collect();
+        assert_eq!(keys, vec!["build", "deploy", "lint", "test"]);
+    }
+
+    #[test]
+    fn compose_services_become_tasks() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: infra\nengine_tasks:\n  exclude: [logs]\n")
+            .with_file("compose.yaml", "services:\n  db:\n    image: postgres\n  web:\n    build: .\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "", &false).unwrap();
+        let mut tasks: Vec<(String, String)> = sortable_tasks.iter().map(|sortable_task| (sortable_task.task.key.clone(), sortable_task.task.command.clone())).collect();
+        tasks.sort();
+
+        assert_eq!(tasks, vec![
+            ("down".to_string(), "docker compose down".to_string()),
+            ("up".to_string(), "docker compose up --detach".to_string()),
+            ("up:db".to_string(), "docker compose up --detach db".to_string()),
+            ("up:web".to_string(), "docker compose up --detach web".to_string()),
+        ]);
+        assert!(matches!(detect_task_engine(&workspace.path(".")), TaskEngine::COMPOSE));
+        assert_eq!(find_task_file(&workspace.path("."), "compose"), Some(workspace.path("compose.yaml")));
+    }
+
+    #[test]
+    fn cargo_workspace_members_become_configs() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntask_engine: cargo\nengine_tasks:\n  include: [build, test]\n")
+            .with_file("Cargo.toml", "[package]\nname = \"app\"\n\n[workspace]\nmembers = [\"crates/*\"]\n")
+            .with_file("crates/core/Cargo.toml", "[package]\nname = \"app-core\"\n")
+            .with_config("crates/cli", "name: cli\ntasks:\n  build: make\n")
+            .with_file("crates/cli/Cargo.toml", "[package]\nname = \"app-cli\"\n");
+
+        let config_structure = workspace.load_structure(".");
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+        let commands: Vec<(u64, String, String)> = sortable_tasks
+            .iter()
+            .map(|SortableTask { task, order, .. }| (*order, workspace.relative(&task.directory), task.command.clone()))
+            .collect();
+
+        assert_eq!(commands, vec![(0, ".".to_string(), "cargo build -p app".to_string()), (1, "crates/core".to_string(), "cargo build -p app-core".to_string())]);
+        let sortable_tasks = resolve_sortable_task(&config_structure, "app-core#test", &true).unwrap();
+        assert_eq!(sortable_tasks[0].task.command, "cargo test -p app-core");
+    }
+
+    #[test]
+    fn auto_engine_prefers_yarn_with_a_lock_file() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\n")
+            .with_package_json(".", &["dev"])
+            .with_file("yarn.lock", "");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "dev", &true).unwrap();
+
+        assert_eq!(sortable_tasks[0].task.command, "yarn run dev");
+    }
+
+    #[test]
+    fn structure_ignores_configs_including_their_ancestors() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - child\n")
+            .with_config("child", "name: child\ndirectories:\n  - ..\n");
+
+        assert_eq!(workspace.render_structure(&workspace.load_structure(".")), "root\n  child");
+    }
+
+    #[test]
+    fn yaml_tasks_depend_on_engine_tasks() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntask_engine: npm\ntasks:\n  deploy:\n    command: ./deploy.sh\n    depends_on: [build]\n")
+            .with_package_json(".", &["build", "lint"]);
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "deploy", &true).unwrap();
+        let dependencies: Vec<&String> = sortable_tasks[0].task.dependencies.iter().map(|task| &task.command).collect();
+
+        assert_eq!(sortable_tasks.len(), 1);
+        assert_eq!(dependencies, vec!["npm run build"]);
+    }
+
+    #[test]
+    fn tasks_accept_a_list_of_steps() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\nconstants:\n  target: dist\ntasks:\n  build: [\"npm ci\", \"npm run build -- ${target}\"]\n  deploy:\n    command: [./deploy.sh]\n    allow_failure: true\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "build", &true).unwrap();
+        let Task { command, steps, .. } = &sortable_tasks[0].task;
+
+        assert_eq!(steps, &vec!["npm ci", "npm run build -- dist"]);
+        assert_eq!(command, "npm ci && npm run build -- dist");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "deploy", &true).unwrap();
+        assert_eq!(sortable_tasks[0].task.steps, vec!["./deploy.sh"]);
+        assert!(sortable_tasks[0].task.options.allow_failure);
+    }
+
+    #[test]
+    fn tasks_of_several_names_are_merged() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [apps/*]\ntasks:\n  lint: eslint .\n")
+            .with_config("apps/web", "name: web\ntasks:\n  build: npm run build\n  test: npm test\n");
+        let config_structure = workspace.load_structure(".");
+
+        let mut sortable_tasks = resolve_sortable_task(&config_structure, "test", &true).unwrap();
+        merge_sortable_tasks(&mut sortable_tasks, resolve_sortable_task(&config_structure, "build", &true).unwrap());
+        merge_sortable_tasks(&mut sortable_tasks, resolve_sortable_task(&config_structure, "lint", &true).unwrap());
+        merge_sortable_tasks(&mut sortable_tasks, resolve_sortable_task(&config_structure, "web#build", &true).unwrap());
+
+        let keys: Vec<(&str, u64)> = sortable_tasks.iter().map(|SortableTask { task, order, .. }| (task.key.as_str(), *order)).collect();
+        assert_eq!(keys, vec![("test", 1), ("build", 1), ("lint", 0)]);
+        assert_eq!(sortable_tasks[1].provenance.len(), 1);
+    }
+
+    #[test]
+    fn configs_included_twice_schedule_their_tasks_once() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [apps, apps/*, apps/web]\n")
+            .with_config("apps", "name: apps\ndirectories: [web]\n")
+            .with_config("apps/web", "name: web\ntasks:\n  build: echo web\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "build", &true).unwrap();
+
+        assert_eq!(sortable_tasks.len(), 1);
+        assert_eq!(sortable_tasks[0].order, 2);
+        let chains: Vec<Vec<String>> = sortable_tasks[0].provenance.iter().map(|config_chain| config_chain.iter().map(|path| workspace.relative(path)).collect()).collect();
+        assert_eq!(chains.len(), 2);
+        assert!(chains.contains(&vec![".".to_string(), "apps/web".to_string()]));
+        assert!(chains.contains(&vec![".".to_string(), "apps".to_string(), "apps/web".to_string()]));
+    }
+
+    #[test]
+    fn new_configs_find_the_parent_that_includes_them() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [\"apps/*\", \"!apps/legacy\"]\n")
+            .with_file("apps/web/index.js", "")
+            .with_file("apps/legacy/index.js", "")
+            .with_file("tools/cli/main.rs", "");
+
+        assert_eq!(find_parent_config_path(&workspace.path("tools/cli")), Some(workspace.config_path(".")));
+
+        let directories: Vec<ConfigFileDirectory> = vec![ConfigFileDirectory::Pattern("apps/*".to_string()), ConfigFileDirectory::Pattern("!apps/legacy".to_string())];
+        let is_included = |directory: &str| includes_config_path(&workspace.path("."), &directories, &workspace.path(directory).join("rask.yaml")).unwrap();
+        assert!(is_included("apps/web"));
+        assert!(!is_included("apps/legacy"));
+        assert!(!is_included("tools/cli"));
+    }
+
+    #[test]
+    fn config_names_address_the_tasks_of_one_config() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [\"packages/*\"]\ntasks:\n  build: echo root\n")
+            .with_config("packages/app", "name: app\ntasks:\n  build: echo app\n")
+            .with_config("packages/lib", "name: lib\ntasks:\n  build: echo lib\n");
+        let config_structure = workspace.load_structure(".");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "app#build", &true).unwrap();
+        assert_eq!(sortable_tasks.iter().map(|sortable_task| sortable_task.task.command.as_str()).collect::<Vec<&str>>(), vec!["echo app"]);
+        assert_eq!(sortable_tasks[0].order, 1);
+
+        let err = resolve_sortable_task(&config_structure, "web#build", &true).unwrap_err();
+        assert_eq!(err, "Unknown config \"web\" in task name \"web#build\"");
+    }
+
+    #[test]
+    fn configs_are_addressed_by_directory_or_colon() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [\"packages/*\"]\ntasks:\n  build: echo root\n  build:docs: echo docs\n")
+            .with_config("packages/app", "name: app\ntasks:\n  build: echo app\n  build:assets: echo assets\n  lint: echo lint\n  test:\n    command: echo test\n    depends_on: [lint]\n");
+        let config_structure = workspace.load_structure(".");
+        let commands = |task_name: &str| -> Vec<String> {
+            resolve_sortable_task(&config_structure, task_name, &false).unwrap().into_iter().map(|sortable_task| sortable_task.task.command).collect()
+        };
+
+        // Addressed tasks match exactly, not by prefix
+        assert_eq!(commands("packages/app#build"), vec!["echo app"]);
+        assert_eq!(commands("app:build"), vec!["echo app"]);
+        assert_eq!(commands("app:test"), vec!["echo test"]);
+        assert_eq!(resolve_sortable_task(&config_structure, "app:test", &false).unwrap()[0].task.dependencies[0].command, "echo lint");
+        // An existing task key wins over the colon syntax
+        assert_eq!(commands("build:docs"), vec!["echo docs"]);
+    }
+
+    #[test]
+    fn config_names_must_be_unique() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [\"packages/*\"]\n")
+            .with_config("packages/app", "name: app\n")
+            .with_config("packages/web", "name: app\n");
+        let entry_config_path = workspace.config_path(".");
+        let mut config_issues = ConfigIssues::new(false);
+        let config_files = discover_config_files(&entry_config_path, &mut config_issues).unwrap();
+
+        let err = parse_config_files(config_files, &entry_config_path, &mut config_issues).unwrap_err();
+
+        assert!(err.starts_with("Config names must be unique within a workspace:\n  - \"app\" is used by "));
+        assert!(err.contains("packages/app/rask.yaml") && err.contains("packages/web/rask.yaml"));
+    }
+
+    #[test]
+    fn invalid_directory_patterns_are_errors() {
+        let workspace = TestWorkspace::new().with_config(".", "name: root\ndirectories: [\"packages/[a\"]\n");
+
+        let err = discover_config_files(&workspace.config_path("."), &mut ConfigIssues::new(false)).unwrap_err();
+
+        assert!(err.starts_with("Failed to read glob pattern"), "{}", err);
+        assert!(err.contains("packages/[a") && err.contains(&workspace.config_path(".").to_string_lossy().to_string()), "{}", err);
+    }
+
+    #[test]
+    fn overridden_constants_may_become_cyclic() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [app]\nconstants:\n  IMAGE: ${NAME}\n  NAME: api\ntasks:\n  build: docker build -t ${IMAGE} .\n")
+            .with_config("app", "name: app\nconstants:\n  NAME: ${IMAGE}-app\ntasks:\n  build: docker build -t ${IMAGE} .\n");
+        let config_structure = workspace.load_structure(".");
+
+        let err = resolve_sortable_task(&config_structure, "build", &true).unwrap_err();
+
+        assert_eq!(err, format!("Unable to resolve the constants of {:?}: ${{IMAGE}}, ${{NAME}} refer back to themselves", workspace.config_path("app")));
+    }
+
+    #[test]
+    fn rejects_zero_weights_and_parallelism() {
+        let parse = |content: &str| {
+            let workspace = TestWorkspace::new().with_config(".", content);
+            let entry_config_path = workspace.config_path(".");
+            let mut config_issues = ConfigIssues::new(false);
+            let config_files = discover_config_files(&entry_config_path, &mut config_issues).unwrap();
+            parse_config_files(config_files, &entry_config_path, &mut config_issues).map(|configs| configs[0].tasks[0].options.get_weight())
+        };
+
+        assert_eq!(parse("name: root\ntasks:\n  build:\n    command: make\n    weight: 4\n"), Ok(4));
+        assert!(parse("name: root\ntasks:\n  build:\n    command: make\n    weight: 0\n").unwrap_err().contains("Invalid weight 0 for task \"build\", expected at least 1"));
+        assert!(parse("name: root\ndefault_parallelism: 0\ntasks:\n  build: make\n").unwrap_err().contains("Invalid default_parallelism 0, expected at least 1"));
+    }
+
+    #[test]
+    fn parses_resource_limits() {
+        let parse = |content: &str| {
+            let workspace = TestWorkspace::new().with_config(".", content);
+            let entry_config_path = workspace.config_path(".");
+            let mut config_issues = ConfigIssues::new(false);
+            let config_files = discover_config_files(&entry_config_path, &mut config_issues).unwrap();
+            parse_config_files(config_files, &entry_config_path, &mut config_issues).map(|configs| (configs[0].tasks[0].options.nice, configs[0].tasks[0].options.max_memory))
+        };
+
+        assert_eq!(parse("name: root\ntasks:\n  build:\n    command: make\n    nice: 10\n    max_memory: 512M\n"), Ok((Some(10), Some(512 * 1024 * 1024))));
+        assert_eq!(parse("name: root\ntasks:\n  build:\n    command: make\n    max_memory: 2gb\n"), Ok((None, Some(2 * 1024 * 1024 * 1024))));
+        assert!(parse("name: root\ntasks:\n  build:\n    command: make\n    nice: 20\n").unwrap_err().contains("Invalid nice value 20 for task \"build\""));
+        assert!(parse("name: root\ntasks:\n  build:\n    command: make\n    max_memory: lots\n").unwrap_err().contains("Invalid max_memory \"lots\" for task \"build\""));
+    }
+
+    #[test]
+    fn yaml_tasks_shadow_engine_tasks() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  build: make\n")
+            .with_package_json(".", &["build", "dev"])
+            .with_file("composer.json", "{\"scripts\": {\"dev\": \"php -S\"}}");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "", &false).unwrap();
+        let mut commands: Vec<&String> = sortable_tasks.iter().map(|sortable_task| &sortable_task.task.command).collect();
+        commands.sort();
+
+        assert_eq!(commands, vec!["composer run dev", "make", "npm run dev"]);
+    }
+
+    #[test]
+    fn matching_dependencies_only_run_through_their_dependent() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  ci:\n    command: echo deploy\n    depends_on: [ci:build]\n  ci:build:\n    command: echo build\n    depends_on: [ci:lint]\n  ci:lint: echo lint\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "ci", &false).unwrap();
+        let dependencies: Vec<&String> = sortable_tasks[0].task.dependencies.iter().map(|task| &task.key).collect();
+
+        assert_eq!(sortable_tasks.len(), 1);
+        assert_eq!(dependencies, vec!["ci:lint", "ci:build"]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_circular_dependencies() {
+        let unknown = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  deploy:\n    command: ./deploy.sh\n    depends_on: [build]\n");
+        let config_file = file::read_config_file(unknown.config_path(".")).unwrap();
+        assert!(parse_config_file(config_file, &[]).is_err());
+
+        let circular = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  a:\n    command: echo a\n    depends_on: [b]\n  b:\n    command: echo b\n    depends_on: [a]\n");
+        assert!(resolve_sortable_task(&circular.load_structure("."), "a", &true).is_err());
+    }
+
+    #[test]
+    fn after_orders_scheduled_tasks_without_adding_them() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [app]\ntasks:\n  ci:lint:\n    command: echo lint\n    after: [\"app#ci:build\"]\n  ci:test:\n    command: echo test\n    after: [ci:lint, ci:missing, \"unknown#ci\"]\n")
+            .with_config("app", "name: app\ntasks:\n  ci:build: echo build\n");
+        let config_structure = workspace.load_structure(".");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "ci", &false).unwrap();
+        let mut orders: Vec<(u64, &String)> = sortable_tasks.iter().map(|SortableTask { task, order, .. }| (*order, &task.command)).collect();
+        orders.sort();
+        assert_eq!(orders, vec![(0, &"echo test".to_string()), (1, &"echo lint".to_string()), (2, &"echo build".to_string())]);
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "ci:test", &true).unwrap();
+        assert_eq!(sortable_tasks.len(), 1);
+        assert_eq!(sortable_tasks[0].order, 0);
+
+        let circular = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  ci:a:\n    command: echo a\n    after: [ci:b]\n  ci:b:\n    command: echo b\n    after: [ci:a]\n");
+        assert!(resolve_sortable_task(&circular.load_structure("."), "ci", &false).is_err());
+    }
+
+    #[test]
+    fn after_keeps_child_configs_below_their_parent() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [app, tools]\ntasks:\n  build: echo root\n")
+            .with_config("app", "name: app\ndirectories: [lib]\ntasks:\n  build: echo app\n")
+            .with_config("app/lib", "name: lib\ntasks:\n  build: echo lib\n")
+            .with_config("tools", "name: tools\ntasks:\n  build:\n    command: echo tools\n    after: [\"app#build\"]\n");
+
+        // app moves below tools, and lib moves along so it still builds before app
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "3 build @ app/lib\n2 build @ app\n1 build @ tools\n0 build @ .");
+    }
+
+    #[test]
+    fn tasks_use_templates_of_the_entry_config() {
+        let templates = "task_templates:\n  docker:\n    parameters: {image: ~, context: .}\n    task:\n      build:\n        command: docker build -t ${registry}/${image} ${context}\n        inputs: [\"${context}/Dockerfile\"]\n      push: docker push ${registry}/${image}\n";
+        let workspace = TestWorkspace::new()
+            .with_config(".", &format!("name: root\ndirectories: [app]\nconstants:\n  registry: ghcr.io\n{}", templates))
+            .with_config("app", "name: app\ntasks:\n  docker:\n    uses: docker\n    with: {image: api}\n");
+        let config_structure = workspace.load_structure(".");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "docker:build", &true).unwrap();
+        assert_eq!(sortable_tasks[0].task.command, "docker build -t ghcr.io/api .");
+        assert_eq!(sortable_tasks[0].task.inputs, vec![workspace.path("app").join("./Dockerfile")]);
+        assert_eq!(resolve_sortable_task(&config_structure, "docker:push", &true).unwrap()[0].task.command, "docker push ghcr.io/api");
+
+        for uses in ["uses: docker", "uses: docker\n    with: {image: api, tag: latest}", "uses: unknown", "uses: docker\n    width: {image: api}", "uses:\n      command: echo"] {
+            let workspace = TestWorkspace::new().with_config(".", &format!("name: root\n{}tasks:\n  docker:\n    {}\n", templates, uses));
+            let entry_config_path = workspace.config_path(".");
+            let config_files = discover_config_files(&entry_config_path, &mut ConfigIssues::new(false)).unwrap();
+            assert!(parse_config_files(config_files, &entry_config_path, &mut ConfigIssues::new(false)).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_stop_signals_and_grace_periods() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  serve:\n    command: ./serve.sh\n    stop_signal: sigint\n    stop_grace_period: 10s\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "serve", &true).unwrap();
+        let TaskOptions { stop_signal, stop_grace_period, .. } = &sortable_tasks[0].task.options;
+        assert_eq!(stop_signal.as_deref(), Some("INT"));
+        assert_eq!(*stop_grace_period, Some(Duration::from_secs(10)));
+
+        let invalid = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  serve:\n    command: ./serve.sh\n    stop_signal: SIGSTOP\n");
+        let config_file = file::read_config_file(invalid.config_path(".")).unwrap();
+        assert!(parse_config_file(config_file, &[]).is_err());
+    }
+
+    #[test]
+    fn parses_platform_constraints() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  sign:\n    command: ./sign.sh\n    os: [Darwin, linux]\n    arch: [arm64]\n  build: make\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "sign", &true).unwrap();
+        let options = &sortable_tasks[0].task.options;
+        assert_eq!((options.os.clone(), options.arch.clone()), (vec!["macos".to_string(), "linux".to_string()], vec!["aarch64".to_string()]));
+        assert_eq!(options.describe_platforms(), "macos, linux on aarch64");
+        assert!(TaskOptions::default().supports_platform());
+        assert!(TaskOptions { os: vec![env::consts::OS.to_string()], ..Default::default() }.supports_platform());
+        let other_os = OPERATING_SYSTEMS.iter().find(|os| **os != env::consts::OS).unwrap();
+        assert!(!TaskOptions { os: vec![other_os.to_string()], ..Default::default() }.supports_platform());
+
+        let invalid = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  sign:\n    command: ./sign.sh\n    os: [beos]\n");
+        let config_file = file::read_config_file(invalid.config_path(".")).unwrap();
+        assert!(parse_config_file(config_file, &[]).is_err());
+    }
+
+    #[test]
+    fn marks_tasks_that_need_confirmation() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  db:\n    reset:\n      command: ./reset.sh\n      confirm: true\n    migrate: ./migrate.sh\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "db", &false).unwrap();
+        let confirmed: Vec<(&str, bool)> = sortable_tasks.iter().map(|sortable_task| (sortable_task.task.key.as_str(), sortable_task.task.options.confirm)).collect();
+        assert!(confirmed.contains(&("db:reset", true)) && confirmed.contains(&("db:migrate", false)));
+    }
+
+    #[test]
+    fn finds_configs_no_pattern_reaches() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [packages/*, \"!packages/legacy\"]\n")
+            .with_config("packages/app", "name: app\n")
+            .with_config("packages/legacy", "name: legacy\n")
+            .with_config("tools/release", "name: release\n")
+            .with_config("packages/app/node_modules/dep", "name: dep\n");
+
+        let unreachable_configs = find_unreachable_configs(&workspace.config_path(".")).unwrap();
+        let unreachable: Vec<(String, Option<String>)> = unreachable_configs
+            .into_iter()
+            .map(|UnreachableConfig { config_path, excluded_by }| (workspace.relative(&config_path), excluded_by))
+            .collect();
+
+        assert_eq!(unreachable, vec![("packages/legacy/rask.yaml".to_string(), Some("!packages/legacy".to_string())), ("tools/release/rask.yaml".to_string(), None)]);
+    }
+
+    #[test]
+    fn injects_the_command_task_into_the_configs_in_scope() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [packages/*, tools/*]\nconstants:\n  runner: npx\n")
+            .with_config("packages/app", "name: app\n")
+            .with_config("packages/lib", "name: lib\n")
+            .with_config("tools/release", "name: release\n");
+        let mut config_issues = ConfigIssues::new(false);
+        let (entry_config_path, config_files) = read_entry_config_files(&workspace.path(".").to_string_lossy(), true, &mut config_issues).unwrap();
+        let mut configs: Vec<Config> = parse_config_files(config_files, &entry_config_path, &mut config_issues).unwrap();
+
+        inject_command_task(&mut configs, &entry_config_path, "${runner} test", &["packages/*".to_string(), "release".to_string()]).unwrap();
+        let config_structure = resolve_config_structure(&entry_config_path, configs.clone()).unwrap();
+        let sortable_tasks = resolve_sortable_task(&config_structure, COMMAND_TASK_KEY, &true).unwrap();
+
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "1 command @ packages/app\n1 command @ packages/lib\n1 command @ tools/release");
+        assert!(sortable_tasks.iter().all(|sortable_task| sortable_task.task.command == "npx test"));
+        assert!(inject_command_task(&mut configs, &entry_config_path, "true", &["apps/*".to_string()]).is_err());
+    }
+
+    #[test]
+    fn ordered_directories_run_in_sequence() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - apps/*\n  - path: libs/*\n    order: 1\n  - path: tools/*\n    order: 2\n    parallel: false\ntasks:\n  build: make\n")
+            .with_config("libs/core", "name: core\ntasks:\n  build: make\n")
+            .with_config("libs/ui", "name: ui\ntasks:\n  build: make\n")
+            .with_config("tools/cli", "name: cli\ntasks:\n  build: make\n")
+            .with_config("tools/lint", "name: lint\ntasks:\n  build: make\n")
+            .with_config("apps/web", "name: web\ndirectories: [plugins/*]\ntasks:\n  build: make\n")
+            .with_config("apps/web/plugins/chat", "name: chat\ntasks:\n  build: make\n");
+        let config_structure = workspace.load_structure(".");
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+
+        // The plugins of web stay below web, after the tools
+        assert_eq!(workspace.render_tasks(&sortable_tasks), [
+            "5 build @ libs/core",
+            "5 build @ libs/ui",
+            "4 build @ tools/cli",
+            "3 build @ tools/lint",
+            "2 build @ apps/web/plugins/chat",
+            "1 build @ apps/web",
+            "0 build @ .",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn synthesizes_a_config_for_engine_files_only() {
+        let workspace = TestWorkspace::new()
+            .with_package_json("web", &["build"])
+            .with_file("docs/README.md", "");
+        let read = |directory: &str, require_config: bool| read_entry_config_files(&workspace.path(directory).to_string_lossy(), require_config, &mut ConfigIssues::new(false));
+
+        let (entry_config_path, config_files) = read("web", false).unwrap();
+        assert_eq!(entry_config_path, workspace.config_path("web"));
+        assert_eq!(config_files.len(), 1);
+        assert_eq!(config_files[0].name, "web");
+        assert!(matches!(config_files[0].task_engine, TaskEngine::AUTO));
+
+        assert!(read("web", true).is_err());
+        assert!(read("docs", false).is_err());
+    }
+
+    #[test]
+    fn single_packages_skip_the_structure() {
+        let workspace = TestWorkspace::new()
+            .with_package_json("web", &["build", "test"])
+            .with_file("cli/Cargo.toml", "[package]\nname = \"cli\"\n\n[workspace]\nmembers = [\"crates/*\"]\n")
+            .with_file("cli/crates/core/Cargo.toml", "[package]\nname = \"cli-core\"\n")
+            .with_package_json("app", &["build"])
+            .with_config("app", "name: app\n");
+        let load = |directory: &str| -> (PathBuf, Vec<Config>) {
+            let mut config_issues = ConfigIssues::new(false);
+            let (entry_config_path, config_files) = read_entry_config_files(&workspace.path(directory).to_string_lossy(), false, &mut config_issues).unwrap();
+            let configs = parse_config_files(config_files, &entry_config_path, &mut config_issues).unwrap();
+            (entry_config_path, configs)
+        };
+
+        let (entry_config_path, configs) = load("web");
+        assert!(is_single_package(&entry_config_path, &configs));
+        let config_structure = create_single_package_structure(configs).unwrap();
+        let sortable_tasks = resolve_sortable_task(&config_structure, "test", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "0 test @ web");
+
+        // A Cargo workspace has members to resolve, a rask.yaml may have anything
+        let (entry_config_path, configs) = load("cli");
+        assert!(!is_single_package(&entry_config_path, &configs));
+        let (entry_config_path, configs) = load("app");
+        assert!(!is_single_package(&entry_config_path, &configs));
+    }
+
+    #[test]
+    fn referenced_tasks_of_other_configs_run_first() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [apps/*, libs/*]\n")
+            .with_config("apps/web", "name: web\ntasks:\n  build:\n    command: echo web\n    depends_on: [\"../../libs/shared#build\"]\n")
+            .with_config("libs/shared", "name: shared\ntasks:\n  build:\n    command: echo shared\n    depends_on: [\"tokens#build\"]\n  test: echo test\n")
+            .with_config("libs/tokens", "name: tokens\ntasks:\n  build: echo tokens\n");
+        let config_structure = workspace.load_structure(".");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "3 build @ libs/tokens\n2 build @ libs/shared\n1 build @ apps/web");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "web#build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "3 build @ libs/tokens\n2 build @ libs/shared\n1 build @ apps/web");
+    }
+
+    #[test]
+    fn referenced_tasks_keep_child_configs_below_them() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [app, tools]\n")
+            .with_config("app", "name: app\ndirectories: [lib]\ntasks:\n  build: echo app\n")
+            .with_config("app/lib", "name: lib\ntasks:\n  build: echo lib\n")
+            .with_config("tools", "name: tools\ntasks:\n  build:\n    command: echo tools\n    depends_on: [\"app#build\"]\n");
+        let config_structure = workspace.load_structure(".");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "3 build @ app/lib\n2 build @ app\n1 build @ tools");
+
+        // lib is not part of a run of tools alone, its reference takes only app
+        let sortable_tasks = resolve_sortable_task(&config_structure, "tools#build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "2 build @ app\n1 build @ tools");
+    }
+
+    #[test]
+    fn shards_keep_referenced_tasks_together() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [apps/*, libs/*]\n")
+            .with_config("apps/web", "name: web\ntasks:\n  build:\n    command: echo web\n    depends_on: [\"shared#build\"]\n")
+            .with_config("apps/admin", "name: admin\ntasks:\n  build: echo admin\n")
+            .with_config("apps/docs", "name: docs\ntasks:\n  build: echo docs\n")
+            .with_config("libs/shared", "name: shared\ntasks:\n  build:\n    command: echo shared\n    depends_on: [\"tokens#build\"]\n")
+            .with_config("libs/tokens", "name: tokens\ntasks:\n  build: echo tokens\n");
+        let config_structure = workspace.load_structure(".");
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+
+        let render_shard = |index: usize| {
+            let groups = group_ordered_tasks(&sortable_tasks, &config_structure);
+            let shard_tasks = schedule::select_shard(sortable_tasks.clone(), groups, &workspace.path("."), schedule::Shard { index, count: 2 });
+            let mut directories: Vec<String> = shard_tasks.iter().map(|sortable_task| workspace.relative(&sortable_task.task.directory)).collect();
+            directories.sort();
+            directories
+        };
+
+        assert_eq!(render_shard(1), vec!["apps/web", "libs/shared", "libs/tokens"]);
+        assert_eq!(render_shard(2), vec!["apps/admin", "apps/docs"]);
+    }
+
+    #[test]
+    fn shards_keep_child_configs_with_their_parent() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [app, tools]\n")
+            .with_config("app", "name: app\ndirectories: [lib]\ntasks:\n  build: echo app\n")
+            .with_config("app/lib", "name: lib\ntasks:\n  build: echo lib\n")
+            .with_config("tools", "name: tools\ntasks:\n  build: echo tools\n");
+        let config_structure = workspace.load_structure(".");
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+
+        // lib runs before app, a shard with app alone would build it without lib
+        let groups = group_ordered_tasks(&sortable_tasks, &config_structure);
+        let shard_tasks = schedule::select_shard(sortable_tasks, groups, &workspace.path("."), schedule::Shard { index: 1, count: 2 });
+        assert_eq!(workspace.render_tasks(&shard_tasks), "2 build @ app/lib\n1 build @ app");
+    }
+
+    #[test]
+    fn rejects_unknown_and_circular_task_references() {
+        let unknown = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [a]\n")
+            .with_config("a", "name: a\ntasks:\n  build:\n    command: echo a\n    depends_on: [\"../b#build\"]\n");
+        assert!(resolve_sortable_task(&unknown.load_structure("."), "build", &true).is_err());
+
+        let circular = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [a, b]\n")
+            .with_config("a", "name: a\ntasks:\n  build:\n    command: echo a\n    depends_on: [\"b#build\"]\n")
+            .with_config("b", "name: b\ntasks:\n  build:\n    command: echo b\n    depends_on: [\"../a#build\"]\n");
+        assert!(resolve_sortable_task(&circular.load_structure("."), "build", &true).is_err());
+    }
+
+    #[test]
+    fn engine_tasks_are_filtered() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\nengine_tasks:\n  include: [build, \"test*\"]\n  exclude: [\"*:watch\"]\ntasks:\n  deploy: ./deploy.sh\n")
+            .with_package_json(".", &["build", "prebuild", "test", "test:unit", "test:watch"]);
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "", &false).unwrap();
+        let mut keys: Vec<&String> = sortable_tasks.iter().map(|sortable_task| &sortable_task.task.key).collect();
+        keys.sort();
+
+        assert_eq!(keys, vec!["build", "deploy", "test", "test:unit"]);
+    }
+
+    #[test]
+    fn children_inherit_and_override_constants() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [child]\nconstants:\n  org: acme\n  image: registry/${org}\ntasks:\n  show: echo ${image}\n")
+            .with_config("child", "name: child\nconstants:\n  org: child\ntasks:\n  show: echo ${image}\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "show", &true).unwrap();
+        let commands: Vec<(u64, String)> = sortable_tasks.iter().map(|SortableTask { task, order, .. }| (*order, task.command.clone())).collect();
+
+        assert!(commands.contains(&(0, "echo registry/acme".to_string())));
+        assert!(commands.contains(&(1, "echo registry/child".to_string())));
+    }
+
+    #[test]
+    fn propagates_tasks_only_the_entry_config_defines() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [packages/*]\ntasks:\n  audit: npm audit\n  build: echo root\n")
+            .with_config("packages/api", "name: api\ntasks:\n  build: echo api\n")
+            .with_config("packages/web", "name: web\n");
+        let config_structure = workspace.load_structure(".");
+
+        let sortable_tasks = resolve_sortable_task(&config_structure, "", &false).unwrap();
+        let sortable_tasks = propagate_entry_tasks(&config_structure, sortable_tasks);
+
+        let mut audits: Vec<(u64, String, String)> = sortable_tasks
+            .iter()
+            .filter(|SortableTask { task, .. }| task.key == "audit")
+            .map(|SortableTask { task, order, .. }| (*order, workspace.relative(&task.directory), task.env["RASK_CONFIG_NAME"].clone()))
+            .collect();
+        audits.sort();
+
+        assert_eq!(audits, vec![
+            (0, ".".to_string(), "root".to_string()),
+            (1, "packages/api".to_string(), "api".to_string()),
+            (1, "packages/web".to_string(), "web".to_string()),
+        ]);
+        assert_eq!(sortable_tasks.iter().filter(|SortableTask { task, .. }| task.key == "build").count(), 2);
+    }
+
+    #[test]
+    fn documents_of_one_file_are_configs_of_their_own() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  build: echo root\n---\nname: api\ndir: services/api\ntasks:\n  build: echo api\n---\nname: docs\ntasks:\n  build: echo docs\n")
+            .with_file("services/api/main.go", "package main\n");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "1 build @ .\n1 build @ services/api\n0 build @ .");
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "api#build", &true).unwrap();
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "1 build @ services/api");
+    }
+
+    #[test]
+    fn entry_config_overrides_engines_by_directory() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [legacy/*, apps/*]\nengine_overrides:\n  \"legacy/*\": composer\n  \"legacy/shop\": none\n")
+            .with_config("legacy/shop", "name: shop\n")
+            .with_package_json("legacy/shop", &["test"])
+            .with_config("legacy/blog", "name: blog\n")
+            .with_file("legacy/blog/composer.json", "{\"scripts\": {\"test\": \"phpunit\"}}")
+            .with_package_json("legacy/blog", &["test"])
+            .with_config("apps/web", "name: web\n")
+            .with_package_json("apps/web", &["test"]);
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "test", &true).unwrap();
+        let sources: Vec<(String, &str)> = sortable_tasks
+            .iter()
+            .map(|SortableTask { task, .. }| (workspace.relative(&task.directory), get_task_source(&task.task_type)))
+            .collect();
+
+        assert_eq!(sources.len(), 2);
+        assert!(sources.contains(&("legacy/blog".to_string(), "composer")));
+        assert!(sources.contains(&("apps/web".to_string(), "npm")));
+    }
+
+    #[test]
+    fn restricted_sources_leave_other_engines_unread() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  build:\n    command: echo build\n    depends_on: [lint]\n")
+            .with_file("package.json", "{ not json");
+        let entry_config_path = workspace.config_path(".");
+        let mut config_issues = ConfigIssues::new(false);
+        let mut config_files = discover_config_files(&entry_config_path, &mut config_issues).unwrap();
+        assert!(parse_config_files(config_files.clone(), &entry_config_path, &mut config_issues).is_err());
+
+        restrict_task_sources(&mut config_files, &["yaml".to_string()]);
+        let configs = parse_config_files(config_files, &entry_config_path, &mut config_issues).unwrap();
+
+        assert_eq!(configs[0].tasks.iter().map(|config_task| config_task.key.as_str()).collect::<Vec<&str>>(), vec!["build"]);
+    }
+
+    fn create_packages_workspace(package_count: usize) -> TestWorkspace {
+        let mut workspace = TestWorkspace::new().with_config(".", "name: root\ndirectories: [packages/*]\n");
+        for index in 0..package_count {
+            let directory = format!("packages/package-{}", index);
+            workspace = workspace
+                .with_config(&directory, &format!("name: package-{}\n", index))
+                .with_package_json(&directory, &["build", "lint", "test"]);
+        }
+
+        workspace
+    }
+
+    #[test]
+    fn parses_a_thousand_packages() {
+        let config_structure = create_packages_workspace(1000).load_structure(".");
+
+        assert_eq!(list_configs(&config_structure).len(), 1001);
+    }
+
+    // Timing depends on the machine, run it with cargo test --release -- --ignored --nocapture.
+    // Ten times the packages should take far less than the hundred times longer of a scan over every config per config.
+    #[test]
+    #[ignore]
+    fn benchmark_parsing_packages() {
+        for package_count in [100, 1000] {
+            let workspace = create_packages_workspace(package_count);
+            let start_time = std::time::Instant::now();
+            workspace.load_structure(".");
+            println!("Parsing {} packages took {:?}", package_count, start_time.elapsed());
+        }
+    }
+
+    #[test]
+    fn filters_tasks_by_source() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [web]\ntasks:\n  test: cargo test\n")
+            .with_config("web", "name: web\ntask_engine: npm\n")
+            .with_package_json("web", &["test"]);
+
+        let sortable_tasks = resolve_sortable_task(&workspace.load_structure("."), "test", &true).unwrap();
+        let sortable_tasks = filter_task_sources(sortable_tasks, &["npm".to_string()]);
+
+        assert_eq!(workspace.render_tasks(&sortable_tasks), "1 test @ web");
+    }
 }
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::utils::config::Task;
+use crate::utils::{file, state};
+
+const COOLDOWN_FILE: &str = "cooldowns.json";
+
+// The cooldown file of the current run and whether it is forced, commands that never call init skip nothing
+static COOLDOWN_FILE_PATH: OnceLock<(PathBuf, bool)> = OnceLock::new();
+// Parallel tasks finish at the same time, the file is read and written by one at a time
+static COOLDOWN_LOCK: Mutex<()> = Mutex::new(());
+
+// When tasks with a cooldown last succeeded, kept in the state directory of the entry config
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Completions {
+    // Seconds since the unix epoch, by the directory and key of a task
+    completions: HashMap<String, u64>,
+}
+
+impl Completions {
+    // How much of the cooldown is left, none when the task may run
+    pub fn get_remaining(&self, task: &Task, now: SystemTime) -> Option<Duration> {
+        let cooldown: Duration = task.options.cooldown?;
+        let completed_at: SystemTime = UNIX_EPOCH + Duration::from_secs(*self.completions.get(&get_completion_key(task))?);
+        let elapsed: Duration = now.duration_since(completed_at).unwrap_or_default();
+
+        cooldown.checked_sub(elapsed).filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn record(&mut self, task: &Task, now: SystemTime) {
+        let seconds: u64 = now.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default();
+        self.completions.insert(get_completion_key(task), seconds);
+    }
+}
+
+fn get_completion_key(task: &Task) -> String {
+    format!("{}#{}", task.directory.to_string_lossy(), task.key)
+}
+
+// Runs skip tasks within their cooldown, forced runs skip nothing
+pub fn init(entry_directory: &Path, force: bool) {
+    let _ = COOLDOWN_FILE_PATH.set((entry_directory.join(state::STATE_DIRECTORY).join(COOLDOWN_FILE), force));
+}
+
+// A missing or broken file means nothing ran recently
+fn read_completions(path: &Path) -> Completions {
+    file::read_json_file::<Completions>(path).unwrap_or_default()
+}
+
+fn write_completions(path: &Path, completions: &Completions) -> Result<(), String> {
+    let entry_directory = path.parent().and_then(Path::parent).ok_or("Failed to get the entry directory of the cooldowns")?;
+    state::get_state_directory(entry_directory)?;
+
+    let content = serde_json::to_string_pretty(completions).map_err(|err| err.to_string())?;
+    file::write_file_content(&path.to_path_buf(), &content)
+}
+
+pub fn get_remaining(task: &Task) -> Option<Duration> {
+    task.options.cooldown?;
+    let (path, force) = COOLDOWN_FILE_PATH.get()?;
+    if *force {
+        return None;
+    }
+
+    read_completions(path).get_remaining(task, SystemTime::now())
+}
+
+// Forced runs record their successes as well, the cooldown starts over
+pub fn record(task: &Task) -> Result<(), String> {
+    let (Some(_), Some((path, _))) = (task.options.cooldown, COOLDOWN_FILE_PATH.get()) else {
+        return Ok(());
+    };
+
+    let _lock = COOLDOWN_LOCK.lock().map_err(|err| err.to_string())?;
+    let mut completions: Completions = read_completions(path);
+    completions.record(task, SystemTime::now());
+
+    write_completions(path, &completions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+    use crate::utils::config::resolve_sortable_task;
+
+    #[test]
+    fn skips_tasks_within_their_cooldown() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  sync:\n    command: ./sync.sh\n    cooldown: 10m\n  build: make\n");
+        let config_structure = workspace.load_structure(".");
+        let sync: Task = resolve_sortable_task(&config_structure, "sync", &true).unwrap().remove(0).task;
+        let build: Task = resolve_sortable_task(&config_structure, "build", &true).unwrap().remove(0).task;
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut completions = Completions::default();
+        assert_eq!(completions.get_remaining(&sync, now), None);
+
+        completions.record(&sync, now);
+        completions.record(&build, now);
+        assert_eq!(completions.get_remaining(&sync, now + Duration::from_secs(180)), Some(Duration::from_secs(420)));
+        assert_eq!(completions.get_remaining(&sync, now + Duration::from_secs(600)), None);
+        assert_eq!(completions.get_remaining(&build, now), None);
+
+        let path = workspace.path(".rask/cooldowns.json");
+        write_completions(&path, &completions).unwrap();
+        assert_eq!(read_completions(&path).get_remaining(&sync, now + Duration::from_secs(60)), Some(Duration::from_secs(540)));
+    }
+}
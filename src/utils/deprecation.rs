@@ -0,0 +1,407 @@
+use std::path::Path;
+use serde_yaml::{Mapping, Value};
+use crate::utils::{file, output};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeprecationScope {
+    // A top level key of a config
+    CONFIG,
+    // An option of a yaml task, like timeout
+    TASK,
+}
+
+// A config field that was renamed or removed. A renamed field keeps a serde alias on its new field
+// until it is removed, so old configs keep working while rask warns about them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deprecation {
+    pub key: &'static str,
+    pub scope: DeprecationScope,
+    // None for a field that was removed, configs are read without it and `rask migrate` drops it
+    pub replacement: Option<&'static str>,
+    pub since: &'static str,
+    pub hint: Option<&'static str>,
+}
+
+// Every deprecated field, in the order they were deprecated
+pub const DEPRECATIONS: [Deprecation; 0] = [];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeprecatedKey {
+    pub deprecation: Deprecation,
+    // The task of a task option
+    pub task: Option<String>,
+}
+
+impl DeprecatedKey {
+    pub fn describe(&self) -> String {
+        let Deprecation { key, replacement, since, hint, .. } = self.deprecation;
+        let location: String = match &self.task {
+            Some(task) => format!("Option \"{}\" of task \"{}\"", key, task),
+            None => format!("Key \"{}\"", key),
+        };
+        let migration: String = match replacement {
+            Some(replacement) => format!("is deprecated since rask {}, it was renamed to \"{}\"", since, replacement),
+            None => format!("was removed in rask {} and has no effect", since),
+        };
+
+        match hint {
+            Some(hint) => format!("{} {}. {}", location, migration, hint),
+            None => format!("{} {}", location, migration),
+        }
+    }
+
+    pub fn describe_migration(&self) -> String {
+        let location: String = match &self.task {
+            Some(task) => format!(" of task \"{}\"", task),
+            None => String::new(),
+        };
+
+        match self.deprecation.replacement {
+            Some(replacement) => format!("Renamed \"{}\"{} to \"{}\"", self.deprecation.key, location, replacement),
+            None => format!("Removed \"{}\"{}", self.deprecation.key, location),
+        }
+    }
+}
+
+fn find_deprecation(deprecations: &[Deprecation], scope: DeprecationScope, key: &str) -> Option<Deprecation> {
+    deprecations.iter().find(|deprecation| deprecation.scope == scope && deprecation.key == key).copied()
+}
+
+pub fn is_deprecated_config_key(key: &str) -> bool {
+    find_deprecation(&DEPRECATIONS, DeprecationScope::CONFIG, key).is_some()
+}
+
+// The fields of a mapping under tasks that make it a task, like ConfigFileTaskValue tells tasks from groups of tasks
+const TASK_FIELDS: [&str; 2] = ["command", "uses"];
+
+// The task mappings under tasks by their key, groups of tasks prefix the keys of their tasks
+fn collect_task_mappings<'a>(tasks: &'a Mapping, prefix: &str, task_mappings: &mut Vec<(String, &'a Mapping)>) {
+    for (key, value) in tasks {
+        let (Some(key), Some(mapping)) = (key.as_str(), value.as_mapping()) else { continue };
+        let key: String = match prefix.is_empty() {
+            true => key.to_string(),
+            false => format!("{}:{}", prefix, key),
+        };
+
+        match TASK_FIELDS.iter().any(|field| mapping.contains_key(*field)) {
+            true => task_mappings.push((key, mapping)),
+            false => collect_task_mappings(mapping, &key, task_mappings),
+        }
+    }
+}
+
+// serde drops the fields it does not know, so the deprecated ones are looked up in the plain yaml
+pub fn find_deprecated_keys(content: &str, deprecations: &[Deprecation]) -> Result<Vec<DeprecatedKey>, String> {
+    let documents: Vec<Value> = file::parse_yaml_documents(content)?;
+    let mut deprecated_keys: Vec<DeprecatedKey> = vec![];
+
+    for mapping in documents.iter().filter_map(|document| document.as_mapping()) {
+        for (key, value) in mapping {
+            let Some(key) = key.as_str() else { continue };
+            if let Some(deprecation) = find_deprecation(deprecations, DeprecationScope::CONFIG, key) {
+                deprecated_keys.push(DeprecatedKey { deprecation, task: None });
+            }
+            if key != "tasks" {
+                continue;
+            }
+
+            // Tasks written as a plain command have no options
+            let mut task_mappings: Vec<(String, &Mapping)> = vec![];
+            if let Some(tasks) = value.as_mapping() {
+                collect_task_mappings(tasks, "", &mut task_mappings);
+            }
+            for (task, options) in task_mappings {
+                for option in options.keys().filter_map(|option| option.as_str()) {
+                    if let Some(deprecation) = find_deprecation(deprecations, DeprecationScope::TASK, option) {
+                        deprecated_keys.push(DeprecatedKey { deprecation, task: Some(task.clone()) });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(deprecated_keys)
+}
+
+fn is_removed(deprecations: &[Deprecation], scope: DeprecationScope, key: &Value) -> bool {
+    key.as_str().and_then(|key| find_deprecation(deprecations, scope, key)).is_some_and(|deprecation| deprecation.replacement.is_none())
+}
+
+// Whether a removed option was dropped from a task, in the tasks or their groups
+fn strip_removed_options(tasks: &mut Mapping, deprecations: &[Deprecation]) -> bool {
+    let mut is_stripped = false;
+
+    for mapping in tasks.values_mut().filter_map(Value::as_mapping_mut) {
+        if !TASK_FIELDS.iter().any(|field| mapping.contains_key(*field)) {
+            is_stripped |= strip_removed_options(mapping, deprecations);
+            continue;
+        }
+
+        let length: usize = mapping.len();
+        mapping.retain(|option, _| !is_removed(deprecations, DeprecationScope::TASK, option));
+        is_stripped |= mapping.len() != length;
+    }
+
+    is_stripped
+}
+
+// The documents of a config without its removed fields, which serde would reject or mistake a task for a group of
+// tasks with, as tasks deny unknown fields. None when nothing was removed, the content then parses as it is.
+pub fn strip_removed_keys(content: &str, deprecations: &[Deprecation]) -> Result<Option<Vec<Value>>, String> {
+    if deprecations.iter().all(|deprecation| deprecation.replacement.is_some()) {
+        return Ok(None);
+    }
+
+    let mut documents: Vec<Value> = file::parse_yaml_documents(content)?;
+    let mut is_stripped = false;
+    for mapping in documents.iter_mut().filter_map(Value::as_mapping_mut) {
+        let length: usize = mapping.len();
+        mapping.retain(|key, _| !is_removed(deprecations, DeprecationScope::CONFIG, key));
+        is_stripped |= mapping.len() != length;
+
+        if let Some(tasks) = mapping.get_mut("tasks").and_then(Value::as_mapping_mut) {
+            is_stripped |= strip_removed_options(tasks, deprecations);
+        }
+    }
+
+    Ok(is_stripped.then_some(documents))
+}
+
+// Warns about the deprecated fields of a config file, the config itself is still used
+pub fn warn_deprecated_keys(config_file_path: &Path, deprecations: &[Deprecation]) {
+    if deprecations.is_empty() {
+        return;
+    }
+
+    let deprecated_keys: Vec<DeprecatedKey> = file::read_file_content(config_file_path.to_path_buf())
+        .and_then(|content| find_deprecated_keys(&content, deprecations))
+        .unwrap_or_default();
+    for deprecated_key in deprecated_keys {
+        output::warning(&format!("{} in {:?}, run `rask migrate` to update it", deprecated_key.describe(), config_file_path));
+    }
+}
+
+// The key of a `key: value` or `key:` line, none for list items, quoted keys and the like
+fn get_line_key(trimmed_line: &str) -> Option<&str> {
+    let (key, rest) = trimmed_line.split_once(':')?;
+    let is_plain_key = !key.is_empty() && key.chars().all(|char| char.is_alphanumeric() || char == '_' || char == '-');
+
+    match is_plain_key && (rest.is_empty() || rest.starts_with(' ')) {
+        true => Some(key),
+        false => None,
+    }
+}
+
+fn get_indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_blank_line(line: &str) -> bool {
+    line.trim().is_empty() || line.trim_start().starts_with('#')
+}
+
+// The lines of block scalars, like the command below `build: |`, their text is a value that may look like a key
+fn find_block_scalar_lines(lines: &[&str]) -> Vec<bool> {
+    let mut block_indentation: Option<usize> = None;
+
+    lines
+        .iter()
+        .map(|line| {
+            if block_indentation.is_some_and(|block_indentation| line.trim().is_empty() || get_indentation(line) > block_indentation) {
+                return true;
+            }
+
+            // The indicator ends the line, after the key or list item it belongs to, like `|`, `>-` or `|2`
+            let value: &str = line.split(" #").next().unwrap_or_default().trim();
+            let is_block_scalar = value.rsplit_once(' ').is_some_and(|(before, indicator)| {
+                (before.ends_with(':') || before.ends_with('-')) && indicator.starts_with(['|', '>']) && indicator[1..].chars().all(|char| char == '-' || char == '+' || char.is_ascii_digit())
+            });
+            block_indentation = is_block_scalar.then(|| get_indentation(line));
+
+            false
+        })
+        .collect()
+}
+
+// The keys right below a line, a mapping under tasks with one of the task fields is a task
+fn get_child_keys<'a>(lines: &[&'a str], scalar_lines: &[bool], line: usize) -> Vec<&'a str> {
+    let indentation: usize = get_indentation(lines[line]);
+    let mut child_indentation: Option<usize> = None;
+    let mut child_keys: Vec<&str> = vec![];
+
+    for (text, _) in lines.iter().zip(scalar_lines).skip(line + 1).filter(|(text, is_scalar)| !is_blank_line(text) && !**is_scalar) {
+        if get_indentation(text) <= indentation {
+            break;
+        }
+        if get_indentation(text) == *child_indentation.get_or_insert(get_indentation(text)) {
+            child_keys.extend(get_line_key(text.trim_start()));
+        }
+    }
+
+    child_keys
+}
+
+// What a line is to the migration, the options of tasks are the lines below a TASK
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineKind {
+    TASKS,
+    GROUP,
+    TASK,
+    OTHER,
+}
+
+// Edits the yaml text like file::add_config_directory, so comments and key order survive.
+// Returns none when nothing is deprecated, and fails on fields in a layout it does not recognise, like a flow mapping.
+pub fn migrate_config_content(content: &str, deprecations: &[Deprecation]) -> Result<Option<String>, String> {
+    if find_deprecated_keys(content, deprecations)?.is_empty() {
+        return Ok(None);
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let scalar_lines: Vec<bool> = find_block_scalar_lines(&lines);
+    let mut migrated_lines: Vec<String> = vec![];
+    // The lines the current line is below, with their indentation
+    let mut parents: Vec<(usize, LineKind)> = vec![];
+    // The indentation of a removed key, the more indented lines after it are its value
+    let mut removed_indentation: Option<usize> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed_line: &str = line.trim_start();
+        let indentation: usize = get_indentation(line);
+        let is_blank = is_blank_line(line) || scalar_lines[index];
+
+        if let Some(removed) = removed_indentation {
+            if is_blank || indentation > removed {
+                continue;
+            }
+            removed_indentation = None;
+        }
+        if is_blank {
+            migrated_lines.push(line.to_string());
+            continue;
+        }
+        if line.starts_with("---") {
+            parents.clear();
+            migrated_lines.push(line.to_string());
+            continue;
+        }
+
+        while parents.last().is_some_and(|(parent_indentation, _)| *parent_indentation >= indentation) {
+            parents.pop();
+        }
+        let key: Option<&str> = get_line_key(trimmed_line);
+        let (scope, kind): (Option<DeprecationScope>, LineKind) = match parents.last().map(|(_, kind)| *kind) {
+            None if key == Some("tasks") => (Some(DeprecationScope::CONFIG), LineKind::TASKS),
+            None => (Some(DeprecationScope::CONFIG), LineKind::OTHER),
+            Some(LineKind::TASKS | LineKind::GROUP) => {
+                let child_keys: Vec<&str> = get_child_keys(&lines, &scalar_lines, index);
+                match (child_keys.iter().any(|child_key| TASK_FIELDS.contains(child_key)), child_keys.is_empty()) {
+                    (true, _) => (None, LineKind::TASK),
+                    (false, false) => (None, LineKind::GROUP),
+                    (false, true) => (None, LineKind::OTHER),
+                }
+            },
+            Some(LineKind::TASK) => (Some(DeprecationScope::TASK), LineKind::OTHER),
+            Some(LineKind::OTHER) => (None, LineKind::OTHER),
+        };
+        parents.push((indentation, kind));
+
+        let deprecation: Option<Deprecation> = scope.zip(key).and_then(|(scope, key)| find_deprecation(deprecations, scope, key));
+        match deprecation {
+            Some(Deprecation { key, replacement: Some(replacement), .. }) => {
+                migrated_lines.push(format!("{}{}{}", &line[..indentation], replacement, &trimmed_line[key.len()..]));
+            },
+            Some(Deprecation { replacement: None, .. }) => removed_indentation = Some(indentation),
+            None => migrated_lines.push(line.to_string()),
+        }
+    }
+
+    let mut migrated_content: String = migrated_lines.join("\n");
+    if content.ends_with('\n') {
+        migrated_content.push('\n');
+    }
+
+    // A renamed key next to its replacement makes a duplicate key, which the yaml parser rejects
+    let remaining_keys: Vec<DeprecatedKey> = find_deprecated_keys(&migrated_content, deprecations).map_err(|err| format!("Migrating would break the config: {}", err))?;
+    match remaining_keys.first() {
+        Some(deprecated_key) => Err(format!("Failed to migrate \"{}\", it is not written as a plain yaml key, update it by hand", deprecated_key.deprecation.key)),
+        None => Ok(Some(migrated_content)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+    use crate::utils::config;
+    use crate::utils::config::ConfigIssues;
+
+    const TEST_DEPRECATIONS: [Deprecation; 3] = [
+        Deprecation { key: "engine", scope: DeprecationScope::CONFIG, replacement: Some("task_engine"), since: "0.9.0", hint: None },
+        Deprecation { key: "cache", scope: DeprecationScope::TASK, replacement: None, since: "0.9.0", hint: Some("Use inputs and outputs instead.") },
+        Deprecation { key: "cwd", scope: DeprecationScope::TASK, replacement: Some("directory"), since: "0.9.0", hint: None },
+    ];
+
+    const CONTENT: &str = "# The root\nname: root\nengine: npm # from package.json\ntasks:\n  build:\n    command: make\n    cwd: app\n    cache:\n      - dist\n\n    timeout: 10m\n  cwd: make cwd\n---\nname: api\ntasks:\n  test: {command: cargo test, cache: true}\n";
+
+    #[test]
+    fn finds_deprecated_keys_of_configs_and_tasks() {
+        let deprecated_keys = find_deprecated_keys(CONTENT, &TEST_DEPRECATIONS).unwrap();
+
+        // The cwd task is a task, not an option
+        assert_eq!(deprecated_keys, vec![
+            DeprecatedKey { deprecation: TEST_DEPRECATIONS[0], task: None },
+            DeprecatedKey { deprecation: TEST_DEPRECATIONS[2], task: Some("build".to_string()) },
+            DeprecatedKey { deprecation: TEST_DEPRECATIONS[1], task: Some("build".to_string()) },
+            DeprecatedKey { deprecation: TEST_DEPRECATIONS[1], task: Some("test".to_string()) },
+        ]);
+        assert_eq!(deprecated_keys[1].describe(), "Option \"cwd\" of task \"build\" is deprecated since rask 0.9.0, it was renamed to \"directory\"");
+        assert_eq!(deprecated_keys[2].describe(), "Option \"cache\" of task \"build\" was removed in rask 0.9.0 and has no effect. Use inputs and outputs instead.");
+        assert!(find_deprecated_keys(CONTENT, &DEPRECATIONS).unwrap().is_empty());
+    }
+
+    #[test]
+    fn migrates_deprecated_keys_in_place() {
+        // The flow mapping of the api tasks is left for the user
+        let error = migrate_config_content(CONTENT, &TEST_DEPRECATIONS).unwrap_err();
+        assert_eq!(error, "Failed to migrate \"cache\", it is not written as a plain yaml key, update it by hand");
+
+        let content = CONTENT.replace("{command: cargo test, cache: true}", "cargo test");
+        let migrated_content = migrate_config_content(&content, &TEST_DEPRECATIONS).unwrap().unwrap();
+        assert_eq!(migrated_content, "# The root\nname: root\ntask_engine: npm # from package.json\ntasks:\n  build:\n    command: make\n    directory: app\n    timeout: 10m\n  cwd: make cwd\n---\nname: api\ntasks:\n  test: cargo test\n");
+        assert_eq!(migrate_config_content(&migrated_content, &TEST_DEPRECATIONS).unwrap(), None);
+
+        let error = migrate_config_content("engine: npm\ntask_engine: npm\n", &TEST_DEPRECATIONS).unwrap_err();
+        assert!(error.starts_with("Migrating would break the config: "), "{}", error);
+    }
+
+    #[test]
+    fn migrates_tasks_of_groups_and_leaves_block_scalars_alone() {
+        let content = "tasks:\n  cache:\n    warm: make warm\n  docs:\n    api:\n      command: make api\n      cwd: docs\n  notes: |\n    cwd: stays\n";
+
+        // The cache group and the text of notes look like options, they are not
+        let deprecated_keys = find_deprecated_keys(content, &TEST_DEPRECATIONS).unwrap();
+        assert_eq!(deprecated_keys, vec![DeprecatedKey { deprecation: TEST_DEPRECATIONS[2], task: Some("docs:api".to_string()) }]);
+
+        let migrated_content = migrate_config_content(content, &TEST_DEPRECATIONS).unwrap().unwrap();
+        assert_eq!(migrated_content, "tasks:\n  cache:\n    warm: make warm\n  docs:\n    api:\n      command: make api\n      directory: docs\n  notes: |\n    cwd: stays\n");
+    }
+
+    #[test]
+    fn loads_configs_without_their_removed_fields() {
+        let workspace = TestWorkspace::new();
+        let config_file_path = workspace.config_path(".");
+        let content = "name: root\ntasks:\n  build:\n    command: make\n    cache:\n      - dist\n  docs:\n    api:\n      command: make api\n      cache: [docs]\n";
+        let load_keys = |deprecations: &[Deprecation]| {
+            let config_files = file::parse_config_documents_with(content, config_file_path.clone(), deprecations).unwrap();
+            let configs = config::parse_config_files(config_files, &config_file_path, &mut ConfigIssues::new(false)).unwrap();
+            let mut keys: Vec<String> = configs[0].tasks.iter().map(|config_task| config_task.key.clone()).collect();
+            keys.sort();
+            keys
+        };
+
+        // Tasks deny unknown fields, so the removed cache option would turn build into a group of tasks
+        assert_eq!(load_keys(&DEPRECATIONS), vec!["build:cache", "build:command", "docs:api:cache", "docs:api:command"]);
+        assert_eq!(load_keys(&TEST_DEPRECATIONS), vec!["build", "docs:api"]);
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::Path;
+use clap::ValueEnum;
+use crate::utils::export::{ConfigExport, TaskExport, WorkspaceExport};
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum DocsFormat {
+    #[default]
+    MARKDOWN,
+    // A standalone page, for docs sites that do not render markdown
+    HTML,
+}
+
+const GENERATED_NOTICE: &str = "Generated by rask docs, do not edit. Run rask docs again to update it.";
+
+// A configured section of the workspace, with paths relative to the entry so the docs are the same in every checkout
+struct ConfigSection<'a> {
+    name: &'a str,
+    file_path: String,
+    children: Vec<(&'a str, String)>,
+    // Sorted by key, engine tasks come in no particular order
+    tasks: Vec<&'a TaskExport>,
+}
+
+pub fn render_docs(workspace_export: &WorkspaceExport, docs_format: DocsFormat) -> String {
+    let entry_directory: &Path = workspace_export.entry.parent().unwrap_or(Path::new("."));
+    let sections: Vec<ConfigSection> = workspace_export.configs
+        .iter()
+        .map(|config_export| create_section(config_export, workspace_export, entry_directory))
+        .collect();
+
+    match docs_format {
+        DocsFormat::MARKDOWN => render_markdown(&sections),
+        DocsFormat::HTML => render_html(&sections),
+    }
+}
+
+fn create_section<'a>(config_export: &'a ConfigExport, workspace_export: &'a WorkspaceExport, entry_directory: &Path) -> ConfigSection<'a> {
+    let ConfigExport { name, file_path, children, tasks, .. } = config_export;
+    let get_relative_path = |path: &Path| path.strip_prefix(entry_directory).unwrap_or(path).to_string_lossy().to_string();
+
+    let mut tasks: Vec<&TaskExport> = tasks.iter().collect();
+    tasks.sort_by(|task, other_task| task.key.cmp(&other_task.key));
+
+    ConfigSection {
+        name,
+        file_path: get_relative_path(file_path),
+        children: children
+            .iter()
+            .filter_map(|child| workspace_export.configs.iter().find(|config_export| &config_export.file_path == child))
+            .map(|child| (child.name.as_str(), get_relative_path(&child.file_path)))
+            .collect(),
+        tasks,
+    }
+}
+
+// Like `lint`, and `after build` for tasks that only order the task
+fn list_dependencies(task: &TaskExport) -> Vec<String> {
+    let mut dependencies: Vec<String> = task.depends_on.clone();
+    dependencies.extend(task.after.iter().map(|after| format!("after {}", after)));
+    dependencies
+}
+
+// Wide enough fences for commands that contain backticks themselves
+fn format_markdown_code(value: &str) -> String {
+    let value = value.replace('|', "\\|").replace('\n', " ");
+    match value.contains('`') {
+        true => format!("`` {} ``", value),
+        false => format!("`{}`", value),
+    }
+}
+
+fn format_markdown_text(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn render_markdown(sections: &[ConfigSection]) -> String {
+    let mut lines: Vec<String> = vec![
+        "# Tasks".to_string(),
+        String::new(),
+        format!("<!-- {} -->", GENERATED_NOTICE),
+    ];
+
+    for ConfigSection { name, file_path, children, tasks } in sections {
+        lines.push(String::new());
+        lines.push(format!("## {}", name));
+        lines.push(String::new());
+        lines.push(format!("Defined in [{}]({}).", file_path, file_path));
+        if !children.is_empty() {
+            let links: Vec<String> = children.iter().map(|(name, file_path)| format!("[{}]({})", name, file_path)).collect();
+            lines.push(format!("Includes {}.", links.join(", ")));
+        }
+        lines.push(String::new());
+
+        if tasks.is_empty() {
+            lines.push("No tasks.".to_string());
+            continue;
+        }
+
+        lines.push("| Task | Description | Command | Dependencies | Source |".to_string());
+        lines.push("|---|---|---|---|---|".to_string());
+        for task in tasks {
+            let dependencies: Vec<String> = list_dependencies(task).iter().map(|dependency| format_markdown_code(dependency)).collect();
+            lines.push(format!(
+                "| {} | {} | {} | {} | {} |",
+                format_markdown_code(&task.key),
+                format_markdown_text(task.description.as_deref().unwrap_or_default()),
+                format_markdown_code(&task.command),
+                dependencies.join(", "),
+                task.source,
+            ));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_html(sections: &[ConfigSection]) -> String {
+    let mut lines: Vec<String> = vec![
+        "<!DOCTYPE html>".to_string(),
+        format!("<!-- {} -->", GENERATED_NOTICE),
+        "<html>".to_string(),
+        "<head><meta charset=\"utf-8\"><title>Tasks</title></head>".to_string(),
+        "<body>".to_string(),
+        "<h1>Tasks</h1>".to_string(),
+    ];
+
+    for ConfigSection { name, file_path, children, tasks } in sections {
+        lines.push(format!("<h2>{}</h2>", escape_html(name)));
+        lines.push(format!("<p>Defined in <a href=\"{}\">{}</a>.</p>", escape_html(file_path), escape_html(file_path)));
+        if !children.is_empty() {
+            let links: Vec<String> = children.iter().map(|(name, file_path)| format!("<a href=\"{}\">{}</a>", escape_html(file_path), escape_html(name))).collect();
+            lines.push(format!("<p>Includes {}.</p>", links.join(", ")));
+        }
+
+        if tasks.is_empty() {
+            lines.push("<p>No tasks.</p>".to_string());
+            continue;
+        }
+
+        lines.push("<table>".to_string());
+        lines.push("<tr><th>Task</th><th>Description</th><th>Command</th><th>Dependencies</th><th>Source</th></tr>".to_string());
+        for task in tasks {
+            let dependencies: Vec<String> = list_dependencies(task).iter().map(|dependency| format!("<code>{}</code>", escape_html(dependency))).collect();
+            lines.push(format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                escape_html(&task.key),
+                escape_html(task.description.as_deref().unwrap_or_default()),
+                escape_html(&task.command),
+                dependencies.join(", "),
+                task.source,
+            ));
+        }
+        lines.push("</table>".to_string());
+    }
+
+    lines.push("</body>".to_string());
+    lines.push("</html>".to_string());
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+    use crate::utils::export::export_workspace;
+
+    #[test]
+    fn renders_the_same_docs_in_every_checkout() {
+        let render = |workspace: &TestWorkspace, docs_format: DocsFormat| {
+            render_docs(&export_workspace(&workspace.config_path("."), &workspace.load_structure(".")).unwrap(), docs_format)
+        };
+        let create_workspace = || TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [packages/*]\n")
+            .with_config("packages/api", "name: api\ntasks:\n  test:\n    command: cargo test | tee <out>\n    description: Runs the unit tests\n    depends_on: [build]\n    after: [lint]\n  build: make\n");
+
+        let workspace = create_workspace();
+        let markdown = render(&workspace, DocsFormat::MARKDOWN);
+        assert_eq!(markdown, render(&create_workspace(), DocsFormat::MARKDOWN));
+        assert!(!markdown.contains(&workspace.path(".").to_string_lossy().to_string()));
+        assert!(markdown.contains("Includes [api](packages/api/rask.yaml)."));
+        assert!(markdown.contains("| `build` |  | `make` |  | yaml |\n| `test` | Runs the unit tests | `cargo test \\| tee <out>` | `build`, `after lint` | yaml |"));
+
+        let html = render(&workspace, DocsFormat::HTML);
+        assert!(html.contains("<td><code>cargo test | tee &lt;out&gt;</code></td>"));
+    }
+}
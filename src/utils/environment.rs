@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use crate::utils::config::{Task, TaskOptions};
+use crate::utils::{file, interpolation};
+use crate::utils::interpolation::Variables;
+
+const SECRET_MARKERS: [&str; 7] = ["SECRET", "TOKEN", "PASSWORD", "PASSWD", "PRIVATE", "CREDENTIAL", "API_KEY"];
+const MASK: &str = "********";
+
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_uppercase();
+
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+pub fn mask_value(name: &str, value: &str) -> String {
+    match is_secret_name(name) && !value.is_empty() {
+        true => MASK.to_string(),
+        false => value.to_string(),
+    }
+}
+
+// Kept with clean_env, without it not even the shell could be found
+const CLEAN_ENV_VARIABLES: [&str; 1] = ["PATH"];
+
+// What a task inherits from the environment rask runs in, only PATH and pass_env for tasks with clean_env
+pub fn resolve_inherited_environment(options: &TaskOptions) -> BTreeMap<String, String> {
+    env::vars()
+        .filter(|(name, _)| !options.clean_env || CLEAN_ENV_VARIABLES.contains(&name.as_str()) || options.pass_env.contains(name))
+        .collect()
+}
+
+// Set by rask while the run goes, or by the shell itself
+const BUILT_IN_VARIABLES: [&str; 3] = ["RASK_ITERATION", "RASK_SEED", "RASK_TMPDIR"];
+const SHELL_VARIABLES: [&str; 14] = ["PWD", "OLDPWD", "SHLVL", "RANDOM", "LINENO", "SECONDS", "UID", "EUID", "PPID", "IFS", "HOSTNAME", "OPTARG", "OPTIND", "REPLY"];
+
+// The `${name}` placeholders left in the command of a task that nothing sets, which the shell would expand to nothing.
+// Forms like `${name:-default}` handle a missing value themselves, and names the command assigns are its own.
+pub fn find_unresolved_variables(task: &Task) -> Vec<String> {
+    let inherited: BTreeMap<String, String> = resolve_inherited_environment(&task.options);
+    let assigned: Vec<String> = find_assigned_variables(&task.command);
+    let mut unresolved: Vec<String> = vec![];
+
+    for name in find_placeholders(&task.command) {
+        let is_resolved = task.env.contains_key(&name)
+            || inherited.contains_key(&name)
+            || assigned.contains(&name)
+            || BUILT_IN_VARIABLES.contains(&name.as_str())
+            || SHELL_VARIABLES.contains(&name.as_str());
+        if !is_resolved && !unresolved.contains(&name) {
+            unresolved.push(name);
+        }
+    }
+
+    unresolved
+}
+
+// Fails before anything runs, listing per task the placeholders nothing sets
+pub fn ensure_resolved_variables(tasks: &[&Task]) -> Result<(), String> {
+    let unresolved_lines: Vec<String> = tasks
+        .iter()
+        .filter_map(|task| {
+            let unresolved: Vec<String> = find_unresolved_variables(task);
+            (!unresolved.is_empty()).then(|| format!("  -  {} @ {:?}: {}", task.key, task.directory, unresolved.join(", ")))
+        })
+        .collect();
+
+    match unresolved_lines.is_empty() {
+        true => Ok(()),
+        false => Err(format!(
+            "The following tasks use variables nothing sets, the shell would expand them to empty strings:\n{}\nSet them in env, constants, an env file or the environment rask runs in, or pass --allow-unresolved",
+            unresolved_lines.join("\n")
+        )),
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_') && name.chars().all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+// The shell leaves ${name} in single quotes as it is, so those spans are blanked out before searching
+fn find_placeholders(command: &str) -> Vec<String> {
+    interpolation::blank_single_quotes(command)
+        .split("${")
+        .skip(1)
+        .filter_map(|rest| rest.find('}').map(|end| &rest[..end]))
+        .filter(|name| is_variable_name(name))
+        .map(str::to_string)
+        .collect()
+}
+
+// Assignments like `name=value`, `export name=value`, `for name in` and `read name`
+fn find_assigned_variables(command: &str) -> Vec<String> {
+    let words: Vec<&str> = command.split(|char: char| char.is_whitespace() || ";&|()".contains(char)).filter(|word| !word.is_empty()).collect();
+    let mut assigned: Vec<String> = vec![];
+
+    for (index, word) in words.iter().enumerate() {
+        if let Some((name, _)) = word.split_once('=') {
+            if is_variable_name(name) {
+                assigned.push(name.to_string());
+            }
+        }
+
+        match *word {
+            "for" | "select" => assigned.extend(words.get(index + 1).filter(|name| is_variable_name(name)).map(|name| name.to_string())),
+            "read" => assigned.extend(words[index + 1..].iter().skip_while(|word| word.starts_with('-')).take_while(|word| is_variable_name(word)).map(|name| name.to_string())),
+            _ => {},
+        }
+    }
+
+    assigned
+}
+
+// The environment a task process receives: the inherited environment with the task env on top.
+pub fn resolve_task_environment(task_env: &Variables, options: &TaskOptions, include_inherited: bool) -> BTreeMap<String, String> {
+    let mut environment: BTreeMap<String, String> = BTreeMap::new();
+
+    if include_inherited {
+        environment.extend(resolve_inherited_environment(options));
+    }
+    environment.extend(task_env.clone());
+
+    environment
+}
+
+// An env file given on the command line, its variables override the env of every task
+#[derive(Debug, Clone)]
+pub struct EnvFile {
+    pub path: PathBuf,
+    pub variables: Variables,
+}
+
+// KEY=value lines like other tools read from .env files, `export` prefixes and surrounding quotes are allowed
+pub fn read_env_file(path: &Path) -> Result<EnvFile, String> {
+    // Absolute, so a resumed run finds it from any directory
+    let path: PathBuf = path.canonicalize().map_err(|err| format!("Unable to find the env file {:?}: {}", path, err))?;
+    let content = file::read_file_content(path.clone()).map_err(|err| format!("Failed to read the env file {:?}: {}", path, err))?;
+    let mut variables = Variables::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line.split_once('=').ok_or(format!("Invalid line {} in the env file {:?}, expected NAME=value", index + 1, path))?;
+        let value = value.trim();
+        let is_quoted = |quote: char| value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote);
+        let value: String = match (is_quoted('"'), is_quoted('\'')) {
+            (true, _) => unescape_env_value(&value[1..value.len() - 1]),
+            (_, true) => value[1..value.len() - 1].to_string(),
+            _ => value.to_string(),
+        };
+
+        variables.insert(name.trim().to_string(), value);
+    }
+
+    Ok(EnvFile { path, variables })
+}
+
+// Marks the env files rask env --write wrote, other files are never overwritten
+const WRITTEN_ENV_FILE_HEADER: &str = "# Written by rask env --write, changes are overwritten";
+
+// The variables as KEY=value lines that read_env_file and tools like docker compose read, secrets are masked unless included
+pub fn format_env_file(variables: &Variables, include_secrets: bool) -> String {
+    let variables: BTreeMap<&String, &String> = variables.iter().collect();
+    let mut content: String = format!("{}\n", WRITTEN_ENV_FILE_HEADER);
+
+    for (name, value) in variables {
+        let value: String = match include_secrets {
+            true => value.clone(),
+            false => mask_value(name, value),
+        };
+        content.push_str(&format!("{}={}\n", name, quote_env_value(&value)));
+    }
+
+    content
+}
+
+// Single quotes keep values literal. Values with a quote or newline need double quotes, where compose expands a `$` unless escaped.
+fn quote_env_value(value: &str) -> String {
+    let is_plain = value.chars().all(|char| char.is_ascii_alphanumeric() || "_-.,:/@+=".contains(char));
+
+    match (is_plain, value.contains(['\'', '\n'])) {
+        (true, _) => value.to_string(),
+        (false, false) => format!("'{}'", value),
+        (false, true) => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('$', "\\$")),
+    }
+}
+
+// The escapes of double quoted values, other backslashes are kept
+fn unescape_env_value(value: &str) -> String {
+    let mut unescaped: String = String::new();
+    let mut characters = value.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        let escaped: Option<char> = match (character, characters.peek()) {
+            ('\\', Some('n')) => Some('\n'),
+            ('\\', Some(next @ ('\\' | '"' | '$'))) => Some(*next),
+            _ => None,
+        };
+
+        match escaped {
+            Some(escaped) => {
+                unescaped.push(escaped);
+                characters.next();
+            },
+            None => unescaped.push(character),
+        }
+    }
+
+    unescaped
+}
+
+// Every file is checked before the first is written, so a refused file leaves all of them as they were
+pub fn write_env_files(env_files: &[(PathBuf, String)]) -> Result<(), String> {
+    for (path, _) in env_files.iter().filter(|(path, _)| path.exists()) {
+        let existing_content = file::read_file_content(path.clone())?;
+        if !existing_content.starts_with(WRITTEN_ENV_FILE_HEADER) {
+            return Err(format!("The env file {:?} was not written by rask, remove it or write to another file with --write=<name>", path));
+        }
+    }
+
+    for (path, content) in env_files {
+        file::write_file_content(path, content)?;
+    }
+
+    Ok(())
+}
+
+// Later files override earlier ones
+pub fn apply_env_files(task: &mut Task, env_files: &[EnvFile]) {
+    for env_file in env_files {
+        task.env.extend(env_file.variables.clone());
+    }
+
+    for dependency in &mut task.dependencies {
+        apply_env_files(dependency, env_files);
+    }
+}
+
+// A variable of the final environment, with the layer it comes from and the layers it overrides
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcedVariable {
+    pub value: String,
+    pub source: String,
+    pub overrides: Vec<String>,
+}
+
+// The environment a task receives built up layer by layer: inherited, the config env and then the env files
+pub fn resolve_sourced_environment(task: &Task, env_files: &[EnvFile]) -> BTreeMap<String, SourcedVariable> {
+    let mut layers: Vec<(String, Variables)> = vec![
+        ("inherited".to_string(), resolve_inherited_environment(&task.options).into_iter().collect()),
+        ("config".to_string(), task.env.clone()),
+    ];
+    layers.extend(env_files.iter().map(|env_file| (env_file.path.to_string_lossy().to_string(), env_file.variables.clone())));
+
+    let mut environment: BTreeMap<String, SourcedVariable> = BTreeMap::new();
+    for (source, variables) in layers {
+        for (name, value) in variables {
+            let overrides: Vec<String> = match environment.remove(&name) {
+                Some(SourcedVariable { source: previous_source, overrides: mut previous_overrides, .. }) => {
+                    previous_overrides.insert(0, previous_source);
+                    previous_overrides
+                },
+                None => vec![],
+            };
+
+            environment.insert(name, SourcedVariable { value, source: source.clone(), overrides });
+        }
+    }
+
+    environment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{create_task, TestWorkspace};
+
+    #[test]
+    fn masks_secrets_and_layers_the_task_env() {
+        assert_eq!(mask_value("GITHUB_TOKEN", "ghp_123"), MASK);
+        assert_eq!(mask_value("db_password", "hunter2"), MASK);
+        assert_eq!(mask_value("API_KEY", ""), "");
+        assert_eq!(mask_value("API_URL", "http://localhost"), "http://localhost");
+
+        let task_env = Variables::from([("PATH".to_string(), "/opt/bin".to_string()), ("STAGE".to_string(), "dev".to_string())]);
+        let environment = resolve_task_environment(&task_env, &TaskOptions::default(), false);
+        assert_eq!(environment.keys().collect::<Vec<&String>>(), vec!["PATH", "STAGE"]);
+
+        // The task env wins over what is inherited
+        let environment = resolve_task_environment(&task_env, &TaskOptions::default(), true);
+        assert_eq!(environment.get("PATH").map(String::as_str), Some("/opt/bin"));
+        assert!(environment.contains_key("CARGO_MANIFEST_DIR"));
+    }
+
+    #[test]
+    fn clean_env_only_inherits_path_and_passed_variables() {
+        let options = TaskOptions { clean_env: true, pass_env: vec!["CARGO_MANIFEST_DIR".to_string()], ..Default::default() };
+
+        let mut names: Vec<String> = resolve_inherited_environment(&options).into_keys().collect();
+        names.sort();
+
+        assert_eq!(names, vec!["CARGO_MANIFEST_DIR", "PATH"]);
+        assert!(resolve_inherited_environment(&TaskOptions::default()).len() > 2);
+    }
+
+    #[test]
+    fn finds_placeholders_nothing_sets() {
+        let task = Task {
+            directory: PathBuf::from("."),
+            env: Variables::from([("API_URL".to_string(), "http://localhost".to_string())]),
+            options: TaskOptions { clean_env: true, ..Default::default() },
+            ..create_task("deploy", "OUT=dist; for file in ${OUT}/*; do upload ${file} ${BUCKET} ${REGION:-eu} ${1}; done && read -r answer && echo ${answer} ${API_URL} ${TARGET} ${BUCKET}")
+        };
+
+        assert_eq!(find_unresolved_variables(&task), vec!["BUCKET", "TARGET"]);
+    }
+
+    #[test]
+    fn ignores_placeholders_in_single_quotes() {
+        assert_eq!(find_placeholders("awk '{ print ${NF} }' && echo \"${HOME} '${USER}'\" \\'${PWD}"), vec!["HOME", "USER", "PWD"]);
+    }
+
+    #[test]
+    fn env_files_override_the_config_env() {
+        let workspace = TestWorkspace::new().with_file(".env.prod", "# production\nexport API_URL=\"https://api.example.com\"\nREGION='eu'\n\nDEBUG=\n");
+        let env_file = read_env_file(&workspace.path(".env.prod")).unwrap();
+        let task = Task {
+            directory: workspace.path("."),
+            env: Variables::from([("API_URL".to_string(), "http://localhost".to_string())]),
+            options: TaskOptions { clean_env: true, ..Default::default() },
+            ..create_task("deploy", "./deploy.sh")
+        };
+
+        let environment = resolve_sourced_environment(&task, &[env_file]);
+        let env_file_path = workspace.path(".env.prod").to_string_lossy().to_string();
+
+        assert_eq!(environment["API_URL"], SourcedVariable { value: "https://api.example.com".to_string(), source: env_file_path.clone(), overrides: vec!["config".to_string()] });
+        assert_eq!(environment["REGION"].value, "eu");
+        assert_eq!(environment["DEBUG"].value, "");
+        assert_eq!(environment["PATH"].source, "inherited");
+    }
+
+    #[test]
+    fn writes_env_files_that_read_back() {
+        let workspace = TestWorkspace::new().with_file(".env", "API_URL=http://localhost\n");
+        let variables = Variables::from([
+            ("API_URL".to_string(), "https://api.example.com".to_string()),
+            ("GREETING".to_string(), "hello $USER".to_string()),
+            ("API_TOKEN".to_string(), "abc".to_string()),
+            ("MOTD".to_string(), "it's \"$HOME\"\nC:\\temp".to_string()),
+        ]);
+
+        let path = workspace.path(".env.rask");
+        assert!(write_env_files(&[(path.clone(), format_env_file(&variables, false)), (workspace.path(".env"), format_env_file(&variables, false))]).is_err());
+        assert!(!path.exists());
+
+        write_env_files(&[(path.clone(), format_env_file(&variables, false))]).unwrap();
+        let EnvFile { variables: masked, .. } = read_env_file(&path).unwrap();
+        assert_eq!(masked["API_TOKEN"], "********");
+        assert_eq!(masked["GREETING"], "hello $USER");
+
+        write_env_files(&[(path.clone(), format_env_file(&variables, true))]).unwrap();
+        assert_eq!(read_env_file(&path).unwrap().variables, variables);
+        assert!(file::read_file_content(path.clone()).unwrap().contains("MOTD=\"it's \\\"\\$HOME\\\"\\nC:\\\\temp\"\n"));
+    }
+}
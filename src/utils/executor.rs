@@ -0,0 +1,669 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use crate::utils::config::Task;
+use crate::utils::failure::{Failure, FailureKind, FailureWatch};
+use crate::utils::output::{Stream, Tone, Verbosity};
+use crate::utils::process::{CapturedOutput, ProcessOutcome, ResourceUsage};
+use crate::utils::report::{TaskResult, TaskResults, TaskStatus};
+use crate::utils::writer::{OutputLimit, OutputStyle, TaskWriter};
+use crate::utils::{config, cooldown, failure, freshness, output, process, running, time, tmp};
+
+// Runs a batch of tasks on worker threads, one child process per task.
+// Concurrency, cancellation, deadlines and output capture are handled here for every mode.
+// Plain threads rather than an async runtime like tokio, rask has no async dependencies and every worker
+// blocks on its child process anyway. Callers only hand over tasks, so a runtime can replace the workers later.
+#[derive(Debug, Clone)]
+pub struct Executor {
+    // The job slots of tasks running at the same time, 1 runs them one by one. Tasks take as many slots as their weight
+    pub concurrency: usize,
+    // Stop starting tasks once one failed, tasks that are running are awaited
+    pub fail_fast: bool,
+    pub deadline: Option<Instant>,
+    pub capture_output: bool,
+    // Tasks of the same config wait for each other, regardless of the serial option of their config
+    pub serial_configs: bool,
+    // Output that is not streamed is captured, so it can be shown when a task fails
+    pub verbosity: Verbosity,
+    pub output_style: OutputStyle,
+    // Shared by the levels of a run, tasks running a command that already ran reuse its result
+    pub results_memo: Option<ResultsMemo>,
+    // Shared by the levels of a run as well, a dependency of several tasks runs once for all of them
+    pub dependency_memo: DependencyMemo,
+}
+
+pub type ResultsMemo = Arc<Mutex<HashMap<String, TaskResult>>>;
+// The outcome of every dependency by its key and directory, a task waits while another one runs the dependency
+pub type DependencyMemo = Arc<Mutex<HashMap<String, Arc<Mutex<Option<Result<(), Failure>>>>>>>;
+
+impl Executor {
+    // Results are handed to `on_result` as they come in, and returned in the order of the tasks
+    pub fn run(&self, tasks: Vec<Task>, on_result: &mut dyn FnMut(&TaskResult)) -> TaskResults {
+        let (sender, receiver) = mpsc::channel::<(usize, TaskResult)>();
+        let mut results: Vec<Option<TaskResult>> = vec![None; tasks.len()];
+        let memo_keys: Vec<Option<String>> = tasks.iter().map(|task| self.results_memo.as_ref().and_then(|_| get_memo_key(task))).collect();
+        let slots: Vec<usize> = tasks.iter().map(|task| self.get_slots(task)).collect();
+        let mut pending_tasks: Vec<(usize, Task)> = tasks.into_iter().enumerate().collect();
+        // The directories of the running serial tasks, by task index
+        let mut busy_directories: Vec<(usize, PathBuf)> = vec![];
+        // Tasks wait for a running task with the same command, to reuse its result
+        let mut busy_memo_keys: Vec<(usize, &String)> = vec![];
+        let mut deduplicated_result: Option<(usize, TaskResult)> = None;
+        let mut running: usize = 0;
+        let mut used_slots: usize = 0;
+        let mut cancelled = false;
+
+        loop {
+            while used_slots < self.concurrency.max(1) && !cancelled && !self.is_deadline_passed() && !process::is_interrupted() {
+                // The first task that is free to start, serial tasks skip past configs that are busy and heavy tasks past a lack of slots
+                let Some(position) = pending_tasks
+                    .iter()
+                    .position(|(index, task)| {
+                        let is_free = used_slots + slots[*index] <= self.concurrency.max(1) && (!self.is_serial(task) || busy_directories.iter().all(|(_, directory)| *directory != task.directory));
+                        is_free && memo_keys[*index].as_ref().is_none_or(|memo_key| busy_memo_keys.iter().all(|(_, busy_memo_key)| *busy_memo_key != memo_key))
+                    }) else {
+                    break;
+                };
+
+                let (index, task) = pending_tasks.remove(position);
+                if let Some(task_result) = self.find_memoized_result(&task, &memo_keys[index]) {
+                    deduplicated_result = Some((index, task_result));
+                    break;
+                }
+                if self.is_serial(&task) {
+                    busy_directories.push((index, task.directory.clone()));
+                }
+                if let Some(memo_key) = &memo_keys[index] {
+                    busy_memo_keys.push((index, memo_key));
+                }
+
+                let sender = sender.clone();
+                let executor = self.clone();
+                thread::spawn(move || {
+                    let _ = sender.send((index, run_task_guarded(task, &executor)));
+                });
+                running += 1;
+                used_slots += slots[index];
+            }
+
+            let (index, task_result) = match deduplicated_result.take() {
+                Some(deduplicated_result) => deduplicated_result,
+                None if running == 0 => break,
+                None => {
+                    // Every worker sends exactly once, panics included, so this never waits forever
+                    let Ok((index, task_result)) = receiver.recv() else {
+                        break;
+                    };
+                    running -= 1;
+                    used_slots -= slots[index];
+                    busy_directories.retain(|(busy_index, _)| *busy_index != index);
+                    busy_memo_keys.retain(|(busy_index, _)| *busy_index != index);
+                    self.memoize_result(&memo_keys[index], &task_result);
+
+                    (index, task_result)
+                },
+            };
+
+            on_result(&task_result);
+            if self.fail_fast && task_result.status == TaskStatus::FAILURE {
+                cancelled = true;
+            }
+            results[index] = Some(task_result);
+        }
+
+        results.into_iter().flatten().collect()
+    }
+
+    // A task heavier than the run allows takes every slot, instead of never starting
+    fn get_slots(&self, task: &Task) -> usize {
+        task.options.get_weight().clamp(1, self.concurrency.max(1))
+    }
+
+    fn is_serial(&self, task: &Task) -> bool {
+        self.serial_configs || task.options.serial
+    }
+
+    fn is_deadline_passed(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // Timeouts and cancellations are about that one process, the next task with the command runs it again
+    fn memoize_result(&self, memo_key: &Option<String>, task_result: &TaskResult) {
+        let (Some(results_memo), Some(memo_key)) = (&self.results_memo, memo_key) else {
+            return;
+        };
+
+        if matches!(task_result.status, TaskStatus::SUCCESS | TaskStatus::FAILURE | TaskStatus::ALLOWED | TaskStatus::SKIPPED) {
+            if let Ok(mut results_memo) = results_memo.lock() {
+                results_memo.entry(memo_key.clone()).or_insert(task_result.clone());
+            }
+        }
+    }
+
+    fn find_memoized_result(&self, task: &Task, memo_key: &Option<String>) -> Option<TaskResult> {
+        let memoized_result: TaskResult = self.results_memo.as_ref()?.lock().ok()?.get(memo_key.as_ref()?)?.clone();
+
+        if self.verbosity != Verbosity::SUMMARY {
+            output::line("DEDUPLICATED", Tone::MUTED, &format!("{} @ {:?}, the same command ran as {} @ {:?}", task.key, task.directory, memoized_result.key, memoized_result.directory));
+        }
+
+        Some(TaskResult {
+            key: task.key.clone(),
+            directory: task.directory.clone(),
+            source: config::get_task_source(&task.task_type),
+            deduplicated: true,
+            duration: 0.0,
+            resource_usage: None,
+            ..memoized_result
+        })
+    }
+}
+
+// Tasks with the same command, directory, environment and options have the same result,
+// dependencies and install steps run more than the command so those tasks always run
+fn get_memo_key(task: &Task) -> Option<String> {
+    if !task.dependencies.is_empty() || task.install.is_some() {
+        return None;
+    }
+
+    let env: BTreeMap<&String, &String> = task.env.iter().collect();
+    Some(format!("{:?}", (&task.directory, &task.command, &task.steps, env, &task.shell, &task.options)))
+}
+
+// A panicking task fails on its own, instead of taking the run down with it
+fn run_task_guarded(task: Task, executor: &Executor) -> TaskResult {
+    let (key, directory, source) = (task.key.clone(), task.directory.clone(), config::get_task_source(&task.task_type));
+
+    catch_unwind(AssertUnwindSafe(|| run_task(task, executor)))
+        .unwrap_or_else(|_| TaskResult {
+            key,
+            directory,
+            source,
+            status: TaskStatus::FAILURE,
+            error: Some("The task panicked while executing".to_string()),
+            failure: None,
+            hint: None,
+            output: vec![],
+            duration: 0.0,
+            resource_usage: None,
+            deduplicated: false,
+        })
+}
+
+// Executes a task and records its outcome, honoring allow_failure
+pub fn run_task(task: Task, executor: &Executor) -> TaskResult {
+    let Executor { deadline, capture_output, verbosity, output_style, ref dependency_memo, .. } = *executor;
+
+    if let Some(skip_reason) = get_skip_reason(&task) {
+        print_skipped(&task, &skip_reason, verbosity);
+        let source = config::get_task_source(&task.task_type);
+        return TaskResult { key: task.key, directory: task.directory, source, status: TaskStatus::SKIPPED, error: None, failure: None, hint: None, output: vec![], duration: 0.0, resource_usage: None, deduplicated: false };
+    }
+
+    let start_time = Instant::now();
+    let captured_output: Option<CapturedOutput> = (capture_output || verbosity != Verbosity::NORMAL).then(CapturedOutput::default);
+    let output_mode = OutputMode { captured_output, verbosity, output_style, resource_usage: Cell::default(), exclusive: executor.concurrency <= 1 };
+    let dependencies_result = run_task_dependencies(&task, deadline, &output_mode, dependency_memo);
+    if let (Ok(_), Some(fresh_reason)) = (&dependencies_result, get_fresh_reason(&task)) {
+        print_skipped(&task, &fresh_reason, verbosity);
+        let source = config::get_task_source(&task.task_type);
+        let (duration, resource_usage) = (start_time.elapsed().as_secs_f64(), output_mode.resource_usage.get());
+        return TaskResult { key: task.key, directory: task.directory, source, status: TaskStatus::SKIPPED, error: None, failure: None, hint: None, output: vec![], duration, resource_usage, deduplicated: false };
+    }
+    let result = dependencies_result.and_then(|_| spawn_task(&task, deadline, &output_mode));
+    if result.is_ok() {
+        record_cooldown(&task, verbosity);
+    }
+
+    let (status, failure) = match (result, task.options.allow_failure) {
+        (Ok(_), _) => (TaskStatus::SUCCESS, None),
+        (Err(failure), _) if failure.kind == FailureKind::TIMEOUT => (TaskStatus::TIMEOUT, Some(failure)),
+        (Err(failure), _) if failure.kind == FailureKind::CANCELLED => (TaskStatus::CANCELLED, Some(failure)),
+        (Err(failure), true) => (TaskStatus::ALLOWED, Some(failure)),
+        (Err(failure), false) => (TaskStatus::FAILURE, Some(failure)),
+    };
+
+    // The output only matters for tasks that need looking into
+    let output: Vec<String> = match (status, output_mode.captured_output) {
+        (TaskStatus::SUCCESS, _) | (_, None) => vec![],
+        (_, Some(captured_output)) => captured_output.lock().map(|lines| lines.iter().cloned().collect()).unwrap_or_default(),
+    };
+
+    TaskResult {
+        source: config::get_task_source(&task.task_type),
+        key: task.key,
+        directory: task.directory,
+        status,
+        error: failure.as_ref().map(|failure| failure.message.clone()),
+        failure: failure.as_ref().map(|failure| failure.kind),
+        hint: failure.and_then(|failure| failure.hint),
+        output,
+        duration: start_time.elapsed().as_secs_f64(),
+        resource_usage: output_mode.resource_usage.get(),
+        deduplicated: false,
+    }
+}
+
+// Dependencies run in order in the same thread, so they also finish first in parallel mode
+fn run_task_dependencies(task: &Task, deadline: Option<Instant>, output_mode: &OutputMode, dependency_memo: &DependencyMemo) -> Result<(), Failure> {
+    for dependency in &task.dependencies {
+        let memo_entry = dependency_memo.lock().unwrap_or_else(|err| err.into_inner()).entry(format!("{:?}", (&dependency.directory, &dependency.key))).or_default().clone();
+        let mut memoized_result = memo_entry.lock().unwrap_or_else(|err| err.into_inner());
+
+        let result: Result<(), Failure> = match memoized_result.clone() {
+            Some(result) => result,
+            None => {
+                let result = run_task_dependency(dependency, deadline, output_mode);
+                // Like a command result, a timeout or cancellation is about that one process
+                if !matches!(&result, Err(failure) if matches!(failure.kind, FailureKind::TIMEOUT | FailureKind::CANCELLED)) {
+                    *memoized_result = Some(result.clone());
+                }
+                result
+            },
+        };
+
+        result.map_err(|failure| failure.wrap(format!("Dependency \"{}\" failed", dependency.key)))?;
+    }
+
+    Ok(())
+}
+
+fn run_task_dependency(dependency: &Task, deadline: Option<Instant>, output_mode: &OutputMode) -> Result<(), Failure> {
+    if let Some(skip_reason) = get_skip_reason(dependency).or_else(|| get_fresh_reason(dependency)) {
+        print_skipped(dependency, &skip_reason, output_mode.verbosity);
+        return Ok(());
+    }
+
+    match spawn_task(dependency, deadline, output_mode) {
+        Ok(_) => {
+            record_cooldown(dependency, output_mode.verbosity);
+            Ok(())
+        },
+        Err(failure) if failure.kind != FailureKind::TIMEOUT && dependency.options.allow_failure => Ok(()),
+        Err(failure) => Err(failure),
+    }
+}
+
+// Tasks for other platforms and tasks within their cooldown have nothing to do
+fn get_skip_reason(task: &Task) -> Option<String> {
+    if !task.options.supports_platform() {
+        return Some(format!("it only runs on {}", task.options.describe_platforms()));
+    }
+
+    cooldown::get_remaining(task).map(|remaining| format!("it succeeded recently, its cooldown ends in {}, --force runs it anyway", time::format_duration(remaining)))
+}
+
+// Nor have tasks with outputs newer than their inputs, checked once the dependencies ran as those may write the inputs
+fn get_fresh_reason(task: &Task) -> Option<String> {
+    freshness::is_up_to_date(task).then(|| "its outputs are newer than its inputs".to_string())
+}
+
+// The task succeeded, a cooldown that can not be saved only means it runs again next time
+fn record_cooldown(task: &Task, verbosity: Verbosity) {
+    if let Err(err) = cooldown::record(task) {
+        if verbosity != Verbosity::SUMMARY {
+            output::warning(&format!("Failed to save the cooldown of {}: {}", task.key, err));
+        }
+    }
+}
+
+fn print_skipped(task: &Task, skip_reason: &str, verbosity: Verbosity) {
+    if verbosity != Verbosity::SUMMARY {
+        output::line("SKIPPED", Tone::MUTED, &format!("{} @ {:?}, {}", task.key, task.directory, skip_reason));
+    }
+}
+
+// Function to execute a command string and wait for it to finish, quiet runs only show its output when it fails
+pub fn execute_task(task: Task, verbosity: Verbosity) -> Result<(), String> {
+    let captured_output: Option<CapturedOutput> = (verbosity != Verbosity::NORMAL).then(CapturedOutput::default);
+    let output_mode = OutputMode { captured_output, verbosity, output_style: OutputStyle::default(), resource_usage: Cell::default(), exclusive: false };
+
+    spawn_task(&task, None, &output_mode).map_err(|failure| {
+        if let Some(captured_output) = &output_mode.captured_output {
+            let lines: Vec<String> = captured_output.lock().map(|lines| lines.iter().cloned().collect()).unwrap_or_default();
+            output::captured_output(&format!("{} @ {:?}", task.key, task.directory), &lines);
+        }
+
+        failure.describe()
+    })
+}
+
+// Where the output of the processes of one task goes, and what they used added up
+struct OutputMode {
+    captured_output: Option<CapturedOutput>,
+    verbosity: Verbosity,
+    output_style: OutputStyle,
+    resource_usage: Cell<Option<ResourceUsage>>,
+    // No other task runs at the same time, so the output can not mix with that of others
+    exclusive: bool,
+}
+
+// Every task gets a fresh scratch directory as RASK_TMPDIR, removed once it succeeds and kept when it fails
+fn spawn_task(task: &Task, deadline: Option<Instant>, output_mode: &OutputMode) -> Result<(), Failure> {
+    let Some(tmp_directory) = tmp::create_task_directory(task) else {
+        return spawn_steps(task, deadline, output_mode);
+    };
+
+    let mut task: Task = task.clone();
+    task.env.insert(tmp::TMP_DIRECTORY_VARIABLE.to_string(), tmp_directory.to_string_lossy().to_string());
+    let result = spawn_steps(&task, deadline, output_mode);
+
+    match &result {
+        Ok(_) => tmp::remove_task_directory(&tmp_directory),
+        Err(_) if output_mode.verbosity != Verbosity::SUMMARY => {
+            output::line("TMP", Tone::MUTED, &format!("Kept {:?} of {} for debugging, `rask clean --tmp` removes it", tmp_directory, task.key));
+        },
+        Err(_) => {},
+    }
+
+    result
+}
+
+// Steps share the directory and env of the task, a failing or terminated step ends the task
+fn spawn_steps(task: &Task, deadline: Option<Instant>, output_mode: &OutputMode) -> Result<(), Failure> {
+    let Task { key, steps, .. } = task;
+
+    if steps.is_empty() {
+        failure::check_command_paths(task)?;
+        return failure::classify_outcome(task, spawn_command(task, deadline, output_mode));
+    }
+
+    for (index, step) in steps.iter().enumerate() {
+        if output_mode.verbosity != Verbosity::SUMMARY {
+            output::line("STEP", Tone::INFO, &format!("{} {}/{}", key, index + 1, steps.len()));
+        }
+        let step_task = Task { command: step.clone(), steps: vec![], ..task.clone() };
+
+        failure::check_command_paths(&step_task)
+            .and_then(|_| failure::classify_outcome(&step_task, spawn_command(&step_task, deadline, output_mode)))
+            .map_err(|failure| failure.wrap(format!("Step {}/{} failed: {}", index + 1, steps.len(), step)))?;
+    }
+
+    Ok(())
+}
+
+// Output is piped, so lines of tasks running at the same time reach the terminal whole. A task running on its own
+// with undecorated output keeps the terminal, so prompts, colors and progress bars work like outside of rask.
+fn spawn_command(task: &Task, deadline: Option<Instant>, output_mode: &OutputMode) -> Result<ProcessOutcome, String> {
+    let Task { key, command, directory, .. } = task;
+    let OutputMode { captured_output, verbosity, output_style, resource_usage, exclusive } = output_mode;
+
+    // The steps and dependencies left of an interrupted run are not started
+    if process::is_interrupted() {
+        return Err("rask was interrupted".to_string());
+    }
+
+    if *verbosity != Verbosity::SUMMARY {
+        output::line("COMMAND", Tone::INFO, &format!("{} {}", command, output::paint(&format!("@ {:?}", directory), Tone::MUTED, Stream::STDOUT)));
+    }
+    let inherits_output = *exclusive && captured_output.is_none() && *verbosity == Verbosity::NORMAL && *output_style == OutputStyle::default()
+        && get_output_limit(task) == OutputLimit::default() && task.options.failure_pattern.is_none();
+    let mut process_command = process::build_command(task);
+    if !inherits_output {
+        process_command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = process::spawn(&mut process_command, &task.options)
+        .map_err(|err| format!("Failed to execute the command with {}: {}", process::get_shell(task), err))?;
+    let writer: Option<TaskWriter> = (*verbosity == Verbosity::NORMAL)
+        .then(|| TaskWriter::new(key, directory, *output_style).with_limit(get_output_limit(task)).with_section(&format!("{} @ {:?}: {}", key, directory, command), *exclusive));
+    let failure_watch: Option<FailureWatch> = task.options.failure_pattern.clone().map(FailureWatch::new);
+    let tee_handles = process::tee_output(&mut child, captured_output, writer.clone(), &failure_watch);
+    let pid: u32 = child.id();
+    running::register(pid, task);
+
+    // A terminated process was not reaped with its usage, what the earlier steps used is only part of the task
+    let outcome = process::wait_for(&mut child, deadline, &task.options).map(|(outcome, process_usage)| {
+        resource_usage.set(match (&outcome, resource_usage.get(), process_usage) {
+            (ProcessOutcome::TERMINATED, _, _) => None,
+            (_, Some(task_usage), Some(process_usage)) => Some(task_usage.add(process_usage)),
+            (_, task_usage, process_usage) => task_usage.or(process_usage),
+        });
+        outcome
+    });
+    let outcome = match running::unregister(pid) {
+        true => Ok(ProcessOutcome::CANCELLED),
+        false => outcome,
+    };
+    for tee_handle in tee_handles {
+        let _ = tee_handle.join();
+    }
+
+    // Only tasks that exited with 0 fail on their output, other outcomes already tell what went wrong
+    let outcome = match (outcome, failure_watch.and_then(|failure_watch| failure_watch.get_matched_line())) {
+        (Ok(ProcessOutcome::COMPLETED(status)), Some(matched_line)) if status.success() => Ok(ProcessOutcome::MATCHED(matched_line)),
+        (outcome, _) => outcome,
+    };
+
+    if let Some(writer) = writer {
+        let succeeded = matches!(&outcome, Ok(ProcessOutcome::COMPLETED(status)) if status.success());
+        writer.finish(succeeded);
+    }
+
+    outcome
+}
+
+pub fn get_output_limit(task: &Task) -> OutputLimit {
+    OutputLimit { lines: task.options.max_output_lines, bytes: task.options.max_output_bytes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use crate::testing::{create_task, TestWorkspace};
+    use crate::utils::config::TaskOptions;
+    use crate::utils::failure::FailurePattern;
+    use crate::utils::file::TaskCache;
+
+    fn create_executor(concurrency: usize, fail_fast: bool) -> Executor {
+        Executor { concurrency, fail_fast, deadline: None, capture_output: false, serial_configs: false, verbosity: Verbosity::NORMAL, output_style: OutputStyle::default(), results_memo: None, dependency_memo: DependencyMemo::default() }
+    }
+
+    #[test]
+    fn returns_results_in_task_order() {
+        let tasks = vec![create_task("slow", "sleep 0.2"), create_task("fast", "true")];
+        let mut completed: Vec<String> = vec![];
+
+        let results = create_executor(2, false).run(tasks, &mut |task_result| completed.push(task_result.key.clone()));
+
+        assert_eq!(completed, vec!["fast", "slow"]);
+        assert_eq!(results.iter().map(|task_result| task_result.key.as_str()).collect::<Vec<&str>>(), vec!["slow", "fast"]);
+    }
+
+    #[test]
+    fn fail_fast_stops_starting_tasks() {
+        let tasks = vec![create_task("first", "false"), create_task("second", "true")];
+
+        let results = create_executor(1, true).run(tasks, &mut |_| {});
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, TaskStatus::FAILURE);
+    }
+
+    #[test]
+    fn allowed_failures_do_not_stop_the_run() {
+        let options = TaskOptions { allow_failure: true, ..Default::default() };
+        let tasks = vec![Task { options, ..create_task("lint", "exit 2") }, create_task("build", "true")];
+
+        let results = create_executor(1, true).run(tasks, &mut |_| {});
+
+        assert_eq!(results.iter().map(|task_result| task_result.status).collect::<Vec<TaskStatus>>(), vec![TaskStatus::ALLOWED, TaskStatus::SUCCESS]);
+        assert!(results[0].error.is_some());
+    }
+
+    #[test]
+    fn keeps_the_output_of_tasks_that_did_not_succeed() {
+        let tasks = vec![create_task("lint", "echo checked"), create_task("test", "echo 1 passed && echo 1 failed >&2 && false")];
+        let executor = Executor { capture_output: true, verbosity: Verbosity::QUIET, ..create_executor(1, false) };
+
+        let results = executor.run(tasks, &mut |_| {});
+
+        assert!(results[0].output.is_empty());
+        let mut output = results[1].output.clone();
+        output.sort();
+        assert_eq!(output, vec!["1 failed", "1 passed"]);
+    }
+
+    #[test]
+    fn without_fail_fast_every_task_runs() {
+        let tasks = vec![create_task("first", "false"), create_task("second", "true")];
+
+        let results = create_executor(1, false).run(tasks, &mut |_| {});
+
+        assert_eq!(results.iter().map(|task_result| task_result.status).collect::<Vec<TaskStatus>>(), vec![TaskStatus::FAILURE, TaskStatus::SUCCESS]);
+    }
+
+    #[test]
+    fn steps_stop_at_the_first_failing_step() {
+        let marker = temp_dir().join(format!("rask-steps-{}", std::process::id()));
+        let steps: Vec<String> = vec!["true".to_string(), "false".to_string(), format!("touch {:?}", marker)];
+        let task = Task { steps, ..create_task("build", "true && false") };
+
+        let task_result = run_task(task, &create_executor(1, false));
+
+        assert_eq!(task_result.status, TaskStatus::FAILURE);
+        assert_eq!(task_result.error.as_deref(), Some("Step 2/3 failed: false (Exited with code 1)"));
+        assert_eq!(task_result.failure, Some(FailureKind::EXITED));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn heavy_tasks_take_several_slots() {
+        let options = TaskOptions { weight: Some(4), ..TaskOptions::default() };
+        let tasks = vec![Task { options, ..create_task("webpack", "sleep 0.2") }, create_task("lint", "sleep 0.1"), create_task("format", "true")];
+        let mut completed: Vec<String> = vec![];
+
+        create_executor(2, false).run(tasks, &mut |task_result| completed.push(task_result.key.clone()));
+
+        // webpack takes both slots, lint and format only start once it finished
+        assert_eq!(completed, vec!["webpack", "format", "lint"]);
+    }
+
+    #[test]
+    fn tasks_fail_on_output_matching_their_failure_pattern() {
+        let failure_pattern = Some(FailurePattern::parse("ERROR|^FAILED").unwrap());
+        let options = TaskOptions { failure_pattern, ..TaskOptions::default() };
+        let task = Task { options: options.clone(), ..create_task("lint", "echo 'ERROR in app.js' >&2; echo done") };
+        // The output is captured, so the matched lines do not end up between the lines of the test run
+        let executor = Executor { capture_output: true, verbosity: Verbosity::QUIET, ..create_executor(1, false) };
+
+        let task_result = run_task(task, &executor);
+
+        assert_eq!(task_result.status, TaskStatus::FAILURE);
+        assert_eq!(task_result.failure, Some(FailureKind::MATCHED));
+        assert_eq!(task_result.error.as_deref(), Some("Exited with code 0, but printed \"ERROR in app.js\""));
+        assert!(task_result.output.iter().any(|line| line.contains("ERROR in app.js")));
+
+        let task = Task { options, ..create_task("test", "echo '1 test FAILED'") };
+        assert_eq!(run_task(task, &executor).status, TaskStatus::SUCCESS);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn tasks_report_the_resources_of_their_steps() {
+        let steps: Vec<String> = vec!["true".to_string(), "sleep 0.1".to_string()];
+        let task = Task { steps, ..create_task("build", "true && sleep 0.1") };
+
+        let resource_usage = run_task(task, &create_executor(1, false)).resource_usage.unwrap();
+        assert!(resource_usage.max_rss > 0);
+
+        // The steps before the one that timed out are not what the task uses
+        let task = Task { steps: vec!["true".to_string(), "sleep 10".to_string()], ..create_task("build", "true && sleep 10") };
+        let executor = Executor { deadline: Some(Instant::now() + std::time::Duration::from_millis(500)), ..create_executor(1, false) };
+        let task_result = run_task(task, &executor);
+        assert_eq!((task_result.status, task_result.resource_usage), (TaskStatus::TIMEOUT, None));
+    }
+
+    #[test]
+    fn partial_lines_are_passed_on_once_the_output_is_quiet() {
+        let captured_output = Some(CapturedOutput::default());
+        let mut child = std::process::Command::new("sh").args(["-c", "printf 'Password: '; sleep 1"]).stdout(Stdio::piped()).spawn().unwrap();
+        let tee_handles = process::tee_output(&mut child, &captured_output, None, &None);
+
+        thread::sleep(std::time::Duration::from_millis(600));
+        let lines: Vec<String> = captured_output.as_ref().unwrap().lock().unwrap().iter().cloned().collect();
+        assert_eq!(lines, vec!["Password:"]);
+
+        child.wait().unwrap();
+        tee_handles.into_iter().for_each(|tee_handle| tee_handle.join().unwrap());
+    }
+
+    #[test]
+    fn freshness_is_checked_after_the_dependencies_ran() {
+        let workspace = TestWorkspace::new().with_file("src.txt", "").with_file("dist.txt", "");
+        let (input, output) = (workspace.path("src.txt"), workspace.path("dist.txt"));
+        std::fs::File::options().write(true).open(&input).unwrap().set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(60)).unwrap();
+        let options = TaskOptions { cache: Some(TaskCache::MTIME), ..TaskOptions::default() };
+        let task = Task { directory: workspace.path("."), inputs: vec![input], outputs: vec![output.clone()], options, ..create_task("build", "echo built > dist.txt") };
+        assert_eq!(run_task(task.clone(), &create_executor(1, false)).status, TaskStatus::SKIPPED);
+
+        // The dependency writes the input, so the outputs are outdated by the time the task would start
+        let task = Task { dependencies: vec![Task { directory: workspace.path("."), ..create_task("generate", "touch src.txt") }], ..task };
+        assert_eq!(run_task(task, &create_executor(1, false)).status, TaskStatus::SUCCESS);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "built\n");
+    }
+
+    #[test]
+    fn tasks_for_other_platforms_are_skipped() {
+        let other_os = if std::env::consts::OS == "linux" { "windows" } else { "linux" };
+        let options = TaskOptions { os: vec![other_os.to_string()], ..TaskOptions::default() };
+        let task = Task { options, ..create_task("sign", "false") };
+
+        assert_eq!(run_task(task, &create_executor(1, false)).status, TaskStatus::SKIPPED);
+    }
+
+    #[test]
+    fn serial_tasks_of_the_same_config_wait_for_each_other() {
+        let mut other_task = create_task("other", "true");
+        other_task.directory = std::env::current_dir().unwrap();
+        let tasks = vec![create_task("slow", "sleep 0.2"), create_task("fast", "true"), other_task];
+        let mut completed: Vec<String> = vec![];
+
+        let executor = Executor { serial_configs: true, ..create_executor(3, false) };
+        executor.run(tasks, &mut |task_result| completed.push(task_result.key.clone()));
+
+        assert_eq!(completed, vec!["other", "slow", "fast"]);
+    }
+
+    #[test]
+    fn dependencies_run_once_per_run() {
+        let workspace = TestWorkspace::new();
+        let install = Task { directory: workspace.path("."), ..create_task("install", "sleep 0.1 && echo ran >> installs.txt") };
+        let failing = Task { directory: workspace.path("."), ..create_task("generate", "echo ran >> generates.txt && exit 1") };
+        let tasks: Vec<Task> = ["build", "lint", "test"]
+            .iter()
+            .map(|key| Task { directory: workspace.path("."), dependencies: vec![install.clone()], ..create_task(key, "true") })
+            .chain(["docs", "types"].iter().map(|key| Task { directory: workspace.path("."), dependencies: vec![failing.clone()], ..create_task(key, "true") }))
+            .collect();
+
+        let executor = create_executor(5, false);
+        let results = executor.run(tasks[..4].to_vec(), &mut |_| {});
+        let results = [results, executor.run(tasks[4..].to_vec(), &mut |_| {})].concat();
+
+        let statuses: Vec<TaskStatus> = results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, vec![TaskStatus::SUCCESS, TaskStatus::SUCCESS, TaskStatus::SUCCESS, TaskStatus::FAILURE, TaskStatus::FAILURE]);
+        assert_eq!(std::fs::read_to_string(workspace.path("installs.txt")).unwrap().lines().count(), 1);
+        assert_eq!(std::fs::read_to_string(workspace.path("generates.txt")).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn tasks_with_the_same_command_reuse_the_result() {
+        let workspace = TestWorkspace::new();
+        let command = "sleep 0.1 && echo ran >> runs.txt";
+        let mut tasks: Vec<Task> = ["lint", "check", "other"].iter().map(|key| Task { directory: workspace.path("."), ..create_task(key, command) }).collect();
+        tasks[2].env.insert("MODE".to_string(), "strict".to_string());
+
+        let executor = Executor { results_memo: Some(ResultsMemo::default()), ..create_executor(3, false) };
+        let results = executor.run(tasks, &mut |_| {});
+
+        let deduplicated: Vec<(&str, bool)> = results.iter().map(|result| (result.key.as_str(), result.deduplicated)).collect();
+        assert_eq!(deduplicated, vec![("lint", false), ("check", true), ("other", false)]);
+        assert!(results.iter().all(|result| result.status == TaskStatus::SUCCESS));
+        assert_eq!(std::fs::read_to_string(workspace.path("runs.txt")).unwrap().lines().count(), 2);
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+
+// Why rask failed, every cause has its own exit code so scripts can branch on it.
+// Signals still end rask with 128 plus the signal number, like shells do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitKind {
+    GENERIC,
+    // A config that could not be read, parsed or resolved
+    CONFIG,
+    // A task failed
+    TASK,
+    // The run exceeded --max-duration
+    TIMEOUT,
+    // Tasks were stopped with `rask kill`, or not confirmed
+    CANCELLED,
+    // No task matched the task name
+    UNMATCHED,
+    // The command line could not be parsed, clap would exit with the code of CONFIG
+    USAGE,
+    // rask was stopped by this signal, and wound down the run
+    INTERRUPTED(i32),
+}
+
+impl ExitKind {
+    pub fn code(&self) -> i32 {
+        match self {
+            ExitKind::GENERIC => 1,
+            ExitKind::CONFIG => 2,
+            ExitKind::TASK => 3,
+            ExitKind::TIMEOUT => 4,
+            ExitKind::CANCELLED => 5,
+            ExitKind::UNMATCHED => 6,
+            ExitKind::USAGE => 7,
+            ExitKind::INTERRUPTED(signal) => 128 + signal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExitError {
+    pub kind: ExitKind,
+    pub message: String,
+}
+
+impl ExitError {
+    pub fn new(kind: ExitKind, message: String) -> Self {
+        ExitError { kind, message }
+    }
+
+    pub fn interrupted(signal: i32) -> Self {
+        ExitError::new(ExitKind::INTERRUPTED(signal), "rask was interrupted".to_string())
+    }
+}
+
+// Errors are strings throughout rask, without a known cause they are generic
+impl From<String> for ExitError {
+    fn from(message: String) -> Self {
+        ExitError::new(ExitKind::GENERIC, message)
+    }
+}
+
+impl Display for ExitError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}", self.message)
+    }
+}
+
+// For map_err on the functions that read and resolve configs
+pub fn config(message: String) -> ExitError {
+    ExitError::new(ExitKind::CONFIG, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_errors_are_generic() {
+        let kinds = [ExitKind::GENERIC, ExitKind::CONFIG, ExitKind::TASK, ExitKind::TIMEOUT, ExitKind::CANCELLED, ExitKind::UNMATCHED, ExitKind::USAGE, ExitKind::INTERRUPTED(2)];
+        let codes: Vec<i32> = kinds.iter().map(ExitKind::code).collect();
+        assert_eq!(codes, vec![1, 2, 3, 4, 5, 6, 7, 130]);
+
+        let error: ExitError = "Failed to read".to_string().into();
+        assert_eq!(error.kind, ExitKind::GENERIC);
+        assert_eq!(config("Unknown key".to_string()).kind.code(), 2);
+    }
+}
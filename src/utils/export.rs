@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::utils::{config, environment};
+use crate::utils::config::{ConfigStructure, ConfigTask, SortableTask, SortableTasks, Task};
+use crate::utils::file::TaskEngine;
+
+// Bumped on every change that is not an addition, consumers check it before reading the rest
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceExport {
+    pub schema_version: u32,
+    pub rask_version: &'static str,
+    pub entry: PathBuf,
+    // Depth first from the entry config, every config once
+    pub configs: Vec<ConfigExport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigExport {
+    pub name: String,
+    pub file_path: PathBuf,
+    pub directory: PathBuf,
+    pub task_engine: TaskEngine,
+    // The file paths of the configs it includes
+    pub children: Vec<PathBuf>,
+    pub tasks: Vec<TaskExport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskExport {
+    pub key: String,
+    pub source: &'static str,
+    pub description: Option<String>,
+    pub command: String,
+    pub steps: Vec<String>,
+    pub depends_on: Vec<String>,
+    pub after: Vec<String>,
+    pub inputs: Vec<PathBuf>,
+    pub outputs: Vec<PathBuf>,
+    // Secret values are masked, like `rask list --env` does
+    pub env: BTreeMap<String, String>,
+}
+
+pub fn export_workspace(entry_config_path: &Path, config_structure: &ConfigStructure) -> Result<WorkspaceExport, String> {
+    // An empty, non-strict task name matches every task, with the constants each config inherits
+    let sortable_tasks: SortableTasks = config::resolve_sortable_task(config_structure, "", &false)?;
+
+    let mut configs: Vec<ConfigExport> = vec![];
+    export_config(config_structure, &sortable_tasks, &mut configs);
+
+    Ok(WorkspaceExport {
+        schema_version: EXPORT_SCHEMA_VERSION,
+        rask_version: env!("CARGO_PKG_VERSION"),
+        entry: entry_config_path.to_path_buf(),
+        configs,
+    })
+}
+
+fn export_config(config_structure: &ConfigStructure, sortable_tasks: &SortableTasks, configs: &mut Vec<ConfigExport>) {
+    let ConfigStructure { config, children, .. } = config_structure;
+    if configs.iter().any(|config_export| config_export.file_path == config.file_path) {
+        return;
+    }
+
+    let tasks: Vec<TaskExport> = config.tasks
+        .iter()
+        .filter_map(|config_task| {
+            // Tasks others depend on are only scheduled through their dependents
+            let task: &Task = sortable_tasks
+                .iter()
+                .flat_map(|SortableTask { task, .. }| task.dependencies.iter().chain([task]))
+                .find(|task| task.key == config_task.key && task.directory == config.dir_path)?;
+
+            Some(export_task(config_task, task))
+        })
+        .collect();
+
+    configs.push(ConfigExport {
+        name: config.name.clone(),
+        file_path: config.file_path.clone(),
+        directory: config.dir_path.clone(),
+        task_engine: config.task_engine.clone(),
+        children: children.iter().map(|child| child.config.file_path.clone()).collect(),
+        tasks,
+    });
+
+    for child in children {
+        export_config(child, sortable_tasks, configs);
+    }
+}
+
+fn export_task(config_task: &ConfigTask, task: &Task) -> TaskExport {
+    let Task { key, command, steps, inputs, outputs, env, .. } = task;
+
+    TaskExport {
+        key: key.clone(),
+        source: config::get_task_source(&config_task.task_type),
+        description: config_task.description.clone(),
+        command: command.clone(),
+        steps: steps.clone(),
+        depends_on: config_task.depends_on.clone(),
+        after: config_task.after.clone(),
+        inputs: inputs.clone(),
+        outputs: outputs.clone(),
+        env: env.iter().map(|(name, value)| (name.clone(), environment::mask_value(name, value))).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn exports_every_config_once_with_its_resolved_tasks() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\nconstants:\n  mode: release\n")
+            .with_config("packages/api", "name: api\ntask_engine: npm\nenv:\n  API_TOKEN: secret\ntasks:\n  build:\n    command: make ${mode}\n    depends_on:\n      - lint\n")
+            .with_package_json("packages/api", &["lint"]);
+
+        let entry_config_path = workspace.config_path(".");
+        let config_structure = workspace.load_structure(".");
+
+        let export = export_workspace(&entry_config_path, &config_structure).unwrap();
+
+        assert_eq!(export.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(export.configs.iter().map(|config| config.name.as_str()).collect::<Vec<&str>>(), vec!["root", "api"]);
+        assert_eq!(export.configs[0].children, vec![workspace.config_path("packages/api")]);
+
+        let tasks = &export.configs[1].tasks;
+        let build = tasks.iter().find(|task| task.key == "build").unwrap();
+        assert_eq!((build.source, build.command.as_str()), ("yaml", "make release"));
+        assert_eq!(build.depends_on, vec!["lint"]);
+        assert_ne!(build.env["API_TOKEN"], "secret");
+        assert_eq!(tasks.iter().find(|task| task.key == "lint").unwrap().source, "npm");
+    }
+}
@@ -0,0 +1,367 @@
+use std::mem::take;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::utils::config::Task;
+use crate::utils::{prerequisites, process};
+use crate::utils::process::ProcessOutcome;
+
+// Exit codes POSIX shells use when they could not run the command
+const COMMAND_NOT_FOUND_EXIT_CODE: i32 = 127;
+const NOT_EXECUTABLE_EXIT_CODE: i32 = 126;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureKind {
+    // The command, or a binary it calls, is not installed
+    #[serde(rename = "command_not_found")]
+    MISSING,
+    #[serde(rename = "permission_denied")]
+    DENIED,
+    // A non-zero exit code
+    EXITED,
+    SIGNAL,
+    TIMEOUT,
+    // The shell itself could not be started
+    SPAWN,
+    // Stopped with `rask kill`, the rest of the run went on
+    CANCELLED,
+    // Exited with 0, but printed a line matching the failure_pattern of the task
+    MATCHED,
+}
+
+// Why a task failed, with a hint on what to do about it when there is a common cause
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Failure {
+    pub fn new(kind: FailureKind, message: String) -> Self {
+        Failure { kind, message, hint: None }
+    }
+
+    // Keeps the kind and hint, for failures of steps and dependencies
+    pub fn wrap(self, context: String) -> Self {
+        Failure { message: format!("{} ({})", context, self.message), ..self }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.hint {
+            Some(hint) => format!("{}. {}", self.message, hint),
+            None => self.message.clone(),
+        }
+    }
+}
+
+// Install hints of the binaries engines and common tasks rely on
+const INSTALL_HINTS: [(&str, &str); 11] = [
+    ("npm", "Is Node.js installed?"),
+    ("node", "Is Node.js installed?"),
+    ("npx", "Is Node.js installed?"),
+    ("yarn", "Is Yarn installed? Try `corepack enable`."),
+    ("pnpm", "Is pnpm installed? Try `corepack enable`."),
+    ("composer", "Is Composer installed? See https://getcomposer.org."),
+    ("php", "Is PHP installed?"),
+    ("cargo", "Is Rust installed? See https://rustup.rs."),
+    ("task", "Is go-task installed? See https://taskfile.dev."),
+    ("just", "Is just installed? See https://just.systems."),
+    ("docker", "Is Docker installed? See https://docs.docker.com/get-docker."),
+];
+
+// A script that is missing or not executable gets an error naming the path, instead of a shell error in the output.
+// Only the command a task starts with is checked, the commands after it may run elsewhere after a cd.
+pub fn check_command_paths(task: &Task) -> Result<(), Failure> {
+    let Some(binary) = prerequisites::find_first_binary(&task.command) else {
+        return Ok(());
+    };
+
+    match process::resolve_relative_command(task, &binary) {
+        Some(path) => check_relative_command(&binary, &path),
+        None => Ok(()),
+    }
+}
+
+fn check_relative_command(binary: &str, path: &Path) -> Result<(), Failure> {
+    if !path.exists() {
+        return Err(Failure {
+            kind: FailureKind::MISSING,
+            message: format!("{} not found, checked {:?}", binary, path),
+            hint: Some("Paths starting with ./ or ../ are relative to the directory of the rask.yaml, unless the command changes it with cd.".to_string()),
+        });
+    }
+
+    if !process::is_executable(path) {
+        return Err(Failure {
+            kind: FailureKind::DENIED,
+            message: format!("{} is not executable, checked {:?}", binary, path),
+            hint: Some(format!("Make it executable with chmod +x {}.", binary)),
+        });
+    }
+
+    Ok(())
+}
+
+// After the shell could not run a command, a relative command of the task that is missing or not executable is the likely cause
+fn find_relative_command_failure(task: &Task, kind: FailureKind) -> Option<Failure> {
+    prerequisites::find_command_binaries(&task.command)
+        .into_iter()
+        .filter_map(|binary| Some((process::resolve_relative_command(task, &binary)?, binary)))
+        .filter_map(|(path, binary)| check_relative_command(&binary, &path).err())
+        .find(|failure| failure.kind == kind)
+}
+
+pub fn classify_outcome(task: &Task, outcome: Result<ProcessOutcome, String>) -> Result<(), Failure> {
+    match outcome {
+        Ok(ProcessOutcome::COMPLETED(exit_status)) if exit_status.success() => Ok(()),
+        Ok(ProcessOutcome::COMPLETED(exit_status)) => Err(classify_exit_status(task, exit_status)),
+        Ok(ProcessOutcome::TERMINATED) => Err(Failure {
+            kind: FailureKind::TIMEOUT,
+            message: "Terminated, the time budget was exceeded".to_string(),
+            hint: Some("Raise --max-duration when the run needs more time.".to_string()),
+        }),
+        Ok(ProcessOutcome::CANCELLED) => Err(Failure {
+            kind: FailureKind::CANCELLED,
+            message: "Cancelled with rask kill".to_string(),
+            hint: None,
+        }),
+        Ok(ProcessOutcome::MATCHED(line)) => Err(Failure {
+            kind: FailureKind::MATCHED,
+            message: format!("Exited with code 0, but printed \"{}\"", line.trim()),
+            hint: Some("The line matches the failure_pattern of the task.".to_string()),
+        }),
+        Err(err) => Err(Failure {
+            kind: FailureKind::SPAWN,
+            message: err,
+            hint: Some("Set `shell:` in the rask.yaml to an installed interpreter.".to_string()),
+        }),
+    }
+}
+
+fn classify_exit_status(task: &Task, exit_status: ExitStatus) -> Failure {
+    if let Some(signal) = get_signal(&exit_status) {
+        return Failure {
+            kind: FailureKind::SIGNAL,
+            message: format!("Killed by signal {}", signal),
+            // SIGKILL is what the OOM killer and exceeded memory limits end with
+            hint: (signal == 9).then(|| "The process was killed, possibly for using too much memory, see max_memory.".to_string()),
+        };
+    }
+
+    match exit_status.code() {
+        Some(COMMAND_NOT_FOUND_EXIT_CODE) => {
+            if let Some(failure) = find_relative_command_failure(task, FailureKind::MISSING) {
+                return failure;
+            }
+            let missing_binary: Option<String> = prerequisites::find_command_binaries(&task.command)
+                .into_iter()
+                .find(|binary| process::resolve_relative_command(task, binary).is_none() && !prerequisites::is_binary_available(binary));
+
+            match missing_binary {
+                Some(binary) => Failure {
+                    kind: FailureKind::MISSING,
+                    message: format!("{} not found", binary),
+                    hint: Some(format!("{} Run `rask env` to check every prerequisite.", get_install_hint(&binary))),
+                },
+                None => Failure::new(FailureKind::MISSING, "Command not found".to_string()),
+            }
+        },
+        Some(NOT_EXECUTABLE_EXIT_CODE) => find_relative_command_failure(task, FailureKind::DENIED).unwrap_or(Failure {
+            kind: FailureKind::DENIED,
+            message: "Permission denied".to_string(),
+            hint: Some("Make sure the command is executable, e.g. chmod +x ./script.sh.".to_string()),
+        }),
+        Some(code) => Failure::new(FailureKind::EXITED, format!("Exited with code {}", code)),
+        None => Failure::new(FailureKind::EXITED, "Exited without a code".to_string()),
+    }
+}
+
+fn get_install_hint(binary: &str) -> String {
+    match INSTALL_HINTS.iter().find(|(name, _)| *name == binary) {
+        Some((_, hint)) => hint.to_string(),
+        None => "Is it installed and in PATH?".to_string(),
+    }
+}
+
+// Tools that print their errors but exit with 0 anyway fail on a line like this.
+// Alternatives are separated by |, each is text found anywhere in a line, at its start with ^ or at its end with $.
+// A \ escapes the character after it, there is no other regex syntax.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FailurePattern {
+    pattern: String,
+    alternatives: Vec<PatternAlternative>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PatternAlternative {
+    text: String,
+    start: bool,
+    end: bool,
+}
+
+impl FailurePattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let mut alternatives: Vec<PatternAlternative> = vec![];
+        let mut alternative = PatternAlternative::default();
+        let mut characters = pattern.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            match character {
+                '\\' => alternative.text.push(characters.next().ok_or("it ends with a \\ that escapes nothing")?),
+                '|' => alternatives.push(take(&mut alternative)),
+                '^' if alternative.text.is_empty() && !alternative.start => alternative.start = true,
+                '$' if matches!(characters.peek(), None | Some('|')) => alternative.end = true,
+                // A pattern means the same as the regex it looks like, or it is rejected
+                '^' | '$' | '.' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' => {
+                    return Err(format!("it uses {} as a regex, only |, ^ at the start and $ at the end are supported, \\{} matches it literally", character, character));
+                },
+                _ => alternative.text.push(character),
+            }
+        }
+        alternatives.push(alternative);
+
+        // An empty alternative would match every line
+        if alternatives.iter().any(|alternative| alternative.text.is_empty()) {
+            return Err("it has an alternative without text".to_string());
+        }
+
+        Ok(FailurePattern { pattern: pattern.to_string(), alternatives })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.alternatives.iter().any(|PatternAlternative { text, start, end }| match (start, end) {
+            (true, true) => line == text,
+            (true, false) => line.starts_with(text.as_str()),
+            (false, true) => line.ends_with(text.as_str()),
+            (false, false) => line.contains(text.as_str()),
+        })
+    }
+}
+
+impl TryFrom<String> for FailurePattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        FailurePattern::parse(&pattern)
+    }
+}
+
+impl From<FailurePattern> for String {
+    fn from(failure_pattern: FailurePattern) -> Self {
+        failure_pattern.pattern
+    }
+}
+
+// The first line of output that matched the failure_pattern of a running task, shared between the threads that stream it
+#[derive(Debug, Clone)]
+pub struct FailureWatch {
+    failure_pattern: FailurePattern,
+    matched_line: Arc<Mutex<Option<String>>>,
+}
+
+impl FailureWatch {
+    pub fn new(failure_pattern: FailurePattern) -> Self {
+        FailureWatch { failure_pattern, matched_line: Arc::default() }
+    }
+
+    pub fn inspect(&self, line: &str) {
+        if let Ok(mut matched_line) = self.matched_line.lock() {
+            if matched_line.is_none() && self.failure_pattern.is_match(line) {
+                *matched_line = Some(line.to_string());
+            }
+        }
+    }
+
+    pub fn get_matched_line(&self) -> Option<String> {
+        self.matched_line.lock().ok().and_then(|matched_line| matched_line.clone())
+    }
+}
+
+#[cfg(unix)]
+fn get_signal(exit_status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+
+    exit_status.signal()
+}
+
+#[cfg(not(unix))]
+fn get_signal(_exit_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{create_task, TestWorkspace};
+
+    fn run(command: &str) -> Result<(), Failure> {
+        let task = create_task("build", command);
+        let outcome = process::build_command(&task).output().map(|output| ProcessOutcome::COMPLETED(output.status)).map_err(|err| err.to_string());
+
+        classify_outcome(&task, outcome)
+    }
+
+    #[test]
+    fn classifies_missing_commands_with_a_hint() {
+        let failure = run("rask-missing-binary --version").unwrap_err();
+
+        assert_eq!(failure.kind, FailureKind::MISSING);
+        assert_eq!(failure.message, "rask-missing-binary not found");
+        assert_eq!(failure.hint.as_deref(), Some("Is it installed and in PATH? Run `rask env` to check every prerequisite."));
+    }
+
+    #[test]
+    fn classifies_exit_codes_and_signals() {
+        assert_eq!(run("exit 3").unwrap_err(), Failure::new(FailureKind::EXITED, "Exited with code 3".to_string()));
+        assert_eq!(run("kill -TERM $$").unwrap_err().kind, FailureKind::SIGNAL);
+        assert_eq!(run("true"), Ok(()));
+    }
+
+    #[test]
+    fn failure_patterns_match_lines_of_output() {
+        let failure_pattern = FailurePattern::parse("ERROR|^FAILED|done with errors$|100\\|200").unwrap();
+
+        assert!(failure_pattern.is_match("[webpack] ERROR in ./src/app.js"));
+        assert!(failure_pattern.is_match("FAILED tests/test_app.py"));
+        assert!(!failure_pattern.is_match("1 test FAILED"));
+        assert!(failure_pattern.is_match("Build done with errors"));
+        assert!(failure_pattern.is_match("status 100|200"));
+        assert!(!failure_pattern.is_match("No errors"));
+
+        assert!(FailurePattern::parse("ERROR|").is_err());
+        assert!(FailurePattern::parse("ERROR\\").is_err());
+        assert!(FailurePattern::parse("ERROR in app.js").unwrap_err().contains("\\. matches it literally"));
+        assert!(FailurePattern::parse("FAIL(ED)?").is_err());
+        assert!(FailurePattern::parse("a^b|c$d").is_err());
+        assert!(FailurePattern::parse("ERROR in app\\.js").unwrap().is_match("ERROR in app.js"));
+        assert_eq!(String::from(failure_pattern), "ERROR|^FAILED|done with errors$|100\\|200");
+    }
+
+    #[test]
+    fn checks_relative_commands_in_the_task_directory() {
+        let workspace = TestWorkspace::new()
+            .with_file("packages/app/scripts/build.sh", "#!/bin/sh\n")
+            .with_file("packages/app/scripts/lint.sh", "#!/bin/sh\n");
+        let directory = workspace.path("packages/app");
+        let create_app_task = |command: &str| Task { directory: directory.clone(), ..create_task("build", command) };
+        std::fs::set_permissions(workspace.path("packages/app/scripts/build.sh"), std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+        assert_eq!(check_command_paths(&create_app_task("./scripts/build.sh --release")), Ok(()));
+        assert_eq!(check_command_paths(&create_app_task("./scripts/lint.sh")).unwrap_err().kind, FailureKind::DENIED);
+
+        // Later commands are only checked once the shell could not run them, they may run in another directory
+        assert_eq!(check_command_paths(&create_app_task("cd scripts && ./build.sh")), Ok(()));
+        assert_eq!(check_command_paths(&create_app_task("echo \"done; ./later\"")), Ok(()));
+        let task = create_app_task("true && ../scripts/test.sh");
+        assert_eq!(check_command_paths(&task), Ok(()));
+
+        let outcome = process::build_command(&task).output().map(|output| ProcessOutcome::COMPLETED(output.status)).map_err(|err| err.to_string());
+        let failure = classify_outcome(&task, outcome).unwrap_err();
+        assert_eq!(failure.kind, FailureKind::MISSING);
+        assert_eq!(failure.message, format!("../scripts/test.sh not found, checked {:?}", directory.join("../scripts/test.sh")));
+    }
+}
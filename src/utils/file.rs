@@ -3,6 +3,8 @@ use std::fs::{canonicalize, read_to_string, write};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use crate::utils::deprecation;
+use crate::utils::deprecation::{Deprecation, DEPRECATIONS};
 
 pub fn read_file_content (path: PathBuf) -> Result<String, String> {
     match read_to_string(path) {
@@ -17,20 +19,20 @@ pub fn write_file_content(file_path: &PathBuf, content: &str) -> Result<(), Stri
     Ok(())
 }
 
-pub fn read_json_file<T: for<'a> Deserialize<'a>>(file_path: &PathBuf) -> Result<T, String> {
-    let content = read_file_content(file_path.clone())?;
+pub fn read_json_file<T: for<'a> Deserialize<'a>>(file_path: &Path) -> Result<T, String> {
+    let content = read_file_content(file_path.to_path_buf())?;
 
     let file_content: T = serde_json::from_str::<T>(&content).map_err(|err| err.to_string())?;
 
     Ok(file_content)
 }
 
-fn read_yaml_file<T: for<'a> Deserialize<'a>>(file_path: &PathBuf) -> Result<T, String> {
-    let content = read_file_content(file_path.clone())?;
-
-    let file_content: T = serde_yaml::from_str::<T>(&content).map_err(|err| err.to_string())?;
-
-    Ok(file_content)
+// Files may hold several documents separated by `---`
+pub fn parse_yaml_documents<T: for<'a> Deserialize<'a>>(content: &str) -> Result<Vec<T>, String> {
+    serde_yaml::Deserializer::from_str(content)
+        .map(T::deserialize)
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|err| err.to_string())
 }
 
 pub fn write_yaml_file<T: Serialize + Debug>(file_path: &PathBuf, data: &T) -> Result<(), String> {
@@ -45,29 +47,247 @@ pub enum TaskEngine {
     COMPOSER,
     NPM,
     YARN,
+    PNPM,
+    CARGO,
+    // go-task, https://taskfile.dev
+    TASKFILE,
+    JUST,
+    // docker compose, https://docs.docker.com/compose
+    COMPOSE,
     NONE,
     #[default]
     AUTO,
 }
 
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFileTask {
+    pub(crate) command: ConfigFileCommand,
+    // What the task is for, rendered by `rask docs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) nice: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_memory: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) allow_failure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) user: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) sudo: bool,
+    // Tasks of the same config, yaml or engine tasks, that run before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) depends_on: Vec<String>,
+    // Tasks that run first when they are part of the run as well, without adding them to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) after: Vec<String>,
+    // Glob patterns relative to the config, rask watch re-runs the task when a matching file changes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) inputs: Vec<String>,
+    // Glob patterns relative to the config of the files the task produces
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) outputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cache: Option<TaskCache>,
+    // Only PATH and the pass_env variables are inherited, the env of the config is still set
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) clean_env: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) pass_env: Vec<String>,
+    // Output past these limits is hidden from the terminal, e.g. 1000 lines or 1M
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_output_lines: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) max_output_bytes: Option<String>,
+    // How the task is stopped on cancellation, e.g. SIGINT and 10s, it is killed once the grace period passes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stop_signal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stop_grace_period: Option<String>,
+    // The platforms the task supports, it is skipped on others, e.g. [linux, macos] and [x86_64]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) os: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) arch: Vec<String>,
+    // Destructive tasks, like resetting a database, only run once the user confirms them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) confirm: bool,
+    // Expensive tasks, like syncing a database, are skipped when they succeeded less than this long ago, e.g. 10m
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cooldown: Option<String>,
+    // Fails the task when a line of its output matches, for tools that exit with 0 on errors, e.g. "ERROR|^FAILED"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) failure_pattern: Option<String>,
+    // The job slots the task takes in parallel runs, heavy builds count as several tasks, 1 when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) weight: Option<u64>,
+}
+
+// How a task decides it has nothing to do
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskCache {
+    // Skipped when the oldest output is newer than the newest input, like make
+    MTIME,
+}
+
+// A single command, or a list of steps that run one after the other
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ConfigFileCommand {
+    String(String),
+    Steps(Vec<String>),
+}
+
+impl Default for ConfigFileCommand {
+    fn default() -> Self {
+        ConfigFileCommand::String(String::new())
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum ConfigFileTaskValue {
     String(String),
+    Steps(Vec<String>),
+    // Must come before the nested tasks, a task is recognised by its `command` field.
+    ConfigFileTask(Box<ConfigFileTask>),
+    // Also before the nested tasks, recognised by its `uses` field
+    TemplateTask(ConfigFileTemplateTask),
     ConfigFileTasks(ConfigFileTasks)
 }
 
 pub type ConfigFileTasks = HashMap<String, ConfigFileTaskValue>;
 
+// A task made from a task template of the entry config, e.g. `uses: docker-build` and `with: {image: api}`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFileTemplateTask {
+    pub(crate) uses: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) with: HashMap<String, String>,
+}
+
+// A reusable task, its ${parameter} placeholders are replaced by the values a config passes with `with`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFileTaskTemplate {
+    // The parameters with their default, parameters without a default have to be passed
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) parameters: HashMap<String, Option<String>>,
+    // A command, steps, a task mapping or nested tasks, like any value under tasks
+    pub(crate) task: ConfigFileTaskValue,
+}
+
+// Glob patterns on the keys of engine tasks, keeps internal scripts out of rask
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EngineTaskFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) exclude: Vec<String>,
+    // Set from the command line, engines outside these task sources are never read
+    #[serde(skip)]
+    pub(crate) sources: Option<Vec<String>>,
+}
+
+impl EngineTaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+}
+
+// A glob pattern of child configs, or one with the order its configs run in, e.g. {path: libs/*, order: 1}
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged, expecting = "a glob pattern, or a mapping with path, order and parallel")]
+pub enum ConfigFileDirectory {
+    Pattern(String),
+    Entry(ConfigFileDirectoryEntry),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFileDirectoryEntry {
+    pub(crate) path: String,
+    // Entries with an order run before the others, the lowest order first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) order: Option<u64>,
+    // With false the matched configs run one after the other, in the order of their paths
+    #[serde(default = "default_parallel", skip_serializing_if = "Clone::clone")]
+    pub(crate) parallel: bool,
+}
+
+fn default_parallel() -> bool {
+    true
+}
+
+impl ConfigFileDirectory {
+    pub fn get_pattern(&self) -> &str {
+        match self {
+            ConfigFileDirectory::Pattern(pattern) => pattern,
+            ConfigFileDirectory::Entry(ConfigFileDirectoryEntry { path, .. }) => path,
+        }
+    }
+
+    pub fn with_pattern(&self, pattern: String) -> ConfigFileDirectory {
+        match self {
+            ConfigFileDirectory::Pattern(_) => ConfigFileDirectory::Pattern(pattern),
+            ConfigFileDirectory::Entry(entry) => ConfigFileDirectory::Entry(ConfigFileDirectoryEntry { path: pattern, ..entry.clone() }),
+        }
+    }
+
+    // The order and whether the configs run side by side, plain patterns have no order and run side by side
+    pub fn get_ordering(&self) -> (Option<u64>, bool) {
+        match self {
+            ConfigFileDirectory::Pattern(_) => (None, true),
+            ConfigFileDirectory::Entry(ConfigFileDirectoryEntry { order, parallel, .. }) => (*order, *parallel),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ConfigFile {
     pub(crate) name: String,
+    // The directory of the config relative to the file, lets the documents of one file describe other directories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) dir: Option<String>,
     #[serde(default, skip_serializing_if = "is_default_task_engine")]
     pub(crate) task_engine: TaskEngine,
+    #[serde(default, skip_serializing_if = "EngineTaskFilter::is_empty")]
+    pub(crate) engine_tasks: EngineTaskFilter,
+    // Directory glob patterns with the engine their configs are forced to use, only read from the entry config
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) engine_overrides: HashMap<String, TaskEngine>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub(crate) directories: Vec<String>,
+    pub(crate) directories: Vec<ConfigFileDirectory>,
     #[serde(default, skip_serializing_if = "ConfigFileTasks::is_empty")]
     pub(crate) tasks: ConfigFileTasks,
+    // Tasks that configs instantiate with `uses`, only read from the entry config
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) task_templates: HashMap<String, ConfigFileTaskTemplate>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) constants: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) env: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) default_task: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) shell: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) before_level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) after_level: Option<String>,
+    // Tasks of this config never run at the same time, also in parallel mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) serial: bool,
+    // Runs the install step of the engine before its tasks when its dependencies are missing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) ensure_installed: bool,
+    // The job slots of parallel runs without --jobs, only read from the entry config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) default_parallelism: Option<u64>,
     // The following fields are not part of the yaml file.
     #[serde(default, skip_serializing_if = "skip_path")]
     pub(crate) __file_path: PathBuf,
@@ -75,6 +295,68 @@ pub struct ConfigFile {
     pub(crate) __dir_path: PathBuf,
 }
 
+// The top level keys of a rask.yaml, kept in sync with ConfigFile for --strict-config
+pub const CONFIG_FILE_KEYS: [&str; 17] = ["name", "dir", "task_engine", "engine_tasks", "engine_overrides", "directories", "tasks", "task_templates", "constants", "env", "default_task", "shell", "before_level", "after_level", "serial", "ensure_installed", "default_parallelism"];
+
+// serde ignores unknown keys, so a typo like `task:` silently drops every task
+pub fn validate_config_file_keys(config_file_path: &Path) -> Result<(), String> {
+    let content = read_file_content(config_file_path.to_path_buf())?;
+
+    let errors: Vec<String> = find_unknown_config_file_keys(&content)?
+        .into_iter()
+        .map(|(key, suggestion)| match suggestion {
+            Some(suggestion) => format!("Unknown key \"{}\" in {:?}, did you mean \"{}\"?", key, config_file_path, suggestion),
+            None => format!("Unknown key \"{}\" in {:?}, expected one of {}", key, config_file_path, CONFIG_FILE_KEYS.join(", ")),
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
+}
+
+// The unknown top level keys of every document, deprecated keys are warned about instead, with the known key they are likely a typo of
+pub fn find_unknown_config_file_keys(content: &str) -> Result<Vec<(String, Option<&'static str>)>, String> {
+    let documents: Vec<serde_yaml::Value> = parse_yaml_documents(content)?;
+
+    Ok(documents
+        .iter()
+        .filter_map(|document| document.as_mapping())
+        .flat_map(|mapping| mapping.keys())
+        .filter_map(|key| key.as_str())
+        .filter(|key| !CONFIG_FILE_KEYS.contains(key) && !deprecation::is_deprecated_config_key(key))
+        .map(|key| (key.to_string(), suggest_config_file_key(key)))
+        .collect())
+}
+
+fn suggest_config_file_key(key: &str) -> Option<&'static str> {
+    CONFIG_FILE_KEYS
+        .into_iter()
+        .map(|known_key| (known_key, edit_distance(key, known_key)))
+        .filter(|(_, distance)| *distance <= 2)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(known_key, _)| known_key)
+}
+
+// Levenshtein distance, the keys are short so the full table is fine
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current: Vec<usize> = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
 fn is_default_task_engine(value: &TaskEngine) -> bool {
     match value {
         // Changing the default is generally discouraged.
@@ -83,23 +365,117 @@ fn is_default_task_engine(value: &TaskEngine) -> bool {
     }
 }
 
-fn skip_path(path: &PathBuf) -> bool {
-    path.to_str().map_or(true, |s| s.is_empty())
+fn skip_path(path: &Path) -> bool {
+    path.to_str().is_none_or(|s| s.is_empty())
 }
 
+pub fn get_parent_directory(path: &Path) -> Result<&Path, String> {
+    path.parent().ok_or(format!("Failed to get the parent directory of {:?}", path))
+}
+
+// Glob patterns are matched as strings, so paths that aren't UTF-8 can't be used in them
+pub fn path_to_str(path: &Path) -> Result<&str, String> {
+    path.to_str().ok_or(format!("The path {:?} is not valid UTF-8", path))
+}
+
+// The first document of a config file
 pub fn read_config_file(config_file_path: PathBuf) -> Result<ConfigFile, String> {
-    let mut config_file = read_yaml_file::<ConfigFile>(&config_file_path)?;
+    read_config_files(config_file_path.clone())?
+        .into_iter()
+        .next()
+        .ok_or(format!("No config found in {:?}", config_file_path))
+}
+
+// Every document of a config file is a config, the ones after the first are known by their name, like rask.yaml#api
+pub fn read_config_files(config_file_path: PathBuf) -> Result<Vec<ConfigFile>, String> {
+    let content = read_file_content(config_file_path.clone())?;
 
-    config_file.__file_path = config_file_path.clone();
-    config_file.__dir_path = config_file_path.parent().unwrap().to_path_buf();
+    parse_config_documents(&content, config_file_path)
+}
+
+// Editors hand over the content they hold, which may differ from the file on disk
+pub fn parse_config_documents(content: &str, config_file_path: PathBuf) -> Result<Vec<ConfigFile>, String> {
+    parse_config_documents_with(content, config_file_path, &DEPRECATIONS)
+}
+
+// Removed fields are dropped before the documents are read, tasks deny unknown fields and would not parse with them
+pub fn parse_config_documents_with(content: &str, config_file_path: PathBuf, deprecations: &[Deprecation]) -> Result<Vec<ConfigFile>, String> {
+    let mut config_files: Vec<ConfigFile> = match deprecation::strip_removed_keys(content, deprecations)? {
+        Some(documents) => documents.into_iter().map(ConfigFile::deserialize).collect::<Result<Vec<ConfigFile>, _>>().map_err(|err| err.to_string())?,
+        None => parse_yaml_documents(content)?,
+    };
+    let file_directory: &Path = get_parent_directory(&config_file_path)?;
+
+    for (index, config_file) in config_files.iter_mut().enumerate() {
+        config_file.__file_path = match index {
+            0 => config_file_path.clone(),
+            _ => get_document_path(&config_file_path, &config_file.name),
+        };
+        config_file.__dir_path = match &config_file.dir {
+            Some(dir) => canonicalize(file_directory.join(dir)).map_err(|err| format!("Failed to resolve the dir {:?} of config \"{}\": {}", dir, config_file.name, err))?,
+            None => file_directory.to_path_buf(),
+        };
+    }
 
-    Ok(config_file)
+    Ok(config_files)
+}
+
+pub fn get_document_path(config_file_path: &Path, name: &str) -> PathBuf {
+    let mut document_path = config_file_path.as_os_str().to_os_string();
+    document_path.push(format!("#{}", name));
+
+    PathBuf::from(document_path)
+}
+
+// The file a document path like rask.yaml#api points into, none for the path of a file
+pub fn get_document_file(document_path: &Path) -> Option<PathBuf> {
+    let (file_name, _) = document_path.file_name()?.to_str()?.split_once('#')?;
+
+    Some(document_path.with_file_name(file_name))
 }
 
 pub fn write_config_file(config_file_path: PathBuf, config_file: ConfigFile) -> Result<(), String> {
     write_yaml_file::<ConfigFile>(&config_file_path, &config_file)
 }
 
+// Edits the yaml text instead of re-serializing the config, so comments and key order survive.
+// Returns none for layouts it does not recognise, like a flow list followed by a comment.
+pub fn add_config_directory(content: &str, directory: &str) -> Option<String> {
+    let mut lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+
+    let Some(key_index) = lines.iter().position(|line| line.starts_with("directories:")) else {
+        lines.extend(["directories:".to_string(), format!("  - {}", directory)]);
+        return Some(lines.join("\n") + "\n");
+    };
+
+    let value = lines[key_index]["directories:".len()..].trim().to_string();
+    if value.starts_with('[') && value.ends_with(']') {
+        let items = value[1..value.len() - 1].trim();
+        lines[key_index] = match items.is_empty() {
+            true => format!("directories: [{}]", directory),
+            false => format!("directories: [{}, {}]", items, directory),
+        };
+    } else if value.is_empty() || value.starts_with('#') {
+        // The new item goes after the last item of the block list, with the same indentation
+        let mut last_item_index = key_index;
+        let mut indentation = "  ".to_string();
+        for (index, line) in lines.iter().enumerate().skip(key_index + 1) {
+            let trimmed_line = line.trim_start();
+            if trimmed_line.starts_with("- ") {
+                last_item_index = index;
+                indentation = line[..line.len() - trimmed_line.len()].to_string();
+            } else if !trimmed_line.is_empty() && !trimmed_line.starts_with('#') {
+                break;
+            }
+        }
+        lines.insert(last_item_index + 1, format!("{}- {}", indentation, directory));
+    } else {
+        return None;
+    }
+
+    Some(lines.join("\n") + "\n")
+}
+
 pub fn parse_path_string<P: AsRef<Path> + Debug + Clone + Copy>(path: P) -> Result<PathBuf, String> {
     let full_path = match canonicalize(path) {
         Ok(full_path) => full_path,
@@ -109,3 +485,42 @@ pub fn parse_path_string<P: AsRef<Path> + Debug + Clone + Copy>(path: P) -> Resu
     Ok(full_path)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_directories(content: &str) -> Vec<String> {
+        serde_yaml::from_str::<ConfigFile>(content).unwrap().directories.iter().map(|directory| directory.get_pattern().to_string()).collect()
+    }
+
+    #[test]
+    fn adds_directories_to_block_lists() {
+        let content = "name: root\n# Packages\ndirectories:\n    - apps/*\n    # Libraries\n    - libs/*\ntasks:\n  build: make\n";
+
+        let content = add_config_directory(content, "tools/cli").unwrap();
+
+        assert_eq!(content, "name: root\n# Packages\ndirectories:\n    - apps/*\n    # Libraries\n    - libs/*\n    - tools/cli\ntasks:\n  build: make\n");
+        assert_eq!(parse_directories(&content), vec!["apps/*", "libs/*", "tools/cli"]);
+    }
+
+    #[test]
+    fn adds_directories_to_flow_lists_and_configs_without_them() {
+        assert_eq!(parse_directories(&add_config_directory("name: root\ndirectories: [apps/*]\n", "tools/cli").unwrap()), vec!["apps/*", "tools/cli"]);
+        assert_eq!(parse_directories(&add_config_directory("name: root\ndirectories: []\n", "tools/cli").unwrap()), vec!["tools/cli"]);
+        assert_eq!(parse_directories(&add_config_directory("name: root", "tools/cli").unwrap()), vec!["tools/cli"]);
+        assert_eq!(add_config_directory("name: root\ndirectories: [apps/*] # packages\n", "tools/cli"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn describes_paths_that_can_not_be_used() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(get_parent_directory(Path::new("/app/rask.yaml")), Ok(Path::new("/app")));
+        assert_eq!(get_parent_directory(Path::new("/")), Err("Failed to get the parent directory of \"/\"".to_string()));
+
+        assert_eq!(path_to_str(Path::new("app/*")), Ok("app/*"));
+        assert!(path_to_str(Path::new(OsStr::from_bytes(b"app/\xff"))).unwrap_err().ends_with("is not valid UTF-8"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::SystemTime;
+use crate::utils::config::Task;
+use crate::utils::file::TaskCache;
+use crate::utils::watch;
+use crate::utils::watch::FileSnapshot;
+
+// Whether a task with a cache can be skipped, when unsure it is not
+pub fn is_up_to_date(task: &Task) -> bool {
+    match task.options.cache {
+        Some(TaskCache::MTIME) => are_outputs_newer(task).unwrap_or(false),
+        None => false,
+    }
+}
+
+// Every output pattern has to match a file, a missing output always means the task has to run
+fn are_outputs_newer(task: &Task) -> Result<bool, String> {
+    let Task { inputs, outputs, .. } = task;
+
+    let mut oldest_output: Option<SystemTime> = None;
+    for output in outputs {
+        let output_snapshot: FileSnapshot = watch::snapshot_files([output].into_iter())?;
+        let Some(modified) = output_snapshot.into_values().min() else {
+            return Ok(false);
+        };
+        oldest_output = Some(oldest_output.map_or(modified, |oldest_output| oldest_output.min(modified)));
+    }
+
+    let newest_input: Option<SystemTime> = watch::snapshot_files(inputs.iter())?.into_values().max();
+
+    Ok(match (oldest_output, newest_input) {
+        (Some(oldest_output), Some(newest_input)) => oldest_output > newest_input,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+    use crate::testing::TestWorkspace;
+    use crate::utils::config::resolve_sortable_task;
+
+    fn load_task(workspace: &TestWorkspace) -> Task {
+        resolve_sortable_task(&workspace.load_structure("."), "build", &true).unwrap().remove(0).task
+    }
+
+    fn touch(workspace: &TestWorkspace, path: &str, age: u64) {
+        let file = File::create(workspace.path(path)).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age)).unwrap();
+    }
+
+    #[test]
+    fn skips_when_every_output_is_newer_than_the_inputs() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  build:\n    command: make\n    cache: mtime\n    inputs: ['src/**']\n    outputs: [dist/app.js, dist/app.css]\n")
+            .with_file("src/app.ts", "")
+            .with_file("dist/app.js", "");
+        touch(&workspace, "src/app.ts", 60);
+        touch(&workspace, "dist/app.js", 30);
+
+        // dist/app.css is missing
+        assert!(!is_up_to_date(&load_task(&workspace)));
+
+        touch(&workspace, "dist/app.css", 90);
+        assert!(!is_up_to_date(&load_task(&workspace)));
+
+        touch(&workspace, "dist/app.css", 0);
+        assert!(is_up_to_date(&load_task(&workspace)));
+    }
+
+    #[test]
+    fn tasks_without_a_cache_always_run() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  build:\n    command: make\n    inputs: [src/app.ts]\n    outputs: [dist/app.js]\n")
+            .with_file("src/app.ts", "")
+            .with_file("dist/app.js", "");
+
+        assert!(!is_up_to_date(&load_task(&workspace)));
+    }
+}
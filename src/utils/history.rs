@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::utils::config::Task;
+use crate::utils::report::{TaskResult, TaskStatus};
+use crate::utils::{file, state};
+
+const HISTORY_FILE: &str = "durations.json";
+
+// New durations weigh in for a third, so a single slow run doesn't throw off the schedule
+const SMOOTHING: f64 = 1.0 / 3.0;
+
+// How long tasks took in previous runs, kept in the state directory of the entry config
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DurationHistory {
+    #[serde(skip)]
+    path: PathBuf,
+    // Seconds, by the directory and key of a task
+    durations: HashMap<String, f64>,
+}
+
+impl DurationHistory {
+    pub fn get(&self, task: &Task) -> Option<f64> {
+        self.durations.get(&get_history_key(&task.key, &task.directory)).copied()
+    }
+
+    // Skipped, terminated, cancelled and deduplicated tasks say nothing about how long a task takes
+    pub fn record(&mut self, task_results: &[TaskResult]) {
+        for TaskResult { key, directory, status, duration, deduplicated, .. } in task_results {
+            if *deduplicated || matches!(status, TaskStatus::SKIPPED | TaskStatus::TIMEOUT | TaskStatus::CANCELLED) {
+                continue;
+            }
+
+            let history_key = get_history_key(key, directory);
+            let smoothed_duration = match self.durations.get(&history_key) {
+                Some(previous_duration) => previous_duration + (duration - previous_duration) * SMOOTHING,
+                None => *duration,
+            };
+            self.durations.insert(history_key, smoothed_duration);
+        }
+    }
+
+    // Longest first packs parallel levels best, tasks without history go first since they could take any time
+    pub fn sort_longest_first(&self, tasks: &mut [Task]) {
+        tasks.sort_by(|a, b| {
+            let a_duration = self.get(a).unwrap_or(f64::INFINITY);
+            let b_duration = self.get(b).unwrap_or(f64::INFINITY);
+            b_duration.total_cmp(&a_duration)
+        });
+    }
+
+    // Levels run one after the other, a level takes as long as its longest task or its share of the jobs, none without a full history
+    pub fn estimate(&self, levels: &[Vec<&Task>], concurrency: Option<usize>) -> Option<f64> {
+        let mut estimate: f64 = 0.0;
+
+        for level in levels {
+            let durations: Vec<f64> = level.iter().map(|task| self.get(task)).collect::<Option<Vec<f64>>>()?;
+            let total: f64 = durations.iter().sum();
+            let longest: f64 = durations.iter().copied().fold(0.0, f64::max);
+
+            estimate += match concurrency {
+                // Heavy tasks take several job slots, which leaves less room for the other tasks of the level
+                Some(concurrency) => {
+                    let slots: Vec<f64> = level.iter().map(|task| task.options.get_weight().clamp(1, concurrency.max(1)) as f64).collect();
+                    let work: f64 = durations.iter().zip(&slots).map(|(duration, slots)| duration * slots).sum();
+                    longest.max(work / slots.iter().sum::<f64>().min(concurrency as f64).max(1.0))
+                },
+                None => total,
+            };
+        }
+
+        Some(estimate)
+    }
+}
+
+fn get_history_key(key: &str, directory: &Path) -> String {
+    format!("{}#{}", directory.to_string_lossy(), key)
+}
+
+// A missing or broken history starts over, it only ever improves the schedule
+pub fn read_history(entry_directory: &Path) -> DurationHistory {
+    let path = entry_directory.join(state::STATE_DIRECTORY).join(HISTORY_FILE);
+    let history = file::read_json_file::<DurationHistory>(&path).unwrap_or_default();
+
+    DurationHistory { path, ..history }
+}
+
+pub fn write_history(history: &DurationHistory) -> Result<(), String> {
+    let entry_directory = history.path.parent().and_then(Path::parent).ok_or("Failed to get the entry directory of the history")?;
+    state::get_state_directory(entry_directory)?;
+
+    let content = serde_json::to_string_pretty(history).map_err(|err| err.to_string())?;
+    file::write_file_content(&history.path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use crate::testing::create_task;
+    use crate::utils::config::TaskOptions;
+
+    fn create_result(key: &str, status: TaskStatus, duration: f64) -> TaskResult {
+        TaskResult { key: key.to_string(), directory: temp_dir(), source: "yaml", status, error: None, failure: None, hint: None, output: vec![], duration, resource_usage: None, deduplicated: false }
+    }
+
+    #[test]
+    fn smooths_recorded_durations() {
+        let mut history = DurationHistory::default();
+
+        history.record(&[create_result("build", TaskStatus::SUCCESS, 9.0), create_result("lint", TaskStatus::SKIPPED, 0.0)]);
+        history.record(&[create_result("build", TaskStatus::FAILURE, 3.0)]);
+
+        assert_eq!(history.get(&create_task("build", "true")), Some(7.0));
+        assert_eq!(history.get(&create_task("lint", "true")), None);
+    }
+
+    #[test]
+    fn schedules_unknown_and_long_tasks_first() {
+        let mut history = DurationHistory::default();
+        history.record(&[create_result("lint", TaskStatus::SUCCESS, 1.0), create_result("test", TaskStatus::SUCCESS, 8.0), create_result("build", TaskStatus::SUCCESS, 4.0)]);
+
+        let mut tasks = vec![create_task("lint", "true"), create_task("build", "true"), create_task("docs", "true"), create_task("test", "true")];
+        history.sort_longest_first(&mut tasks);
+        assert_eq!(tasks.iter().map(|task| task.key.as_str()).collect::<Vec<&str>>(), vec!["docs", "test", "build", "lint"]);
+
+        let (lint, test, build) = (create_task("lint", "true"), create_task("test", "true"), create_task("build", "true"));
+        let levels: Vec<Vec<&Task>> = vec![vec![&lint, &test], vec![&build]];
+        assert_eq!(history.estimate(&levels, None), Some(13.0));
+        assert_eq!(history.estimate(&levels, Some(2)), Some(12.0));
+
+        // A build taking both slots leaves less room for lint, without a limit they run side by side
+        let heavy_build = Task { options: TaskOptions { weight: Some(2), ..TaskOptions::default() }, ..create_task("build", "true") };
+        assert_eq!(history.estimate(&[vec![&lint, &build]], Some(2)), Some(4.0));
+        assert_eq!(history.estimate(&[vec![&lint, &heavy_build]], Some(2)), Some(4.5));
+        assert_eq!(history.estimate(&[vec![&lint, &heavy_build]], Some(usize::MAX)), Some(4.0));
+        assert_eq!(history.estimate(&[vec![&tasks[0]]], None), None);
+    }
+}
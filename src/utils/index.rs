@@ -0,0 +1,109 @@
+use std::fs::remove_file;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::utils::config::{Config, ConfigIssues, ConfigTask};
+use crate::utils::{config, file, state};
+
+const INDEX_FILE: &str = "index.json";
+const INDEX_VERSION: u32 = 1;
+
+// A small summary of the workspace, cheap enough to read on every tab completion
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WorkspaceIndex {
+    pub version: u32,
+    pub entry: PathBuf,
+    pub tasks: Vec<String>,
+    pub configs: Vec<String>,
+}
+
+fn create_workspace_index(entry_config_path: &Path, configs: &[Config]) -> WorkspaceIndex {
+    let mut tasks: Vec<String> = configs
+        .iter()
+        .flat_map(|config| config.tasks.iter().map(|ConfigTask { key, .. }| key.clone()))
+        .collect();
+    tasks.sort();
+    tasks.dedup();
+
+    let mut config_names: Vec<String> = configs.iter().map(|config| config.name.clone()).collect();
+    config_names.sort();
+    config_names.dedup();
+
+    WorkspaceIndex {
+        version: INDEX_VERSION,
+        entry: entry_config_path.to_path_buf(),
+        tasks,
+        configs: config_names,
+    }
+}
+
+pub fn write_workspace_index(entry_config_path: &Path, configs: &[Config]) -> Result<(), String> {
+    let entry_directory = entry_config_path.parent().ok_or("Failed to get parent directory")?;
+
+    let index = create_workspace_index(entry_config_path, configs);
+    let content = serde_json::to_string(&index).map_err(|err| err.to_string())?;
+    file::write_file_content(&state::get_state_directory(entry_directory)?.join(INDEX_FILE), &content)
+}
+
+// Single packages are never indexed, their engine file is read instead since it is the only file there is
+pub fn read_workspace_index(start_directory: &Path) -> Option<WorkspaceIndex> {
+    let Some(index_path) = state::find_state_file(start_directory, INDEX_FILE) else {
+        return read_single_package_index(start_directory);
+    };
+    let index = file::read_json_file::<WorkspaceIndex>(&index_path).ok()?;
+
+    // An index without its rask.yaml was written before the workspace became a single package, or by a rask that indexed those
+    if !index.entry.exists() {
+        let _ = remove_file(&index_path);
+        return read_single_package_index(start_directory);
+    }
+
+    (index.version == INDEX_VERSION).then_some(index)
+}
+
+fn read_single_package_index(directory: &Path) -> Option<WorkspaceIndex> {
+    // Workspaces are only completed once `list` or `run` indexed them, discovering them on every tab takes too long
+    if config::resolve_config_path(directory).is_ok() {
+        return None;
+    }
+
+    let mut config_issues = ConfigIssues::new(false);
+    let (entry_config_path, config_files) = config::read_entry_config_files(&directory.to_string_lossy(), false, &mut config_issues).ok()?;
+    let configs: Vec<Config> = config::parse_config_files(config_files, &entry_config_path, &mut config_issues).ok()?;
+
+    config::is_single_package(&entry_config_path, &configs).then(|| create_workspace_index(&entry_config_path, &configs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn reads_the_written_index_from_below_the_entry() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [app]\ntasks:\n  test: cargo test\n  build: cargo build\n")
+            .with_config("app", "name: app\ntasks:\n  build: make\n")
+            .with_file("app/src/main.c", "");
+
+        write_workspace_index(&workspace.config_path("."), &workspace.load_configs(".")).unwrap();
+        let index = read_workspace_index(&workspace.path("app/src")).unwrap();
+        assert_eq!((index.tasks, index.configs), (vec!["build".to_string(), "test".to_string()], vec!["app".to_string(), "root".to_string()]));
+
+        // An index of another rask version is not trusted
+        let outdated_index = WorkspaceIndex { version: INDEX_VERSION + 1, ..create_workspace_index(&workspace.config_path("."), &[]) };
+        let workspace = workspace.with_file(".rask/index.json", &serde_json::to_string(&outdated_index).unwrap());
+        assert!(read_workspace_index(&workspace.path("app")).is_none());
+    }
+
+    #[test]
+    fn ignores_the_stale_index_of_a_single_package() {
+        let workspace = TestWorkspace::new().with_package_json(".", &["build", "test"]);
+        let stale_index = WorkspaceIndex { version: INDEX_VERSION, entry: workspace.path("rask.yaml"), tasks: vec!["removed".to_string()], configs: vec![] };
+        let workspace = workspace.with_file(".rask/index.json", &serde_json::to_string(&stale_index).unwrap());
+
+        let index = read_workspace_index(&workspace.path(".")).unwrap();
+
+        assert_eq!(index.tasks, vec!["build", "test"]);
+        assert!(!workspace.path(".rask/index.json").exists());
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs::metadata;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::utils::config::{Task, TaskType};
+use crate::utils::{executor, file, state};
+use crate::utils::output;
+use crate::utils::output::{Tone, Verbosity};
+
+// What an engine installs into and how, for configs with `ensure_installed: true`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallStep {
+    pub command: &'static str,
+    // The directory the install step creates, its absence means nothing is installed.
+    // None for steps that install outside the project, like cargo fetch, they leave a stamp in the state directory instead.
+    pub marker: Option<&'static str>,
+    pub lock_file: &'static str,
+}
+
+pub fn get_install_step(task_type: &TaskType) -> Option<InstallStep> {
+    match task_type {
+        TaskType::NPM => Some(InstallStep { command: "npm install", marker: Some("node_modules"), lock_file: "package-lock.json" }),
+        TaskType::YARN => Some(InstallStep { command: "yarn install", marker: Some("node_modules"), lock_file: "yarn.lock" }),
+        TaskType::PNPM => Some(InstallStep { command: "pnpm install", marker: Some("node_modules"), lock_file: "pnpm-lock.yaml" }),
+        TaskType::COMPOSER => Some(InstallStep { command: "composer install", marker: Some("vendor"), lock_file: "composer.lock" }),
+        TaskType::CARGO => Some(InstallStep { command: "cargo fetch", marker: None, lock_file: "Cargo.lock" }),
+        TaskType::SHELL | TaskType::TASKFILE | TaskType::JUST | TaskType::COMPOSE => None,
+    }
+}
+
+// Written once a step without a marker installed, next to the config
+const INSTALL_STAMP_FILE: &str = "installed";
+
+fn get_marker_path(directory: &Path, install_step: &InstallStep) -> PathBuf {
+    match install_step.marker {
+        Some(marker) => directory.join(marker),
+        None => directory.join(state::STATE_DIRECTORY).join(INSTALL_STAMP_FILE),
+    }
+}
+
+// Why the install step has to run, a lock file changed after the last install counts as missing
+pub fn find_install_reason(directory: &Path, install_step: &InstallStep) -> Option<String> {
+    let InstallStep { marker, lock_file, .. } = install_step;
+    let modified = |path: PathBuf| -> Option<SystemTime> { metadata(path).and_then(|metadata| metadata.modified()).ok() };
+
+    let Some(installed_at) = modified(get_marker_path(directory, install_step)) else {
+        return Some(match marker {
+            Some(marker) => format!("{} is missing", marker),
+            None => "it was never installed".to_string(),
+        });
+    };
+
+    match modified(directory.join(lock_file)) {
+        Some(locked_at) if locked_at > installed_at => Some(format!("{} changed since the last install", lock_file)),
+        _ => None,
+    }
+}
+
+// Every directory installs once, however many of its tasks are planned
+pub fn ensure_installed(tasks: &[&Task], verbosity: Verbosity) -> Result<(), String> {
+    let mut handled_directories: Vec<(&PathBuf, &InstallStep)> = vec![];
+
+    for task in tasks {
+        let Some(install_step) = &task.install else {
+            continue;
+        };
+        if handled_directories.contains(&(&task.directory, install_step)) {
+            continue;
+        }
+        handled_directories.push((&task.directory, install_step));
+
+        if let Some(reason) = find_install_reason(&task.directory, install_step) {
+            if verbosity != Verbosity::SUMMARY {
+                output::line("INSTALL", Tone::INFO, &format!("{} @ {:?}, {}", install_step.command, task.directory, reason));
+            }
+
+            let install_task = Task { key: "install".to_string(), command: install_step.command.to_string(), steps: vec![], dependencies: vec![], inputs: vec![], outputs: vec![], install: None, ..(*task).clone() };
+            executor::execute_task(install_task, verbosity).map_err(|err| format!("Failed to install the dependencies of {:?} with {}: {}", task.directory, install_step.command, err))?;
+            if install_step.marker.is_none() {
+                write_install_stamp(&task.directory)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Rewritten on every install, so its modification time is the last install
+fn write_install_stamp(directory: &Path) -> Result<(), String> {
+    let state_directory: PathBuf = state::get_state_directory(directory)?;
+
+    file::write_file_content(&state_directory.join(INSTALL_STAMP_FILE), "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn installs_when_the_marker_is_missing_or_outdated() {
+        let workspace = TestWorkspace::new().with_package_json(".", &["build"]);
+        let install_step = get_install_step(&TaskType::NPM).unwrap();
+
+        assert_eq!(find_install_reason(&workspace.path("."), &install_step), Some("node_modules is missing".to_string()));
+
+        std::fs::create_dir(workspace.path("node_modules")).unwrap();
+        assert_eq!(find_install_reason(&workspace.path("."), &install_step), None);
+
+        let lock_file = File::create(workspace.path("package-lock.json")).unwrap();
+        lock_file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert_eq!(find_install_reason(&workspace.path("."), &install_step), Some("package-lock.json changed since the last install".to_string()));
+    }
+
+    #[test]
+    fn installs_steps_without_a_marker_until_they_leave_a_stamp() {
+        let workspace = TestWorkspace::new().with_file("Cargo.toml", "[package]\nname = \"app\"\n").with_file("target/debug/app", "");
+        let install_step = get_install_step(&TaskType::CARGO).unwrap();
+
+        // Building creates target, that does not fetch the dependencies
+        assert_eq!(find_install_reason(&workspace.path("."), &install_step), Some("it was never installed".to_string()));
+
+        write_install_stamp(&workspace.path(".")).unwrap();
+        assert_eq!(find_install_reason(&workspace.path("."), &install_step), None);
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+pub type Variables = HashMap<String, String>;
+
+pub fn interpolate(value: &str, variables: &Variables) -> String {
+    replace_placeholders(value, value, variables)
+}
+
+// Like the shell, a `${name}` within single quotes is left as it is
+pub fn interpolate_command(command: &str, variables: &Variables) -> String {
+    replace_placeholders(command, &blank_single_quotes(command), variables)
+}
+
+// The placeholders are searched for in a copy of the value of the same length, with the spans to leave alone blanked out
+fn replace_placeholders(value: &str, searched: &str, variables: &Variables) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut position: usize = 0;
+
+    while let Some(start) = searched[position..].find("${").map(|start| position + start) {
+        result.push_str(&value[position..start]);
+
+        match searched[start + 2..].find('}').map(|end| start + 2 + end) {
+            Some(end) => {
+                match variables.get(&value[start + 2..end]) {
+                    Some(variable) => result.push_str(variable),
+                    // Unknown placeholders are left for the shell to expand.
+                    None => result.push_str(&value[start..=end]),
+                }
+                position = end + 1;
+            }
+            None => {
+                position = start;
+                break;
+            }
+        }
+    }
+    result.push_str(&value[position..]);
+
+    result
+}
+
+// Replaces the single-quoted spans of a command, quotes included, with as many spaces as they take bytes
+pub fn blank_single_quotes(command: &str) -> String {
+    let (mut blanked, mut in_single_quotes, mut in_double_quotes, mut escaped) = (String::with_capacity(command.len()), false, false, false);
+    for char in command.chars() {
+        match (char, in_single_quotes, escaped) {
+            ('\'', _, false) if !in_double_quotes => in_single_quotes = !in_single_quotes,
+            (_, true, _) => {},
+            ('\\', _, false) => escaped = true,
+            _ => {
+                in_double_quotes ^= char == '"' && !escaped;
+                escaped = false;
+            },
+        }
+        match in_single_quotes || char == '\'' {
+            true => blanked.extend(std::iter::repeat_n(' ', char.len_utf8())),
+            false => blanked.push(char),
+        }
+    }
+
+    blanked
+}
+
+// Like interpolate, with `${name:-default}` for missing values, and without a shell to leave unknown names to
+pub fn interpolate_strict(value: &str, variables: &Variables) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after_start = &rest[start + 2..];
+        let end = after_start.find('}').ok_or(format!("Unclosed placeholder in {:?}", value))?;
+
+        let (name, default) = match after_start[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&after_start[..end], None),
+        };
+        // Like the shell, an empty value falls back to the default as well
+        match (variables.get(name).filter(|variable| default.is_none() || !variable.is_empty()), default) {
+            (Some(variable), _) => result.push_str(variable),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => return Err(format!("${{{}}} is not set", name)),
+        }
+        rest = &after_start[end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+pub fn merge_variables(inherited: &Variables, own: &Variables) -> Variables {
+    let mut variables: Variables = inherited.clone();
+    variables.extend(own.clone());
+
+    variables
+}
+
+pub fn resolve_variables(variables: &Variables) -> Result<Variables, String> {
+    let cyclic_names: Vec<&String> = find_cyclic_names(variables);
+    if !cyclic_names.is_empty() {
+        let placeholders: Vec<String> = cyclic_names.iter().map(|name| format!("${{{}}}", name)).collect();
+        return Err(format!("{} refer back to themselves", placeholders.join(", ")));
+    }
+
+    // Constants may be computed from other constants, resolve until stable.
+    // Without cycles every chain of references is resolved within a pass per constant.
+    let mut variables: Variables = variables.clone();
+    for _ in 0..=variables.len() {
+        let resolved: Variables = variables
+            .iter()
+            .map(|(key, value)| (key.clone(), interpolate(value, &variables)))
+            .collect();
+
+        if resolved == variables {
+            break;
+        }
+        variables = resolved;
+    }
+
+    Ok(variables)
+}
+
+// The names of the other variables a value has a `${name}` of
+fn find_references<'a>(value: &str, variables: &'a Variables) -> Vec<&'a String> {
+    value
+        .split("${")
+        .skip(1)
+        .filter_map(|rest| rest.find('}').map(|end| &rest[..end]))
+        .filter_map(|name| variables.get_key_value(name).map(|(name, _)| name))
+        .collect()
+}
+
+// The variables that refer back to themselves, directly or through others, sorted to report them the same way every time
+fn find_cyclic_names(variables: &Variables) -> Vec<&String> {
+    let mut cyclic_names: Vec<&String> = variables
+        .iter()
+        .filter(|(name, value)| {
+            let mut visited: Vec<&String> = vec![];
+            let mut pending: Vec<&String> = find_references(value, variables);
+            while let Some(reference) = pending.pop() {
+                if reference == *name {
+                    return true;
+                }
+                if !visited.contains(&reference) {
+                    visited.push(reference);
+                    pending.extend(find_references(&variables[reference], variables));
+                }
+            }
+
+            false
+        })
+        .map(|(name, _)| name)
+        .collect();
+    cyclic_names.sort();
+
+    cyclic_names
+}
+
+pub fn interpolate_variables(values: &Variables, variables: &Variables) -> Variables {
+    values
+        .iter()
+        .map(|(key, value)| (key.clone(), interpolate(value, variables)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_constants_computed_from_others() {
+        let constants = Variables::from([
+            ("IMAGE".to_string(), "${REGISTRY}/${NAME}:${TAG}".to_string()),
+            ("REGISTRY".to_string(), "ghcr.io/${ORG}".to_string()),
+            ("ORG".to_string(), "rask".to_string()),
+            ("NAME".to_string(), "api".to_string()),
+            ("TAG".to_string(), "${HOME}".to_string()),
+        ]);
+
+        assert_eq!(resolve_variables(&constants).unwrap()["IMAGE"], "ghcr.io/rask/api:${HOME}");
+    }
+
+    #[test]
+    fn cyclic_constants_are_errors() {
+        let constants = Variables::from([
+            ("A".to_string(), "${B}".to_string()),
+            ("B".to_string(), "x${A}".to_string()),
+            ("C".to_string(), "${A}".to_string()),
+            ("D".to_string(), "${D}".to_string()),
+        ]);
+
+        // C only refers to the cycle, it is not part of it
+        assert_eq!(resolve_variables(&constants), Err("${A}, ${B}, ${D} refer back to themselves".to_string()));
+    }
+
+    #[test]
+    fn commands_keep_single_quoted_placeholders() {
+        let variables = Variables::from([("ORG".to_string(), "rask".to_string())]);
+
+        assert_eq!(interpolate("echo '${ORG}'", &variables), "echo 'rask'");
+        assert_eq!(interpolate_command("echo '${ORG}' \"${ORG} '${ORG}'\" \\'${ORG} ${HOME}", &variables), "echo '${ORG}' \"rask 'rask'\" \\'rask ${HOME}");
+        assert_eq!(interpolate_command("echo 'café' ${ORG} ${unclosed", &variables), "echo 'café' rask ${unclosed");
+    }
+}
@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::read_dir;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::utils::{build_info, config, deprecation, file};
+use crate::utils::config::ConfigIssues;
+use crate::utils::deprecation::DEPRECATIONS;
+use crate::utils::file::ConfigFile;
+
+// Offered as a code lens on every task without confirm, the server runs it so editors only need a generic client
+const RUN_TASK_COMMAND: &str = "rask.runTask";
+
+// Codes and kinds of https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/
+const METHOD_NOT_FOUND: i64 = -32601;
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const MESSAGE_ERROR: u8 = 1;
+const MESSAGE_INFO: u8 = 3;
+const KIND_FUNCTION: u8 = 3;
+const KIND_PROPERTY: u8 = 10;
+const KIND_VALUE: u8 = 12;
+const KIND_FOLDER: u8 = 19;
+
+// Messages are JSON bodies behind a Content-Length header, None once the editor closes the stream
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut content_length: Option<usize> = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(|err| format!("Failed to read a message header: {}", err))? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let content_length: usize = content_length.ok_or("A message arrived without a Content-Length header")?;
+    let mut body: Vec<u8> = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|err| format!("Failed to read a message: {}", err))?;
+
+    serde_json::from_slice(&body).map(Some).map_err(|err| format!("Failed to parse a message: {}", err))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<(), String> {
+    let body: String = message.to_string();
+
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| writer.flush())
+        .map_err(|err| format!("Failed to write a message: {}", err))
+}
+
+// Tasks started from a code lens report back from their own thread, the lock keeps messages whole
+fn send(message: &Value) {
+    let _ = write_message(&mut io::stdout().lock(), message);
+}
+
+fn send_notification(method: &str, params: Value) {
+    send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+// Serves one editor over stdin and stdout until it sends exit
+pub fn serve() -> Result<(), String> {
+    let mut reader = io::stdin().lock();
+    // The content of the open documents by uri, as the editor holds them
+    let mut documents: HashMap<String, String> = HashMap::new();
+
+    while let Some(message) = read_message(&mut reader)? {
+        let method: &str = message["method"].as_str().unwrap_or_default();
+        let params: &Value = &message["params"];
+        let uri: String = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+
+        let result: Option<Value> = match method {
+            "initialize" => Some(get_capabilities()),
+            "shutdown" => Some(Value::Null),
+            "exit" => return Ok(()),
+            "textDocument/didOpen" => {
+                documents.insert(uri.clone(), params["textDocument"]["text"].as_str().unwrap_or_default().to_string());
+                publish_diagnostics(&uri, &documents);
+                None
+            },
+            // Documents are synced in full, the last change holds the whole content
+            "textDocument/didChange" => {
+                if let Some(text) = params["contentChanges"].as_array().and_then(|changes| changes.last()).and_then(|change| change["text"].as_str()) {
+                    documents.insert(uri.clone(), text.to_string());
+                }
+                publish_diagnostics(&uri, &documents);
+                None
+            },
+            // Directories created since the last change may fix a pattern
+            "textDocument/didSave" => {
+                publish_diagnostics(&uri, &documents);
+                None
+            },
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                send_notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] }));
+                None
+            },
+            "textDocument/completion" => {
+                let (line, character) = (params["position"]["line"].as_u64().unwrap_or_default() as usize, params["position"]["character"].as_u64().unwrap_or_default() as usize);
+                let config_directory: PathBuf = get_document_directory(&uri);
+                Some(json!(get_completions(documents.get(&uri).map(String::as_str).unwrap_or_default(), line, character, &config_directory)))
+            },
+            "textDocument/codeLens" => Some(json!(get_code_lenses(documents.get(&uri).map(String::as_str).unwrap_or_default(), &uri_to_path(&uri)))),
+            "workspace/executeCommand" if params["command"] == RUN_TASK_COMMAND => {
+                let arguments: Vec<&str> = params["arguments"].as_array().map(|arguments| arguments.iter().filter_map(Value::as_str).collect()).unwrap_or_default();
+                if let [config_file_path, address] = arguments[..] {
+                    run_task(Path::new(config_file_path), address);
+                }
+                Some(Value::Null)
+            },
+            _ => None,
+        };
+
+        // Notifications have no id and get no response, unknown requests are answered with an error
+        match (message.get("id"), result) {
+            (Some(id), Some(result)) => send(&json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            (Some(id), None) => send(&json!({ "jsonrpc": "2.0", "id": id, "error": { "code": METHOD_NOT_FOUND, "message": format!("Unsupported method {}", method) } })),
+            (None, _) => {},
+        }
+    }
+
+    Ok(())
+}
+
+fn get_capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+            "completionProvider": { "triggerCharacters": [" ", "/", "[", ","] },
+            "codeLensProvider": { "resolveProvider": false },
+            "executeCommandProvider": { "commands": [RUN_TASK_COMMAND] },
+        },
+        "serverInfo": { "name": "rask", "version": build_info::get_build_info().version },
+    })
+}
+
+fn publish_diagnostics(uri: &str, documents: &HashMap<String, String>) {
+    if let Some(content) = documents.get(uri) {
+        send_notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": get_diagnostics(content, &uri_to_path(uri)) }));
+    }
+}
+
+// Runs the task through the workspace root, so the task gets the constants and env it gets from the command line.
+// Nothing is confirmed for the user, a dependency that asks for confirmation fails the run with a hint to use a terminal.
+fn run_task(config_file_path: &Path, address: &str) {
+    let entry_config_path: PathBuf = find_root_config_path(config_file_path);
+    let (config_file_path, address) = (config_file_path.to_path_buf(), address.to_string());
+
+    thread::spawn(move || {
+        let output = env::current_exe().and_then(|rask| {
+            Command::new(rask)
+                .args(["run", &address, "--entry"])
+                .arg(&entry_config_path)
+                .current_dir(file::get_parent_directory(&config_file_path).unwrap_or(Path::new(".")))
+                // Stdout is the connection to the editor
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .output()
+        });
+
+        let (message_type, message) = match output {
+            Ok(output) if output.status.success() => (MESSAGE_INFO, format!("rask run {} succeeded", address)),
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason: &str = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("it exited with a failure");
+                (MESSAGE_ERROR, format!("rask run {} failed: {}", address, reason))
+            },
+            Err(err) => (MESSAGE_ERROR, format!("Failed to start rask run {}: {}", address, err)),
+        };
+        send_notification("window/showMessage", json!({ "type": message_type, "message": message }));
+    });
+}
+
+fn find_root_config_path(config_file_path: &Path) -> PathBuf {
+    let mut root_config_path: PathBuf = config_file_path.to_path_buf();
+    while let Some(parent_config_path) = root_config_path.parent().and_then(config::find_parent_config_path) {
+        root_config_path = parent_config_path;
+    }
+
+    root_config_path
+}
+
+// Only file uris point at a config, percent escapes are decoded byte by byte
+fn uri_to_path(uri: &str) -> PathBuf {
+    let encoded: &[u8] = uri.strip_prefix("file://").unwrap_or(uri).as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(encoded.len());
+
+    let mut index = 0;
+    while index < encoded.len() {
+        let escaped: Option<u8> = (encoded[index] == b'%')
+            .then(|| encoded.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            None => {
+                decoded.push(encoded[index]);
+                index += 1;
+            },
+        }
+    }
+
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+fn get_document_directory(uri: &str) -> PathBuf {
+    let config_file_path: PathBuf = uri_to_path(uri);
+
+    file::get_parent_directory(&config_file_path).map(Path::to_path_buf).unwrap_or_default()
+}
+
+// A `key: value` line, enough to find tasks and what the cursor is on while the yaml is typed and does not parse
+#[derive(Debug, Clone)]
+struct KeyLine {
+    line: usize,
+    indent: usize,
+    key: String,
+    value: String,
+}
+
+fn get_indent(text: &str) -> usize {
+    text.len() - text.trim_start_matches(' ').len()
+}
+
+fn parse_key_line(line: usize, text: &str) -> Option<KeyLine> {
+    let rest: &str = text.trim_start_matches(' ');
+    if rest.is_empty() || rest.starts_with('#') || rest.starts_with('-') {
+        return None;
+    }
+
+    let (key, after_key): (&str, &str) = match rest.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => {
+            let end: usize = rest[1..].find(quote)? + 1;
+            (&rest[1..end], rest[end + 1..].strip_prefix(':')?)
+        },
+        _ => {
+            // A colon only ends the key when a space or the end of the line follows, like in build:docs: make
+            let end: usize = rest.char_indices().find(|(index, character)| *character == ':' && rest[index + 1..].chars().next().is_none_or(|next| next == ' ')).map(|(index, _)| index)?;
+            (&rest[..end], &rest[end + 1..])
+        },
+    };
+
+    Some(KeyLine { line, indent: get_indent(text), key: key.to_string(), value: after_key.trim().to_string() })
+}
+
+// The key lines right below a parent, ending at the first line that is not indented deeper
+fn get_child_key_lines(lines: &[&str], start: usize, parent_indent: Option<usize>) -> Vec<KeyLine> {
+    let mut child_indent: Option<usize> = None;
+    let mut key_lines: Vec<KeyLine> = vec![];
+
+    for (line, text) in lines.iter().enumerate().skip(start) {
+        if text.trim().is_empty() || text.trim_start().starts_with('#') {
+            continue;
+        }
+        if text.trim_end() == "---" || parent_indent.is_some_and(|parent_indent| get_indent(text) <= parent_indent) {
+            break;
+        }
+
+        if get_indent(text) == *child_indent.get_or_insert(get_indent(text)) {
+            key_lines.extend(parse_key_line(line, text));
+        }
+    }
+
+    key_lines
+}
+
+// The start line of every document of the file
+fn get_document_starts(lines: &[&str]) -> Vec<usize> {
+    let mut document_starts: Vec<usize> = vec![0];
+    document_starts.extend(lines.iter().enumerate().filter(|(_, text)| text.trim_end() == "---").map(|(line, _)| line + 1));
+
+    document_starts
+}
+
+// The tasks of every document by the line they are defined on, groups of tasks prefix the keys of their tasks
+fn find_task_lines(lines: &[&str]) -> Vec<(usize, String, usize)> {
+    let mut task_lines: Vec<(usize, String, usize)> = vec![];
+
+    for (document, document_start) in get_document_starts(lines).into_iter().enumerate() {
+        let Some(tasks_line) = get_child_key_lines(lines, document_start, None).into_iter().find(|key_line| key_line.key == "tasks" && key_line.value.is_empty()) else {
+            continue;
+        };
+
+        let mut tasks: Vec<(String, usize)> = vec![];
+        collect_task_lines(lines, &tasks_line, "", &mut tasks);
+        task_lines.extend(tasks.into_iter().map(|(key, line)| (document, key, line)));
+    }
+
+    task_lines
+}
+
+fn collect_task_lines(lines: &[&str], parent: &KeyLine, prefix: &str, tasks: &mut Vec<(String, usize)>) {
+    for child in get_child_key_lines(lines, parent.line + 1, Some(parent.indent)) {
+        let key: String = match prefix.is_empty() {
+            true => child.key.clone(),
+            false => format!("{}:{}", prefix, child.key),
+        };
+
+        // A task is recognised by its command field, like the config parser does
+        let grandchildren: Vec<KeyLine> = get_child_key_lines(lines, child.line + 1, Some(child.indent));
+        match child.value.is_empty() && !grandchildren.is_empty() && grandchildren.iter().all(|grandchild| grandchild.key != "command") {
+            true => collect_task_lines(lines, &child, &key, tasks),
+            false => tasks.push((key, child.line)),
+        }
+    }
+}
+
+// Whether a task asks for confirmation, in its block or flow mapping
+fn is_confirmed_task(lines: &[&str], line: usize) -> bool {
+    let Some(task_line) = parse_key_line(line, lines[line]) else {
+        return false;
+    };
+    if task_line.value.starts_with('{') {
+        return task_line.value.trim_matches(['{', '}']).split(',').any(|option| option.split_once(':').is_some_and(|(key, value)| key.trim() == "confirm" && value.trim() == "true"));
+    }
+
+    get_child_key_lines(lines, line + 1, Some(task_line.indent)).iter().any(|option| option.key == "confirm" && option.value == "true")
+}
+
+// Positions count UTF-16 code units, like the protocol does, the prefix of a line up to one
+fn get_line_prefix(text: &str, character: usize) -> &str {
+    let mut units: usize = 0;
+    let end: usize = text.char_indices().find(|(_, char)| {
+        units += char.len_utf16();
+        units > character
+    }).map(|(index, _)| index).unwrap_or(text.len());
+
+    &text[..end]
+}
+
+fn create_range(lines: &[&str], line: usize) -> Value {
+    let length: usize = lines.get(line).map(|text| text.encode_utf16().count()).unwrap_or_default();
+
+    json!({ "start": { "line": line, "character": 0 }, "end": { "line": line, "character": length } })
+}
+
+// A run lens above every task, addressed by the config name so only that config runs it.
+// Tasks that ask for confirmation get none, the editor has no terminal to answer in.
+pub fn get_code_lenses(content: &str, config_file_path: &Path) -> Vec<Value> {
+    let lines: Vec<&str> = content.lines().collect();
+    let names: Vec<Option<String>> = get_document_starts(&lines)
+        .into_iter()
+        .map(|document_start| get_child_key_lines(&lines, document_start, None).into_iter().find(|key_line| key_line.key == "name").map(|key_line| key_line.value.trim_matches(['"', '\'']).to_string()))
+        .collect();
+
+    find_task_lines(&lines)
+        .into_iter()
+        .filter(|(_, _, line)| !is_confirmed_task(&lines, *line))
+        .map(|(document, key, line)| {
+            let address: String = match &names[document] {
+                Some(name) => format!("{}#{}", name, key),
+                None => key.clone(),
+            };
+            json!({
+                "range": create_range(&lines, line),
+                "command": { "title": format!("▶ Run {}", key), "command": RUN_TASK_COMMAND, "arguments": [config_file_path.to_string_lossy(), address] },
+            })
+        })
+        .collect()
+}
+
+pub fn get_completions(content: &str, line: usize, character: usize, config_directory: &Path) -> Vec<Value> {
+    let lines: Vec<&str> = content.lines().collect();
+    let text: &str = lines.get(line).copied().unwrap_or_default();
+    let prefix: String = get_line_prefix(text, character).to_string();
+
+    let (key, typed): (Option<String>, String) = match (prefix.trim_start().strip_prefix('-'), parse_key_line(line, &prefix)) {
+        // A list item belongs to the closest key above it that is indented less, or as much for compact lists
+        (Some(item), _) => {
+            let parent: Option<String> = lines[..line.min(lines.len())]
+                .iter()
+                .enumerate()
+                .rev()
+                .filter_map(|(parent_line, parent_text)| parse_key_line(parent_line, parent_text))
+                .find(|key_line| key_line.indent < get_indent(&prefix) || (key_line.indent == get_indent(&prefix) && key_line.value.is_empty()))
+                .map(|key_line| key_line.key);
+            (parent, item.trim_start().to_string())
+        },
+        // Flow lists like [build, lint] complete the item after the last separator
+        (None, Some(key_line)) => match key_line.value.rfind(['[', ',']) {
+            Some(separator) => (Some(key_line.key), key_line.value[separator + 1..].trim_start().to_string()),
+            None => (Some(key_line.key), key_line.value),
+        },
+        (None, None) => (None, prefix.trim_start().to_string()),
+    };
+
+    // Quotes around a value are not part of what is completed
+    let typed: &str = typed.trim_start_matches(['"', '\'']);
+    let typed_start: usize = character.saturating_sub(typed.encode_utf16().count());
+    let range = json!({ "start": { "line": line, "character": typed_start }, "end": { "line": line, "character": character } });
+    let create_item = |label: String, kind: u8, detail: Option<String>| json!({ "label": label, "kind": kind, "detail": detail, "textEdit": { "range": range, "newText": label } });
+
+    match (key.as_deref(), get_indent(&prefix)) {
+        (Some("task_engine"), _) => ["auto", "none"]
+            .into_iter()
+            .chain(build_info::SUPPORTED_ENGINES)
+            .map(|engine| create_item(engine.to_string(), KIND_VALUE, None))
+            .collect(),
+        (Some("default_task" | "depends_on" | "after"), _) => {
+            let document: usize = get_document_starts(&lines).iter().filter(|document_start| **document_start <= line).count() - 1;
+            find_task_lines(&lines)
+                .into_iter()
+                .filter(|(task_document, _, _)| *task_document == document)
+                .map(|(_, key, _)| create_item(key, KIND_FUNCTION, None))
+                .collect()
+        },
+        (Some("directories"), _) => get_directory_completions(typed, config_directory).into_iter().map(|(label, detail)| create_item(label, KIND_FOLDER, detail)).collect(),
+        (None, 0) => file::CONFIG_FILE_KEYS.into_iter().map(|config_file_key| create_item(config_file_key.to_string(), KIND_PROPERTY, None)).collect(),
+        _ => vec![],
+    }
+}
+
+// The directories next to what is typed, with whether a config lives in them
+fn get_directory_completions(typed: &str, config_directory: &Path) -> Vec<(String, Option<String>)> {
+    let (negation, typed): (&str, &str) = match typed.strip_prefix('!') {
+        Some(typed) => ("!", typed),
+        None => ("", typed),
+    };
+    let base: &str = typed.rsplit_once('/').map(|(base, _)| base).unwrap_or_default();
+    let Ok(entries) = read_dir(config_directory.join(base)) else {
+        return vec![];
+    };
+
+    let mut directories: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.') && !config::SKIPPED_DIRECTORIES.contains(&name.as_str()))
+        .map(|name| match base.is_empty() {
+            true => name,
+            false => format!("{}/{}", base, name),
+        })
+        .collect();
+    directories.sort();
+
+    let mut completions: Vec<(String, Option<String>)> = vec![];
+    let wildcard: String = match base.is_empty() {
+        true => "*".to_string(),
+        false => format!("{}/*", base),
+    };
+    let wildcard_count: usize = config::find_pattern_config_paths(config_directory, &wildcard).len();
+    if wildcard_count > 0 {
+        completions.push((format!("{}{}", negation, wildcard), Some(format!("{} configs", wildcard_count))));
+    }
+    for directory in directories {
+        let detail: Option<String> = (!config::find_pattern_config_paths(config_directory, &directory).is_empty()).then(|| "has a rask.yaml".to_string());
+        completions.push((format!("{}{}", negation, directory), detail));
+    }
+
+    completions
+}
+
+fn create_diagnostic(lines: &[&str], line: usize, severity: u8, message: String) -> Value {
+    json!({ "range": create_range(lines, line), "severity": severity, "source": "rask", "message": message })
+}
+
+fn find_line(lines: &[&str], needle: &str) -> usize {
+    lines.iter().position(|text| text.contains(needle)).unwrap_or_default()
+}
+
+// Checks what `rask validate` checks of a single file, on the content the editor holds
+pub fn get_diagnostics(content: &str, config_file_path: &Path) -> Vec<Value> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    // The parse error carries the position, the errors after it do not
+    for document in serde_yaml::Deserializer::from_str(content) {
+        if let Err(err) = ConfigFile::deserialize(document) {
+            let line: usize = err.location().map(|location| location.line().saturating_sub(1)).unwrap_or_default();
+            return vec![create_diagnostic(&lines, line, SEVERITY_ERROR, err.to_string())];
+        }
+    }
+
+    let mut diagnostics: Vec<Value> = vec![];
+    for (key, suggestion) in file::find_unknown_config_file_keys(content).unwrap_or_default() {
+        let message: String = match suggestion {
+            Some(suggestion) => format!("Unknown key \"{}\", did you mean \"{}\"?", key, suggestion),
+            None => format!("Unknown key \"{}\", expected one of {}", key, file::CONFIG_FILE_KEYS.join(", ")),
+        };
+        diagnostics.push(create_diagnostic(&lines, find_line(&lines, &format!("{}:", key)), SEVERITY_WARNING, message));
+    }
+    for deprecated_key in deprecation::find_deprecated_keys(content, &DEPRECATIONS).unwrap_or_default() {
+        let line: usize = find_line(&lines, &format!("{}:", deprecated_key.deprecation.key));
+        diagnostics.push(create_diagnostic(&lines, line, SEVERITY_WARNING, format!("{}, run `rask migrate` to update it", deprecated_key.describe())));
+    }
+
+    let config_files: Vec<ConfigFile> = match file::parse_config_documents(content, config_file_path.to_path_buf()) {
+        Ok(config_files) => config_files,
+        Err(err) => {
+            diagnostics.push(create_diagnostic(&lines, 0, SEVERITY_ERROR, err));
+            return diagnostics;
+        },
+    };
+
+    // The patterns of child configs may use constants of the root, which only a run sees
+    for ConfigFile { directories, constants, __dir_path: config_directory, .. } in &config_files {
+        let variables = match config::get_directory_variables(constants) {
+            Ok(variables) => variables,
+            Err(err) => {
+                diagnostics.push(create_diagnostic(&lines, find_line(&lines, "constants:"), SEVERITY_ERROR, err));
+                continue;
+            },
+        };
+        for directory in directories {
+            let line: usize = find_line(&lines, directory.get_pattern());
+            match config::interpolate_directories(std::slice::from_ref(directory), &variables, config_file_path).map(|mut directories| directories.remove(0).get_pattern().to_string()) {
+                Ok(pattern) if !pattern.starts_with('!') && config::find_pattern_config_paths(config_directory, &pattern).is_empty() => {
+                    diagnostics.push(create_diagnostic(&lines, line, SEVERITY_WARNING, format!("No rask.yaml matches {:?}", pattern)));
+                },
+                Ok(_) => {},
+                Err(err) => diagnostics.push(create_diagnostic(&lines, line, SEVERITY_WARNING, err)),
+            }
+        }
+    }
+
+    // Task options, steps and engines are checked by parsing the configs like a run does
+    if let Err(err) = config::parse_config_files(config_files, config_file_path, &mut ConfigIssues::new(false)) {
+        let line: usize = find_task_lines(&lines)
+            .into_iter()
+            .find(|(_, key, _)| err.contains(&format!("\"{}\"", key)))
+            .map(|(_, _, line)| line)
+            .unwrap_or_default();
+        diagnostics.push(create_diagnostic(&lines, line, SEVERITY_ERROR, err));
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::testing::TestWorkspace;
+
+    fn get_labels(items: &[Value], field: &str) -> Vec<String> {
+        items.iter().map(|item| item.pointer(field).and_then(Value::as_str).unwrap_or_default().to_string()).collect()
+    }
+
+    #[test]
+    fn reads_and_writes_framed_messages() {
+        let mut buffer: Vec<u8> = vec![];
+        write_message(&mut buffer, &json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" })).unwrap();
+        write_message(&mut buffer, &json!({ "jsonrpc": "2.0", "method": "exit" })).unwrap();
+
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["method"], "shutdown");
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["method"], "exit");
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+        assert_eq!(uri_to_path("file:///home/my%20app/rask.yaml"), PathBuf::from("/home/my app/rask.yaml"));
+    }
+
+    #[test]
+    fn completes_keys_engines_tasks_and_directories() {
+        let workspace = TestWorkspace::new()
+            .with_config("packages/app", "name: app\n")
+            .with_file("packages/docs/README.md", "");
+        let content = "name: root\ntask_engine: \ndefault_task: \ndirectories:\n  - packages/\ntasks:\n  lint: eslint .\n  build:\n    command: make\n    depends_on: [lint, \n  docs:\n    api: make api\n";
+        let complete = |line: usize, character: usize| get_completions(content, line, character, &workspace.path("."));
+
+        assert!(get_labels(&complete(0, 2), "/label").contains(&"directories".to_string()));
+        assert_eq!(get_labels(&complete(1, 13), "/label"), vec!["auto", "none", "composer", "npm", "yarn", "pnpm", "cargo", "taskfile", "just", "compose"]);
+        assert_eq!(get_labels(&complete(2, 14), "/label"), vec!["lint", "build", "docs:api"]);
+        assert_eq!(get_labels(&complete(9, 23), "/label"), vec!["lint", "build", "docs:api"]);
+
+        let directories = complete(4, 13);
+        assert_eq!(get_labels(&directories, "/label"), vec!["packages/*", "packages/app", "packages/docs"]);
+        assert_eq!(get_labels(&directories, "/detail"), vec!["1 configs", "has a rask.yaml", ""]);
+        assert_eq!(directories[1]["textEdit"]["range"]["start"]["character"], 4);
+
+        // The emoji takes two UTF-16 code units
+        assert_eq!(get_line_prefix("# 🚀 build", 4), "# 🚀");
+        let edit = &get_completions("# 🚀\ndefault_task: 🚀\ntasks:\n  lint: eslint .\n", 1, 16, &workspace.path("."))[0]["textEdit"];
+        assert_eq!((edit["range"]["start"]["character"].as_u64(), edit["range"]["end"]["character"].as_u64()), (Some(14), Some(16)));
+    }
+
+    #[test]
+    fn reports_lenses_and_diagnostics() {
+        let workspace = TestWorkspace::new().with_config("packages/app", "name: app\n");
+        let content = "name: root\ndirectories: [packages/*, tools/*]\ntaks: {}\ntasks:\n  lint: eslint .\n  deploy:\n    command: ./deploy.sh\n    stop_grace_period: soon\n  reset:\n    command: ./reset.sh\n    confirm: true\n  seed: {command: ./seed.sh, confirm: true}\n---\nname: docs\ntasks:\n  build: mkdocs build\n";
+        let config_file_path = workspace.config_path(".");
+
+        let lenses = get_code_lenses(content, &config_file_path);
+        assert_eq!(get_labels(&lenses, "/command/title"), vec!["▶ Run lint", "▶ Run deploy", "▶ Run build"]);
+        assert_eq!(get_labels(&lenses, "/command/arguments/1"), vec!["root#lint", "root#deploy", "docs#build"]);
+
+        let diagnostics = get_diagnostics(content, &config_file_path);
+        let lines: Vec<u64> = diagnostics.iter().map(|diagnostic| diagnostic["range"]["start"]["line"].as_u64().unwrap()).collect();
+        assert_eq!(lines, vec![2, 1, 5]);
+
+        let lenses = get_code_lenses("tasks:\n  lint:\n    command: eslint .\n    confirm: false\n", &config_file_path);
+        assert_eq!(get_labels(&lenses, "/command/title"), vec!["▶ Run lint"]);
+        assert!(diagnostics[0]["message"].as_str().unwrap().contains("did you mean \"tasks\""));
+        assert!(diagnostics[1]["message"].as_str().unwrap().contains("tools/*"));
+
+        let diagnostics = get_diagnostics("name: root\ntasks: [\n", &config_file_path);
+        assert_eq!(diagnostics[0]["severity"], SEVERITY_ERROR);
+    }
+}
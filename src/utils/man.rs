@@ -0,0 +1,108 @@
+use clap::{Arg, Command};
+
+// Renders a roff man page from the clap definitions, so it never drifts from --help
+pub fn render_man_page(command: &Command, topics: &[(&str, &str)]) -> String {
+    // Building fills in the defaults, like how many values an argument takes
+    let mut command: Command = command.clone();
+    command.build();
+    let name: &str = command.get_name();
+    let mut lines: Vec<String> = vec![
+        format!(".TH {} 1 \"\" \"{} {}\"", name.to_uppercase(), name, command.get_version().unwrap_or_default()),
+        ".SH NAME".to_string(),
+        format!("{} \\- {}", name, escape(&command.get_about().map(|about| about.to_string()).unwrap_or_default())),
+        ".SH SYNOPSIS".to_string(),
+        format!(".B {}", name),
+        "[\\fIOPTIONS\\fR] [\\fICOMMAND\\fR]".to_string(),
+        ".SH OPTIONS".to_string(),
+    ];
+    lines.extend(render_arguments(&command, true));
+
+    lines.push(".SH COMMANDS".to_string());
+    for subcommand in command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()) {
+        lines.push(".TP".to_string());
+        lines.push(format!(".B {} {}", name, subcommand.get_name()));
+        lines.push(escape(&subcommand.get_about().map(|about| about.to_string()).unwrap_or_default()));
+        lines.push(".RS".to_string());
+        lines.extend(render_arguments(subcommand, false));
+        lines.push(".RE".to_string());
+    }
+
+    for (topic, text) in topics {
+        lines.push(format!(".SH {}", topic.to_uppercase()));
+        lines.push(".nf".to_string());
+        // The first line of a topic is its title, the section header replaces it
+        lines.extend(text.lines().skip(1).map(escape));
+        lines.push(".fi".to_string());
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// Global options and --help are only listed once, at the top
+fn render_arguments(command: &Command, is_top_level: bool) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    let is_listed = |argument: &&Arg| !argument.is_hide_set() && (is_top_level || (!argument.is_global_set() && argument.get_id() != "help"));
+    for argument in command.get_arguments().filter(is_listed) {
+        lines.push(".TP".to_string());
+        lines.push(format!(".B {}", escape(&format_argument(argument))));
+        lines.push(escape(&argument.get_help().map(|help| help.to_string()).unwrap_or_default()));
+    }
+
+    lines
+}
+
+fn format_argument(argument: &Arg) -> String {
+    let mut names: Vec<String> = vec![];
+    if let Some(short) = argument.get_short() {
+        names.push(format!("-{}", short));
+    }
+    if let Some(long) = argument.get_long() {
+        names.push(format!("--{}", long));
+    }
+
+    let value_name: String = argument
+        .get_value_names()
+        .and_then(|value_names| value_names.first())
+        .map(|value_name| value_name.to_string())
+        .unwrap_or(argument.get_id().to_string().to_uppercase());
+    let takes_value: bool = argument.get_num_args().is_some_and(|num_args| num_args.takes_values());
+
+    match (names.is_empty(), takes_value) {
+        (true, _) => format!("<{}>", value_name),
+        (false, true) => format!("{} <{}>", names.join(", "), value_name),
+        (false, false) => names.join(", "),
+    }
+}
+
+// Backslashes and leading dots or quotes would be read as roff requests
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\\\").replace('-', "\\-");
+
+    match text.starts_with('.') || text.starts_with('\'') {
+        true => format!("\\&{}", text),
+        false => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{ArgAction, Arg};
+
+    #[test]
+    fn renders_commands_options_and_topics() {
+        let command = Command::new("rask")
+            .version("1.0.0")
+            .about("Runs tasks")
+            .arg(Arg::new("color").long("color").help("When to use colors"))
+            .subcommand(Command::new("run").about("Run tasks").arg(Arg::new("parallel").long("parallel").action(ArgAction::SetTrue).help("Run at the same time")));
+
+        let man_page = render_man_page(&command, &[("caching", "CACHING\n.cache: mtime")]);
+
+        assert!(man_page.starts_with(".TH RASK 1 \"\" \"rask 1.0.0\"\n.SH NAME\nrask \\- Runs tasks\n"));
+        assert!(man_page.contains(".B \\-\\-color <COLOR>\nWhen to use colors\n"));
+        assert!(man_page.contains(".B rask run\nRun tasks\n.RS\n.TP\n.B \\-\\-parallel\nRun at the same time\n.RE\n"));
+        assert!(man_page.contains(".SH CACHING\n.nf\n\\&.cache: mtime\n.fi\n"));
+    }
+}
@@ -1,2 +1,42 @@
 pub mod config;
 pub mod file;
+pub mod interpolation;
+pub mod process;
+pub mod environment;
+pub mod prompt;
+pub mod output;
+pub mod report;
+pub mod time;
+pub mod state;
+pub mod index;
+pub mod affected;
+pub mod run_state;
+pub mod executor;
+pub mod prerequisites;
+pub mod watch;
+pub mod cargo;
+pub mod build_info;
+pub mod install;
+pub mod writer;
+pub mod stats;
+pub mod export;
+pub mod freshness;
+pub mod man;
+pub mod failure;
+pub mod supervisor;
+pub mod vcs;
+pub mod version;
+pub mod history;
+pub mod ci;
+pub mod schedule;
+pub mod running;
+pub mod lsp;
+pub mod plan;
+pub mod exit;
+pub mod docs;
+pub mod package_manager;
+pub mod tmp;
+pub mod query;
+pub mod cooldown;
+pub mod shadowing;
+pub mod deprecation;
@@ -0,0 +1,120 @@
+use std::env;
+use std::io::{stderr, stdout, IsTerminal};
+use std::sync::OnceLock;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum ColorChoice {
+    #[default]
+    AUTO,
+    ALWAYS,
+    NEVER,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Tone {
+    INFO,
+    SUCCESS,
+    WARNING,
+    ERROR,
+    MUTED,
+}
+
+// How much of a run is printed while it is going, captured output of failed tasks is always shown
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Verbosity {
+    // Task output and lifecycle lines are streamed
+    #[default]
+    NORMAL,
+    // Only the lifecycle lines are printed, task output is captured
+    QUIET,
+    // Nothing is printed until the run completes
+    SUMMARY,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Stream {
+    STDOUT,
+    STDERR,
+}
+
+static COLOR_CHOICE: OnceLock<ColorChoice> = OnceLock::new();
+
+pub fn init(color_choice: ColorChoice) {
+    let _ = COLOR_CHOICE.set(color_choice);
+}
+
+pub fn use_color(stream: Stream) -> bool {
+    match COLOR_CHOICE.get().copied().unwrap_or_default() {
+        ColorChoice::ALWAYS => true,
+        ColorChoice::NEVER => false,
+        // See https://no-color.org and https://bixense.com/clicolors
+        ColorChoice::AUTO => {
+            if env::var("NO_COLOR").is_ok_and(|value| !value.is_empty()) {
+                return false;
+            }
+
+            if env::var("CLICOLOR_FORCE").is_ok_and(|value| !value.is_empty() && value != "0") {
+                return true;
+            }
+
+            if env::var("CLICOLOR").is_ok_and(|value| value == "0") {
+                return false;
+            }
+
+            match stream {
+                Stream::STDOUT => stdout().is_terminal(),
+                Stream::STDERR => stderr().is_terminal(),
+            }
+        }
+    }
+}
+
+pub fn paint(text: &str, tone: Tone, stream: Stream) -> String {
+    if !use_color(stream) {
+        return text.to_string();
+    }
+
+    let code = match tone {
+        Tone::INFO => "36",
+        Tone::SUCCESS => "32",
+        Tone::WARNING => "33",
+        Tone::ERROR => "31",
+        Tone::MUTED => "2",
+    };
+
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+// Prints a `[LABEL] message` line, only the label is colored.
+pub fn line(label: &str, tone: Tone, message: &str) {
+    println!("{} {}", paint(&format!("[{}]", label), tone, Stream::STDOUT), message);
+}
+
+pub fn error_line(label: &str, tone: Tone, message: &str) {
+    eprintln!("{} {}", paint(&format!("[{}]", label), tone, Stream::STDERR), message);
+}
+
+pub fn warning(message: &str) {
+    error_line("WARNING", Tone::WARNING, message);
+}
+
+pub fn success(message: &str) {
+    println!("{}", paint(message, Tone::SUCCESS, Stream::STDOUT));
+}
+
+pub fn failure(message: &str) {
+    println!("{}", paint(message, Tone::ERROR, Stream::STDOUT));
+}
+
+pub fn error(message: &str) {
+    eprintln!("{}", paint(message, Tone::ERROR, Stream::STDERR));
+}
+
+// Replays the captured output of a task that did not succeed
+pub fn captured_output(title: &str, lines: &[String]) {
+    error_line("OUTPUT", Tone::ERROR, title);
+    for line in lines {
+        eprintln!("  {}", line);
+    }
+}
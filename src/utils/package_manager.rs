@@ -0,0 +1,225 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use serde::Deserialize;
+use crate::utils::config::{Task, TaskType};
+use crate::utils::file;
+
+pub const PACKAGE_JSON_FILE: &str = "package.json";
+pub const YARN_LOCK_FILE: &str = "yarn.lock";
+pub const PNPM_LOCK_FILE: &str = "pnpm-lock.yaml";
+
+// The tools of `engines` that rask checks, others like vscode are not on the path
+const CHECKED_ENGINES: [&str; 4] = ["node", "npm", "yarn", "pnpm"];
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageJsonFile {
+    #[serde(default)]
+    pub scripts: HashMap<String, String>,
+    // The corepack spec, like pnpm@9.1.0 with an optional +sha512 hash
+    #[serde(default)]
+    pub package_manager: Option<String>,
+    // Semver ranges per tool, like {"node": ">=18"}
+    #[serde(default)]
+    pub engines: BTreeMap<String, String>,
+}
+
+pub fn read_package_json(dir_path: &Path) -> Result<PackageJsonFile, String> {
+    file::read_json_file::<PackageJsonFile>(&dir_path.join(PACKAGE_JSON_FILE))
+}
+
+// The name and version of a corepack spec, versions are exact
+fn parse_package_manager(package_manager: &str) -> Option<(&str, &str)> {
+    let (name, version) = package_manager.split_once('@')?;
+    Some((name, version.split('+').next().unwrap_or(version)))
+}
+
+// packageManager says which tool the project uses, without it the lock file decides and npm is the fallback
+pub fn detect_package_manager(dir_path: &Path) -> TaskType {
+    let package_manager: Option<String> = read_package_json(dir_path).ok().and_then(|package_json| package_json.package_manager);
+
+    match package_manager.as_deref().and_then(parse_package_manager) {
+        Some(("npm", _)) => TaskType::NPM,
+        Some(("yarn", _)) => TaskType::YARN,
+        Some(("pnpm", _)) => TaskType::PNPM,
+        _ if dir_path.join(YARN_LOCK_FILE).exists() => TaskType::YARN,
+        _ if dir_path.join(PNPM_LOCK_FILE).exists() => TaskType::PNPM,
+        _ => TaskType::NPM,
+    }
+}
+
+// Missing parts match anything, 18 is 18.x.x. Pre-release and build suffixes are ignored.
+fn parse_version(value: &str) -> Option<Vec<u64>> {
+    let value = value.trim().trim_start_matches(['v', '=']);
+    let value = value.split(['-', '+']).next().unwrap_or(value);
+    let mut parts: Vec<u64> = vec![];
+
+    for part in value.split('.').filter(|part| !part.is_empty()) {
+        if ["x", "X", "*"].contains(&part) {
+            break;
+        }
+        parts.push(part.parse().ok()?);
+    }
+
+    Some(parts)
+}
+
+// Compares the parts the bound has, so <=18 includes 18.9.0 like npm does
+fn compare_prefix(version: &[u64], bound: &[u64]) -> Ordering {
+    let padded: Vec<u64> = (0..bound.len()).map(|index| version.get(index).copied().unwrap_or(0)).collect();
+    padded.as_slice().cmp(bound)
+}
+
+fn satisfies_comparator(version: &[u64], comparator: &str) -> Option<bool> {
+    let operators = [">=", "<=", ">", "<", "^", "~", "="];
+    let operator: &str = operators.into_iter().find(|operator| comparator.starts_with(operator)).unwrap_or("");
+    let bound: Vec<u64> = parse_version(&comparator[operator.len()..])?;
+
+    Some(match operator {
+        ">=" => compare_prefix(version, &bound).is_ge(),
+        "<=" => compare_prefix(version, &bound).is_le(),
+        ">" => compare_prefix(version, &bound).is_gt(),
+        "<" => compare_prefix(version, &bound).is_lt(),
+        // The parts up to the first non-zero one are fixed, ^0.2.3 allows 0.2.x
+        "^" => {
+            let fixed = bound.iter().position(|part| *part != 0).map(|position| position + 1).unwrap_or(bound.len());
+            compare_prefix(version, &bound).is_ge() && compare_prefix(version, &bound[..fixed]).is_eq()
+        },
+        "~" => compare_prefix(version, &bound).is_ge() && compare_prefix(version, &bound[..bound.len().min(2)]).is_eq(),
+        _ => compare_prefix(version, &bound).is_eq(),
+    })
+}
+
+// The common subset of npm semver ranges, ranges it does not understand are satisfied
+pub fn satisfies_range(version: &str, range: &str) -> bool {
+    let Some(version) = parse_version(version) else {
+        return true;
+    };
+
+    range.split("||").any(|alternative| {
+        let satisfies = match alternative.split_once(" - ") {
+            Some((lower, upper)) => [format!(">={}", lower.trim()), format!("<={}", upper.trim())]
+                .iter()
+                .map(|comparator| satisfies_comparator(&version, comparator))
+                .collect::<Option<Vec<bool>>>(),
+            // Operators may be followed by a space, like `>= 18`
+            None => alternative
+                .replace("> ", ">").replace("< ", "<").replace("= ", "=").replace("^ ", "^").replace("~ ", "~")
+                .split_whitespace()
+                .map(|comparator| satisfies_comparator(&version, comparator))
+                .collect::<Option<Vec<bool>>>(),
+        };
+
+        satisfies.is_none_or(|results| results.iter().all(|result| *result))
+    })
+}
+
+// The version of a tool as the directory sees it, corepack shims answer with the pinned version
+fn get_local_version(binary: &str, dir_path: &Path) -> Option<String> {
+    let output = Command::new(binary)
+        .arg("--version")
+        .current_dir(dir_path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8(output.stdout).ok().map(|stdout| stdout.trim().to_string()).filter(|version| !version.is_empty())
+}
+
+fn find_mismatches(dir_path: &Path, get_version: &mut dyn FnMut(&str, &Path) -> Option<String>) -> Vec<String> {
+    let Ok(PackageJsonFile { package_manager, engines, .. }) = read_package_json(dir_path) else {
+        return vec![];
+    };
+
+    let mut requirements: Vec<(&str, String, &str)> = vec![];
+    if let Some((name, version)) = package_manager.as_deref().and_then(parse_package_manager) {
+        requirements.push((name, format!("={}", version), "packageManager"));
+    }
+    for (name, range) in engines.iter().filter(|(name, _)| CHECKED_ENGINES.contains(&name.as_str())) {
+        requirements.push((name, range.clone(), "engines"));
+    }
+
+    // Tools that are not installed fail with their own hint once a task uses them
+    requirements
+        .into_iter()
+        .filter_map(|(name, range, field)| {
+            let local_version = get_version(name, dir_path)?;
+            (!satisfies_range(&local_version, &range)).then(|| {
+                format!("{:?} asks for {} {} in {}, {} is installed", dir_path.join(PACKAGE_JSON_FILE), name, range.trim_start_matches('='), field, local_version)
+            })
+        })
+        .collect()
+}
+
+// Checked once per directory with package.json tasks, tools are asked for their version once per directory
+pub fn find_version_mismatches(tasks: &[&Task]) -> Vec<String> {
+    let mut directories: Vec<&PathBuf> = vec![];
+    for task in tasks.iter().filter(|task| matches!(task.task_type, TaskType::NPM | TaskType::YARN | TaskType::PNPM)) {
+        if !directories.contains(&&task.directory) {
+            directories.push(&task.directory);
+        }
+    }
+
+    directories
+        .into_iter()
+        .flat_map(|directory| find_mismatches(directory, &mut get_local_version))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn matches_common_semver_ranges() {
+        let cases = [
+            ("20.11.1", ">=18", true),
+            ("16.20.0", ">= 18", false),
+            ("18.9.0", "<=18", true),
+            ("9.1.0", "^9.0.0", true),
+            ("10.0.0", "^9.0.0", false),
+            ("0.3.0", "^0.2.3", false),
+            ("1.2.9", "~1.2.3", true),
+            ("1.3.0", "~1.2.3", false),
+            ("v20.1.0", "^18 || ^20", true),
+            ("19.0.0", "18.x", false),
+            ("1.5.0", "1.0.0 - 2.0.0", true),
+            ("9.1.0", "=9.1.0", true),
+            ("4.0.0", "latest", true),
+        ];
+
+        for (version, range, expected) in cases {
+            assert_eq!(satisfies_range(version, range), expected, "{} in {}", version, range);
+        }
+    }
+
+    #[test]
+    fn package_manager_wins_over_lock_files() {
+        let workspace = TestWorkspace::new()
+            .with_file("pinned/package.json", "{\"packageManager\": \"pnpm@9.1.0+sha512.abc\", \"engines\": {\"node\": \">=18\", \"vscode\": \"^1.80.0\"}}")
+            .with_file("pinned/yarn.lock", "")
+            .with_file("locked/package.json", "{}")
+            .with_file("locked/pnpm-lock.yaml", "");
+
+        assert_eq!(detect_package_manager(&workspace.path("pinned")), TaskType::PNPM);
+        assert_eq!(detect_package_manager(&workspace.path("locked")), TaskType::PNPM);
+        assert_eq!(detect_package_manager(&workspace.path(".")), TaskType::NPM);
+
+        let mut asked: Vec<String> = vec![];
+        let mismatches = find_mismatches(&workspace.path("pinned"), &mut |binary, _| {
+            asked.push(binary.to_string());
+            match binary {
+                "pnpm" => Some("8.6.0".to_string()),
+                _ => Some("v20.1.0".to_string()),
+            }
+        });
+
+        assert_eq!(asked, vec!["pnpm", "node"]);
+        assert_eq!(mismatches, vec![format!("{:?} asks for pnpm 9.1.0 in packageManager, 8.6.0 is installed", workspace.path("pinned/package.json"))]);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::utils::{file, install, output};
+use crate::utils::config::{LevelHooks, SortableTask, SortableTasks, Task, TaskOptions, TaskType};
+
+// Bumped on every change that is not an addition, plans of another schema are refused
+pub const PLAN_SCHEMA_VERSION: u32 = 1;
+
+// The resolved tasks of a run, written by `rask plan` and executed as they are by `rask run --plan`.
+// Paths are relative to the entry directory, so a plan also runs in another checkout of the repository.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Plan {
+    pub schema_version: u32,
+    pub rask_version: String,
+    pub task_name: String,
+    // Read when the plan runs, so their values stay out of the plan
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_files: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_level: Option<PlanTask>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_level: Option<PlanTask>,
+    // The default_parallelism of the entry config, --jobs still wins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_parallelism: Option<u64>,
+    pub tasks: Vec<PlanTask>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlanTask {
+    pub key: String,
+    // The level of the task, dependencies and hooks run with whatever they belong to
+    #[serde(default)]
+    pub order: u64,
+    pub task_type: TaskType,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<String>,
+    pub directory: PathBuf,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub options: TaskOptions,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<PlanTask>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<PathBuf>,
+    // The install step follows from the task type, only whether it runs is planned
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub install: bool,
+}
+
+pub fn create_plan(entry_directory: &Path, task_name: &str, env_files: &[PathBuf], level_hooks: &LevelHooks, default_parallelism: Option<u64>, sortable_tasks: &SortableTasks) -> Plan {
+    let LevelHooks { before, after } = level_hooks;
+
+    Plan {
+        schema_version: PLAN_SCHEMA_VERSION,
+        rask_version: env!("CARGO_PKG_VERSION").to_string(),
+        task_name: task_name.to_string(),
+        env_files: env_files.iter().map(|env_file| get_relative_path(env_file, entry_directory)).collect(),
+        before_level: before.as_ref().map(|hook| create_plan_task(hook, 0, entry_directory)),
+        after_level: after.as_ref().map(|hook| create_plan_task(hook, 0, entry_directory)),
+        default_parallelism,
+        tasks: sortable_tasks.iter().map(|SortableTask { task, order, .. }| create_plan_task(task, *order, entry_directory)).collect(),
+    }
+}
+
+fn create_plan_task(task: &Task, order: u64, entry_directory: &Path) -> PlanTask {
+    let Task { key, command, steps, directory, env, options, shell, dependencies, inputs, outputs, install, task_type } = task;
+
+    PlanTask {
+        key: key.clone(),
+        order,
+        task_type: *task_type,
+        command: command.clone(),
+        steps: steps.clone(),
+        directory: get_relative_path(directory, entry_directory),
+        env: env.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+        shell: shell.clone(),
+        options: options.clone(),
+        dependencies: dependencies.iter().map(|dependency| create_plan_task(dependency, 0, entry_directory)).collect(),
+        inputs: inputs.iter().map(|input| get_relative_path(input, entry_directory)).collect(),
+        outputs: outputs.iter().map(|output| get_relative_path(output, entry_directory)).collect(),
+        install: install.is_some(),
+    }
+}
+
+pub fn read_plan(plan_path: &Path) -> Result<Plan, String> {
+    let plan: Plan = file::read_json_file(plan_path).map_err(|err| format!("Failed to read the plan {:?}: {}", plan_path, err))?;
+
+    if plan.schema_version != PLAN_SCHEMA_VERSION {
+        return Err(format!("The plan {:?} has schema version {}, this rask reads version {}, plan it again", plan_path, plan.schema_version, PLAN_SCHEMA_VERSION));
+    }
+    if plan.rask_version != env!("CARGO_PKG_VERSION") {
+        output::warning(&format!("The plan {:?} was made by rask {}, this is rask {}", plan_path, plan.rask_version, env!("CARGO_PKG_VERSION")));
+    }
+
+    Ok(plan)
+}
+
+impl Plan {
+    // The tasks exactly as planned, placed in the checkout of the given entry directory
+    pub fn resolve(&self, entry_directory: &Path) -> (SortableTasks, LevelHooks, Vec<PathBuf>) {
+        let sortable_tasks: SortableTasks = self.tasks
+            .iter()
+            .map(|plan_task| SortableTask { task: resolve_plan_task(plan_task, entry_directory), order: plan_task.order, provenance: vec![] })
+            .collect();
+        let level_hooks = LevelHooks {
+            before: self.before_level.as_ref().map(|hook| resolve_plan_task(hook, entry_directory)),
+            after: self.after_level.as_ref().map(|hook| resolve_plan_task(hook, entry_directory)),
+        };
+        let env_files: Vec<PathBuf> = self.env_files.iter().map(|env_file| get_absolute_path(env_file, entry_directory)).collect();
+
+        (sortable_tasks, level_hooks, env_files)
+    }
+}
+
+fn resolve_plan_task(plan_task: &PlanTask, entry_directory: &Path) -> Task {
+    let PlanTask { key, task_type, command, steps, directory, env, shell, options, dependencies, inputs, outputs, install, .. } = plan_task;
+
+    Task {
+        key: key.clone(),
+        command: command.clone(),
+        steps: steps.clone(),
+        directory: get_absolute_path(directory, entry_directory),
+        env: env.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+        options: options.clone(),
+        shell: shell.clone(),
+        dependencies: dependencies.iter().map(|dependency| resolve_plan_task(dependency, entry_directory)).collect(),
+        inputs: inputs.iter().map(|input| get_absolute_path(input, entry_directory)).collect(),
+        outputs: outputs.iter().map(|output| get_absolute_path(output, entry_directory)).collect(),
+        install: install.then(|| install::get_install_step(task_type)).flatten(),
+        task_type: *task_type,
+    }
+}
+
+// Paths outside the entry directory stay absolute, the entry directory itself is `.`
+fn get_relative_path(path: &Path, entry_directory: &Path) -> PathBuf {
+    match path.strip_prefix(entry_directory) {
+        Ok(relative_path) if relative_path.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative_path) => relative_path.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn get_absolute_path(path: &Path, entry_directory: &Path) -> PathBuf {
+    match path == Path::new(".") {
+        true => entry_directory.to_path_buf(),
+        false => entry_directory.join(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+    use crate::utils::config::{resolve_level_hooks, resolve_sortable_task};
+
+    #[test]
+    fn plans_resolve_in_another_checkout() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [packages/*]\nbefore_level: echo level\ntasks:\n  build:\n    command: make\n    depends_on: [lint]\n  lint: eslint .\n")
+            .with_config("packages/app", "name: app\nenv:\n  MODE: prod\ntasks:\n  build:\n    command: [npm ci, npm run build]\n    inputs: [src/**]\n    outputs: [dist/app.js]\n");
+        let config_structure = workspace.load_structure(".");
+        let level_hooks = resolve_level_hooks(&config_structure.config).unwrap();
+        let sortable_tasks = resolve_sortable_task(&config_structure, "build", &true).unwrap();
+
+        let plan = create_plan(&workspace.path("."), "build", &[workspace.path(".env")], &level_hooks, None, &sortable_tasks);
+        let content = serde_json::to_string(&plan).unwrap();
+        assert!(!content.contains(&workspace.path(".").to_string_lossy().to_string()));
+
+        let other_checkout = TestWorkspace::new();
+        let plan: Plan = serde_json::from_str(&content).unwrap();
+        let (planned_tasks, planned_hooks, env_files) = plan.resolve(&other_checkout.path("."));
+
+        assert_eq!(other_checkout.render_tasks(&planned_tasks), workspace.render_tasks(&sortable_tasks));
+        let app_build = &planned_tasks.iter().find(|sortable_task| sortable_task.task.directory == other_checkout.path("packages/app")).unwrap().task;
+        assert_eq!(app_build.steps, vec!["npm ci", "npm run build"]);
+        assert_eq!(app_build.env.get("MODE").map(String::as_str), Some("prod"));
+        assert_eq!(app_build.outputs, vec![other_checkout.path("packages/app/dist/app.js")]);
+        let root_build = &planned_tasks.iter().find(|sortable_task| sortable_task.task.directory == other_checkout.path(".")).unwrap().task;
+        assert_eq!(root_build.dependencies[0].key, "lint");
+        assert_eq!(planned_hooks.before.map(|hook| hook.command), Some("echo level".to_string()));
+        assert_eq!(env_files, vec![other_checkout.path(".env")]);
+    }
+}
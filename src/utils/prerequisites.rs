@@ -0,0 +1,143 @@
+use std::mem::take;
+use crate::utils::interpolation::Variables;
+use crate::utils::process;
+
+// Builtins and keywords of POSIX shells, these never need a binary
+const SHELL_BUILTINS: [&str; 34] = [
+    ".", ":", "[", "alias", "break", "case", "cd", "command", "continue", "do", "done", "echo", "elif", "else", "esac", "eval", "exec",
+    "exit", "export", "false", "fi", "for", "if", "local", "printf", "read", "return", "set", "shift", "source", "test", "then", "true", "unset",
+];
+
+// Words that are followed by the actual command
+const COMMAND_PREFIXES: [&str; 10] = ["!", "do", "elif", "else", "exec", "if", "then", "time", "until", "while"];
+
+// Best-effort, the first word of every command in a pipeline or list
+pub fn find_command_binaries(command: &str) -> Vec<String> {
+    let mut binaries: Vec<String> = vec![];
+
+    for binary in split_commands(command).iter().filter_map(|segment| get_command_binary(segment)) {
+        if !binaries.contains(&binary) {
+            binaries.push(binary);
+        }
+    }
+
+    binaries
+}
+
+// The binary the command starts with, none when it starts with a builtin like cd
+pub fn find_first_binary(command: &str) -> Option<String> {
+    split_commands(command).first().and_then(|segment| get_command_binary(segment))
+}
+
+// Separators within quotes are text, like the ; of `echo "done; ./later"`
+fn split_commands(command: &str) -> Vec<String> {
+    let mut segments: Vec<String> = vec![];
+    let mut segment = String::new();
+    let mut quote: Option<char> = None;
+    let mut characters = command.chars();
+
+    while let Some(character) = characters.next() {
+        match (quote, character) {
+            (Some(open_quote), _) if character == open_quote => quote = None,
+            // A backslash escapes the next character, except within single quotes
+            (None | Some('"'), '\\') => {
+                segment.push(character);
+                segment.extend(characters.next());
+                continue;
+            },
+            (None, '\'' | '"') => quote = Some(character),
+            (None, '\n' | ';' | '|' | '&' | '(' | ')' | '`') => {
+                segments.push(take(&mut segment));
+                continue;
+            },
+            _ => {},
+        }
+        segment.push(character);
+    }
+    segments.push(segment);
+
+    segments.into_iter().filter(|segment| !segment.trim().is_empty()).collect()
+}
+
+fn get_command_binary(segment: &str) -> Option<String> {
+    // Leading assignments like `FOO=bar make` set env for the command that follows
+    let binary = segment
+        .split_whitespace()
+        .find(|word| !((word.contains('=') && !word.starts_with('=')) || COMMAND_PREFIXES.contains(word)))
+        .filter(|word| !SHELL_BUILTINS.contains(word))?;
+
+    // Quoted words may hold spaces, which the words of the segment cut apart
+    let is_quoted = binary.starts_with(['"', '\'']);
+    let is_variable = binary.starts_with('$');
+
+    (!binary.is_empty() && !is_quoted && !is_variable).then(|| binary.to_string())
+}
+
+// Variables referenced as $NAME or ${NAME}, shell specials like $1 and $? are left out
+pub fn find_env_references(command: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let mut rest = command;
+
+    while let Some(position) = rest.find('$') {
+        rest = &rest[position + 1..];
+        let braced = rest.starts_with('{');
+        let name: String = rest
+            .trim_start_matches('{')
+            .chars()
+            .take_while(|char| char.is_ascii_alphanumeric() || *char == '_')
+            .collect();
+
+        let starts_with_letter = name.chars().next().is_some_and(|char| char.is_ascii_alphabetic() || char == '_');
+        if starts_with_letter && !names.contains(&name) {
+            names.push(name.clone());
+        }
+
+        rest = &rest[name.len() + usize::from(braced)..];
+    }
+
+    names
+}
+
+pub fn is_binary_available(binary: &str) -> bool {
+    process::find_executable(binary).is_some()
+}
+
+pub fn is_env_available(name: &str, env: &Variables) -> bool {
+    env.contains_key(name) || std::env::var_os(name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_binaries_of_every_command() {
+        let binaries = find_command_binaries("cd app && NODE_ENV=production npm run build | tee build.log; echo done");
+
+        assert_eq!(binaries, vec!["npm", "tee"]);
+    }
+
+    #[test]
+    fn looks_past_shell_keywords() {
+        assert_eq!(find_command_binaries("if command -v docker; then docker compose up; fi"), vec!["docker"]);
+    }
+
+    #[test]
+    fn skips_quoted_text() {
+        assert_eq!(find_command_binaries("echo \"done; ./later\" && printf 'a | b' | grep -q a"), vec!["grep"]);
+        assert_eq!(find_first_binary("cd sub && ./build.sh"), None);
+        assert_eq!(find_first_binary("NODE_ENV=test ./scripts/test.sh; ./later.sh").as_deref(), Some("./scripts/test.sh"));
+    }
+
+    #[test]
+    fn skips_variables_used_as_commands() {
+        assert_eq!(find_command_binaries("$EDITOR file.txt || vim file.txt"), vec!["vim"]);
+    }
+
+    #[test]
+    fn finds_env_references() {
+        let names = find_env_references("deploy --token $API_TOKEN --region ${REGION} --args \"$@\" $1 $HOME/bin");
+
+        assert_eq!(names, vec!["API_TOKEN", "REGION", "HOME"]);
+    }
+}
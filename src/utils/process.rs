@@ -0,0 +1,993 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::utils::config::{Task, TaskOptions};
+use crate::utils::{environment, output};
+use crate::utils::failure::FailureWatch;
+use crate::utils::output::Stream;
+use crate::utils::writer::TaskWriter;
+
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    // Absolute and relative paths are used as is, like a shell would
+    if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') {
+        let path = PathBuf::from(name);
+        return path.is_file().then_some(path);
+    }
+
+    let paths = env::var_os("PATH")?;
+
+    env::split_paths(&paths)
+        .flat_map(|directory| executable_candidates(&directory, name))
+        .find(|candidate| candidate.is_file())
+}
+
+// Commands starting with ./ or ../ run from the task directory, so that is where they are looked up
+pub fn resolve_relative_command(task: &Task, binary: &str) -> Option<PathBuf> {
+    (binary.starts_with("./") || binary.starts_with("../")).then(|| task.directory.join(binary.trim_start_matches("./")))
+}
+
+#[cfg(unix)]
+pub fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(windows)]
+fn executable_candidates(directory: &std::path::Path, name: &str) -> Vec<PathBuf> {
+    let extensions = env::var("PATHEXT").unwrap_or(".COM;.EXE;.BAT;.CMD".to_string());
+
+    let mut candidates: Vec<PathBuf> = vec![directory.join(name)];
+    candidates.extend(extensions.split(';').filter(|extension| !extension.is_empty()).map(|extension| directory.join(format!("{}{}", name, extension))));
+
+    candidates
+}
+
+#[cfg(not(windows))]
+fn executable_candidates(directory: &std::path::Path, name: &str) -> Vec<PathBuf> {
+    vec![directory.join(name)]
+}
+
+#[cfg(windows)]
+pub const DEFAULT_SHELL: &str = "cmd";
+#[cfg(not(windows))]
+pub const DEFAULT_SHELL: &str = "sh";
+
+pub fn get_shell(task: &Task) -> &str {
+    task.shell.as_deref().unwrap_or(DEFAULT_SHELL)
+}
+
+// The flag that makes the shell execute the next argument as a command
+fn shell_command_flag(shell: &str) -> &'static str {
+    let name = std::path::Path::new(shell)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match name.as_str() {
+        "cmd" => "/C",
+        "powershell" | "pwsh" => "-Command",
+        _ => "-c",
+    }
+}
+
+// Checks the shells up front, so a missing interpreter is reported before any task ran
+pub fn ensure_shells(tasks: &[&Task]) -> Result<(), String> {
+    let mut shells: Vec<&str> = tasks.iter().map(|task| get_shell(task)).collect();
+    shells.sort();
+    shells.dedup();
+
+    for shell in shells {
+        if find_executable(shell).is_none() {
+            let keys: Vec<String> = tasks
+                .iter()
+                .filter(|task| get_shell(task) == shell)
+                .map(|task| task.key.clone())
+                .collect();
+
+            return Err(format!("The shell \"{}\" of the tasks {} could not be found in PATH. Install it, or set `shell:` in the rask.yaml to an available interpreter, e.g. `shell: bash`", shell, keys.join(", ")));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn build_command(task: &Task) -> Command {
+    let Task { key, command, directory, env, options, .. } = task;
+    let shell = get_shell(task);
+
+    let mut program: Vec<String> = resource_limit_prefix(key, options);
+    program.extend(privilege_prefix(options, env));
+    program.extend([shell.to_string(), shell_command_flag(shell).to_string(), command.clone()]);
+
+    let mut process = Command::new(&program[0]);
+    if options.clean_env {
+        process.env_clear().envs(environment::resolve_inherited_environment(options));
+    }
+    process
+        .args(&program[1..])
+        .current_dir(directory)
+        .envs(env);
+
+    // Tasks lead their own process group, so their whole subtree is signalled together
+    #[cfg(unix)]
+    if leads_process_groups() {
+        use std::os::unix::process::CommandExt;
+        process.process_group(0);
+    }
+
+    process
+}
+
+// Signals a task can ask to be stopped with, the numbers are the same on Linux and macOS
+pub const STOP_SIGNALS: [(&str, i32); 5] = [("HUP", 1), ("INT", 2), ("QUIT", 3), ("KILL", 9), ("TERM", 15)];
+
+const DEFAULT_STOP_SIGNAL: &str = "TERM";
+
+// Accepts SIGINT as well as INT, the name without the SIG prefix is returned
+pub fn parse_stop_signal(value: &str) -> Option<String> {
+    let name = value.trim().to_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+
+    STOP_SIGNALS.iter().find(|(signal, _)| *signal == name).map(|(signal, _)| signal.to_string())
+}
+
+fn get_stop_signal(options: &TaskOptions) -> (&str, i32) {
+    let name: &str = options.stop_signal.as_deref().unwrap_or(DEFAULT_STOP_SIGNAL);
+
+    STOP_SIGNALS.iter().find(|(signal, _)| *signal == name).copied().unwrap_or(("TERM", 15))
+}
+
+fn get_stop_grace_period(options: &TaskOptions) -> Duration {
+    options.stop_grace_period.unwrap_or(TERMINATION_GRACE_PERIOD)
+}
+
+// A task in a group of its own is in the background of the terminal, so reading it stops the task with SIGTTIN.
+// When rask is the foreground job, tasks stay in its group and the terminal signals them along with rask.
+// rask itself then signals a task together with the processes it started, see get_signal_targets.
+pub fn leads_process_groups() -> bool {
+    #[cfg(unix)]
+    {
+        use std::sync::OnceLock;
+
+        static LEADS_PROCESS_GROUPS: OnceLock<bool> = OnceLock::new();
+        *LEADS_PROCESS_GROUPS.get_or_init(|| !process_groups::is_foreground_job())
+    }
+
+    #[cfg(not(unix))]
+    false
+}
+
+// Spawns a task built with build_command, it is stopped like the task asks when rask is interrupted
+pub fn spawn(command: &mut Command, options: &TaskOptions) -> std::io::Result<Child> {
+    let child = command.spawn()?;
+
+    #[cfg(unix)]
+    process_groups::track(child.id(), leads_process_groups(), get_stop_signal(options).1, get_stop_grace_period(options));
+
+    #[cfg(not(unix))]
+    let _ = options;
+
+    Ok(child)
+}
+
+// Stops tracking a task that completed, processes it left running are left alone as tasks may start daemons on purpose
+pub fn release(child: &Child) {
+    #[cfg(unix)]
+    process_groups::untrack(child.id());
+
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+#[cfg(unix)]
+const SIGKILL: i32 = 9;
+
+// What a signal for a task goes to: the whole group of a task that leads one.
+// A task in the group of rask is signalled along with every process it started, so `npm run` does not leave node behind.
+#[cfg(unix)]
+fn get_signal_targets(pid: u32, process_group: bool) -> Vec<i32> {
+    match process_group {
+        true => vec![-(pid as i32)],
+        false => std::iter::once(pid as i32).chain(find_descendants(pid)).collect(),
+    }
+}
+
+// Whether the signal reached any of the targets, which it does not once they are gone
+#[cfg(unix)]
+fn signal_targets(targets: &[i32], signal: i32) -> bool {
+    targets.iter().filter(|target| process_groups::send_signal(**target, signal)).count() > 0
+}
+
+// A process in the process table, a zombie exited and only waits to be reaped
+#[cfg(unix)]
+struct ProcessEntry {
+    pid: i32,
+    parent_pid: i32,
+    process_group: i32,
+    is_zombie: bool,
+}
+
+// The processes a task started and the ones they started in turn, found through their parent pid
+#[cfg(unix)]
+fn find_descendants(pid: u32) -> Vec<i32> {
+    let process_entries: Vec<ProcessEntry> = list_processes();
+    let mut descendants: Vec<i32> = vec![];
+    let mut parents: Vec<i32> = vec![pid as i32];
+
+    while let Some(parent) = parents.pop() {
+        for process_entry in process_entries.iter().filter(|process_entry| process_entry.parent_pid == parent) {
+            if !descendants.contains(&process_entry.pid) {
+                descendants.push(process_entry.pid);
+                parents.push(process_entry.pid);
+            }
+        }
+    }
+
+    descendants
+}
+
+// Zombies do not count, in containers whose init does not reap them they would keep a stopped task alive
+#[cfg(unix)]
+fn are_targets_running(targets: &[i32]) -> bool {
+    list_processes()
+        .iter()
+        .filter(|process_entry| !process_entry.is_zombie)
+        .any(|process_entry| targets.iter().any(|target| *target == process_entry.pid || -*target == process_entry.process_group))
+}
+
+// The state, parent pid and process group are the first fields after the parenthesized command name
+#[cfg(target_os = "linux")]
+fn list_processes() -> Vec<ProcessEntry> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![];
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: i32 = entry.file_name().to_str()?.parse().ok()?;
+            let stat: String = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            let mut fields = stat.rsplit_once(')')?.1.split_whitespace();
+            let is_zombie: bool = fields.next()? == "Z";
+            Some(ProcessEntry { pid, parent_pid: fields.next()?.parse().ok()?, process_group: fields.next()?.parse().ok()?, is_zombie })
+        })
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn list_processes() -> Vec<ProcessEntry> {
+    let Ok(output) = Command::new("ps").args(["-A", "-o", "pid=", "-o", "ppid=", "-o", "pgid=", "-o", "stat="]).output() else {
+        return vec![];
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(ProcessEntry {
+                pid: fields.next()?.parse().ok()?,
+                parent_pid: fields.next()?.parse().ok()?,
+                process_group: fields.next()?.parse().ok()?,
+                is_zombie: fields.next()?.starts_with('Z'),
+            })
+        })
+        .collect()
+}
+
+// Sends the stop signal, waits for the targets to be gone within the grace period, and kills what is left.
+// Processes started after the stop signal are looked up again, so they are killed as well.
+#[cfg(unix)]
+fn stop_targets(pid: u32, process_group: bool, options: &TaskOptions, mut is_stopped: impl FnMut(&[i32]) -> Result<bool, String>) -> Result<(), String> {
+    let targets: Vec<i32> = get_signal_targets(pid, process_group);
+
+    if signal_targets(&targets, get_stop_signal(options).1) {
+        let grace_deadline = Instant::now() + get_stop_grace_period(options);
+        while Instant::now() < grace_deadline {
+            if is_stopped(&targets)? {
+                return Ok(());
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    let mut remaining_targets: Vec<i32> = get_signal_targets(pid, process_group);
+    remaining_targets.retain(|target| !targets.contains(target));
+    signal_targets(&[targets, remaining_targets].concat(), SIGKILL);
+
+    Ok(())
+}
+
+pub fn is_task_alive(pid: u32, process_group: bool) -> bool {
+    #[cfg(unix)]
+    return process_groups::send_signal(match process_group { true => -(pid as i32), false => pid as i32 }, 0);
+
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, process_group);
+        false
+    }
+}
+
+// When a process started, tells it apart from a later process that got the same pid
+pub fn get_start_time(pid: u32) -> Option<String> {
+    // The name of the command is in parentheses and may hold spaces, the start time is the 20th field after it
+    #[cfg(target_os = "linux")]
+    return std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?.rsplit_once(')')?.1.split_whitespace().nth(19).map(str::to_string);
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let output = Command::new("ps").args(["-o", "lstart=", "-p", &pid.to_string()]).output().ok()?;
+        let start_time: String = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!start_time.is_empty()).then_some(start_time)
+    }
+}
+
+// Stops a task another rask process runs, that process reaps it
+pub fn stop_task(pid: u32, process_group: bool, options: &TaskOptions) -> Result<(), String> {
+    #[cfg(unix)]
+    return stop_targets(pid, process_group, options, |targets| Ok(!are_targets_running(targets)));
+
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, process_group, options);
+        Err("Stopping the tasks of another rask process is only supported on unix".to_string())
+    }
+}
+
+// The signal that interrupted rask, the run stops starting tasks and winds down once it is set
+pub fn get_interruption() -> Option<i32> {
+    #[cfg(unix)]
+    return process_groups::get_interruption();
+
+    #[cfg(not(unix))]
+    None
+}
+
+pub fn is_interrupted() -> bool {
+    get_interruption().is_some()
+}
+
+// Interrupting rask stops the running tasks like they ask, and lets rask wind down the run itself.
+// A second interrupt kills them and rask at once, also while tasks are still within their stop_grace_period.
+#[cfg(unix)]
+mod process_groups {
+    use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+    use std::sync::Once;
+    use std::thread;
+    use std::time::Duration;
+
+    const SIGINT: i32 = 2;
+    const SIGKILL: i32 = 9;
+    const SIGTERM: i32 = 15;
+    const SLOTS: usize = 256;
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    static PIDS: [AtomicI32; SLOTS] = [const { AtomicI32::new(0) }; SLOTS];
+    static PROCESS_GROUPS: [AtomicBool; SLOTS] = [const { AtomicBool::new(false) }; SLOTS];
+    static STOP_SIGNALS: [AtomicI32; SLOTS] = [const { AtomicI32::new(SIGTERM) }; SLOTS];
+    static GRACE_MILLIS: [AtomicU64; SLOTS] = [const { AtomicU64::new(0) }; SLOTS];
+    static INTERRUPTION: AtomicI32 = AtomicI32::new(0);
+    static HANDLER: Once = Once::new();
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn kill(pid: i32, sig: i32) -> i32;
+        fn isatty(fd: i32) -> i32;
+        fn tcgetpgrp(fd: i32) -> i32;
+        fn getpgrp() -> i32;
+        fn _exit(status: i32) -> !;
+    }
+
+    // Whether rask runs in the foreground of the terminal it writes to or reads from
+    pub fn is_foreground_job() -> bool {
+        unsafe { [0, 1, 2].into_iter().find(|fd| isatty(*fd) == 1).is_some_and(|fd| tcgetpgrp(fd) == getpgrp()) }
+    }
+
+    fn get_target(index: usize, pid: i32) -> i32 {
+        match PROCESS_GROUPS[index].load(Ordering::SeqCst) {
+            true => -pid,
+            false => pid,
+        }
+    }
+
+    // Only async-signal-safe calls are made here
+    extern "C" fn interrupt(signum: i32) {
+        let is_repeated = INTERRUPTION.swap(signum, Ordering::SeqCst) != 0;
+
+        for (index, pid) in PIDS.iter().enumerate() {
+            let pid = pid.load(Ordering::SeqCst);
+            // The terminal already sent Ctrl+C to the tasks that share the group of rask
+            let is_signalled_by_terminal = signum == SIGINT && !PROCESS_GROUPS[index].load(Ordering::SeqCst);
+            if pid > 0 && (is_repeated || !is_signalled_by_terminal) {
+                let stop_signal = match is_repeated {
+                    true => SIGKILL,
+                    false => STOP_SIGNALS[index].load(Ordering::SeqCst),
+                };
+                unsafe { kill(get_target(index, pid), stop_signal) };
+            }
+        }
+
+        if is_repeated {
+            unsafe { _exit(128 + signum) }
+        }
+    }
+
+    // Whether the signal reached the pid, or the group for a negative pid
+    pub fn send_signal(target: i32, signum: i32) -> bool {
+        unsafe { kill(target, signum) == 0 }
+    }
+
+    // Tasks that outlast their grace period after an interrupt are killed, the workers waiting for them then reap them.
+    // The handler can only signal the tasks, the processes that tasks in the group of rask started are stopped from here.
+    fn kill_after_grace_period() {
+        while INTERRUPTION.load(Ordering::SeqCst) == 0 {
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let signum: i32 = INTERRUPTION.load(Ordering::SeqCst);
+        let mut descendants: Vec<i32> = vec![];
+        for (index, pid) in PIDS.iter().enumerate() {
+            let pid = pid.load(Ordering::SeqCst);
+            if pid > 0 && !PROCESS_GROUPS[index].load(Ordering::SeqCst) {
+                for descendant in super::find_descendants(pid as u32) {
+                    if signum != SIGINT {
+                        send_signal(descendant, STOP_SIGNALS[index].load(Ordering::SeqCst));
+                    }
+                    descendants.push(descendant);
+                }
+            }
+        }
+
+        let grace_millis: u64 = PIDS.iter().enumerate().filter(|(_, pid)| pid.load(Ordering::SeqCst) > 0).map(|(index, _)| GRACE_MILLIS[index].load(Ordering::SeqCst)).max().unwrap_or_default();
+        thread::sleep(Duration::from_millis(grace_millis));
+
+        for (index, pid) in PIDS.iter().enumerate() {
+            let pid = pid.load(Ordering::SeqCst);
+            if pid > 0 {
+                send_signal(get_target(index, pid), SIGKILL);
+            }
+        }
+        for descendant in descendants {
+            send_signal(descendant, SIGKILL);
+        }
+    }
+
+    pub fn get_interruption() -> Option<i32> {
+        match INTERRUPTION.load(Ordering::SeqCst) {
+            0 => None,
+            signum => Some(signum),
+        }
+    }
+
+    pub fn track(pid: u32, process_group: bool, stop_signal: i32, grace_period: Duration) {
+        HANDLER.call_once(|| {
+            unsafe {
+                signal(SIGINT, interrupt);
+                signal(SIGTERM, interrupt);
+            }
+            thread::spawn(kill_after_grace_period);
+        });
+
+        // With every slot taken the task is still stopped on timeouts, only not on interrupts
+        if let Some(index) = PIDS.iter().position(|slot| slot.compare_exchange(0, pid as i32, Ordering::SeqCst, Ordering::SeqCst).is_ok()) {
+            PROCESS_GROUPS[index].store(process_group, Ordering::SeqCst);
+            STOP_SIGNALS[index].store(stop_signal, Ordering::SeqCst);
+            GRACE_MILLIS[index].store(grace_period.as_millis() as u64, Ordering::SeqCst);
+        }
+    }
+
+    pub fn untrack(pid: u32) {
+        for slot in &PIDS {
+            let _ = slot.compare_exchange(pid as i32, 0, Ordering::SeqCst, Ordering::SeqCst);
+        }
+    }
+}
+
+pub fn build_shell_command(command: &str) -> Command {
+    let mut process = Command::new(DEFAULT_SHELL);
+    process.arg(shell_command_flag(DEFAULT_SHELL)).arg(command);
+
+    process
+}
+
+// Credentials are requested once before the run, so a parallel run never stalls on a password prompt
+pub fn ensure_privileges(tasks: &[&Task]) -> Result<(), String> {
+    let privileged_keys: Vec<String> = tasks
+        .iter()
+        .filter(|task| task.options.requires_privileges())
+        .map(|task| task.key.clone())
+        .collect();
+
+    if privileged_keys.is_empty() {
+        return Ok(());
+    }
+
+    validate_privileges(&privileged_keys)
+}
+
+#[cfg(unix)]
+fn validate_privileges(privileged_keys: &[String]) -> Result<(), String> {
+    use std::io::{stdin, IsTerminal};
+
+    let sudo = find_executable("sudo")
+        .ok_or(format!("The tasks {} require sudo, but sudo could not be found", privileged_keys.join(", ")))?;
+
+    let interactive = stdin().is_terminal();
+    let validated = Command::new(sudo)
+        .arg(match interactive { true => "-v", false => "-nv" })
+        .status()
+        .is_ok_and(|status| status.success());
+
+    match (validated, interactive) {
+        (true, _) => Ok(()),
+        (false, true) => Err(format!("Unable to obtain sudo credentials for the tasks {}", privileged_keys.join(", "))),
+        (false, false) => Err(format!("The tasks {} require sudo, which needs a password while rask is not running interactively. Run rask from a terminal or allow these commands without a password", privileged_keys.join(", "))),
+    }
+}
+
+#[cfg(not(unix))]
+fn validate_privileges(privileged_keys: &[String]) -> Result<(), String> {
+    Err(format!("The tasks {} use sudo or user, which are only supported on Unix", privileged_keys.join(", ")))
+}
+
+fn privilege_prefix(options: &TaskOptions, env: &HashMap<String, String>) -> Vec<String> {
+    if !options.requires_privileges() {
+        return vec![];
+    }
+
+    // Non-interactive, the credentials were validated before the run started
+    let mut prefix: Vec<String> = vec!["sudo".to_string(), "-n".to_string()];
+    if let Some(user) = &options.user {
+        prefix.extend(["-u".to_string(), user.clone()]);
+    }
+
+    // sudo resets the environment, keep the variables rask provides
+    if !env.is_empty() {
+        let mut names: Vec<&String> = env.keys().collect();
+        names.sort();
+        prefix.push(format!("--preserve-env={}", names.iter().map(|name| name.as_str()).collect::<Vec<&str>>().join(",")));
+    }
+    prefix.push("--".to_string());
+
+    prefix
+}
+
+// The tail of the output of a task, shared between the threads that stream it
+pub type CapturedOutput = Arc<Mutex<VecDeque<String>>>;
+
+pub const CAPTURED_LINE_LIMIT: usize = 200;
+
+// Streams the piped output of a child through the writer while keeping its last lines, or only keeps them without a writer.
+// Every line passes the failure watch, the captured tail may have dropped the line that matched.
+pub fn tee_output(child: &mut Child, captured_output: &Option<CapturedOutput>, writer: Option<TaskWriter>, failure_watch: &Option<FailureWatch>) -> Vec<JoinHandle<()>> {
+    let mut handles: Vec<JoinHandle<()>> = vec![];
+
+    if let Some(stdout) = child.stdout.take() {
+        handles.push(tee_stream(stdout, Stream::STDOUT, captured_output.clone(), writer.clone(), failure_watch.clone()));
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        handles.push(tee_stream(stderr, Stream::STDERR, captured_output.clone(), writer, failure_watch.clone()));
+    }
+
+    handles
+}
+
+// A line without a newline is passed on once the output went quiet for this long, so prompts and progress show up
+const PARTIAL_LINE_IDLE_TIME: Duration = Duration::from_millis(200);
+
+fn tee_stream<R: Read + Send + 'static>(mut stream: R, target: Stream, captured_output: Option<CapturedOutput>, writer: Option<TaskWriter>, failure_watch: Option<FailureWatch>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        // Reads block, so they happen on their own thread and this one notices when the output goes quiet
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        thread::spawn(move || {
+            let mut buffer = [0; 8192];
+            while let Ok(size @ 1..) = stream.read(&mut buffer) {
+                if sender.send(buffer[..size].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let handle_line = |line: &[u8]| {
+            if let Some(writer) = &writer {
+                writer.write_line(target, line);
+            }
+
+            if let Some(failure_watch) = &failure_watch {
+                failure_watch.inspect(String::from_utf8_lossy(line).trim_end());
+            }
+
+            if let Some(Ok(mut captured_output)) = captured_output.as_ref().map(|captured_output| captured_output.lock()) {
+                if captured_output.len() == CAPTURED_LINE_LIMIT {
+                    captured_output.pop_front();
+                }
+                captured_output.push_back(String::from_utf8_lossy(line).trim_end().to_string());
+            }
+        };
+
+        let mut pending: Vec<u8> = vec![];
+        loop {
+            match receiver.recv_timeout(PARTIAL_LINE_IDLE_TIME) {
+                Ok(chunk) => {
+                    pending.extend_from_slice(&chunk);
+                    let complete: usize = pending.iter().rposition(|byte| *byte == b'\n').map_or(0, |end| end + 1);
+                    for line in pending[..complete].split_inclusive(|byte| *byte == b'\n') {
+                        handle_line(line);
+                    }
+                    pending.drain(..complete);
+                },
+                Err(RecvTimeoutError::Timeout) if !pending.is_empty() => handle_line(&std::mem::take(&mut pending)),
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        if !pending.is_empty() {
+            handle_line(&pending);
+        }
+    })
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum ProcessOutcome {
+    COMPLETED(ExitStatus),
+    // Stopped by rask because the deadline passed
+    TERMINATED,
+    // Stopped with `rask kill`
+    CANCELLED,
+    // Exited with 0, but printed this line matching the failure_pattern of the task
+    MATCHED(String),
+}
+
+// The CPU time and peak memory of a process, with those of the children it waited for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    // In seconds
+    pub user_cpu: f64,
+    pub system_cpu: f64,
+    // In bytes, of the largest process rather than their sum
+    pub max_rss: u64,
+}
+
+impl ResourceUsage {
+    // Steps and dependencies run one after another, their CPU time adds up while only the largest one counts for memory
+    pub fn add(self, other: ResourceUsage) -> Self {
+        ResourceUsage {
+            user_cpu: self.user_cpu + other.user_cpu,
+            system_cpu: self.system_cpu + other.system_cpu,
+            max_rss: self.max_rss.max(other.max_rss),
+        }
+    }
+
+    // Like 1.25s
+    pub fn describe_cpu(&self) -> String {
+        format!("{:.2}s", self.user_cpu + self.system_cpu)
+    }
+
+    // Like 312M, in the units max_memory takes
+    pub fn describe_max_rss(&self) -> String {
+        let units: [(u64, &str); 3] = [(1024u64.pow(3), "G"), (1024u64.pow(2), "M"), (1024, "K")];
+
+        match units.iter().find(|(size, _)| self.max_rss >= *size) {
+            Some((size, unit)) => format!("{:.0}{}", self.max_rss as f64 / *size as f64, unit),
+            None => format!("{}B", self.max_rss),
+        }
+    }
+}
+
+pub fn wait_for(child: &mut Child, deadline: Option<Instant>, options: &TaskOptions) -> Result<(ProcessOutcome, Option<ResourceUsage>), String> {
+    let Some(deadline) = deadline else {
+        let (status, resource_usage) = reap(child, true)?.ok_or("Failed to wait for command")?;
+        release(child);
+        return Ok((ProcessOutcome::COMPLETED(status), resource_usage));
+    };
+
+    loop {
+        if let Some((status, resource_usage)) = reap(child, false)? {
+            release(child);
+            return Ok((ProcessOutcome::COMPLETED(status), resource_usage));
+        }
+
+        // Terminated processes are reaped by terminate, what they used until then is not reported
+        if Instant::now() >= deadline {
+            terminate(child, options)?;
+            return Ok((ProcessOutcome::TERMINATED, None));
+        }
+
+        sleep(POLL_INTERVAL);
+    }
+}
+
+// Like Child::wait, or Child::try_wait without blocking, with the resources the process used
+#[cfg(unix)]
+fn reap(child: &mut Child, blocking: bool) -> Result<Option<(ExitStatus, Option<ResourceUsage>)>, String> {
+    resource_usage::wait(child.id(), blocking)
+        .map(|reaped| reaped.map(|(status, resource_usage)| (status, Some(resource_usage))))
+        .map_err(|err| format!("Failed to wait for command: {}", err))
+}
+
+#[cfg(not(unix))]
+fn reap(child: &mut Child, blocking: bool) -> Result<Option<(ExitStatus, Option<ResourceUsage>)>, String> {
+    let status = match blocking {
+        true => child.wait().map(Some),
+        false => child.try_wait(),
+    };
+
+    status
+        .map(|status| status.map(|status| (status, None)))
+        .map_err(|err| format!("Failed to wait for command: {}", err))
+}
+
+// wait4 reaps a child like waitpid, and fills in what it and the children it waited for used.
+// The child is reaped behind the back of std, so it must not be waited for again.
+#[cfg(unix)]
+mod resource_usage {
+    use std::ffi::c_long;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use crate::utils::process::ResourceUsage;
+
+    const WNOHANG: i32 = 1;
+    // Linux reports the peak memory in kilobytes, macOS in bytes
+    #[cfg(target_vendor = "apple")]
+    const MAX_RSS_UNIT: u64 = 1;
+    #[cfg(not(target_vendor = "apple"))]
+    const MAX_RSS_UNIT: u64 = 1024;
+
+    #[repr(C)]
+    struct Timeval {
+        tv_sec: c_long,
+        #[cfg(target_vendor = "apple")]
+        tv_usec: i32,
+        #[cfg(not(target_vendor = "apple"))]
+        tv_usec: c_long,
+    }
+
+    #[repr(C)]
+    struct Rusage {
+        ru_utime: Timeval,
+        ru_stime: Timeval,
+        ru_maxrss: c_long,
+        // Page faults, context switches and the like, not reported
+        ru_other: [c_long; 13],
+    }
+
+    extern "C" {
+        fn wait4(pid: i32, status: *mut i32, options: i32, rusage: *mut Rusage) -> i32;
+    }
+
+    fn get_seconds(timeval: &Timeval) -> f64 {
+        timeval.tv_sec as f64 + timeval.tv_usec as f64 / 1_000_000.0
+    }
+
+    // None while the child still runs, only when not blocking
+    pub fn wait(pid: u32, blocking: bool) -> io::Result<Option<(ExitStatus, ResourceUsage)>> {
+        let mut status: i32 = 0;
+        let mut rusage = Rusage { ru_utime: Timeval { tv_sec: 0, tv_usec: 0 }, ru_stime: Timeval { tv_sec: 0, tv_usec: 0 }, ru_maxrss: 0, ru_other: [0; 13] };
+        let options: i32 = if blocking { 0 } else { WNOHANG };
+
+        loop {
+            match unsafe { wait4(pid as i32, &mut status, options, &mut rusage) } {
+                0 => return Ok(None),
+                -1 => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                },
+                _ => break,
+            }
+        }
+
+        let resource_usage = ResourceUsage {
+            user_cpu: get_seconds(&rusage.ru_utime),
+            system_cpu: get_seconds(&rusage.ru_stime),
+            max_rss: rusage.ru_maxrss.max(0) as u64 * MAX_RSS_UNIT,
+        };
+
+        Ok(Some((ExitStatus::from_raw(status), resource_usage)))
+    }
+}
+
+// Asks the task to stop with its stop signal, and kills it when it did not stop within the grace period
+pub fn terminate(child: &mut Child, options: &TaskOptions) -> Result<(), String> {
+    // The task is reaped while waiting, so only the processes it started keep the targets alive
+    #[cfg(unix)]
+    stop_targets(child.id(), leads_process_groups(), options, |targets| {
+        child.try_wait().map_err(|err| format!("Failed to wait for command: {}", err))?;
+        Ok(!are_targets_running(targets))
+    })?;
+
+    #[cfg(not(unix))]
+    let _ = options;
+
+    let _ = child.kill();
+    child.wait().map_err(|err| format!("Failed to wait for command: {}", err))?;
+    release(child);
+
+    Ok(())
+}
+
+fn resource_limit_prefix(key: &str, options: &TaskOptions) -> Vec<String> {
+    let TaskOptions { nice, max_memory, .. } = options;
+    let mut prefix: Vec<String> = vec![];
+
+    if let Some(max_memory) = max_memory {
+        prefix.extend(memory_limit_prefix(key, *max_memory));
+    }
+
+    if let Some(nice) = nice {
+        prefix.extend(priority_prefix(key, *nice));
+    }
+
+    prefix
+}
+
+#[cfg(target_os = "linux")]
+fn memory_limit_prefix(key: &str, max_memory: u64) -> Vec<String> {
+    use std::path::Path;
+    use std::process::Stdio;
+    use std::sync::OnceLock;
+
+    // The cgroup v2 limit is applied through a transient systemd scope, which works without root.
+    static SYSTEMD_RUN: OnceLock<Option<PathBuf>> = OnceLock::new();
+    let systemd_run = SYSTEMD_RUN.get_or_init(|| {
+        if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+            return None;
+        }
+
+        let systemd_run = find_executable("systemd-run")?;
+        let usable = Command::new(&systemd_run)
+            .args(["--user", "--scope", "--quiet", "--collect", "true"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+
+        usable.then_some(systemd_run)
+    });
+
+    match systemd_run {
+        Some(systemd_run) => vec![
+            systemd_run.to_string_lossy().to_string(),
+            "--user".to_string(),
+            "--scope".to_string(),
+            "--quiet".to_string(),
+            "--collect".to_string(),
+            "-p".to_string(),
+            format!("MemoryMax={}", max_memory),
+            "--".to_string(),
+        ],
+        None => {
+            output::warning(&format!("Unable to apply max_memory to \"{}\", cgroup v2 with a systemd user session is required", key));
+            vec![]
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn memory_limit_prefix(key: &str, _max_memory: u64) -> Vec<String> {
+    output::warning(&format!("max_memory is only supported on Linux, running \"{}\" without a memory limit", key));
+    vec![]
+}
+
+#[cfg(unix)]
+fn priority_prefix(_key: &str, nice: i32) -> Vec<String> {
+    let mut prefix: Vec<String> = vec!["nice".to_string(), "-n".to_string(), nice.to_string()];
+
+    #[cfg(target_os = "linux")]
+    if let Some(ionice) = find_executable("ionice") {
+        // Best-effort IO class, the level follows the nice value (0 is the highest priority, 7 the lowest)
+        let level = ((nice + 20) / 5).clamp(0, 7);
+        prefix.extend([ionice.to_string_lossy().to_string(), "-c".to_string(), "2".to_string(), "-n".to_string(), level.to_string()]);
+    }
+
+    prefix
+}
+
+#[cfg(not(unix))]
+fn priority_prefix(key: &str, _nice: i32) -> Vec<String> {
+    output::warning(&format!("nice is not supported on this platform, running \"{}\" with the default priority", key));
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn adds_and_describes_resource_usage() {
+        let usage = ResourceUsage { user_cpu: 1.0, system_cpu: 0.25, max_rss: 300 * 1024 * 1024 };
+        let other_usage = ResourceUsage { user_cpu: 0.5, system_cpu: 0.0, max_rss: 2048 };
+
+        assert_eq!(usage.add(other_usage), ResourceUsage { user_cpu: 1.5, system_cpu: 0.25, max_rss: 300 * 1024 * 1024 });
+        assert_eq!((usage.describe_cpu(), usage.describe_max_rss()), ("1.25s".to_string(), "300M".to_string()));
+        assert_eq!(other_usage.describe_max_rss(), "2K");
+        assert_eq!(ResourceUsage { max_rss: 512, ..usage }.describe_max_rss(), "512B");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn nice_lowers_the_priority_of_a_task() {
+        let read_niceness = |task: &Task| -> i32 { String::from_utf8(build_command(task).output().unwrap().stdout).unwrap().trim().parse().unwrap() };
+        let niceness = read_niceness(&testing::create_task("build", "nice"));
+
+        let options = TaskOptions { nice: Some(5), ..Default::default() };
+        let task = Task { options, ..testing::create_task("build", "nice") };
+        assert_eq!(read_niceness(&task), (niceness + 5).min(19));
+    }
+
+    #[test]
+    fn privileged_tasks_run_through_sudo_with_their_env() {
+        let env = HashMap::from([("STAGE".to_string(), "prod".to_string()), ("API_URL".to_string(), "http://localhost".to_string())]);
+        assert!(privilege_prefix(&TaskOptions::default(), &env).is_empty());
+
+        let options = TaskOptions { sudo: true, ..Default::default() };
+        assert_eq!(privilege_prefix(&options, &HashMap::new()), vec!["sudo", "-n", "--"]);
+
+        let options = TaskOptions { user: Some("deploy".to_string()), ..Default::default() };
+        assert_eq!(privilege_prefix(&options, &env), vec!["sudo", "-n", "-u", "deploy", "--preserve-env=API_URL,STAGE", "--"]);
+    }
+
+    // Starts a shell that runs sleep in the background, with the pid of sleep
+    #[cfg(unix)]
+    fn spawn_with_grandchild(command: &mut Command) -> (Child, i32) {
+        use std::io::{BufRead, BufReader};
+
+        let mut child = command.stdout(std::process::Stdio::piped()).spawn().unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+
+        (child, line.trim().parse().unwrap())
+    }
+
+    // A killed process may take a moment to be gone
+    #[cfg(unix)]
+    fn is_stopped(pid: i32) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while are_targets_running(&[pid]) && Instant::now() < deadline {
+            sleep(POLL_INTERVAL);
+        }
+
+        !are_targets_running(&[pid])
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stopping_a_task_stops_the_processes_it_started() {
+        let command = "sleep 100 & echo $!; wait";
+        let options = TaskOptions { stop_grace_period: Some(Duration::from_millis(200)), ..Default::default() };
+
+        // A task in the group of rask, like in the foreground of a terminal, as `rask kill` stops it
+        let (mut child, grandchild) = spawn_with_grandchild(&mut build_shell_command(command));
+        stop_task(child.id(), false, &options).unwrap();
+        child.wait().unwrap();
+        assert!(is_stopped(grandchild));
+
+        // A task of this run, stopped on a timeout or a restart of rask dev
+        let task = Task { options: options.clone(), ..testing::create_task("serve", command) };
+        let (mut child, grandchild) = spawn_with_grandchild(&mut build_command(&task));
+        terminate(&mut child, &task.options).unwrap();
+        assert!(is_stopped(grandchild));
+    }
+}
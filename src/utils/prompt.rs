@@ -0,0 +1,81 @@
+use std::io::{stdin, stdout, BufRead, IsTerminal, Write};
+use crate::utils::config::Task;
+use crate::utils::exit::{ExitError, ExitKind};
+use crate::utils::output;
+use crate::utils::output::{Stream, Tone};
+
+pub fn confirm(question: &str) -> Result<bool, String> {
+    if !stdin().is_terminal() {
+        return Err(format!("{} Unable to ask for confirmation in a non-interactive shell, pass --yes to continue", question));
+    }
+
+    print!("{} [y/N] ", question);
+    stdout().flush().map_err(|err| format!("Failed to write to stdout: {}", err))?;
+
+    let mut answer = String::new();
+    stdin().read_line(&mut answer).map_err(|err| format!("Failed to read answer: {}", err))?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// Reads one answer, an empty answer takes the default
+pub fn ask(question: &str, default: &str) -> Result<String, String> {
+    if !stdin().is_terminal() {
+        return Err(format!("{} Unable to ask in a non-interactive shell", question));
+    }
+
+    match default.is_empty() {
+        true => print!("{} ", question),
+        false => print!("{} [{}] ", question, default),
+    }
+    stdout().flush().map_err(|err| format!("Failed to write to stdout: {}", err))?;
+
+    read_answer(&mut stdin().lock(), question, default)
+}
+
+// Apart from ask, which only reads from a terminal
+fn read_answer(input: &mut impl BufRead, question: &str, default: &str) -> Result<String, String> {
+    let mut answer = String::new();
+    let read = input.read_line(&mut answer).map_err(|err| format!("Failed to read answer: {}", err))?;
+    if read == 0 {
+        return Err(format!("{} No answer, the input was closed", question));
+    }
+
+    Ok(match answer.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    })
+}
+
+// Shows what the tasks marked with `confirm` are about to run and where, before anything runs
+pub fn confirm_tasks(tasks: &[&Task], yes: bool) -> Result<(), ExitError> {
+    let confirmed_tasks: Vec<&&Task> = tasks.iter().filter(|task| task.options.confirm).collect();
+    if yes || confirmed_tasks.is_empty() {
+        return Ok(());
+    }
+
+    for task in &confirmed_tasks {
+        output::line("CONFIRM", Tone::WARNING, &format!("{} {}", task.command, output::paint(&format!("@ {:?}", task.directory), Tone::MUTED, Stream::STDOUT)));
+    }
+
+    let keys: Vec<&str> = confirmed_tasks.iter().map(|task| task.key.as_str()).collect();
+    match confirm(&format!("Run {}?", keys.join(", ")))? {
+        true => Ok(()),
+        false => Err(ExitError::new(ExitKind::CANCELLED, format!("Cancelled, {} was not confirmed", keys.join(", ")))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_answers_take_the_default() {
+        let mut input: &[u8] = b"\n  packages/*  \n";
+
+        assert_eq!(read_answer(&mut input, "Name:", "web"), Ok("web".to_string()));
+        assert_eq!(read_answer(&mut input, "Directories:", ""), Ok("packages/*".to_string()));
+        // A closed input would otherwise keep taking the default of a question asked in a loop
+        assert_eq!(read_answer(&mut input, "Command:", "make"), Err("Command: No answer, the input was closed".to_string()));
+    }
+}
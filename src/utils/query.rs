@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+use clap::ValueEnum;
+use globset::{Glob, GlobMatcher};
+use serde::Serialize;
+use crate::utils::config;
+use crate::utils::config::{ConfigStructure, ConfigTask};
+
+// Every task of the workspace once, with the depends_on edges between them, yaml and engine tasks alike
+#[derive(Debug, Clone)]
+pub struct TaskGraph {
+    pub nodes: Vec<TaskNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskNode {
+    pub config_name: String,
+    // Relative to the entry directory, . for the entry config
+    pub directory: PathBuf,
+    pub key: String,
+    // Positions in the nodes of the graph
+    pub dependencies: Vec<usize>,
+}
+
+impl TaskNode {
+    pub fn get_name(&self) -> String {
+        format!("{}#{}", self.config_name, self.key)
+    }
+}
+
+pub fn build_task_graph(config_structure: &ConfigStructure) -> TaskGraph {
+    let entry_directory: PathBuf = config_structure.config.dir_path.clone();
+    let mut config_tasks: Vec<(&ConfigStructure, &ConfigTask)> = vec![];
+    collect_config_tasks(config_structure, &mut vec![], &mut config_tasks);
+
+    let mut nodes: Vec<TaskNode> = config_tasks
+        .iter()
+        .map(|(ConfigStructure { config, .. }, config_task)| TaskNode {
+            config_name: config.name.clone(),
+            directory: match config.dir_path.strip_prefix(&entry_directory) {
+                Ok(directory) if directory.as_os_str().is_empty() => PathBuf::from("."),
+                Ok(directory) => directory.to_path_buf(),
+                Err(_) => config.dir_path.clone(),
+            },
+            key: config_task.key.clone(),
+            dependencies: vec![],
+        })
+        .collect();
+
+    for (position, (ConfigStructure { config, .. }, config_task)) in config_tasks.iter().enumerate() {
+        nodes[position].dependencies = config::find_task_dependencies(config_structure, config, config_task)
+            .into_iter()
+            .filter_map(|(config_path, key)| config_tasks.iter().position(|(ConfigStructure { config, .. }, config_task)| config.file_path == config_path && config_task.key == key))
+            .collect();
+    }
+
+    TaskGraph { nodes }
+}
+
+// Depth first from the entry config, every config once with children by directory and tasks by key
+fn collect_config_tasks<'a>(config_structure: &'a ConfigStructure, visited: &mut Vec<PathBuf>, config_tasks: &mut Vec<(&'a ConfigStructure, &'a ConfigTask)>) {
+    let ConfigStructure { config, children, .. } = config_structure;
+    if visited.contains(&config.file_path) {
+        return;
+    }
+    visited.push(config.file_path.clone());
+
+    let mut tasks: Vec<&ConfigTask> = config.tasks.iter().collect();
+    tasks.sort_by(|task, other_task| task.key.cmp(&other_task.key));
+    config_tasks.extend(tasks.into_iter().map(|config_task| (config_structure, config_task)));
+
+    let mut children: Vec<&ConfigStructure> = children.iter().collect();
+    children.sort_by(|child, other_child| child.config.dir_path.cmp(&other_child.config.dir_path));
+    for child in children {
+        collect_config_tasks(child, visited, config_tasks);
+    }
+}
+
+// A query like deps(api#build), rdeps(lib#build, 1) or *#test. Tasks are addressed as config#key, where the
+// config is its name or its directory relative to the entry, both parts are glob patterns and a key alone matches every config.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    TASKS(String),
+    // The tasks a task needs, itself included, up to an optional depth
+    DEPS(String, Option<usize>),
+    // The tasks that need a task, itself included
+    RDEPS(String, Option<usize>),
+}
+
+pub fn parse_query(expression: &str) -> Result<Query, String> {
+    let expression = expression.trim();
+    let Some((function, rest)) = expression.split_once('(') else {
+        return Ok(Query::TASKS(expression.to_string()));
+    };
+    let arguments: &str = rest.strip_suffix(')').ok_or(format!("Invalid query \"{}\", missing the closing parenthesis", expression))?;
+
+    let (pattern, depth): (&str, Option<usize>) = match arguments.split_once(',') {
+        Some((pattern, depth)) => (pattern, Some(depth.trim().parse().map_err(|_| format!("Invalid depth \"{}\" in query \"{}\"", depth.trim(), expression))?)),
+        None => (arguments, None),
+    };
+    let pattern: String = pattern.trim().to_string();
+
+    match function.trim() {
+        "deps" => Ok(Query::DEPS(pattern, depth)),
+        "rdeps" => Ok(Query::RDEPS(pattern, depth)),
+        function => Err(format!("Unknown query function \"{}\", expected deps or rdeps", function)),
+    }
+}
+
+fn create_matcher(pattern: &str) -> Result<GlobMatcher, String> {
+    Glob::new(pattern).map(|glob| glob.compile_matcher()).map_err(|err| format!("Invalid pattern \"{}\": {}", pattern, err))
+}
+
+fn find_matching_nodes(task_graph: &TaskGraph, pattern: &str) -> Result<Vec<usize>, String> {
+    let (config_matcher, key_matcher): (Option<GlobMatcher>, GlobMatcher) = match pattern.split_once('#') {
+        Some((config_pattern, key_pattern)) => (Some(create_matcher(config_pattern)?), create_matcher(key_pattern)?),
+        None => (None, create_matcher(pattern)?),
+    };
+
+    Ok(task_graph.nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| {
+            let matches_config = config_matcher.as_ref().is_none_or(|config_matcher| config_matcher.is_match(&node.config_name) || config_matcher.is_match(&node.directory));
+            matches_config && key_matcher.is_match(&node.key)
+        })
+        .map(|(position, _)| position)
+        .collect())
+}
+
+// Breadth first, so a depth of 1 gives the direct dependencies or dependents
+fn walk(task_graph: &TaskGraph, start: Vec<usize>, depth: Option<usize>, get_next: &dyn Fn(usize) -> Vec<usize>) -> Vec<usize> {
+    let mut found: Vec<usize> = start.clone();
+    let mut frontier: Vec<usize> = start;
+    let mut level: usize = 0;
+
+    while !frontier.is_empty() && depth.is_none_or(|depth| level < depth) {
+        let mut next_frontier: Vec<usize> = vec![];
+        for next in frontier.into_iter().flat_map(get_next) {
+            if !found.contains(&next) && next < task_graph.nodes.len() {
+                found.push(next);
+                next_frontier.push(next);
+            }
+        }
+        frontier = next_frontier;
+        level += 1;
+    }
+
+    found
+}
+
+// The positions of the matching tasks, in the order of the graph
+pub fn run_query(task_graph: &TaskGraph, query: &Query) -> Result<Vec<usize>, String> {
+    let mut positions: Vec<usize> = match query {
+        Query::TASKS(pattern) => find_matching_nodes(task_graph, pattern)?,
+        Query::DEPS(pattern, depth) => walk(task_graph, find_matching_nodes(task_graph, pattern)?, *depth, &|position| task_graph.nodes[position].dependencies.clone()),
+        Query::RDEPS(pattern, depth) => walk(task_graph, find_matching_nodes(task_graph, pattern)?, *depth, &|position| {
+            (0..task_graph.nodes.len()).filter(|dependent| task_graph.nodes[*dependent].dependencies.contains(&position)).collect()
+        }),
+    };
+    positions.sort();
+
+    Ok(positions)
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum QueryFormat {
+    // One config#key per line, for piping into other commands
+    #[default]
+    NAMES,
+    JSON,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub name: String,
+    pub config: String,
+    pub directory: PathBuf,
+    pub key: String,
+    pub depends_on: Vec<String>,
+}
+
+pub fn create_query_results(task_graph: &TaskGraph, positions: &[usize]) -> Vec<QueryResult> {
+    positions
+        .iter()
+        .map(|position| &task_graph.nodes[*position])
+        .map(|node| QueryResult {
+            name: node.get_name(),
+            config: node.config_name.clone(),
+            directory: node.directory.clone(),
+            key: node.key.clone(),
+            depends_on: node.dependencies.iter().map(|dependency| task_graph.nodes[*dependency].get_name()).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn queries_dependencies_and_dependents() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [apps/*, libs/*]\n")
+            .with_config("apps/api", "name: api\ntasks:\n  build:\n    command: cargo build\n    depends_on: [codegen, \"../../libs/core#build\"]\n  codegen: make gen\n  test: cargo test\n")
+            .with_config("apps/web", "name: web\ntasks:\n  build:\n    command: npm run build\n    depends_on: [\"core#build\"]\n")
+            .with_config("libs/core", "name: core\ntasks:\n  build:\n    command: make\n    depends_on: [\"util#build\"]\n")
+            .with_config("libs/util", "name: util\ntasks:\n  build: make\n");
+        let task_graph = build_task_graph(&workspace.load_structure("."));
+
+        let query = |expression: &str| -> Vec<String> {
+            let positions = run_query(&task_graph, &parse_query(expression).unwrap()).unwrap();
+            create_query_results(&task_graph, &positions).into_iter().map(|result| result.name).collect()
+        };
+
+        assert_eq!(query("deps(api#build)"), vec!["api#build", "api#codegen", "core#build", "util#build"]);
+        assert_eq!(query("deps(api#build, 1)"), vec!["api#build", "api#codegen", "core#build"]);
+        assert_eq!(query("rdeps(libs/util#build)"), vec!["api#build", "web#build", "core#build", "util#build"]);
+        assert_eq!(query("*#test"), vec!["api#test"]);
+        assert_eq!(create_query_results(&task_graph, &run_query(&task_graph, &Query::TASKS("web#build".to_string())).unwrap())[0].depends_on, vec!["core#build"]);
+        assert!(parse_query("tests(api#build)").is_err());
+    }
+}
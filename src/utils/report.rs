@@ -0,0 +1,345 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::utils::failure::FailureKind;
+use crate::utils::process::ResourceUsage;
+use crate::utils::file;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReportFormat {
+    #[default]
+    JSON,
+    // A result matrix and the failures, to paste into a pull request comment
+    MARKDOWN,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    SUCCESS,
+    FAILURE,
+    // Failed, but marked with `allow_failure` so the run continues
+    ALLOWED,
+    // Terminated because the run exceeded its time budget
+    TIMEOUT,
+    // Not run, the cache of the task found it up to date
+    SKIPPED,
+    // Stopped with `rask kill` while the rest of the run went on
+    CANCELLED,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskResult {
+    pub key: String,
+    pub directory: PathBuf,
+    // yaml, or the engine the task comes from
+    pub source: &'static str,
+    pub status: TaskStatus,
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureKind>,
+    // What likely fixes the failure, for common causes like a missing binary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    // The last lines of output, only captured for reports and kept for tasks that did not succeed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output: Vec<String>,
+    // In seconds, dependencies included
+    pub duration: f64,
+    // The CPU time and peak memory of the processes of the task and its dependencies, on unix only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_usage: Option<ResourceUsage>,
+    // The result of an earlier task of the run with the same command, which this task did not run again
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deduplicated: bool,
+}
+
+pub type TaskResults = Vec<TaskResult>;
+
+pub fn has_failures(results: &TaskResults) -> bool {
+    results.iter().any(|result| matches!(result.status, TaskStatus::FAILURE | TaskStatus::CANCELLED))
+}
+
+pub fn get_results_with_status(results: &TaskResults, status: TaskStatus) -> TaskResults {
+    results
+        .iter()
+        .filter(|result| result.status == status)
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+struct JsonReport<'a> {
+    success: bool,
+    duration: f32,
+    tasks: &'a TaskResults,
+}
+
+fn format_json_report(results: &TaskResults, duration: f32) -> Result<String, String> {
+    let report = JsonReport { success: !has_failures(results), duration, tasks: results };
+
+    serde_json::to_string_pretty(&report).map_err(|err| format!("Failed to serialize the report: {}", err))
+}
+
+pub fn write_json_report(report_path: &Path, results: &TaskResults, duration: f32) -> Result<(), String> {
+    file::write_file_content(&report_path.to_path_buf(), &format_json_report(results, duration)?)
+}
+
+// Sent as a bearer token when set
+pub const REPORT_TOKEN_ENV: &str = "RASK_REPORT_TOKEN";
+const UPLOAD_ATTEMPTS: u32 = 3;
+const UPLOAD_BACKOFF: Duration = Duration::from_secs(1);
+
+// POSTs the JSON report with curl, waiting twice as long after every failed attempt
+pub fn upload_json_report(report_url: &str, results: &TaskResults, duration: f32) -> Result<(), String> {
+    let curl_config: String = create_upload_config(report_url, &format_json_report(results, duration)?, std::env::var(REPORT_TOKEN_ENV).ok());
+    let mut backoff: Duration = UPLOAD_BACKOFF;
+
+    for attempt in 1..=UPLOAD_ATTEMPTS {
+        match post_with_curl(&curl_config) {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt == UPLOAD_ATTEMPTS => return Err(format!("Gave up after {} attempts: {}", UPLOAD_ATTEMPTS, err)),
+            Err(_) => {
+                sleep(backoff);
+                backoff *= 2;
+            },
+        }
+    }
+
+    Ok(())
+}
+
+// The config is passed on stdin, so the token and the report never show up in the process list
+fn create_upload_config(report_url: &str, content: &str, token: Option<String>) -> String {
+    let mut lines: Vec<String> = vec![
+        format!("url = {}", quote_curl_value(report_url)),
+        "request = \"POST\"".to_string(),
+        "header = \"Content-Type: application/json\"".to_string(),
+        format!("data-binary = {}", quote_curl_value(content)),
+    ];
+
+    if let Some(token) = token {
+        lines.push(format!("header = {}", quote_curl_value(&format!("Authorization: Bearer {}", token))));
+    }
+
+    lines.join("\n")
+}
+
+fn quote_curl_value(value: &str) -> String {
+    let escaped: String = value
+        .chars()
+        .map(|char| match char {
+            '\\' => "\\\\".to_string(),
+            '"' => "\\\"".to_string(),
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            _ => char.to_string(),
+        })
+        .collect();
+
+    format!("\"{}\"", escaped)
+}
+
+fn post_with_curl(curl_config: &str) -> Result<(), String> {
+    let mut child = Command::new("curl")
+        .args(["--config", "-", "--fail", "--silent", "--show-error", "--max-time", "30", "--output", "/dev/null"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to run curl: {}", err))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(curl_config.as_bytes()).map_err(|err| format!("Failed to pass the report to curl: {}", err))?;
+    }
+
+    let output = child.wait_with_output().map_err(|err| format!("Failed to wait for curl: {}", err))?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+pub fn write_report(report_path: &Path, report_format: ReportFormat, results: &TaskResults, duration: f32) -> Result<(), String> {
+    match report_format {
+        ReportFormat::JSON => write_json_report(report_path, results, duration),
+        ReportFormat::MARKDOWN => file::write_file_content(&report_path.to_path_buf(), &format_markdown_report(results, duration)),
+    }
+}
+
+// The status of every task per config, None where a config has no result for the task
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultMatrix {
+    pub tasks: Vec<String>,
+    pub rows: Vec<(String, Vec<Option<TaskStatus>>)>,
+}
+
+impl ResultMatrix {
+    // Only worth printing when it is an actual grid
+    pub fn is_multi_task(&self) -> bool {
+        self.tasks.len() > 1 && self.rows.len() > 1
+    }
+}
+
+pub fn get_status_symbol(status: Option<TaskStatus>) -> &'static str {
+    match status {
+        Some(TaskStatus::SUCCESS) => "✓",
+        Some(TaskStatus::FAILURE) => "✗",
+        Some(TaskStatus::ALLOWED) => "!",
+        Some(TaskStatus::TIMEOUT) => "⌛",
+        Some(TaskStatus::SKIPPED) => "-",
+        Some(TaskStatus::CANCELLED) => "⊘",
+        None => " ",
+    }
+}
+
+// Configs are named by their directory relative to the directory all of them share
+pub fn build_result_matrix(results: &TaskResults) -> ResultMatrix {
+    let mut tasks: Vec<String> = vec![];
+    let mut directories: Vec<&PathBuf> = vec![];
+    for TaskResult { key, directory, .. } in results {
+        if !tasks.contains(key) {
+            tasks.push(key.clone());
+        }
+        if !directories.contains(&directory) {
+            directories.push(directory);
+        }
+    }
+
+    let common_directory: PathBuf = find_common_directory(&directories);
+    let rows = directories
+        .iter()
+        .map(|directory| {
+            let statuses = tasks
+                .iter()
+                .map(|task| results.iter().find(|result| &result.key == task && &result.directory == *directory).map(|result| result.status))
+                .collect();
+            let name = match directory.strip_prefix(&common_directory) {
+                Ok(relative_directory) if !relative_directory.as_os_str().is_empty() => relative_directory.to_string_lossy().to_string(),
+                _ => ".".to_string(),
+            };
+
+            (name, statuses)
+        })
+        .collect();
+
+    ResultMatrix { tasks, rows }
+}
+
+fn find_common_directory(directories: &[&PathBuf]) -> PathBuf {
+    let Some(first_directory) = directories.first() else {
+        return PathBuf::new();
+    };
+
+    first_directory
+        .ancestors()
+        .find(|ancestor| directories.iter().all(|directory| directory.starts_with(ancestor)))
+        .unwrap_or(Path::new(""))
+        .to_path_buf()
+}
+
+pub fn format_markdown_report(results: &TaskResults, duration: f32) -> String {
+    let result_matrix = build_result_matrix(results);
+    let mut lines: Vec<String> = vec![
+        format!("### rask {}", match has_failures(results) { true => "failed", false => "succeeded" }),
+        String::new(),
+        format!("| Config | {} |", result_matrix.tasks.join(" | ")),
+        format!("|---|{}", "---|".repeat(result_matrix.tasks.len())),
+    ];
+
+    for (name, statuses) in &result_matrix.rows {
+        let symbols: Vec<&str> = statuses.iter().map(|status| get_status_symbol(*status)).collect();
+        lines.push(format!("| {} | {} |", name, symbols.join(" | ")));
+    }
+
+    lines.push(String::new());
+    lines.push(format!("{} tasks within {} seconds", results.len(), (duration * 100.0).round() / 100.0));
+
+    for TaskResult { key, directory, error, hint, output, .. } in get_results_with_status(results, TaskStatus::FAILURE) {
+        lines.push(String::new());
+        lines.push(format!("<details><summary>{} @ {} failed: {}</summary>", key, directory.to_string_lossy(), error.unwrap_or_default()));
+        lines.push(String::new());
+        if let Some(hint) = hint {
+            lines.push(format!("Hint: {}", hint));
+            lines.push(String::new());
+        }
+        lines.push("```".to_string());
+        lines.extend(output);
+        lines.push("```".to_string());
+        lines.push("</details>".to_string());
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_result(key: &str, directory: &str, status: TaskStatus) -> TaskResult {
+        TaskResult { key: key.to_string(), directory: PathBuf::from(directory), source: "yaml", status, error: None, failure: None, hint: None, output: vec![], duration: 0.0, resource_usage: None, deduplicated: false }
+    }
+
+    #[test]
+    fn reports_the_output_of_failed_tasks_as_json() {
+        let failed_result = TaskResult { error: Some("Exited with code 1".to_string()), output: vec!["1 failed".to_string()], ..create_result("test", "app", TaskStatus::FAILURE) };
+        let results = vec![create_result("lint", "app", TaskStatus::SUCCESS), failed_result];
+
+        let report: serde_json::Value = serde_json::from_str(&format_json_report(&results, 1.5).unwrap()).unwrap();
+
+        assert_eq!((&report["success"], &report["duration"]), (&serde_json::json!(false), &serde_json::json!(1.5)));
+        assert_eq!(report["tasks"][0]["status"], "success");
+        assert!(report["tasks"][0].get("output").is_none());
+        assert_eq!((&report["tasks"][1]["status"], &report["tasks"][1]["output"]), (&serde_json::json!("failure"), &serde_json::json!(["1 failed"])));
+    }
+
+    #[test]
+    fn quotes_the_report_in_the_curl_config() {
+        let curl_config = create_upload_config("https://ci.example.com/reports", "{\n  \"path\": \"C:\\\\repo\"\n}", Some("secret".to_string()));
+
+        assert_eq!(curl_config, [
+            "url = \"https://ci.example.com/reports\"",
+            "request = \"POST\"",
+            "header = \"Content-Type: application/json\"",
+            "data-binary = \"{\\n  \\\"path\\\": \\\"C:\\\\\\\\repo\\\"\\n}\"",
+            "header = \"Authorization: Bearer secret\"",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn builds_a_matrix_of_configs_and_tasks() {
+        let results = vec![
+            create_result("build", "/repo/packages/api", TaskStatus::SUCCESS),
+            create_result("test", "/repo/packages/api", TaskStatus::FAILURE),
+            create_result("build", "/repo", TaskStatus::SKIPPED),
+        ];
+
+        let result_matrix = build_result_matrix(&results);
+
+        assert!(result_matrix.is_multi_task());
+        assert_eq!(result_matrix, ResultMatrix {
+            tasks: vec!["build".to_string(), "test".to_string()],
+            rows: vec![
+                ("packages/api".to_string(), vec![Some(TaskStatus::SUCCESS), Some(TaskStatus::FAILURE)]),
+                (".".to_string(), vec![Some(TaskStatus::SKIPPED), None]),
+            ],
+        });
+    }
+
+    #[test]
+    fn formats_a_markdown_report() {
+        let results = vec![
+            create_result("build", "/repo/api", TaskStatus::SUCCESS),
+            create_result("build", "/repo/web", TaskStatus::FAILURE),
+        ];
+
+        let markdown = format_markdown_report(&results, 1.5);
+
+        assert!(markdown.starts_with("### rask failed\n\n| Config | build |\n|---|---|\n| api | ✓ |\n| web | ✗ |\n"));
+        assert!(markdown.contains("<details><summary>build @ /repo/web failed: </summary>"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs::{create_dir_all, remove_file};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::utils::config::{SortableTask, SortableTasks};
+use crate::utils::report::{TaskResult, TaskStatus};
+use crate::utils::{file, state};
+
+const RUNS_DIRECTORY: &str = "runs";
+
+// A task of the execution plan, tasks are identified by their key and directory
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlannedTask {
+    pub key: String,
+    pub directory: PathBuf,
+    pub order: u64,
+    pub succeeded: bool,
+}
+
+// The execution plan of a run, kept up to date so a killed run can be resumed
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunState {
+    pub id: String,
+    pub entry: PathBuf,
+    pub task_name: String,
+    // The task names of a task file, see --task-file
+    #[serde(default)]
+    pub task_names: Vec<String>,
+    pub strict: bool,
+    pub parallel: bool,
+    // The job slots of a parallel run, see --jobs and default_parallelism
+    #[serde(default)]
+    pub jobs: Option<usize>,
+    // Tasks of the entry config ran in every config, see --propagate
+    #[serde(default)]
+    pub propagate: bool,
+    // Read again on resume, see --env-file
+    #[serde(default)]
+    pub env_files: Vec<PathBuf>,
+    // The one-off task and where it ran, see --command and --scope
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub scope: Vec<String>,
+    // Runs of a plan file resume from the plan, not from the configs
+    #[serde(default)]
+    pub plan: Option<PathBuf>,
+    pub tasks: Vec<PlannedTask>,
+}
+
+impl RunState {
+    pub fn new(entry_config_path: &Path, task_name: &str, strict: bool, parallel: bool, sortable_tasks: &SortableTasks) -> Self {
+        RunState {
+            id: generate_run_id(),
+            entry: entry_config_path.to_path_buf(),
+            task_name: task_name.to_string(),
+            task_names: vec![],
+            strict,
+            parallel,
+            jobs: None,
+            propagate: false,
+            env_files: vec![],
+            command: None,
+            scope: vec![],
+            plan: None,
+            tasks: sortable_tasks
+                .iter()
+                .map(|SortableTask { task, order, .. }| PlannedTask { key: task.key.clone(), directory: task.directory.clone(), order: *order, succeeded: false })
+                .collect(),
+        }
+    }
+
+    // Allowed failures count as succeeded, a resumed run would not stop on them either
+    pub fn record(&mut self, task_result: &TaskResult) {
+        let TaskResult { key, directory, status, .. } = task_result;
+        let succeeded = matches!(status, TaskStatus::SUCCESS | TaskStatus::ALLOWED | TaskStatus::SKIPPED);
+
+        for planned_task in self.tasks.iter_mut() {
+            if &planned_task.key == key && &planned_task.directory == directory {
+                planned_task.succeeded = succeeded;
+            }
+        }
+    }
+
+    pub fn is_pending(&self, sortable_task: &SortableTask) -> bool {
+        let SortableTask { task, .. } = sortable_task;
+
+        self.tasks
+            .iter()
+            .any(|planned_task| planned_task.key == task.key && planned_task.directory == task.directory && !planned_task.succeeded)
+    }
+}
+
+// Sortable by creation time, the process id keeps runs started in the same second apart
+pub fn generate_run_id() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    format!("{}-{}", seconds, process::id())
+}
+
+// Ids are used in a path, anything but the generated `<seconds>-<pid>` could point outside of .rask/runs
+fn is_run_id(id: &str) -> bool {
+    id.split_once('-').is_some_and(|(seconds, pid)| [seconds, pid].iter().all(|part| !part.is_empty() && part.chars().all(|char| char.is_ascii_digit())))
+}
+
+fn get_run_state_path(entry_config_path: &Path, id: &str) -> Result<PathBuf, String> {
+    let entry_directory = entry_config_path.parent().ok_or("Failed to get parent directory")?;
+    let runs_directory = state::get_state_directory(entry_directory)?.join(RUNS_DIRECTORY);
+    create_dir_all(&runs_directory).map_err(|err| format!("Failed to create {:?}: {}", runs_directory, err))?;
+
+    Ok(runs_directory.join(format!("{}.json", id)))
+}
+
+pub fn write_run_state(run_state: &RunState) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(run_state).map_err(|err| err.to_string())?;
+    file::write_file_content(&get_run_state_path(&run_state.entry, &run_state.id)?, &content)
+}
+
+pub fn remove_run_state(run_state: &RunState) -> Result<(), String> {
+    let run_state_path = get_run_state_path(&run_state.entry, &run_state.id)?;
+    remove_file(&run_state_path).map_err(|err| format!("Failed to remove {:?}: {}", run_state_path, err))
+}
+
+pub fn read_run_state(start_directory: &Path, id: &str) -> Result<RunState, String> {
+    if !is_run_id(id) {
+        return Err(format!("Invalid run id \"{}\", expected an id like 1700000000-4242 as printed by rask run", id));
+    }
+
+    let file_name = Path::new(RUNS_DIRECTORY).join(format!("{}.json", id));
+    let run_state_path = state::find_state_file(start_directory, &file_name.to_string_lossy())
+        .ok_or(format!("Unable to find run {}, it either completed or never started here", id))?;
+
+    file::read_json_file::<RunState>(&run_state_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn only_reads_generated_run_ids() {
+        let workspace = TestWorkspace::new().with_file("secrets.json", "{}");
+
+        assert!(is_run_id(&generate_run_id()));
+        assert!(read_run_state(&workspace.path("."), "1700000000-4242").unwrap_err().starts_with("Unable to find run 1700000000-4242"));
+        for id in ["../../secrets", "1700000000", "1700000000-", "-4242", "1700000000-42/../42"] {
+            assert!(read_run_state(&workspace.path("."), id).unwrap_err().starts_with("Invalid run id"), "{}", id);
+        }
+    }
+}
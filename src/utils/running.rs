@@ -0,0 +1,161 @@
+use std::fs::{create_dir_all, read_dir, remove_file};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::utils::config::Task;
+use crate::utils::{file, process, state};
+
+const RUNNING_DIRECTORY: &str = "running";
+
+// A task process of a run, one file per process so `rask kill` can find it from another shell
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunningTask {
+    pub pid: u32,
+    // Whether the task leads a process group, which is then signalled as a whole
+    #[serde(default)]
+    pub process_group: bool,
+    pub key: String,
+    pub directory: PathBuf,
+    pub stop_signal: Option<String>,
+    pub stop_grace_period: Option<Duration>,
+    // When the process started, a pid that was handed to another process since has another start time
+    #[serde(default)]
+    pub start_time: Option<String>,
+}
+
+static RUNNING_DIRECTORY_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+// Runs register their processes next to the entry config, commands that never call this register nothing
+pub fn init(entry_directory: &Path) -> Result<(), String> {
+    let running_directory = state::get_state_directory(entry_directory)?.join(RUNNING_DIRECTORY);
+    create_dir_all(&running_directory).map_err(|err| format!("Failed to create {:?}: {}", running_directory, err))?;
+    let _ = RUNNING_DIRECTORY_PATH.set(running_directory);
+
+    Ok(())
+}
+
+// The registry is a convenience, a task still runs when it could not be registered
+pub fn register(pid: u32, task: &Task) {
+    if let Some(running_directory) = RUNNING_DIRECTORY_PATH.get() {
+        write_running_task(running_directory, pid, task);
+    }
+}
+
+fn write_running_task(running_directory: &Path, pid: u32, task: &Task) {
+    let running_task = RunningTask {
+        pid,
+        process_group: process::leads_process_groups(),
+        key: task.key.clone(),
+        directory: task.directory.clone(),
+        stop_signal: task.options.stop_signal.clone(),
+        stop_grace_period: task.options.stop_grace_period,
+        start_time: process::get_start_time(pid),
+    };
+    if let Ok(content) = serde_json::to_string_pretty(&running_task) {
+        let _ = file::write_file_content(&running_directory.join(format!("{}.json", pid)), &content);
+    }
+}
+
+// Whether the task was cancelled with `rask kill` while it ran
+pub fn unregister(pid: u32) -> bool {
+    RUNNING_DIRECTORY_PATH.get().is_some_and(|running_directory| remove_running_task(running_directory, pid))
+}
+
+fn remove_running_task(running_directory: &Path, pid: u32) -> bool {
+    let _ = remove_file(running_directory.join(format!("{}.json", pid)));
+    remove_file(get_cancel_marker_path(running_directory, pid)).is_ok()
+}
+
+// Processes that are gone, because their run crashed, are left out and their entries removed
+pub fn list_running_tasks(entry_directory: &Path) -> Vec<RunningTask> {
+    let running_directory = entry_directory.join(state::STATE_DIRECTORY).join(RUNNING_DIRECTORY);
+    let Ok(entries) = read_dir(&running_directory) else {
+        return vec![];
+    };
+
+    let mut running_tasks: Vec<RunningTask> = vec![];
+    for path in entries.flatten().map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|extension| extension == "json")) {
+        match file::read_json_file::<RunningTask>(&path) {
+            Ok(running_task) if is_still_running(&running_task) => running_tasks.push(running_task),
+            Ok(_) => {
+                let _ = remove_file(&path);
+            },
+            Err(_) => {},
+        }
+    }
+    running_tasks.sort_by(|a, b| (&a.directory, &a.key).cmp(&(&b.directory, &b.key)));
+
+    running_tasks
+}
+
+// The pid of a crashed run may belong to an unrelated process by now, which must never be signalled
+fn is_still_running(running_task: &RunningTask) -> bool {
+    match (&running_task.start_time, process::get_start_time(running_task.pid)) {
+        (Some(start_time), Some(current_start_time)) if *start_time != current_start_time => false,
+        _ => process::is_task_alive(running_task.pid, running_task.process_group),
+    }
+}
+
+// Marked before it is signalled, so the run reports the task as cancelled instead of failed
+pub fn mark_cancelled(entry_directory: &Path, pid: u32) -> Result<(), String> {
+    let running_directory = entry_directory.join(state::STATE_DIRECTORY).join(RUNNING_DIRECTORY);
+    file::write_file_content(&get_cancel_marker_path(&running_directory, pid), "")
+}
+
+fn get_cancel_marker_path(running_directory: &Path, pid: u32) -> PathBuf {
+    running_directory.join(format!("{}.cancelled", pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+    use crate::testing::{self, TestWorkspace};
+
+    #[test]
+    fn lists_registered_tasks_until_they_are_unregistered() {
+        let workspace = TestWorkspace::new();
+        let running_directory = workspace.path(".rask/running");
+        create_dir_all(&running_directory).unwrap();
+        let task = testing::create_task("build", "sleep 5");
+        let mut child = process::build_command(&task).stdin(Stdio::null()).spawn().unwrap();
+
+        write_running_task(&running_directory, child.id(), &task);
+        let keys: Vec<String> = list_running_tasks(&workspace.path(".")).into_iter().map(|running_task| running_task.key).collect();
+        assert_eq!(keys, vec!["build"]);
+
+        mark_cancelled(&workspace.path("."), child.id()).unwrap();
+        assert!(remove_running_task(&running_directory, child.id()));
+        assert!(list_running_tasks(&workspace.path(".")).is_empty());
+        assert!(!remove_running_task(&running_directory, child.id()));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn skips_and_removes_stale_entries() {
+        let workspace = TestWorkspace::new();
+        let running_directory = workspace.path(".rask/running");
+        create_dir_all(&running_directory).unwrap();
+
+        // A task that exited, and one whose pid now belongs to another process
+        let mut exited = Command::new("true").spawn().unwrap();
+        exited.wait().unwrap();
+        write_running_task(&running_directory, exited.id(), &testing::create_task("lint", "true"));
+        let reused_pid: u32 = std::process::id();
+        write_running_task(&running_directory, reused_pid, &testing::create_task("test", "cargo test"));
+        let path = running_directory.join(format!("{}.json", reused_pid));
+        let running_task = RunningTask { process_group: false, ..file::read_json_file::<RunningTask>(&path).unwrap() };
+        file::write_file_content(&path, &serde_json::to_string(&running_task).unwrap()).unwrap();
+        let keys: Vec<String> = list_running_tasks(&workspace.path(".")).into_iter().map(|running_task| running_task.key).collect();
+        assert_eq!(keys, vec!["test"]);
+
+        let running_task = RunningTask { start_time: Some("0".to_string()), ..running_task };
+        file::write_file_content(&path, &serde_json::to_string(&running_task).unwrap()).unwrap();
+        assert!(list_running_tasks(&workspace.path(".")).is_empty());
+        assert_eq!(read_dir(&running_directory).unwrap().count(), 0);
+    }
+}
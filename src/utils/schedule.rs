@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+use crate::utils::config::{SortableTask, SortableTasks, Task};
+
+// Start order of the tasks of a level, one task of every config in turn, so one config with many tasks
+// does not hold a shared runner. The seed decides the order of the configs, the same seed gives the same order.
+pub fn interleave_configs(tasks: Vec<Task>, seed: u64) -> Vec<Task> {
+    let mut config_queues: Vec<(PathBuf, Vec<Task>)> = vec![];
+    for task in tasks {
+        match config_queues.iter_mut().find(|(directory, _)| *directory == task.directory) {
+            Some((_, queue)) => queue.push(task),
+            None => config_queues.push((task.directory.clone(), vec![task])),
+        }
+    }
+
+    // The plan does not keep a stable order, sorting first makes the order depend on the seed alone
+    config_queues.sort_by(|(a_directory, _), (b_directory, _)| a_directory.cmp(b_directory));
+    for (_, queue) in config_queues.iter_mut() {
+        queue.sort_by(|a_task, b_task| a_task.key.cmp(&b_task.key));
+    }
+    shuffle(&mut config_queues, seed);
+
+    let mut queues: Vec<std::vec::IntoIter<Task>> = config_queues.into_iter().map(|(_, queue)| queue.into_iter()).collect();
+    let mut interleaved_tasks: Vec<Task> = vec![];
+    loop {
+        let round: Vec<Task> = queues.iter_mut().filter_map(|queue| queue.next()).collect();
+        if round.is_empty() {
+            break;
+        }
+        interleaved_tasks.extend(round);
+    }
+
+    interleaved_tasks
+}
+
+// One of the parts a run is split into for parallel CI jobs, 1-based like `--shard 2/3`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+pub fn parse_shard(value: &str) -> Result<Shard, String> {
+    let invalid = || format!("Invalid shard \"{}\", expected the shard and the number of shards like 1/3", value);
+    let (index, count) = value.split_once('/').ok_or_else(invalid)?;
+    let (index, count): (usize, usize) = (index.trim().parse().map_err(|_| invalid())?, count.trim().parse().map_err(|_| invalid())?);
+
+    match index >= 1 && index <= count {
+        true => Ok(Shard { index, count }),
+        false => Err(format!("Invalid shard \"{}\", the shard has to be between 1 and {}", value, count.max(1))),
+    }
+}
+
+// The tasks of one shard. Groups of tasks that are ordered against each other stay whole, so every shard runs
+// what its tasks need in the order the whole run would. Groups are placed largest first on the shard with the fewest tasks, ties are broken by the
+// directory relative to the entry and the key, so every CI job computes the same split.
+pub fn select_shard(sortable_tasks: SortableTasks, groups: Vec<Vec<usize>>, entry_directory: &Path, shard: Shard) -> SortableTasks {
+    let get_name = |position: &usize| {
+        let task: &Task = &sortable_tasks[*position].task;
+        (task.directory.strip_prefix(entry_directory).unwrap_or(&task.directory).to_path_buf(), task.key.clone())
+    };
+
+    let mut groups: Vec<(Vec<usize>, (PathBuf, String))> = groups
+        .into_iter()
+        .filter_map(|group| {
+            let name = group.iter().map(get_name).min()?;
+            Some((group, name))
+        })
+        .collect();
+    groups.sort_by(|(group, name), (other_group, other_name)| other_group.len().cmp(&group.len()).then_with(|| name.cmp(other_name)));
+
+    let mut shard_sizes: Vec<usize> = vec![0; shard.count];
+    let mut selected_positions: Vec<usize> = vec![];
+    for (group, _) in groups {
+        let smallest_shard: usize = (0..shard.count).min_by_key(|shard_index| shard_sizes[*shard_index]).unwrap_or(0);
+        shard_sizes[smallest_shard] += group.len();
+        if smallest_shard + 1 == shard.index {
+            selected_positions.extend(group);
+        }
+    }
+
+    sortable_tasks
+        .into_iter()
+        .enumerate()
+        .filter(|(position, _)| selected_positions.contains(position))
+        .map(|(_, sortable_task): (usize, SortableTask)| sortable_task)
+        .collect()
+}
+
+// Fisher-Yates with splitmix64, small and stable across platforms and releases
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state: u64 = seed;
+    for index in (1..items.len()).rev() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut random: u64 = state;
+        random = (random ^ (random >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        random = (random ^ (random >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        random ^= random >> 31;
+
+        items.swap(index, (random % (index as u64 + 1)) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::create_task;
+
+    fn render(tasks: &[Task]) -> Vec<String> {
+        tasks.iter().map(|task| format!("{}@{}", task.key, task.directory.to_string_lossy())).collect()
+    }
+
+    #[test]
+    fn takes_one_task_of_every_config_in_turn() {
+        let tasks: Vec<Task> = [("build", "app"), ("build:docs", "app"), ("build:types", "app"), ("build", "lib"), ("build", "cli")]
+            .into_iter()
+            .map(|(key, directory)| Task { directory: PathBuf::from(directory), ..create_task(key, &format!("echo {}", key)) })
+            .collect();
+
+        let interleaved = render(&interleave_configs(tasks.clone(), 7));
+        assert_eq!(interleaved, render(&interleave_configs(tasks.iter().rev().cloned().collect(), 7)));
+        assert_eq!(interleaved.iter().take(3).filter(|task| task.starts_with("build@")).count(), 3);
+        assert_eq!(interleaved.last().map(String::as_str), Some("build:types@app"));
+
+        let orders: Vec<Vec<String>> = (0..16).map(|seed| render(&interleave_configs(tasks.clone(), seed))).collect();
+        assert!(orders.iter().any(|order| *order != orders[0]));
+    }
+
+    #[test]
+    fn parses_shards_of_a_run() {
+        assert_eq!(parse_shard("2/3"), Ok(Shard { index: 2, count: 3 }));
+        assert!(parse_shard("0/3").is_err());
+        assert!(parse_shard("4/3").is_err());
+        assert!(parse_shard("1").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use crate::utils::config::{ConfigStructure, ConfigTask, SortableTasks, Task, TaskType};
+use crate::utils::config;
+
+// A yaml task of a config with the key of a yaml task of an ancestor, but another command. Both run, which is rarely meant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowedTask {
+    pub key: String,
+    pub config_path: PathBuf,
+    pub ancestor_config_path: PathBuf,
+}
+
+impl ShadowedTask {
+    pub fn describe(&self) -> String {
+        format!("Task \"{}\" of {:?} shadows the task of {:?} with another command, both run", self.key, self.config_path, self.ancestor_config_path)
+    }
+}
+
+// What a task does, tasks that do the same thing under one key are a plain repetition
+fn get_semantics(config_task: &ConfigTask) -> (String, &Vec<String>) {
+    (config::resolve_config_task_command(config_task), &config_task.steps)
+}
+
+// Engine tasks share keys like build and test by convention, only yaml tasks are compared
+pub fn find_shadowed_tasks(config_structure: &ConfigStructure) -> Vec<ShadowedTask> {
+    let mut shadowed_tasks: Vec<ShadowedTask> = vec![];
+    collect_shadowed_tasks(config_structure, &mut vec![], &mut shadowed_tasks);
+
+    shadowed_tasks
+}
+
+// A run only warns about the shadowing tasks it schedules, itself or as a dependency
+pub fn filter_scheduled_tasks(shadowed_tasks: Vec<ShadowedTask>, sortable_tasks: &SortableTasks) -> Vec<ShadowedTask> {
+    let scheduled_tasks: Vec<&Task> = sortable_tasks
+        .iter()
+        .flat_map(|sortable_task| sortable_task.task.dependencies.iter().chain([&sortable_task.task]))
+        .collect();
+
+    shadowed_tasks
+        .into_iter()
+        .filter(|shadowed_task| scheduled_tasks.iter().any(|task| task.key == shadowed_task.key && shadowed_task.config_path.parent() == Some(task.directory.as_path())))
+        .collect()
+}
+
+fn collect_shadowed_tasks<'a>(config_structure: &'a ConfigStructure, ancestor_tasks: &mut Vec<(&'a Path, &'a ConfigTask)>, shadowed_tasks: &mut Vec<ShadowedTask>) {
+    let ConfigStructure { config, children, .. } = config_structure;
+    let yaml_tasks: Vec<&ConfigTask> = config.tasks.iter().filter(|config_task| config_task.task_type == TaskType::SHELL).collect();
+
+    for config_task in &yaml_tasks {
+        // The nearest ancestor is the one the task shadows
+        let shadowed = ancestor_tasks.iter().rev().find(|(_, ancestor_task)| ancestor_task.key == config_task.key);
+        if let Some((ancestor_config_path, ancestor_task)) = shadowed {
+            let shadowed_task = ShadowedTask { key: config_task.key.clone(), config_path: config.file_path.clone(), ancestor_config_path: ancestor_config_path.to_path_buf() };
+            if get_semantics(ancestor_task) != get_semantics(config_task) && !shadowed_tasks.contains(&shadowed_task) {
+                shadowed_tasks.push(shadowed_task);
+            }
+        }
+    }
+
+    let ancestor_count: usize = ancestor_tasks.len();
+    ancestor_tasks.extend(yaml_tasks.into_iter().map(|config_task| (config.file_path.as_path(), config_task)));
+    for child in children {
+        collect_shadowed_tasks(child, ancestor_tasks, shadowed_tasks);
+    }
+    ancestor_tasks.truncate(ancestor_count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn finds_tasks_shadowing_an_ancestor() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [apps/*]\ntasks:\n  build: make all\n  lint: eslint .\n  test: make test\n")
+            .with_config("apps/web", "name: web\ndirectories: [plugins/*]\ntasks:\n  build: npm run build\n  lint: eslint .\n")
+            .with_config("apps/web/plugins/chat", "name: chat\ntasks:\n  build: make chat\n")
+            .with_package_json("apps/api", &["test"])
+            .with_config("apps/api", "name: api\n");
+        let config_structure = workspace.load_structure(".");
+
+        // lint does the same thing, the test script of api is an engine task
+        let shadowed_tasks: Vec<ShadowedTask> = find_shadowed_tasks(&config_structure);
+        assert_eq!(shadowed_tasks, vec![
+            ShadowedTask { key: "build".to_string(), config_path: workspace.config_path("apps/web"), ancestor_config_path: workspace.config_path(".") },
+            ShadowedTask { key: "build".to_string(), config_path: workspace.config_path("apps/web/plugins/chat"), ancestor_config_path: workspace.config_path("apps/web") },
+        ]);
+
+        // Running test does not run the shadowing builds
+        let test_tasks = config::resolve_sortable_task(&config_structure, "test", &true).unwrap();
+        assert_eq!(filter_scheduled_tasks(shadowed_tasks.clone(), &test_tasks), vec![]);
+        let build_tasks = config::resolve_sortable_task(&config_structure, "web:build", &true).unwrap();
+        assert_eq!(filter_scheduled_tasks(shadowed_tasks, &build_tasks), vec![
+            ShadowedTask { key: "build".to_string(), config_path: workspace.config_path("apps/web"), ancestor_config_path: workspace.config_path(".") },
+        ]);
+    }
+}
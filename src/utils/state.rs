@@ -0,0 +1,25 @@
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use crate::utils::file;
+
+pub const STATE_DIRECTORY: &str = ".rask";
+
+// The state directory lives next to the entry config and is ignored by git
+pub fn get_state_directory(entry_directory: &Path) -> Result<PathBuf, String> {
+    let state_directory = entry_directory.join(STATE_DIRECTORY);
+
+    if !state_directory.exists() {
+        create_dir_all(&state_directory).map_err(|err| format!("Failed to create {:?}: {}", state_directory, err))?;
+        file::write_file_content(&state_directory.join(".gitignore"), "*\n")?;
+    }
+
+    Ok(state_directory)
+}
+
+// Searches from the given directory upwards, like git does for .git
+pub fn find_state_file(start_directory: &Path, file_name: &str) -> Option<PathBuf> {
+    start_directory
+        .ancestors()
+        .map(|directory| directory.join(STATE_DIRECTORY).join(file_name))
+        .find(|state_file| state_file.is_file())
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use crate::utils::config;
+use crate::utils::config::{Config, ConfigStructure, ConfigTask};
+
+pub const LARGEST_CONFIG_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelStats {
+    pub configs: usize,
+    pub tasks: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceStats {
+    pub config_count: usize,
+    pub task_count: usize,
+    // By where the tasks come from, the most common source first
+    pub tasks_per_source: Vec<(&'static str, usize)>,
+    pub largest_configs: Vec<(String, usize)>,
+    // By nesting depth, the entry config is level 0
+    pub levels: Vec<LevelStats>,
+}
+
+impl WorkspaceStats {
+    pub fn average_tasks_per_config(&self) -> f32 {
+        match self.config_count {
+            0 => 0.0,
+            config_count => self.task_count as f32 / config_count as f32,
+        }
+    }
+
+    pub fn deepest_nesting(&self) -> usize {
+        self.levels.len().saturating_sub(1)
+    }
+}
+
+pub fn collect_workspace_stats(configs: &[Config], config_structure: &ConfigStructure) -> WorkspaceStats {
+    let mut tasks_per_source: Vec<(&'static str, usize)> = vec![];
+    for ConfigTask { task_type, .. } in configs.iter().flat_map(|config| &config.tasks) {
+        let source = config::get_task_source(task_type);
+        match tasks_per_source.iter_mut().find(|(existing_source, _)| *existing_source == source) {
+            Some((_, count)) => *count += 1,
+            None => tasks_per_source.push((source, 1)),
+        }
+    }
+    tasks_per_source.sort_by(|(source_a, count_a), (source_b, count_b)| count_b.cmp(count_a).then(source_a.cmp(source_b)));
+
+    let mut largest_configs: Vec<(String, usize)> = configs.iter().map(|config| (config.name.clone(), config.tasks.len())).collect();
+    largest_configs.sort_by(|(name_a, count_a), (name_b, count_b)| count_b.cmp(count_a).then(name_a.cmp(name_b)));
+    largest_configs.truncate(LARGEST_CONFIG_LIMIT);
+
+    // Configs included by several parents run at their deepest level, like their tasks do
+    let mut config_depths: HashMap<PathBuf, (usize, usize)> = HashMap::new();
+    collect_config_depths(config_structure, 0, &mut config_depths);
+
+    let mut levels: Vec<LevelStats> = vec![];
+    for (depth, task_count) in config_depths.into_values() {
+        if levels.len() <= depth {
+            levels.resize(depth + 1, LevelStats::default());
+        }
+        levels[depth].configs += 1;
+        levels[depth].tasks += task_count;
+    }
+
+    WorkspaceStats {
+        config_count: configs.len(),
+        task_count: configs.iter().map(|config| config.tasks.len()).sum(),
+        tasks_per_source,
+        largest_configs,
+        levels,
+    }
+}
+
+fn collect_config_depths(config_structure: &ConfigStructure, depth: usize, config_depths: &mut HashMap<PathBuf, (usize, usize)>) {
+    let ConfigStructure { config, children, .. } = config_structure;
+
+    let entry = config_depths.entry(config.file_path.clone()).or_insert((depth, config.tasks.len()));
+    entry.0 = entry.0.max(depth);
+
+    for child in children {
+        collect_config_depths(child, depth + 1, config_depths);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn reports_the_shape_of_the_workspace() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories:\n  - packages/*\ntasks:\n  build: echo root\n")
+            .with_config("packages/api", "name: api\ndirectories:\n  - plugins/*\ntasks:\n  build: echo api\n  test: echo api\n")
+            .with_config("packages/api/plugins/auth", "name: auth\ntasks:\n  build: echo auth\n")
+            .with_config("packages/web", "name: web\ntask_engine: npm\n")
+            .with_package_json("packages/web", &["build", "lint", "test"]);
+
+        let (configs, config_structure) = (workspace.load_configs("."), workspace.load_structure("."));
+
+        let stats = collect_workspace_stats(&configs, &config_structure);
+
+        assert_eq!((stats.config_count, stats.task_count), (4, 7));
+        assert_eq!(stats.tasks_per_source, vec![("yaml", 4), ("npm", 3)]);
+        assert_eq!(stats.largest_configs[0], ("web".to_string(), 3));
+        assert_eq!(stats.average_tasks_per_config(), 1.75);
+        assert_eq!(stats.deepest_nesting(), 2);
+        assert_eq!(stats.levels, vec![
+            LevelStats { configs: 1, tasks: 1 },
+            LevelStats { configs: 2, tasks: 5 },
+            LevelStats { configs: 1, tasks: 1 },
+        ]);
+    }
+}
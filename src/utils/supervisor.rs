@@ -0,0 +1,173 @@
+use std::mem::take;
+use std::process::{Child, Stdio};
+use std::thread::JoinHandle;
+use crate::utils::config::Task;
+use crate::utils::output::{Stream, Tone, Verbosity};
+use crate::utils::writer::{OutputStyle, TaskWriter};
+use crate::utils::{executor, failure, output, process};
+
+// A long running task, kept after it exits so it only starts again once its config changes
+struct DevProcess {
+    task: Task,
+    child: Option<Child>,
+    tee_handles: Vec<JoinHandle<()>>,
+}
+
+// Keeps one process running per task, in line with the tasks the configs currently declare
+pub struct Supervisor {
+    processes: Vec<DevProcess>,
+    output_style: OutputStyle,
+}
+
+impl Supervisor {
+    pub fn new(output_style: OutputStyle) -> Self {
+        Supervisor { processes: vec![], output_style }
+    }
+
+    pub fn running(&self) -> usize {
+        self.processes.iter().filter(|dev_process| dev_process.child.is_some()).count()
+    }
+
+    // Stops the processes of removed and changed tasks, then starts the processes of new tasks
+    pub fn sync(&mut self, tasks: Vec<Task>) {
+        let (kept, removed): (Vec<DevProcess>, Vec<DevProcess>) = take(&mut self.processes)
+            .into_iter()
+            .partition(|dev_process| tasks.iter().any(|task| is_same_task(&dev_process.task, task)));
+
+        for dev_process in removed {
+            output::line("DEV", Tone::WARNING, &format!("Stopping {}", describe(&dev_process.task)));
+            stop(dev_process);
+        }
+        self.processes = kept;
+
+        for task in tasks {
+            if !self.processes.iter().any(|dev_process| is_same_task(&dev_process.task, &task)) {
+                self.processes.push(start(task, self.output_style));
+            }
+        }
+    }
+
+    // Reports the processes that exited on their own
+    pub fn reap(&mut self) {
+        for dev_process in &mut self.processes {
+            let Some(child) = &mut dev_process.child else {
+                continue;
+            };
+
+            if let Ok(Some(exit_status)) = child.try_wait() {
+                process::release(child);
+                for tee_handle in take(&mut dev_process.tee_handles) {
+                    let _ = tee_handle.join();
+                }
+                dev_process.child = None;
+
+                let message = format!("{} exited with {}, it starts again when its config changes", describe(&dev_process.task), exit_status);
+                match exit_status.success() {
+                    true => output::line("DEV", Tone::MUTED, &message),
+                    false => output::error_line("DEV", Tone::ERROR, &message),
+                }
+            }
+        }
+    }
+
+    // Waits for the processes an interrupt asked to stop, the ones that outlast their grace period are killed
+    pub fn wait(&mut self) {
+        for dev_process in &mut self.processes {
+            if let Some(mut child) = dev_process.child.take() {
+                let _ = child.wait();
+                process::release(&child);
+            }
+            for tee_handle in take(&mut dev_process.tee_handles) {
+                let _ = tee_handle.join();
+            }
+        }
+    }
+}
+
+// A changed command or environment is a different task, so its process is restarted
+fn is_same_task(left: &Task, right: &Task) -> bool {
+    left.key == right.key && left.directory == right.directory && left.command == right.command && left.env == right.env
+}
+
+fn describe(task: &Task) -> String {
+    format!("{} @ {:?}", task.key, task.directory)
+}
+
+// Dependencies run to completion first, a failing dependency leaves the task stopped
+fn start(task: Task, output_style: OutputStyle) -> DevProcess {
+    let prepared = task.dependencies
+        .iter()
+        .try_for_each(|dependency| executor::execute_task(dependency.clone(), Verbosity::NORMAL))
+        .and_then(|_| failure::check_command_paths(&task).map_err(|failure| failure.describe()));
+
+    let spawned = prepared.and_then(|_| {
+        process::spawn(process::build_command(&task).stdout(Stdio::piped()).stderr(Stdio::piped()), &task.options)
+            .map_err(|err| format!("Failed to execute the command with {}: {}", process::get_shell(&task), err))
+    });
+
+    match spawned {
+        Ok(mut child) => {
+            output::line("DEV", Tone::INFO, &format!("Starting {} {}", task.command, output::paint(&format!("@ {:?}", task.directory), Tone::MUTED, Stream::STDOUT)));
+            let writer = TaskWriter::new(&task.key, &task.directory, output_style).with_limit(executor::get_output_limit(&task));
+            let tee_handles = process::tee_output(&mut child, &None, Some(writer), &None);
+
+            DevProcess { task, child: Some(child), tee_handles }
+        },
+        Err(err) => {
+            output::error_line("DEV", Tone::ERROR, &format!("Unable to start {}: {}", describe(&task), err));
+
+            DevProcess { task, child: None, tee_handles: vec![] }
+        },
+    }
+}
+
+fn stop(dev_process: DevProcess) {
+    let DevProcess { task, child, tee_handles } = dev_process;
+
+    if let Some(mut child) = child {
+        if let Err(err) = process::terminate(&mut child, &task.options) {
+            output::error_line("DEV", Tone::ERROR, &format!("Failed to stop {}: {}", describe(&task), err));
+        }
+    }
+
+    for tee_handle in tee_handles {
+        let _ = tee_handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+    use std::time::Duration;
+    use crate::testing::create_task;
+
+    #[test]
+    fn starts_new_tasks_and_stops_removed_tasks() {
+        let mut supervisor = Supervisor::new(OutputStyle::default());
+
+        supervisor.sync(vec![create_task("web", "sleep 10"), create_task("api", "sleep 10")]);
+        assert_eq!(supervisor.running(), 2);
+
+        supervisor.sync(vec![create_task("web", "sleep 10")]);
+        assert_eq!(supervisor.running(), 1);
+
+        supervisor.sync(vec![]);
+        assert_eq!(supervisor.running(), 0);
+    }
+
+    #[test]
+    fn exited_tasks_only_start_again_when_they_change() {
+        let mut supervisor = Supervisor::new(OutputStyle::default());
+
+        supervisor.sync(vec![create_task("web", "true")]);
+        sleep(Duration::from_millis(200));
+        supervisor.reap();
+        supervisor.sync(vec![create_task("web", "true")]);
+        assert_eq!(supervisor.running(), 0);
+
+        supervisor.sync(vec![create_task("web", "sleep 10")]);
+        assert_eq!(supervisor.running(), 1);
+        supervisor.sync(vec![]);
+    }
+}
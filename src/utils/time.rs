@@ -0,0 +1,60 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Parses durations like `90`, `90s`, `10m`, `1h30m` or `500ms`, plain numbers are seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("Empty duration".to_string());
+    }
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut duration = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.find(|character: char| !character.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!("Invalid duration \"{}\", expected something like 90s, 10m or 1h30m", value));
+        }
+
+        let amount: u64 = rest[..digits].parse().map_err(|_| format!("Invalid duration \"{}\"", value))?;
+        rest = &rest[digits..];
+
+        let unit_length = rest.find(|character: char| character.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = &rest[..unit_length];
+        rest = &rest[unit_length..];
+
+        duration += match unit {
+            "ms" => Duration::from_millis(amount),
+            "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount * 60),
+            "h" => Duration::from_secs(amount * 60 * 60),
+            "d" => Duration::from_secs(amount * 60 * 60 * 24),
+            _ => return Err(format!("Invalid duration unit \"{}\" in \"{}\", expected ms, s, m, h or d", unit, value)),
+        };
+    }
+
+    Ok(duration)
+}
+
+pub fn format_seconds(duration: Duration) -> f32 {
+    (duration.as_secs_f32() * 100.0).round() / 100.0
+}
+
+// The largest two units, like 7m12s or 2h5m, in the format parse_duration reads
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let parts = [(seconds / 86_400, "d"), (seconds % 86_400 / 3_600, "h"), (seconds % 3_600 / 60, "m"), (seconds % 60, "s")];
+    let first = parts.iter().position(|(amount, _)| *amount > 0).unwrap_or(parts.len() - 1);
+
+    parts[first..].iter().take(2).filter(|(amount, _)| *amount > 0 || first == parts.len() - 1).map(|(amount, unit)| format!("{}{}", amount, unit)).collect()
+}
+
+// The UTC time of day as HH:MM:SS
+pub fn format_clock(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default() % 86_400;
+
+    format!("{:02}:{:02}:{:02}", seconds / 3_600, seconds % 3_600 / 60, seconds % 60)
+}
@@ -0,0 +1,155 @@
+use std::fs::{create_dir_all, read_dir, remove_dir, remove_dir_all};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::utils::config::Task;
+use crate::utils::{process, state};
+
+const TMP_DIRECTORY: &str = "tmp";
+pub const TMP_DIRECTORY_VARIABLE: &str = "RASK_TMPDIR";
+
+// The scratch space of the current attempt at a run, .rask/tmp/<run>/<pid>, with the entry directory it is relative to
+static RUN_TMP_DIRECTORY: OnceLock<(PathBuf, PathBuf)> = OnceLock::new();
+// Tasks with the same key and directory run more than once in a run, as dependencies or repeated
+static TASK_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Runs give their tasks a scratch directory, commands that never call this give none
+pub fn init(entry_directory: &Path, run_id: &str) -> Result<(), String> {
+    let run_tmp_directory = create_attempt_directory(entry_directory, run_id)?;
+    let _ = RUN_TMP_DIRECTORY.set((entry_directory.to_path_buf(), run_tmp_directory));
+
+    Ok(())
+}
+
+// Resuming a run is another attempt with its own process, which starts counting tasks from 0 again.
+// Every attempt gets a directory of its own, so no task finds the files an earlier attempt kept.
+fn create_attempt_directory(entry_directory: &Path, run_id: &str) -> Result<PathBuf, String> {
+    let attempt_directory = state::get_state_directory(entry_directory)?.join(TMP_DIRECTORY).join(run_id).join(std::process::id().to_string());
+
+    // Only an attempt that ended long ago had this pid
+    let _ = remove_dir_all(&attempt_directory);
+    create_dir_all(&attempt_directory).map_err(|err| format!("Failed to create {:?}: {}", attempt_directory, err))?;
+
+    Ok(attempt_directory)
+}
+
+// A fresh directory named after the task, like 3-test-packages_app, none when it could not be created
+pub fn create_task_directory(task: &Task) -> Option<PathBuf> {
+    let (entry_directory, run_tmp_directory) = RUN_TMP_DIRECTORY.get()?;
+
+    create_task_directory_in(entry_directory, run_tmp_directory, task)
+}
+
+fn create_task_directory_in(entry_directory: &Path, run_tmp_directory: &Path, task: &Task) -> Option<PathBuf> {
+    let relative_directory: &Path = task.directory.strip_prefix(entry_directory).unwrap_or(&task.directory);
+    let name: String = format!("{}-{}-{}", TASK_COUNTER.fetch_add(1, Ordering::Relaxed), task.key, relative_directory.to_string_lossy())
+        .chars()
+        .map(|character| match character.is_ascii_alphanumeric() || character == '-' || character == '.' {
+            true => character,
+            false => '_',
+        })
+        .collect();
+
+    let task_directory = run_tmp_directory.join(name.trim_end_matches(['-', '_']));
+    create_dir_all(&task_directory).ok()?;
+
+    Some(task_directory)
+}
+
+pub fn remove_task_directory(task_directory: &Path) {
+    let _ = remove_dir_all(task_directory);
+}
+
+// Only the directory of an attempt where every task succeeded is empty, the others are kept for debugging
+pub fn finish() {
+    if let Some((_, run_tmp_directory)) = RUN_TMP_DIRECTORY.get() {
+        remove_empty_directories(run_tmp_directory);
+    }
+}
+
+// The directory of the run goes as well, once none of its attempts kept anything
+fn remove_empty_directories(run_tmp_directory: &Path) {
+    if remove_dir(run_tmp_directory).is_ok() {
+        if let Some(run_directory) = run_tmp_directory.parent() {
+            let _ = remove_dir(run_directory);
+        }
+    }
+}
+
+// A run is still going while the process of one of its attempts is
+fn is_run_alive(run_directory: &Path) -> bool {
+    read_dir(run_directory)
+        .map(|entries| entries.flatten().filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok()).any(|pid| process::is_task_alive(pid, false)))
+        .unwrap_or_default()
+}
+
+// Removes the scratch directories kept by earlier runs, returns how many runs had one. Runs that are still going keep theirs.
+pub fn clean(entry_directory: &Path) -> Result<usize, String> {
+    let tmp_directory = entry_directory.join(state::STATE_DIRECTORY).join(TMP_DIRECTORY);
+    let Ok(entries) = read_dir(&tmp_directory) else {
+        return Ok(0);
+    };
+
+    let mut run_count: usize = 0;
+    for run_directory in entries.flatten().map(|entry| entry.path()).filter(|run_directory| !is_run_alive(run_directory)) {
+        remove_dir_all(&run_directory).map_err(|err| format!("Failed to remove {:?}: {}", run_directory, err))?;
+        run_count += 1;
+    }
+    let _ = remove_dir(&tmp_directory);
+
+    Ok(run_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use crate::testing::{self, TestWorkspace};
+
+    #[test]
+    fn cleans_the_kept_directories_of_every_run() {
+        let mut exited = Command::new("true").spawn().unwrap();
+        exited.wait().unwrap();
+        let workspace = TestWorkspace::new()
+            .with_file(&format!(".rask/tmp/1700000000-10/{}/0-build-app/partial.o", exited.id()), "")
+            .with_file(&format!(".rask/tmp/1700000000-11/{}/2-test-lib/fixture.json", exited.id()), "{}")
+            .with_file(".rask/history.json", "{}");
+
+        assert_eq!(clean(&workspace.path(".")), Ok(2));
+        assert!(!workspace.path(".rask/tmp").exists());
+        assert!(workspace.path(".rask/history.json").exists());
+        assert_eq!(clean(&workspace.path(".")), Ok(0));
+    }
+
+    #[test]
+    fn keeps_the_directories_of_runs_that_are_still_going() {
+        let live_attempt = format!(".rask/tmp/1700000000-12/{}/0-build-app/partial.o", std::process::id());
+        let workspace = TestWorkspace::new().with_file(&live_attempt, "");
+
+        assert_eq!(clean(&workspace.path(".")), Ok(0));
+        assert!(workspace.path(&live_attempt).exists());
+    }
+
+    #[test]
+    fn gives_every_attempt_and_task_a_fresh_directory() {
+        let workspace = TestWorkspace::new().with_file(&format!(".rask/tmp/1700000000-13/{}/0-build/stale.o", std::process::id()), "");
+        let task = Task { directory: workspace.path("."), ..testing::create_task("build", "make") };
+
+        // A resumed run counts its tasks from 0 again, the files of the earlier attempt with this pid are gone
+        let attempt_directory = create_attempt_directory(&workspace.path("."), "1700000000-13").unwrap();
+        assert_eq!(read_dir(&attempt_directory).unwrap().count(), 0);
+
+        let succeeded = create_task_directory_in(&workspace.path("."), &attempt_directory, &task).unwrap();
+        let failed = create_task_directory_in(&workspace.path("."), &attempt_directory, &task).unwrap();
+        assert_ne!(succeeded, failed);
+        assert!(failed.file_name().unwrap().to_string_lossy().ends_with("-build"));
+
+        remove_task_directory(&succeeded);
+        remove_empty_directories(&attempt_directory);
+        assert!(!succeeded.exists() && failed.exists());
+
+        remove_task_directory(&failed);
+        remove_empty_directories(&attempt_directory);
+        assert!(!workspace.path(".rask/tmp/1700000000-13").exists());
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum VcsKind {
+    // The first repository found from the directory upwards
+    #[default]
+    AUTO,
+    GIT,
+    // Mercurial
+    HG,
+    // Jujutsu
+    JJ,
+}
+
+// A version control system that knows which files changed since a revision
+pub trait Vcs {
+    fn name(&self) -> &'static str;
+
+    // The marker that identifies the root of a repository
+    fn marker(&self) -> &'static str;
+
+    // Changed files relative to the repository root, including changes that are not committed yet
+    fn changed_files(&self, root: &Path, since: &str) -> Result<Vec<String>, String>;
+}
+
+struct Git;
+
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn marker(&self) -> &'static str {
+        ".git"
+    }
+
+    fn changed_files(&self, root: &Path, since: &str) -> Result<Vec<String>, String> {
+        let mut changed_files: Vec<String> = run_vcs(self, root, &["diff", "--name-only", "--no-renames", since, "--"])?;
+        changed_files.extend(run_vcs(self, root, &["ls-files", "--others", "--exclude-standard"])?);
+
+        Ok(changed_files)
+    }
+}
+
+struct Mercurial;
+
+impl Vcs for Mercurial {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn marker(&self) -> &'static str {
+        ".hg"
+    }
+
+    fn changed_files(&self, root: &Path, since: &str) -> Result<Vec<String>, String> {
+        run_vcs(self, root, &["status", "--rev", since, "--no-status", "--modified", "--added", "--removed", "--unknown"])
+    }
+}
+
+struct Jujutsu;
+
+impl Vcs for Jujutsu {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn marker(&self) -> &'static str {
+        ".jj"
+    }
+
+    // The working copy is a commit in jj, so it is always part of the diff
+    fn changed_files(&self, root: &Path, since: &str) -> Result<Vec<String>, String> {
+        run_vcs(self, root, &["diff", "--name-only", "--from", since])
+    }
+}
+
+// Jujutsu repositories are often colocated with git, so .jj is looked for first
+fn get_backends(vcs_kind: VcsKind) -> Vec<Box<dyn Vcs>> {
+    match vcs_kind {
+        VcsKind::AUTO => vec![Box::new(Jujutsu), Box::new(Git), Box::new(Mercurial)],
+        VcsKind::GIT => vec![Box::new(Git)],
+        VcsKind::HG => vec![Box::new(Mercurial)],
+        VcsKind::JJ => vec![Box::new(Jujutsu)],
+    }
+}
+
+// Searches from the directory upwards for the root of a repository of one of the backends
+fn find_repository(vcs_kind: VcsKind, directory: &Path) -> Result<(Box<dyn Vcs>, PathBuf), String> {
+    for ancestor in directory.ancestors() {
+        for backend in get_backends(vcs_kind) {
+            if ancestor.join(backend.marker()).exists() {
+                return Ok((backend, ancestor.to_path_buf()));
+            }
+        }
+    }
+
+    match vcs_kind {
+        VcsKind::AUTO => Err(format!("No git, hg or jj repository found at {:?} or above", directory)),
+        _ => Err(format!("No {} repository found at {:?} or above", get_backends(vcs_kind)[0].name(), directory)),
+    }
+}
+
+// The absolute paths of the files that changed since the revision, in the repository containing the directory
+pub fn find_changed_files(vcs_kind: VcsKind, directory: &Path, since: &str) -> Result<Vec<PathBuf>, String> {
+    let (vcs, root) = find_repository(vcs_kind, directory)?;
+
+    let mut changed_files: Vec<PathBuf> = vcs
+        .changed_files(&root, since)?
+        .iter()
+        .map(|changed_file| root.join(changed_file))
+        .collect();
+    changed_files.sort();
+    changed_files.dedup();
+
+    Ok(changed_files)
+}
+
+fn run_vcs(vcs: &dyn Vcs, root: &Path, args: &[&str]) -> Result<Vec<String>, String> {
+    let output = Command::new(vcs.name())
+        .args(args)
+        .current_dir(root)
+        .output()
+        .map_err(|err| format!("Failed to run {}: {}", vcs.name(), err))?;
+
+    if !output.status.success() {
+        return Err(format!("{} {} failed: {}", vcs.name(), args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use crate::testing::TestWorkspace;
+
+    fn git(workspace: &TestWorkspace, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=rask", "-c", "user.email=rask@example.com"])
+            .args(args)
+            .current_dir(workspace.path("."))
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    }
+
+    #[test]
+    fn finds_the_files_changed_since_a_git_revision() {
+        let workspace = TestWorkspace::new()
+            .with_file("packages/app/main.js", "1")
+            .with_file("packages/lib/index.js", "1");
+        git(&workspace, &["init", "--quiet"]);
+        git(&workspace, &["add", "."]);
+        git(&workspace, &["commit", "--quiet", "-m", "initial"]);
+
+        write(workspace.path("packages/app/main.js"), "2").unwrap();
+        create_dir_all(workspace.path("packages/web")).unwrap();
+        write(workspace.path("packages/web/new.js"), "").unwrap();
+
+        let changed_files = find_changed_files(VcsKind::AUTO, &workspace.path("packages/lib"), "HEAD").unwrap();
+
+        assert_eq!(workspace.relative_paths(&changed_files), vec!["packages/app/main.js", "packages/web/new.js"]);
+        assert!(find_changed_files(VcsKind::HG, &workspace.path("."), "HEAD").is_err());
+    }
+}
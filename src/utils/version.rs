@@ -0,0 +1,118 @@
+use std::path::Path;
+use clap::ValueEnum;
+use serde_json::Value;
+use crate::utils::config::{self, Config, ConfigStructure, SortableTask, SortableTasks, Task, TaskOptions, TaskType};
+use crate::utils::file;
+
+pub const VERSION_TASK: &str = "version";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum BumpLevel {
+    PATCH,
+    MINOR,
+    MAJOR,
+}
+
+impl BumpLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BumpLevel::PATCH => "patch",
+            BumpLevel::MINOR => "minor",
+            BumpLevel::MAJOR => "major",
+        }
+    }
+}
+
+// One config bumping its version, through its own version task or the one of its engine
+#[derive(Debug, Clone)]
+pub struct VersionStep {
+    pub task: Task,
+    pub order: u64,
+    pub native: bool,
+}
+
+// The deepest configs bump first, so packages are versioned before the packages including them
+pub fn plan_version_bump(config_structure: &ConfigStructure, bump_level: BumpLevel) -> Result<Vec<VersionStep>, String> {
+    let version_tasks: SortableTasks = config::resolve_sortable_task(config_structure, VERSION_TASK, &true)?;
+    let mut version_steps: Vec<VersionStep> = vec![];
+
+    for (config, order, _) in config::list_configs(config_structure) {
+        // A version script of package.json is the lifecycle hook `npm version` runs once it bumped, not a bump itself
+        let version_task: Option<&SortableTask> = version_tasks
+            .iter()
+            .find(|SortableTask { task, .. }| task.directory == config.dir_path && !matches!(task.task_type, TaskType::NPM | TaskType::YARN | TaskType::PNPM));
+
+        let (task, native): (Task, bool) = match version_task {
+            Some(SortableTask { task, .. }) => (task.clone(), false),
+            None => match create_native_version_task(config, bump_level) {
+                Some(task) => (task, true),
+                None => continue,
+            },
+        };
+
+        let mut task = task;
+        task.env.insert("RASK_BUMP".to_string(), bump_level.as_str().to_string());
+        version_steps.push(VersionStep { task, order, native });
+    }
+
+    version_steps.sort_by_key(|version_step| std::cmp::Reverse(version_step.order));
+
+    Ok(version_steps)
+}
+
+// Only npm packages have a native bump, Cargo, Composer and task runners keep versions in ways rask can't tell
+fn create_native_version_task(config: &Config, bump_level: BumpLevel) -> Option<Task> {
+    if !has_package_version(&config.dir_path) {
+        return None;
+    }
+
+    Some(Task {
+        key: VERSION_TASK.to_string(),
+        command: format!("npm version {} --no-git-tag-version", bump_level.as_str()),
+        steps: vec![],
+        directory: config.dir_path.clone(),
+        env: config.env.clone(),
+        options: TaskOptions::default(),
+        shell: config.shell.clone(),
+        dependencies: vec![],
+        inputs: vec![],
+        outputs: vec![],
+        install: None,
+        task_type: TaskType::NPM,
+    })
+}
+
+fn has_package_version(dir_path: &Path) -> bool {
+    let package_json_path = dir_path.join("package.json");
+
+    package_json_path.exists()
+        && file::read_json_file::<Value>(&package_json_path).is_ok_and(|package_json| package_json.get("version").is_some_and(Value::is_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWorkspace;
+
+    #[test]
+    fn plans_version_tasks_and_npm_bumps_deepest_first() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [packages/*]\ntasks:\n  version: ./scripts/bump.sh\n")
+            .with_config("packages/app", "name: app\n")
+            .with_file("packages/app/package.json", "{\"name\": \"app\", \"version\": \"1.0.0\", \"scripts\": {\"version\": \"git add CHANGELOG.md\"}}")
+            .with_config("packages/docs", "name: docs\n")
+            .with_file("packages/docs/package.json", "{\"name\": \"docs\", \"private\": true}");
+
+        let version_steps = plan_version_bump(&workspace.load_structure("."), BumpLevel::MINOR).unwrap();
+        let planned: Vec<(String, String, bool)> = version_steps
+            .iter()
+            .map(|VersionStep { task, native, .. }| (workspace.relative(&task.directory), task.command.clone(), *native))
+            .collect();
+
+        assert_eq!(planned, vec![
+            ("packages/app".to_string(), "npm version minor --no-git-tag-version".to_string(), true),
+            (".".to_string(), "./scripts/bump.sh".to_string(), false),
+        ]);
+        assert!(version_steps.iter().all(|version_step| version_step.task.env["RASK_BUMP"] == "minor"));
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use globset::{GlobSet, GlobSetBuilder};
+use crate::utils::config::{self, SortableTask, SortableTasks};
+use crate::utils::file;
+
+// The modification time of every file matching the inputs of the watched tasks
+pub type FileSnapshot = HashMap<PathBuf, SystemTime>;
+
+pub fn snapshot_inputs(sortable_tasks: &SortableTasks) -> Result<FileSnapshot, String> {
+    snapshot_files(sortable_tasks.iter().flat_map(|SortableTask { task, .. }| &task.inputs))
+}
+
+// The files matching any of the absolute glob patterns
+pub fn snapshot_files<'a>(patterns: impl Iterator<Item = &'a PathBuf>) -> Result<FileSnapshot, String> {
+    let mut file_snapshot: FileSnapshot = HashMap::new();
+
+    for input in patterns {
+        // A trailing `**` only yields directories with the glob crate, while it matches every file with globset
+        let pattern: String = match input.ends_with("**") {
+            true => file::path_to_str(&input.join("*"))?.to_string(),
+            false => file::path_to_str(input)?.to_string(),
+        };
+        for path in glob::glob(&pattern).map_err(|err| format!("Failed to read input pattern {:?}: {}", pattern, err))?.flatten() {
+            // Files can disappear between globbing and reading them, the next snapshot picks that up
+            if let Ok(modified) = path.metadata().and_then(|metadata| metadata.modified()) {
+                if path.is_file() {
+                    file_snapshot.insert(path, modified);
+                }
+            }
+        }
+    }
+
+    Ok(file_snapshot)
+}
+
+// Added, modified and removed files, sorted for stable output
+pub fn find_changed_files(previous: &FileSnapshot, current: &FileSnapshot) -> Vec<PathBuf> {
+    let mut changed_files: Vec<PathBuf> = current
+        .iter()
+        .filter(|(path, modified)| previous.get(*path) != Some(modified))
+        .map(|(path, _)| path.clone())
+        .chain(previous.keys().filter(|path| !current.contains_key(*path)).cloned())
+        .collect();
+
+    changed_files.sort();
+    changed_files
+}
+
+// The tasks with an input matching one of the changed files, the deepest levels first like rask run
+pub fn find_triggered_tasks<'a>(sortable_tasks: &'a SortableTasks, changed_files: &[PathBuf]) -> Result<Vec<&'a SortableTask>, String> {
+    let mut triggered_tasks: Vec<&SortableTask> = vec![];
+
+    for sortable_task in sortable_tasks {
+        let input_set: GlobSet = create_input_set(&sortable_task.task.inputs)?;
+        if changed_files.iter().any(|changed_file| input_set.is_match(changed_file)) {
+            triggered_tasks.push(sortable_task);
+        }
+    }
+
+    triggered_tasks.sort_by_key(|sortable_task| std::cmp::Reverse(sortable_task.order));
+
+    Ok(triggered_tasks)
+}
+
+fn create_input_set(inputs: &[PathBuf]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for input in inputs {
+        builder.add(config::create_glob(input)?);
+    }
+
+    builder.build().map_err(|err| format!("Failed to create the input patterns: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_file;
+    use std::time::Duration;
+    use crate::testing::TestWorkspace;
+    use crate::utils::config::resolve_sortable_task;
+
+    fn load_tasks(workspace: &TestWorkspace) -> SortableTasks {
+        resolve_sortable_task(&workspace.load_structure("."), "", &false).unwrap()
+    }
+
+    #[test]
+    fn changed_files_trigger_the_tasks_with_matching_inputs() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ndirectories: [web]\ntasks:\n  docs:\n    command: make docs\n    inputs: [\"docs/**/*.md\"]\n")
+            .with_config("web", "name: web\ntasks:\n  build:\n    command: npm run build\n    inputs: [\"src/**\"]\n  lint: npm run lint\n");
+
+        let sortable_tasks = load_tasks(&workspace);
+        let triggered_tasks = find_triggered_tasks(&sortable_tasks, &[workspace.path("web/src/app/main.ts")]).unwrap();
+        assert_eq!(triggered_tasks.iter().map(|sortable_task| sortable_task.task.key.as_str()).collect::<Vec<&str>>(), vec!["build"]);
+
+        let triggered_tasks = find_triggered_tasks(&sortable_tasks, &[workspace.path("docs/guide/intro.md"), workspace.path("web/src/index.ts")]).unwrap();
+        assert_eq!(triggered_tasks.iter().map(|sortable_task| sortable_task.task.key.as_str()).collect::<Vec<&str>>(), vec!["build", "docs"]);
+    }
+
+    #[test]
+    fn snapshots_detect_added_modified_and_removed_files() {
+        let workspace = TestWorkspace::new()
+            .with_config(".", "name: root\ntasks:\n  build:\n    command: make\n    inputs: [\"src/*.c\"]\n  test:\n    command: make test\n    inputs: [\"tests/**\"]\n")
+            .with_file("src/main.c", "int main;")
+            .with_file("src/util.c", "int util;")
+            .with_file("tests/unit/util.c", "int test;");
+
+        let sortable_tasks = load_tasks(&workspace);
+        let previous = snapshot_inputs(&sortable_tasks).unwrap();
+        assert_eq!(previous.len(), 3);
+
+        let mut current = previous.clone();
+        current.insert(workspace.path("src/main.c"), SystemTime::now() + Duration::from_secs(1));
+        current.insert(workspace.path("src/new.c"), SystemTime::now());
+        remove_file(workspace.path("src/util.c")).unwrap();
+        current.remove(&workspace.path("src/util.c"));
+
+        let changed_files = find_changed_files(&previous, &current);
+        assert_eq!(workspace.relative_paths(&changed_files), vec!["src/main.c", "src/new.c", "src/util.c"]);
+        assert_eq!(snapshot_inputs(&sortable_tasks).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{stderr, stdout, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use crate::utils::ci::{CiGroup, CiProvider};
+use crate::utils::output::Stream;
+use crate::utils::{output, time};
+
+// How the lines of task output are decorated
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutputStyle {
+    // Every line starts with the task it comes from, tells interleaved output of parallel tasks apart
+    pub prefix: bool,
+    pub timestamps: bool,
+    // The output of a command is written as one collapsible section of the CI log
+    pub fold: Option<CiProvider>,
+}
+
+// Whole lines are written while holding this lock, so concurrent tasks never tear each others lines
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+// The most output of a task that reaches the terminal, the captured tail for reports and failures keeps going
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutputLimit {
+    pub lines: Option<u64>,
+    pub bytes: Option<u64>,
+}
+
+// What is written so far, shared between the stdout and stderr writer of a task
+#[derive(Debug, Default)]
+struct OutputCounter {
+    lines: u64,
+    bytes: u64,
+    truncated: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Admission {
+    WRITE,
+    // The line that crosses the limit is replaced by a truncation marker
+    TRUNCATE,
+    DROP,
+}
+
+impl OutputCounter {
+    fn admit(&mut self, limit: OutputLimit, size: usize) -> Admission {
+        if self.truncated {
+            return Admission::DROP;
+        }
+
+        let exceeds_lines = limit.lines.is_some_and(|lines| self.lines + 1 > lines);
+        let exceeds_bytes = limit.bytes.is_some_and(|bytes| self.bytes + size as u64 > bytes);
+        if exceeds_lines || exceeds_bytes {
+            self.truncated = true;
+            return Admission::TRUNCATE;
+        }
+
+        self.lines += 1;
+        self.bytes += size as u64;
+        Admission::WRITE
+    }
+}
+
+const PREFIX_COLORS: [&str; 6] = ["36", "35", "34", "33", "32", "94"];
+
+// Writes the output of one task, the prefix keeps the same color for the whole run
+#[derive(Debug, Clone)]
+pub struct TaskWriter {
+    label: String,
+    style: OutputStyle,
+    limit: OutputLimit,
+    counter: Arc<Mutex<OutputCounter>>,
+    section: Option<Arc<Section>>,
+    // The lines held back while folding, stdout and stderr end up in the same section
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+// A task running on its own streams its lines into a section that is already open. Tasks running at the same time hold
+// theirs back and write the section once they finish, so sections never mix. Only those know if it can be collapsed.
+#[derive(Debug)]
+struct Section {
+    group: CiGroup,
+    title: String,
+    streaming: bool,
+}
+
+impl TaskWriter {
+    pub fn new(key: &str, directory: &Path, style: OutputStyle) -> Self {
+        let directory_name = directory.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+        TaskWriter { label: format!("{}@{}", key, directory_name), style, limit: OutputLimit::default(), counter: Arc::default(), section: None, buffer: Arc::default() }
+    }
+
+    // Opens the section the output is folded into when the style folds, a streaming section is written right away
+    pub fn with_section(self, title: &str, streaming: bool) -> Self {
+        let Some(provider) = self.style.fold else {
+            return self;
+        };
+
+        let section = Section { group: CiGroup::new(provider), title: title.to_string(), streaming };
+        if streaming {
+            let _guard = WRITE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+            let _ = stdout().lock().write_all(section.group.format_start(title, false).as_bytes());
+        }
+
+        TaskWriter { section: Some(Arc::new(section)), ..self }
+    }
+
+    pub fn with_limit(self, limit: OutputLimit) -> Self {
+        TaskWriter { limit, ..self }
+    }
+
+    pub fn write_line(&self, stream: Stream, line: &[u8]) {
+        let admission = self.counter.lock().map(|mut counter| counter.admit(self.limit, line.len())).unwrap_or(Admission::WRITE);
+        let marker: String;
+        let line: &[u8] = match admission {
+            Admission::WRITE => line,
+            Admission::DROP => return,
+            Admission::TRUNCATE => {
+                let counter = self.counter.lock().unwrap_or_else(|err| err.into_inner());
+                marker = format!("... output truncated after {} lines and {} bytes, the rest of {} is hidden\n", counter.lines, counter.bytes, self.label);
+                marker.as_bytes()
+            },
+        };
+
+        let line: Vec<u8> = format_line(&self.label, self.style, line, SystemTime::now(), output::use_color(stream));
+        if self.section.as_ref().is_some_and(|section| !section.streaming) {
+            self.buffer.lock().unwrap_or_else(|err| err.into_inner()).extend_from_slice(&line);
+            return;
+        }
+
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let _ = match stream {
+            Stream::STDOUT => stdout().lock().write_all(&line),
+            Stream::STDERR => stderr().lock().write_all(&line),
+        };
+    }
+
+    // Closes the section, writing the held back output in one go
+    pub fn finish(&self, succeeded: bool) {
+        let Some(section) = &self.section else {
+            return;
+        };
+
+        let mut content: Vec<u8> = match section.streaming {
+            true => vec![],
+            false => section.group.format_start(&section.title, succeeded).into_bytes(),
+        };
+        content.append(&mut self.buffer.lock().unwrap_or_else(|err| err.into_inner()));
+        content.extend_from_slice(section.group.format_end().as_bytes());
+
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let _ = stdout().lock().write_all(&content);
+    }
+}
+
+// Bytes are passed on as they are, a last line without a newline gets one
+fn format_line(label: &str, style: OutputStyle, line: &[u8], now: SystemTime, color: bool) -> Vec<u8> {
+    let mut decoration: String = String::new();
+
+    if style.timestamps {
+        decoration.push_str(&format!("{} ", time::format_clock(now)));
+    }
+
+    if style.prefix {
+        let prefix = format!("[{}]", label);
+        match color {
+            true => {
+                let color_code = PREFIX_COLORS[label.bytes().map(usize::from).sum::<usize>() % PREFIX_COLORS.len()];
+                decoration.push_str(&format!("\x1b[{}m{}\x1b[0m ", color_code, prefix));
+            },
+            false => decoration.push_str(&format!("{} ", prefix)),
+        }
+    }
+
+    let mut formatted_line: Vec<u8> = decoration.into_bytes();
+    formatted_line.extend_from_slice(line);
+    if !line.ends_with(b"\n") {
+        formatted_line.push(b'\n');
+    }
+
+    formatted_line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn decorates_whole_lines() {
+        let style = OutputStyle { prefix: true, timestamps: true, fold: None };
+        let now = UNIX_EPOCH + Duration::from_secs(3_723);
+
+        assert_eq!(format_line("build@api", style, b"compiled\n", now, false), b"01:02:03 [build@api] compiled\n");
+        assert_eq!(format_line("build@api", OutputStyle::default(), b"no newline", now, false), b"no newline\n");
+    }
+
+    #[test]
+    fn truncates_once_the_limit_is_reached() {
+        let limit = OutputLimit { lines: Some(2), bytes: Some(10) };
+        let mut counter = OutputCounter::default();
+
+        assert_eq!(counter.admit(limit, 4), Admission::WRITE);
+        assert_eq!(counter.admit(limit, 8), Admission::TRUNCATE);
+        assert_eq!(counter.admit(limit, 1), Admission::DROP);
+
+        let mut counter = OutputCounter::default();
+        assert_eq!([counter.admit(limit, 1), counter.admit(limit, 1), counter.admit(limit, 1)], [Admission::WRITE, Admission::WRITE, Admission::TRUNCATE]);
+        assert_eq!(OutputCounter::default().admit(OutputLimit::default(), 1_000_000), Admission::WRITE);
+    }
+
+    #[test]
+    fn colors_only_the_prefix() {
+        let style = OutputStyle { prefix: true, timestamps: false, fold: None };
+        let line = String::from_utf8(format_line("build@api", style, b"done\n", UNIX_EPOCH, true)).unwrap();
+
+        assert!(line.starts_with("\x1b["));
+        assert!(line.ends_with("[build@api]\x1b[0m done\n"));
+    }
+}
@@ -0,0 +1,175 @@
+use std::env;
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, remove_file, write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// A temporary directory with a rask.yaml per directory, named after the test
+fn create_workspace(name: &str, configs: &[(&str, &str)]) -> PathBuf {
+    let root = env::temp_dir().join(format!("rask-run-test-{}-{}", process::id(), name));
+    for (directory, content) in configs {
+        // Collecting the components drops the . of the root directory
+        let directory: PathBuf = root.join(directory).components().collect();
+        create_dir_all(&directory).unwrap();
+        write(directory.join("rask.yaml"), content).unwrap();
+    }
+
+    root
+}
+
+// What rask printed to stdout and stderr, with its exit status
+fn run_rask(root: &Path, arguments: &[&str]) -> (String, Output) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rask"))
+        .args(["run", "--entry", &root.to_string_lossy(), "--color", "never"])
+        .args(arguments)
+        .env_remove("CI")
+        .output()
+        .unwrap();
+    let printed = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+
+    (printed, output)
+}
+
+#[test]
+fn summary_only_prints_only_the_summary() {
+    // app shadows the build of root, which a run warns about
+    let root = create_workspace("summary-only", &[(".", "name: root\ndirectories: [app]\ntasks:\n  build: echo root\n"), ("app", "name: app\ntasks:\n  build: echo app\n")]);
+
+    let (printed, output) = run_rask(&root, &["build"]);
+    assert!(output.status.success());
+    assert!(printed.contains("[WARNING] Task \"build\""));
+
+    let (printed, output) = run_rask(&root, &["build", "--summary-only", "--round-robin"]);
+    assert!(output.status.success());
+    let lines: Vec<&str> = printed.lines().collect();
+    assert!(lines[0].starts_with("STATUS"), "{}", printed);
+    assert!(lines[1..3].iter().all(|line| line.starts_with("SUCCESS  build")), "{}", printed);
+    assert!(lines[3].starts_with("Successfully executed 2 tasks"), "{}", printed);
+    assert_eq!(lines.len(), 4, "{}", printed);
+
+    remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn repeated_runs_report_the_failure_rate() {
+    let root = create_workspace("repeat", &[(".", "name: root\ntasks:\n  flaky: test \"$RASK_ITERATION\" != 2\n")]);
+
+    // Every iteration runs, and gets the next seed
+    let (printed, output) = run_rask(&root, &["flaky", "--repeat", "3", "--seed", "7"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(printed.contains("[ITERATION] 3 (seed 9)"), "{}", printed);
+    assert!(printed.contains("1 of 3 iterations failed (33.33% failure rate)"), "{}", printed);
+    assert!(printed.contains("Failed iterations: #2 (seed 8)"), "{}", printed);
+
+    let (printed, output) = run_rask(&root, &["flaky", "--repeat-until-failure", "--seed", "7"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(printed.contains("1 of 2 iterations failed (50.00% failure rate)"), "{}", printed);
+
+    remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn usage_errors_do_not_exit_like_config_errors() {
+    let root = create_workspace("usage", &[(".", "name: root\ntasks:\n  build: echo root\n")]);
+
+    let (printed, output) = run_rask(&root, &["build", "--no-such-flag"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(printed.contains("unexpected argument '--no-such-flag'"), "{}", printed);
+
+    // A broken config keeps its own code
+    write(root.join("rask.yaml"), "name: [root\n").unwrap();
+    assert_eq!(run_rask(&root, &["build"]).1.status.code(), Some(2));
+
+    remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn level_hooks_wrap_every_level() {
+    // The configs below the root run in the level before it
+    let hooks = "before_level: echo before $RASK_LEVEL >> log\nafter_level: echo after $RASK_LEVEL $RASK_LEVEL_STATUS >> log\n";
+    let root_config = format!("name: root\ndirectories: [app]\n{}tasks:\n  build: echo root >> log\n", hooks);
+    let root = create_workspace("level-hooks", &[(".", &root_config), ("app", "name: app\ntasks:\n  build: echo app >> ../log\n")]);
+
+    let (printed, output) = run_rask(&root, &["build"]);
+    assert!(output.status.success(), "{}", printed);
+    assert_eq!(read_to_string(root.join("log")).unwrap(), "before 1\napp\nafter 1 success\nbefore 0\nroot\nafter 0 success\n");
+
+    // The after hook still tears down a failed level, the levels after it do not run
+    remove_file(root.join("log")).unwrap();
+    write(root.join("app/rask.yaml"), "name: app\ntasks:\n  build: echo app >> ../log; exit 1\n").unwrap();
+    let (printed, output) = run_rask(&root, &["build"]);
+    assert_eq!(output.status.code(), Some(3), "{}", printed);
+    assert_eq!(read_to_string(root.join("log")).unwrap(), "before 1\napp\nafter 1 failure\n");
+
+    // A failing before hook skips its level and the after hook
+    remove_file(root.join("log")).unwrap();
+    write(root.join("rask.yaml"), root_config.replace("$RASK_LEVEL >> log", "$RASK_LEVEL >> log; exit 1")).unwrap();
+    let (printed, output) = run_rask(&root, &["build"]);
+    assert_eq!(output.status.code(), Some(3), "{}", printed);
+    assert!(printed.contains("The before_level hook failed for level 1"), "{}", printed);
+    assert_eq!(read_to_string(root.join("log")).unwrap(), "before 1\n");
+
+    remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn tasks_past_the_budget_are_terminated() {
+    let root = create_workspace("max-duration", &[(".", "name: root\ntasks:\n  slow: sleep 10\n")]);
+
+    let start_time = Instant::now();
+    let (printed, output) = run_rask(&root, &["slow", "--max-duration", "300ms"]);
+    assert_eq!(output.status.code(), Some(4), "{}", printed);
+    assert!(start_time.elapsed() < Duration::from_secs(5), "{}", printed);
+    assert!(printed.contains("Terminated slow"), "{}", printed);
+    assert!(printed.contains("Time budget exceeded after executing"), "{}", printed);
+
+    remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn resumed_runs_only_run_what_did_not_succeed() {
+    let root = create_workspace("resume", &[(".", "name: root\ndirectories: [app]\ntasks:\n  build: test -f fixed && echo root >> log\n"), ("app", "name: app\ntasks:\n  build: echo app >> ../log\n")]);
+
+    let (printed, output) = run_rask(&root, &["build"]);
+    assert_eq!(output.status.code(), Some(3), "{}", printed);
+    assert_eq!(read_to_string(root.join("log")).unwrap(), "app\n");
+    let run_id = printed.split("rask resume ").nth(1).and_then(|rest| rest.split('`').next()).unwrap().to_string();
+
+    // Resume looks the run up from the current directory
+    let resume = || Command::new(env!("CARGO_BIN_EXE_rask")).args(["resume", &run_id, "--color", "never"]).current_dir(&root).env_remove("CI").output().unwrap();
+    write(root.join("fixed"), "").unwrap();
+    let output = resume();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(read_to_string(root.join("log")).unwrap(), "app\nroot\n");
+
+    // A completed run can not be resumed again
+    assert!(!resume().status.success());
+
+    remove_dir_all(root).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn killed_tasks_are_cancelled_with_what_they_started() {
+    // The sleep is a grandchild of rask, the task waits on it in the foreground
+    let root = create_workspace("kill", &[(".", "name: root\ntasks:\n  serve: sh -c 'sleep 100 & echo $! > pid; wait'\n")]);
+    let mut run = Command::new(env!("CARGO_BIN_EXE_rask")).args(["run", "serve", "--entry", &root.to_string_lossy()]).env_remove("CI").stdout(Stdio::null()).stderr(Stdio::null()).spawn().unwrap();
+
+    // Kill reports the task as not running until the run registered it
+    let kill = || Command::new(env!("CARGO_BIN_EXE_rask")).args(["kill", "serve", "--entry", &root.to_string_lossy()]).output().unwrap();
+    let start_time = Instant::now();
+    while !root.join("pid").exists() || kill().status.code() == Some(6) {
+        assert!(start_time.elapsed() < Duration::from_secs(10), "The task never started");
+        sleep(Duration::from_millis(50));
+    }
+
+    assert_eq!(run.wait().unwrap().code(), Some(5));
+    let pid: String = read_to_string(root.join("pid")).unwrap().trim().to_string();
+    // Nothing reaps the orphaned sleep in some containers, a zombie is stopped all the same
+    let state: String = read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+    assert!(state.is_empty() || state.contains(") Z "), "{}", state);
+    assert_eq!(kill().status.code(), Some(6));
+
+    remove_dir_all(root).unwrap();
+}